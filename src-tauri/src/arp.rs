@@ -0,0 +1,176 @@
+// src-tauri/src/arp.rs
+use anyhow::{Context, Result};
+use pnet_datalink::{Channel, MacAddr, NetworkInterface};
+use pnet_packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
+use pnet_packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet_packet::Packet;
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// How long a resolved MAC address is trusted before it is looked up again
+const ARP_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long to wait for a reply to an ARP request
+const ARP_REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ARP_PACKET_LEN: usize = 28;
+
+/// Caches next-hop MAC addresses per interface
+pub struct ArpCache {
+    entries: RwLock<HashMap<(u32, Ipv4Addr), (MacAddr, Instant)>>,
+    /// Lookups being resolved in the background
+    pending: Mutex<HashSet<(u32, Ipv4Addr)>>,
+}
+
+impl Default for ArpCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArpCache {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The MAC address of `target` on `interface` if it is known, without
+    /// waiting. Unknown and expired entries are resolved in the background
+    /// for later packets; an expired one is still returned meanwhile.
+    pub async fn lookup(self: &Arc<Self>, interface: &NetworkInterface, source_ip: Ipv4Addr, target: Ipv4Addr) -> Option<MacAddr> {
+        let key = (interface.index, target);
+        let cached = self.entries.read().await.get(&key).copied();
+        if let Some((mac, resolved_at)) = cached {
+            if resolved_at.elapsed() < ARP_CACHE_TTL {
+                return Some(mac);
+            }
+        }
+
+        if self.pending.lock().unwrap_or_else(|e| e.into_inner()).insert(key) {
+            let cache = Arc::clone(self);
+            let interface = interface.clone();
+            tokio::spawn(async move {
+                if let Err(e) = cache.resolve(&interface, source_ip, target).await {
+                    debug!("Failed to resolve {} on '{}': {:#}", target, interface.name, e);
+                }
+                cache.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+            });
+        }
+        cached.map(|(mac, _)| mac)
+    }
+
+    /// Resolve `target` to a MAC address on `interface`, using the cache when fresh
+    pub async fn resolve(&self, interface: &NetworkInterface, source_ip: Ipv4Addr, target: Ipv4Addr) -> Result<MacAddr> {
+        let key = (interface.index, target);
+        if let Some((mac, resolved_at)) = self.entries.read().await.get(&key) {
+            if resolved_at.elapsed() < ARP_CACHE_TTL {
+                return Ok(*mac);
+            }
+        }
+
+        let interface = interface.clone();
+        let mac = tokio::task::spawn_blocking(move || {
+            lookup_neighbor_table(&interface.name, target)
+                .map(Ok)
+                .unwrap_or_else(|| send_arp_request(&interface, source_ip, target))
+        })
+        .await
+        .context("ARP resolution task failed")??;
+
+        self.entries.write().await.insert(key, (mac, Instant::now()));
+        Ok(mac)
+    }
+}
+
+/// Check the OS neighbor table before putting a request on the wire
+#[cfg(target_os = "linux")]
+fn lookup_neighbor_table(interface_name: &str, target: Ipv4Addr) -> Option<MacAddr> {
+    // Columns: IP address, HW type, Flags, HW address, Mask, Device
+    let table = std::fs::read_to_string("/proc/net/arp").ok()?;
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || fields[5] != interface_name || fields[0].parse::<Ipv4Addr>().ok()? != target {
+            return None;
+        }
+        // Flags 0x0 means the entry is incomplete
+        if fields[2] == "0x0" {
+            return None;
+        }
+        fields[3].parse::<MacAddr>().ok().filter(|mac| *mac != MacAddr::zero())
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lookup_neighbor_table(_interface_name: &str, _target: Ipv4Addr) -> Option<MacAddr> {
+    None
+}
+
+/// Broadcast an ARP request for `target` and wait for the reply
+fn send_arp_request(interface: &NetworkInterface, source_ip: Ipv4Addr, target: Ipv4Addr) -> Result<MacAddr> {
+    let source_mac = interface.mac.context("Interface has no MAC address")?;
+
+    let config = pnet_datalink::Config {
+        read_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    let (mut tx, mut rx) = match pnet_datalink::channel(interface, config) {
+        Ok(Channel::Ethernet(tx, rx)) => (tx, rx),
+        Ok(_) => return Err(anyhow::anyhow!("Unsupported channel type")),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut frame = [0u8; ETHERNET_HEADER_LEN + ARP_PACKET_LEN];
+    {
+        let mut ethernet = MutableEthernetPacket::new(&mut frame).context("Failed to build Ethernet header")?;
+        ethernet.set_destination(MacAddr::broadcast());
+        ethernet.set_source(source_mac);
+        ethernet.set_ethertype(EtherTypes::Arp);
+    }
+    {
+        let mut arp = MutableArpPacket::new(&mut frame[ETHERNET_HEADER_LEN..]).context("Failed to build ARP packet")?;
+        arp.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp.set_protocol_type(EtherTypes::Ipv4);
+        arp.set_hw_addr_len(6);
+        arp.set_proto_addr_len(4);
+        arp.set_operation(ArpOperations::Request);
+        arp.set_sender_hw_addr(source_mac);
+        arp.set_sender_proto_addr(source_ip);
+        arp.set_target_hw_addr(MacAddr::zero());
+        arp.set_target_proto_addr(target);
+    }
+
+    tx.send_to(&frame, None)
+        .context("Interface does not support sending")?
+        .context("Failed to send ARP request")?;
+
+    let deadline = Instant::now() + ARP_REPLY_TIMEOUT;
+    while Instant::now() < deadline {
+        let received = match rx.next() {
+            Ok(received) => received,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(ethernet) = EthernetPacket::new(received) else {
+            continue;
+        };
+        if ethernet.get_ethertype() != EtherTypes::Arp {
+            continue;
+        }
+        let Some(arp) = ArpPacket::new(ethernet.payload()) else {
+            continue;
+        };
+        if arp.get_operation() == ArpOperations::Reply && arp.get_sender_proto_addr() == target {
+            return Ok(arp.get_sender_hw_addr());
+        }
+    }
+
+    Err(anyhow::anyhow!("No ARP reply from {} on {}", target, interface.name))
+}
@@ -1,9 +1,28 @@
 // src/bin/cli.rs
-use clap::Parser;
-use netboost_pro_lib::InterfaceManager;
+use clap::{Parser, Subcommand};
+use netboost_pro_lib::{
+    benchmark_interface_count, benchmark_router, check_privileges, check_vpn, disable_kill_switch, default_control_path, diagnose, default_daemon_log_file, default_pid_file, init_logging,
+    install_service, process_running, read_pid_file, run_interface_benchmark, run_speed_test, send_control_request, simulate, spawn_detached,
+    start_control_server, uninstall_service, CheckStatus, ConfigWatcher, ControlRequest, ControlStatus, DiagnosticsReport, DropReason, DropReasons, InterfaceFilter,
+    InterfaceManager, LoadBalancingMode, NetBoostConfig, PacketSource, PerformanceMonitor, PerformanceStats, PidFile, RelayConfig,
+    ServiceLink, ServiceRequest, Settings, SimulationReport, SpeedTestResult, TunConfig, VirtualNetworkInterface, VpnPolicy, SERVICE_NAME,
+};
+use std::ffi::OsString;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long --daemon waits for the background service to answer control commands
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long --stop waits for the background service to exit
+const DAEMON_STOP_TIMEOUT: Duration = Duration::from_secs(15);
+const DAEMON_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How long the --monitor dashboard says the statistics were reset
+const RESET_NOTICE_DURATION: Duration = Duration::from_secs(5);
 
 /// NetBoost Pro Command-Line Interface
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Start the NetBoost Pro service
@@ -17,21 +36,1103 @@ struct Args {
     /// List all available interfaces
     #[arg(short, long)]
     list: bool,
+
+    /// Print the interface listing as JSON instead of human-readable text
+    #[arg(long)]
+    json: bool,
+
+    /// Address and prefix for the TUN interface (e.g. 10.8.0.1/24)
+    #[arg(long, value_name = "ADDR/PREFIX", value_parser = parse_cidr)]
+    tun_address: Option<(Ipv4Addr, u8)>,
+
+    /// Name of the TUN interface
+    #[arg(long, value_name = "NAME")]
+    tun_name: Option<String>,
+
+    /// MTU of the TUN interface
+    #[arg(long, value_name = "BYTES")]
+    mtu: Option<u16>,
+
+    /// Apply this TOML config file on top of the saved settings; other flags
+    /// still take precedence
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Load balancing mode (round_robin, latency_based, bandwidth_based, balanced, weighted, redundant, active_passive, adaptive, proportional)
+    #[arg(long, value_name = "MODE")]
+    load_balancing_mode: Option<LoadBalancingMode>,
+
+    /// What to do if another VPN is carrying traffic at startup (coexist, refuse, take_precedence)
+    #[arg(long, value_name = "POLICY")]
+    vpn_policy: Option<VpnPolicy>,
+
+    /// Leave the routing table alone instead of routing traffic into the TUN
+    #[arg(long)]
+    no_routes: bool,
+
+    /// Block all traffic that doesn't go through the TUN while running,
+    /// and if the service dies, until it is stopped cleanly
+    #[arg(long)]
+    kill_switch: bool,
+
+    /// Remove kill-switch rules left behind when the service died
+    #[arg(long)]
+    disable_kill_switch: bool,
+
+    /// Seconds spent probing interfaces before serving traffic; 0 skips it
+    #[arg(long, value_name = "SECS")]
+    warmup: Option<u64>,
+
+    /// Tasks processing packets at once; 0 runs one per CPU core
+    #[arg(long, value_name = "N")]
+    workers: Option<usize>,
+
+    /// Route packets from a pcap capture, or `synthetic[:COUNT]` generated
+    /// traffic, without creating a TUN, and report where they would go
+    #[arg(long, value_name = "PCAP")]
+    simulate: Option<PacketSource>,
+
+    /// Replay a pcap or pcapng capture through the router and summarize how
+    /// it would be distributed across interfaces
+    #[arg(long, value_name = "FILE")]
+    analyze: Option<PathBuf>,
+
+    /// Also write logs to this file, rotated daily
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Start the service with a live terminal dashboard instead of plain output
+    #[arg(long)]
+    monitor: bool,
+
+    /// With --start, run the service in the background, logging to the
+    /// configured log file
+    #[arg(long, requires = "start", conflicts_with = "monitor")]
+    daemon: bool,
+
+    /// Record the service's PID in this file while it runs; --daemon and
+    /// --stop use a default location
+    #[arg(long, value_name = "PATH")]
+    pid_file: Option<PathBuf>,
+
+    /// Cleanly stop the service started with --daemon
+    #[arg(long)]
+    stop: bool,
+
+    /// Register the service with systemd or the Windows service manager to
+    /// start at boot, with the other flags given here
+    #[arg(long, conflicts_with = "uninstall_service")]
+    install_service: bool,
+
+    /// Stop and remove the service registered by --install-service
+    #[arg(long)]
+    uninstall_service: bool,
+
+    /// Run under the Windows service manager; passed by the installed service
+    #[arg(long, hide = true)]
+    service: bool,
+
+    /// Append the interface chosen for each new flow to this JSON Lines file
+    #[arg(long, value_name = "PATH")]
+    flow_log: Option<PathBuf>,
+
+    /// Copy one in --sample-rate routed packets, with the interface each was
+    /// routed to, to this pcapng file
+    #[arg(long, value_name = "PATH")]
+    sample: Option<PathBuf>,
+
+    /// Send packet samples to this UDP collector instead of a file
+    #[arg(long, value_name = "HOST:PORT", conflicts_with = "sample")]
+    sample_collector: Option<std::net::SocketAddr>,
+
+    /// Sample one in this many routed packets
+    #[arg(long, value_name = "N")]
+    sample_rate: Option<u32>,
+
+    /// Bond interfaces through the relay server at HOST:PORT
+    #[arg(long, value_name = "HOST:PORT", requires = "relay_token")]
+    relay: Option<String>,
+
+    /// Shared secret identifying this client to the relay
+    #[arg(long, value_name = "TOKEN")]
+    relay_token: Option<String>,
+
+    /// Leave interfaces whose name matches this glob (e.g. `docker*`) out of
+    /// aggregation, on top of the saved filter; may be repeated
+    #[arg(long, value_name = "GLOB")]
+    exclude_interface: Vec<String>,
+
+    /// Time routing decisions in every load balancing mode against mock
+    /// interfaces; build with --release for meaningful numbers
+    #[arg(long)]
+    bench_router: bool,
+
+    /// Mock interfaces used by --bench-router, at most 256
+    #[arg(long, value_name = "COUNT", default_value_t = 4)]
+    bench_interfaces: usize,
+
+    /// Decisions timed per mode by --bench-router
+    #[arg(long, value_name = "COUNT", default_value_t = 100_000)]
+    bench_decisions: usize,
+
+    /// Zero the performance counters of a running service
+    #[arg(long)]
+    reset_stats: bool,
+
+    /// Check privileges, the TUN driver and each interface's path to the
+    /// internet, and explain whatever would stop aggregation working
+    #[arg(long)]
+    diagnose: bool,
+
+    /// Measure download throughput over each interface alone and all of them together
+    #[arg(long)]
+    speedtest: bool,
+
+    /// Plain HTTP URL of the file downloaded by --speedtest
+    #[arg(long, value_name = "URL")]
+    speedtest_url: Option<String>,
+
+    /// Seconds each phase of --speedtest downloads for
+    #[arg(long, value_name = "SECS")]
+    speedtest_duration: Option<u64>,
+
+    /// Measure one interface's download, upload, latency and loss on its own,
+    /// using the --speedtest servers and duration
+    #[arg(long, value_name = "NAME|INDEX")]
+    bench_interface: Option<String>,
+
+    /// Unix socket or Windows named pipe the service accepts control
+    /// commands on, and the control subcommands connect to
+    #[arg(long, value_name = "PATH")]
+    control_socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Commands for a service started with --start
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Show what the running service is doing
+    Status,
+    /// Print the running service's performance statistics as JSON
+    Stats,
+    /// Change the running service's load balancing mode
+    SetMode { mode: LoadBalancingMode },
+    /// Return an interface to aggregation
+    Enable { index: u32 },
+    /// Take an interface out of aggregation
+    Disable { index: u32 },
+}
+
+fn parse_cidr(value: &str) -> Result<(Ipv4Addr, u8), String> {
+    let (address, prefix) = value
+        .split_once('/')
+        .ok_or_else(|| format!("expected ADDR/PREFIX, got '{}'", value))?;
+    let address = address
+        .parse::<Ipv4Addr>()
+        .map_err(|e| format!("invalid address '{}': {}", address, e))?;
+    let prefix = prefix
+        .parse::<u8>()
+        .map_err(|e| format!("invalid prefix '{}': {}", prefix, e))?;
+    Ok((address, prefix))
+}
+
+fn tun_config_from_args(args: &Args, mut config: TunConfig) -> TunConfig {
+    if let Some((address, prefix)) = args.tun_address {
+        config.address = address;
+        config.netmask_prefix = prefix;
+    }
+    if let Some(name) = &args.tun_name {
+        config.name = name.clone();
+    }
+    if let Some(mtu) = args.mtu {
+        config.mtu = mtu;
+    }
+    config
+}
+
+/// The saved settings with the `--config` file, if any, applied on top
+fn load_settings(args: &Args) -> anyhow::Result<Settings> {
+    let mut settings = Settings::load();
+    if let Some(path) = &args.config {
+        NetBoostConfig::load(path)?.apply_to(&mut settings)?;
+    }
+    Ok(settings)
+}
+
+/// The saved interface filter plus any `--exclude-interface` globs
+fn interface_filter(args: &Args, settings: &Settings) -> InterfaceFilter {
+    let mut filter = settings.interface_filter.clone();
+    for name in &args.exclude_interface {
+        if !filter.exclude.names.contains(name) {
+            filter.exclude.names.push(name.clone());
+        }
+    }
+    filter
+}
+
+/// Command-line flags override the config file and persisted settings. They
+/// are applied again after every config reload, so they keep winning.
+fn apply_cli_overrides(args: &Args, settings: &mut Settings) {
+    settings.tun_config = tun_config_from_args(args, settings.tun_config.clone());
+    if let Some(mode) = args.load_balancing_mode {
+        settings.load_balancing_mode = mode;
+    }
+    if let Some(secs) = args.warmup {
+        settings.warmup_secs = secs;
+    }
+    if let Some(workers) = args.workers {
+        settings.packet_workers = workers;
+    }
+    if let Some(policy) = args.vpn_policy {
+        settings.vpn_policy = policy;
+    }
+    if args.no_routes {
+        settings.manage_routes = false;
+    }
+    if args.kill_switch {
+        settings.kill_switch = true;
+    }
+    if let Some(path) = &args.flow_log {
+        settings.flow_log.enabled = true;
+        settings.flow_log.file = Some(path.clone());
+    }
+    if args.sample.is_some() || args.sample_collector.is_some() {
+        settings.packet_sampler.enabled = true;
+        settings.packet_sampler.file = args.sample.clone();
+        settings.packet_sampler.collector = args.sample_collector;
+    }
+    if let Some(rate) = args.sample_rate {
+        settings.packet_sampler.rate = rate;
+    }
+    settings.interface_filter = interface_filter(args, settings);
+    if let (Some(endpoint), Some(auth_token)) = (&args.relay, &args.relay_token) {
+        settings.relay = Some(RelayConfig::new(endpoint.clone(), auth_token.clone()));
+    }
 }
 
-fn main() {
-    env_logger::init();
-    
+/// Run the aggregation service until it stops or the process is asked to exit
+async fn run_service(args: &Args, link: &mut ServiceLink) -> anyhow::Result<()> {
+    let mut settings = load_settings(args)?;
+    apply_cli_overrides(args, &mut settings);
+    settings.tun_config.validate()?;
+    check_privileges()?;
+    let _pid_file = args.pid_file.as_deref().map(PidFile::create).transpose()?;
+    check_vpn(&mut settings)?;
+    InterfaceManager::discover_usable(&settings.interface_filter)?;
+
+    println!("Starting NetBoost Pro service...");
+    println!(
+        "TUN interface: {} ({}/{}, MTU {})",
+        settings.tun_config.name,
+        settings.tun_config.address,
+        settings.tun_config.netmask_prefix,
+        settings.tun_config.mtu
+    );
+
+    let vni = Arc::new(VirtualNetworkInterface::new(settings.tun_config.clone(), Arc::new(PerformanceMonitor::new())).await?);
+    vni.apply_settings(&settings).await;
+
+    let mut service = {
+        let vni = Arc::clone(&vni);
+        tokio::spawn(async move { vni.run().await })
+    };
+
+    println!("NetBoost Pro is running. Press Ctrl-C to stop.");
+
+    let control_path = args.control_socket.clone().unwrap_or_else(default_control_path);
+    let control_server = match start_control_server(&control_path, Arc::clone(&vni)).await {
+        Ok(server) => Some(server),
+        Err(e) => {
+            eprintln!("Control commands unavailable: {:#}", e);
+            None
+        }
+    };
+    let config_reloader = match &args.config {
+        Some(path) => Some(tokio::spawn(reload_config(ConfigWatcher::new(path)?, Arc::clone(&vni), settings.clone(), args.clone()))),
+        None => None,
+    };
+    link.ready("Routing traffic");
+
+    let stop_requested = async {
+        let service_manager_stop = async {
+            // Pausing sends everything over the primary interface without stopping
+            loop {
+                match link.next_request().await {
+                    ServiceRequest::Stop => break,
+                    ServiceRequest::Pause => {
+                        vni.set_connection_aggregation(false).await;
+                        link.paused(true);
+                    }
+                    ServiceRequest::Resume => {
+                        vni.set_connection_aggregation(settings.connection_aggregation).await;
+                        link.paused(false);
+                    }
+                }
+            }
+        };
+        tokio::select! {
+            _ = shutdown_signal() => {}
+            _ = service_manager_stop => {}
+        }
+    };
+
+    let exit_requested = async {
+        if args.monitor {
+            // Signals still stop the service while the dashboard has the terminal
+            tokio::select! {
+                result = run_dashboard(&vni) => {
+                    if let Err(e) = result {
+                        eprintln!("Dashboard failed: {:#}", e);
+                    }
+                }
+                _ = stop_requested => {}
+            }
+        } else {
+            stop_requested.await;
+        }
+    };
+
+    tokio::select! {
+        result = &mut service => {
+            if let Some(reloader) = &config_reloader {
+                reloader.abort();
+            }
+            stop_control_server(control_server, &control_path);
+            return result?;
+        }
+        _ = exit_requested => {
+            println!("Shutdown requested, stopping NetBoost Pro...");
+            link.stopping();
+            match vni.stop().await {
+                Ok(report) => println!("Flushed {} queued packets, dropped {}.", report.drained, report.dropped),
+                Err(e) => eprintln!("Shutdown did not complete cleanly: {:#}", e),
+            }
+        }
+    }
+
+    if let Some(reloader) = &config_reloader {
+        reloader.abort();
+    }
+    stop_control_server(control_server, &control_path);
+    service.await?
+}
+
+fn stop_control_server(server: Option<tokio::task::JoinHandle<()>>, path: &std::path::Path) {
+    if let Some(server) = server {
+        server.abort();
+        // Named pipes go away with their handles; socket files don't
+        if cfg!(unix) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Run the service on the Windows service manager's thread until it asks the service to stop
+#[cfg(windows)]
+fn run_windows_service(args: Args) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::block_in_place(|| {
+        netboost_pro_lib::run_as_windows_service(move |mut link| runtime.block_on(run_service(&args, &mut link)))
+    })
+}
+
+#[cfg(not(windows))]
+fn run_windows_service(_args: Args) -> anyhow::Result<()> {
+    anyhow::bail!("--service is only for the Windows service; use --install-service to register with systemd")
+}
+
+/// Run a control subcommand against the service listening on the control socket
+async fn run_control_command(args: &Args, command: &Command) -> anyhow::Result<()> {
+    let path = args.control_socket.clone().unwrap_or_else(default_control_path);
+    let request = match command {
+        Command::Status => ControlRequest::Status,
+        Command::Stats => ControlRequest::Stats,
+        Command::SetMode { mode } => ControlRequest::SetMode { mode: *mode },
+        Command::Enable { index } => ControlRequest::SetInterfaceEnabled { index: *index, enabled: true },
+        Command::Disable { index } => ControlRequest::SetInterfaceEnabled { index: *index, enabled: false },
+    };
+
+    match request {
+        ControlRequest::Status => {
+            let status: ControlStatus = send_control_request(&path, &request).await?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+            println!("TUN interface: {}", status.virtual_interface_name.as_deref().unwrap_or("unknown"));
+            println!("Uptime: {}", format_uptime(status.uptime_seconds));
+            println!("Aggregation: {}", if status.aggregation_enabled { "enabled" } else { "disabled" });
+            match status.effective_load_balancing_mode {
+                Some(effective) if effective != status.load_balancing_mode => {
+                    println!("Load balancing mode: {} ({})", status.load_balancing_mode, effective)
+                }
+                _ => println!("Load balancing mode: {}", status.load_balancing_mode),
+            }
+            println!("Active interfaces: {} of {}", status.active_interface_count, status.interfaces.len());
+            for interface in &status.interfaces {
+                println!("  {:>4}  {} ({})", interface.index, interface.name, interface.ip_address);
+            }
+        }
+        ControlRequest::Stats => {
+            let stats: PerformanceStats = send_control_request(&path, &request).await?;
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        }
+        _ => {
+            let message: String = send_control_request(&path, &request).await?;
+            println!("{}", message);
+        }
+    }
+    Ok(())
+}
+
+/// Start the service in the background with the same flags and wait until it
+/// answers control commands
+async fn run_daemon(args: &Args) -> anyhow::Result<()> {
+    let pid_path = args.pid_file.clone().unwrap_or_else(default_pid_file);
+    if let Some(pid) = read_pid_file(&pid_path)? {
+        anyhow::bail!("NetBoost Pro is already running (PID {})", pid);
+    }
+    let log_file = args.log_file.clone().or(Settings::load().logging.file).unwrap_or_else(default_daemon_log_file);
+
+    let mut child_args: Vec<OsString> = std::env::args_os().skip(1).filter(|arg| arg != "--daemon").collect();
+    if args.pid_file.is_none() {
+        child_args.extend(["--pid-file".into(), pid_path.into_os_string()]);
+    }
+    if args.log_file.is_none() {
+        child_args.extend(["--log-file".into(), log_file.clone().into_os_string()]);
+    }
+    let mut child = spawn_detached(child_args)?;
+
+    let control_path = args.control_socket.clone().unwrap_or_else(default_control_path);
+    let deadline = Instant::now() + DAEMON_START_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("The service exited ({}) before it was ready; see {}", status, log_file.display());
+        }
+        if send_control_request::<ControlStatus>(&control_path, &ControlRequest::Status).await.is_ok() {
+            println!("NetBoost Pro is running in the background (PID {}).", child.id());
+            break;
+        }
+        if Instant::now() >= deadline {
+            println!("NetBoost Pro started in the background (PID {}) but isn't answering control commands yet.", child.id());
+            break;
+        }
+        tokio::time::sleep(DAEMON_POLL_INTERVAL).await;
+    }
+    println!("Logging to {} (rotated daily).", log_file.display());
+    Ok(())
+}
+
+/// Ask the background service to shut down over the control socket and wait for it to exit
+async fn stop_daemon(args: &Args) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let pid_path = args.pid_file.clone().unwrap_or_else(default_pid_file);
+    let pid = read_pid_file(&pid_path)?
+        .with_context(|| format!("No running NetBoost Pro service is recorded in {}", pid_path.display()))?;
+
+    // The reply can be lost when the service exits straight after sending it; the exit is what counts
+    let control_path = args.control_socket.clone().unwrap_or_else(default_control_path);
+    let reply = send_control_request::<String>(&control_path, &ControlRequest::Shutdown).await;
+
+    let deadline = Instant::now() + DAEMON_STOP_TIMEOUT;
+    while process_running(pid) {
+        if Instant::now() >= deadline {
+            let error = match reply {
+                Ok(_) => anyhow::anyhow!("it is still running after {:?}", DAEMON_STOP_TIMEOUT),
+                Err(e) => e,
+            };
+            return Err(error.context(format!("NetBoost Pro (PID {}) did not stop", pid)));
+        }
+        tokio::time::sleep(DAEMON_POLL_INTERVAL).await;
+    }
+    match reply {
+        Ok(message) => println!("{}", message),
+        Err(_) => println!("NetBoost Pro stopped."),
+    }
+    Ok(())
+}
+
+/// Apply each valid new version of the `--config` file to the running
+/// service, keeping the command-line overrides
+async fn reload_config(mut watcher: ConfigWatcher, vni: Arc<VirtualNetworkInterface>, mut settings: Settings, args: Args) {
+    while let Some(config) = watcher.next().await {
+        let previous = settings.clone();
+        if let Err(e) = config.apply_to(&mut settings) {
+            tracing::error!("Keeping the previous config: {:#}", e);
+            settings = previous;
+            continue;
+        }
+        apply_cli_overrides(&args, &mut settings);
+        vni.apply_config_changes(&previous, &settings).await;
+        tracing::info!("Reloaded config from {}", watcher.path().display());
+    }
+}
+
+/// The reasons packets were dropped for, like " (send_error 3, queue_full 1)"
+fn format_drop_reasons(drops: &DropReasons) -> String {
+    let reasons: Vec<String> = DropReason::ALL
+        .into_iter()
+        .filter(|&reason| drops.get(reason) > 0)
+        .map(|reason| format!("{} {}", reason.as_str(), drops.get(reason)))
+        .collect();
+    if reasons.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", reasons.join(", "))
+    }
+}
+
+/// Puts the terminal back the way it was, even if the dashboard is dropped
+/// because the service stopped
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> std::io::Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen, crossterm::cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::cursor::Show, crossterm::terminal::LeaveAlternateScreen);
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Redraw the live dashboard every second until the user presses q, Esc or
+/// Ctrl-C. The whole screen is redrawn so stray log lines don't linger.
+async fn run_dashboard(vni: &VirtualNetworkInterface) -> anyhow::Result<()> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    let _guard = TerminalGuard::enter()?;
+    let mut ticks = tokio::time::interval(std::time::Duration::from_millis(50));
+    let mut redraw_at = tokio::time::Instant::now();
+    let mut last_uptime = Duration::ZERO;
+    let mut reset_notice_until = None;
+
+    loop {
+        ticks.tick().await;
+
+        while event::poll(std::time::Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        return Ok(());
+                    }
+                }
+                Event::Resize(_, _) => redraw_at = tokio::time::Instant::now(),
+                _ => {}
+            }
+        }
+
+        let now = tokio::time::Instant::now();
+        if now >= redraw_at {
+            let stats = vni.get_performance_stats().await;
+            // --reset-stats restarts the session clock along with the counters
+            if stats.uptime < last_uptime {
+                reset_notice_until = Some(now + RESET_NOTICE_DURATION);
+            }
+            last_uptime = stats.uptime;
+            let notice = reset_notice_until.filter(|until| now < *until).map(|_| "Performance statistics reset");
+            draw_dashboard(vni, &stats, notice).await?;
+            redraw_at = now + std::time::Duration::from_secs(1);
+        }
+    }
+}
+
+async fn draw_dashboard(vni: &VirtualNetworkInterface, stats: &PerformanceStats, notice: Option<&str>) -> anyhow::Result<()> {
+    use crossterm::{cursor::MoveTo, style::Print, terminal::{Clear, ClearType}};
+    use std::io::Write;
+
+    let health = vni.get_interface_health().await;
+    let mode = match (vni.get_load_balancing_mode().await, stats.effective_load_balancing_mode) {
+        (LoadBalancingMode::Adaptive, Some(strategy)) => format!("adaptive ({})", strategy),
+        (mode, _) => mode.to_string(),
+    };
+    let aggregation = vni.is_connection_aggregation_enabled().await;
+
+    let mut lines = vec![
+        format!(
+            "NetBoost Pro  up {}  mode {}  aggregation {}",
+            format_uptime(stats.uptime.as_secs()),
+            mode,
+            if aggregation { "on" } else { "off" }
+        ),
+        format!(
+            "Upload {}  Download {}  Effective {}  Loss {:.2}%",
+            format_bitrate(stats.upload_bps),
+            format_bitrate(stats.download_bps),
+            format_bitrate(stats.aggregate_bandwidth_bps),
+            stats.packet_loss_rate * 100.0
+        ),
+        format!(
+            "Packets received {}  forwarded {}  dropped {}{}",
+            stats.packets_received,
+            stats.packets_forwarded,
+            stats.packets_dropped.total,
+            format_drop_reasons(&stats.packets_dropped)
+        ),
+        String::new(),
+        format!("{:<16} {:>14} {:>10} {:>10} {:>7}  {}", "Interface", "Throughput", "Latency", "Jitter", "Loss", "Health"),
+    ];
+    for interface in vni.interfaces().await {
+        let throughput = stats.interfaces.get(&interface.index).map_or(0, |iface| iface.current_bps);
+        let status = health.get(&interface.index);
+        lines.push(format!(
+            "{:<16} {:>14} {:>10} {:>10} {:>7}  {}",
+            interface.name,
+            format_bitrate(throughput),
+            status
+                .and_then(|status| status.last_latency_ms)
+                .map_or("-".to_string(), |latency| format!("{:.0} ms", latency)),
+            status
+                .and_then(|status| status.jitter_ms)
+                .map_or("-".to_string(), |jitter| format!("{:.1} ms", jitter)),
+            status.map_or("-".to_string(), |status| format!("{:.0}%", status.last_packet_loss * 100.0)),
+            match status {
+                Some(status) if status.circuit_open => "send failures",
+                Some(status) if status.healthy => "healthy",
+                Some(_) => "degraded",
+                None => "not probed",
+            }
+        ));
+    }
+    lines.push(String::new());
+    if let Some(notice) = notice {
+        lines.push(notice.to_string());
+    }
+    lines.push("Press q to stop NetBoost Pro".to_string());
+
+    let (width, height) = crossterm::terminal::size()?;
+    let mut stdout = std::io::stdout();
+    crossterm::queue!(stdout, Clear(ClearType::All))?;
+    for (row, line) in lines.iter().take(height as usize).enumerate() {
+        let line: String = line.chars().take(width as usize).collect();
+        crossterm::queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn format_bitrate(bps: u64) -> String {
+    match bps {
+        0..1_000 => format!("{} bps", bps),
+        1_000..1_000_000 => format!("{:.1} Kbps", bps as f64 / 1_000.0),
+        1_000_000..1_000_000_000 => format!("{:.1} Mbps", bps as f64 / 1_000_000.0),
+        _ => format!("{:.2} Gbps", bps as f64 / 1_000_000_000.0),
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Zero the statistics of the service listening on the control socket
+async fn reset_stats(args: &Args) -> anyhow::Result<()> {
+    let control_path = args.control_socket.clone().unwrap_or_else(default_control_path);
+    let message: String = send_control_request(&control_path, &ControlRequest::ResetStats).await?;
+    println!("{}", message);
+    Ok(())
+}
+
+/// Run the diagnostics and print them as a checklist. Fails if any check did.
+async fn run_diagnose(args: &Args) -> anyhow::Result<()> {
+    let mut settings = load_settings(args)?;
+    settings.interface_filter = interface_filter(args, &settings);
+    if !args.json {
+        println!("Checking whether NetBoost Pro can route traffic...");
+    }
+    let report = diagnose(&settings).await;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_diagnostics_report(&report);
+    }
+    if !report.passed() {
+        anyhow::bail!("{} check(s) failed", report.failures().count());
+    }
+    Ok(())
+}
+
+fn print_diagnostics_report(report: &DiagnosticsReport) {
+    let mut interface = None;
+    for check in &report.checks {
+        if check.interface.is_some() && check.interface != interface {
+            interface = check.interface.clone();
+            println!();
+            println!("{}:", check.interface.as_deref().unwrap_or_default());
+        }
+        let mark = match check.status {
+            CheckStatus::Pass => "[ok]  ",
+            CheckStatus::Warn => "[warn]",
+            CheckStatus::Fail => "[FAIL]",
+            CheckStatus::Skipped => "[skip]",
+        };
+        let indent = if check.interface.is_some() { "  " } else { "" };
+        println!("{}{} {:<13} {}", indent, mark, check.name, check.reason);
+    }
+    println!();
+    if report.passed() {
+        println!("Everything NetBoost Pro needs is working.");
+    }
+}
+
+/// Time the routing hot path in every mode and print ns per decision
+async fn run_router_benchmark(args: &Args) -> anyhow::Result<()> {
+    let results = benchmark_router(args.bench_interfaces, args.bench_decisions).await;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    println!(
+        "Routing {} decisions per mode across {} interfaces",
+        args.bench_decisions,
+        benchmark_interface_count(args.bench_interfaces)
+    );
+    if cfg!(debug_assertions) {
+        println!("  (debug build; use --release for representative numbers)");
+    }
+    for result in &results {
+        println!(
+            "  {:<16} {:>10.0} ns/decision {:>12.0} decisions/s",
+            result.mode.as_str(),
+            result.nanos_per_decision(),
+            result.decisions_per_second()
+        );
+    }
+    Ok(())
+}
+
+/// Replay a workload through the router and print where each packet went
+async fn run_simulation(args: &Args, source: PacketSource) -> anyhow::Result<()> {
+    let mut settings = load_settings(args)?;
+    if let Some(mode) = args.load_balancing_mode {
+        settings.load_balancing_mode = mode;
+    }
+
+    let report = simulate(source, &settings).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_simulation_report(&report, settings.load_balancing_mode);
+    }
+    Ok(())
+}
+
+fn print_simulation_report(report: &SimulationReport, mode: LoadBalancingMode) {
+    println!("Simulated {} packets with {} load balancing", report.packets, mode);
+    println!(
+        "  Dropped: {} ({} over rate limits, {} malformed)",
+        report.dropped, report.throttled, report.invalid
+    );
+    println!();
+
+    println!("Per interface:");
+    let routed = (report.packets - report.dropped).max(1) as f64;
+    for (name, tally) in &report.interfaces {
+        println!(
+            "  {:<16} {:>8} packets {:>12} bytes {:>6.1}%",
+            name,
+            tally.packets,
+            tally.bytes,
+            tally.packets as f64 / routed * 100.0
+        );
+    }
+    println!();
+
+    println!("Per traffic type:");
+    for (traffic_type, packets) in &report.traffic_types {
+        let by_interface: Vec<String> = report
+            .interfaces
+            .iter()
+            .filter_map(|(name, tally)| tally.traffic_types.get(traffic_type).map(|count| format!("{} {}", name, count)))
+            .collect();
+        println!("  {:<16} {:>8} packets  ({})", format!("{:?}", traffic_type), packets, by_interface.join(", "));
+    }
+    println!();
+
+    match report.average_confidence() {
+        Some(average) => println!("Confidence (average {:.2}):", average),
+        None => println!("Confidence:"),
+    }
+    let buckets = report.confidence_histogram.len();
+    for (i, count) in report.confidence_histogram.iter().enumerate() {
+        println!(
+            "  {:.1}-{:.1} {:>8} packets",
+            i as f32 / buckets as f32,
+            (i + 1) as f32 / buckets as f32,
+            count
+        );
+    }
+}
+
+/// Run a speed test over every discovered interface and print the results
+async fn run_speedtest(args: &Args) -> anyhow::Result<()> {
+    let mut config = Settings::load().speed_test;
+    if let Some(url) = &args.speedtest_url {
+        config.url = url.clone();
+    }
+    if let Some(duration) = args.speedtest_duration {
+        config.duration_secs = duration;
+    }
+
+    let interfaces = InterfaceManager::discover();
+    if !args.json {
+        println!(
+            "Testing {} interface(s) against {} ({}s per phase)...",
+            interfaces.len(),
+            config.url,
+            config.duration().as_secs()
+        );
+    }
+    let result = run_speed_test(&config, &interfaces).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        print_speed_test_result(&result);
+    }
+    Ok(())
+}
+
+/// Benchmark the interface named by --bench-interface and print the result
+async fn run_interface_bench(args: &Args, interface: &str) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let mut config = Settings::load().speed_test;
+    if let Some(url) = &args.speedtest_url {
+        config.url = url.clone();
+    }
+    if let Some(duration) = args.speedtest_duration {
+        config.duration_secs = duration;
+    }
+
+    let interface = InterfaceManager::discover()
+        .into_iter()
+        .find(|candidate| candidate.name == interface || candidate.index.to_string() == interface)
+        .with_context(|| format!("No interface named {}", interface))?;
+    if !args.json {
+        println!("Benchmarking {} for {}s per direction...", interface.name, config.duration_secs);
+    }
+    let result = run_interface_benchmark(&config, &interface, Duration::from_secs(config.duration_secs)).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+    let mbps = |bps: u64| bps as f64 / 1_000_000.0;
+    println!();
+    println!("  Download: {:.1} Mbps", mbps(result.download_bps));
+    match (result.upload_bps, &result.upload_error) {
+        (Some(bps), _) => println!("  Upload:   {:.1} Mbps", mbps(bps)),
+        (None, error) => println!("  Upload:   failed: {}", error.as_deref().unwrap_or("unknown error")),
+    }
+    match result.latency_ms {
+        Some(latency) => println!("  Latency:  {:.1} ms", latency),
+        None => println!("  Latency:  no connection succeeded"),
+    }
+    println!("  Loss:     {:.0}%", result.packet_loss * 100.0);
+    Ok(())
+}
+
+fn print_speed_test_result(result: &SpeedTestResult) {
+    let mbps = |bps: u64| bps as f64 / 1_000_000.0;
+
+    println!();
+    println!("  {:<16} {:>12} {:>12} {:>10}", "Interface", "Alone", "Combined", "Latency");
+    for interface in &result.interfaces {
+        match &interface.error {
+            Some(error) => println!("  {:<16} failed: {}", interface.name, error),
+            None => println!(
+                "  {:<16} {:>7.1} Mbps {:>7.1} Mbps {:>7.1} ms",
+                interface.name,
+                mbps(interface.single_bps),
+                mbps(interface.aggregate_bps),
+                interface.latency_ms.unwrap_or_default()
+            ),
+        }
+    }
+    println!();
+    println!("Fastest single link: {:.1} Mbps", mbps(result.fastest_single_bps));
+    println!("All links combined:  {:.1} Mbps", mbps(result.aggregate_bps));
+    match result.speedup() {
+        Some(speedup) if result.aggregation_helped() => println!("Aggregation was {:.0}% faster than the fastest link.", (speedup - 1.0) * 100.0),
+        Some(_) => println!("Aggregation did not beat the fastest link."),
+        None => println!("No interface could reach the test server."),
+    }
+}
+
+/// Resolve when the process receives SIGINT, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
     let args = Args::parse();
 
-    if args.start {
-        println!("Starting NetBoost Pro service...");
-        println!("Note: Full service implementation requires GUI mode.");
-        println!("Run the main application for full functionality.");
+    let mut log_settings = Settings::load().logging;
+    if let Some(path) = &args.log_file {
+        log_settings.file = Some(path.clone());
+    }
+    // A Windows service has no console to log to
+    if args.service && log_settings.file.is_none() {
+        log_settings.file = Some(default_daemon_log_file());
+    }
+    init_logging(&log_settings);
+
+    let simulation_source = match (&args.analyze, &args.simulate) {
+        (Some(path), _) => Some(PacketSource::Pcap(path.clone())),
+        (None, source) => source.clone(),
+    };
+
+    if let Some(command) = &args.command {
+        if let Err(e) = run_control_command(&args, command).await {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    } else if let Some(source) = simulation_source {
+        if let Err(e) = run_simulation(&args, source).await {
+            eprintln!("Simulation failed: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.diagnose {
+        if let Err(e) = run_diagnose(&args).await {
+            eprintln!("Diagnostics found problems: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.bench_router {
+        if let Err(e) = run_router_benchmark(&args).await {
+            eprintln!("Benchmark failed: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if let Some(interface) = &args.bench_interface {
+        if let Err(e) = run_interface_bench(&args, interface).await {
+            eprintln!("Interface benchmark failed: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.speedtest {
+        if let Err(e) = run_speedtest(&args).await {
+            eprintln!("Speed test failed: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.daemon {
+        if let Err(e) = run_daemon(&args).await {
+            eprintln!("Failed to start NetBoost Pro in the background: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.stop {
+        if let Err(e) = stop_daemon(&args).await {
+            eprintln!("Failed to stop NetBoost Pro: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.install_service {
+        // The installed service starts itself, so leave out what would conflict with that
+        let service_args: Vec<OsString> = std::env::args_os()
+            .skip(1)
+            .filter(|arg| !["--install-service", "--start", "-s", "--daemon", "--monitor"].contains(&arg.to_string_lossy().as_ref()))
+            .collect();
+        match install_service(&service_args) {
+            Ok(()) => println!("Installed the {} service; it starts at boot.", SERVICE_NAME),
+            Err(e) => {
+                eprintln!("Failed to install the service: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if args.uninstall_service {
+        match uninstall_service() {
+            Ok(()) => println!("Removed the {} service.", SERVICE_NAME),
+            Err(e) => {
+                eprintln!("Failed to remove the service: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if args.disable_kill_switch {
+        match disable_kill_switch() {
+            Ok(()) => println!("Kill-switch disabled; traffic flows normally again."),
+            Err(e) => {
+                eprintln!("Failed to disable the kill-switch: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if args.service {
+        if let Err(e) = run_windows_service(args) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    } else if args.start || args.monitor {
+        if let Err(e) = run_service(&args, &mut ServiceLink::default()).await {
+            eprintln!("NetBoost Pro failed: {:#}", e);
+            std::process::exit(1);
+        }
+        println!("NetBoost Pro stopped.");
+    } else if args.reset_stats {
+        if let Err(e) = reset_stats(&args).await {
+            eprintln!("Failed to reset statistics: {:#}", e);
+            std::process::exit(1);
+        }
+    } else if (args.discover || args.list) && args.json {
+        match InterfaceManager::new() {
+            Ok(manager) => {
+                let settings = Settings::load();
+                let filter = interface_filter(&args, &settings);
+                let excluded: Vec<&str> = manager
+                    .get_all_interfaces()
+                    .iter()
+                    .filter(|interface| !filter.allows(interface))
+                    .map(|interface| interface.name.as_str())
+                    .collect();
+                let listing = serde_json::json!({
+                    "interfaces": manager.get_all_interfaces(),
+                    "primary": manager.get_preferred_primary(settings.primary_interface.as_deref()),
+                    "excluded": excluded,
+                });
+                println!("{}", serde_json::to_string_pretty(&listing).expect("interface listing is serializable"));
+            }
+            Err(e) => {
+                eprintln!("Error discovering interfaces: {}", e);
+                std::process::exit(1);
+            }
+        }
     } else if args.discover || args.list {
         println!("Discovering network interfaces...");
         match InterfaceManager::new() {
             Ok(manager) => {
+                let settings = Settings::load();
+                let filter = interface_filter(&args, &settings);
                 let interfaces = manager.get_all_interfaces();
                 
                 if interfaces.is_empty() {
@@ -46,10 +1147,19 @@ fn main() {
                         println!("  Description: {}", interface.description);
                         println!("  IP Address: {}", interface.ip_address);
                         println!("  Index: {}", interface.index);
+                        println!("  Type: {:?}", interface.interface_type);
+                        println!("  Metered: {}", if interface.is_metered { "yes" } else { "no" });
+                        if !filter.allows(interface) {
+                            println!("  Aggregated: no (excluded by the interface filter)");
+                        }
+                        match interface.link_speed_mbps {
+                            Some(speed) => println!("  Link Speed: {} Mbps", speed),
+                            None => println!("  Link Speed: unknown"),
+                        }
                         println!();
                     }
                     
-                    if let Some(primary) = manager.get_primary_interface() {
+                    if let Some(primary) = manager.get_preferred_primary(settings.primary_interface.as_deref()) {
                         println!("Primary interface: {} ({})", primary.name, primary.ip_address);
                     }
                 }
@@ -65,6 +1175,40 @@ fn main() {
         println!("Available options:");
         println!("  --discover  Discover and list network interfaces");
         println!("  --list      List all available interfaces");
-        println!("  --start     Start the NetBoost Pro service (limited in CLI mode)");
+        println!("  --json      With --list/--discover, print the listing as JSON");
+        println!("  --start     Start the NetBoost Pro service and run until Ctrl-C");
+        println!("  --monitor   Start the service with a live dashboard of throughput, latency and loss");
+        println!("  --daemon    With --start, run the service in the background and write a PID file");
+        println!("  --stop      Cleanly stop the background service");
+        println!("  --install-service          Start the service at boot with the other flags given (systemd or Windows)");
+        println!("  --uninstall-service        Stop and remove the installed service");
+        println!("  --kill-switch              With --start, block traffic that doesn't go through NetBoost Pro");
+        println!("  --disable-kill-switch      Remove kill-switch rules left behind by a crash");
+        println!("  --pid-file PATH            PID file of the service (default {})", default_pid_file().display());
+        println!("  --reset-stats  Zero the performance counters of a running service");
+        println!("  --diagnose     Check privileges, the TUN driver and each interface, and explain failures");
+        println!("  status | stats             Show the state or statistics of a running service");
+        println!("  set-mode MODE              Change a running service's load balancing mode");
+        println!("  enable INDEX | disable INDEX  Return or remove an interface of a running service");
+        println!("  --control-socket PATH      Control socket of the service (default {})", default_control_path().display());
+        println!("  --log-file PATH            Also write logs to PATH, rotated daily");
+        println!("  --flow-log PATH            With --start, log the interface chosen for each new flow to PATH");
+        println!("  --sample PATH              With --start, copy 1 in --sample-rate N routed packets to a pcapng file");
+        println!("  --sample-collector ADDR    Send packet samples to a UDP collector instead");
+        println!("  --exclude-interface GLOB   Leave matching interfaces out of aggregation (repeatable)");
+        println!("  --relay HOST:PORT          With --start, bond interfaces through a relay server (needs --relay-token)");
+        println!("  --relay-token TOKEN        Shared secret for --relay");
+        println!("  --simulate PCAP            Route a pcap capture (or synthetic[:COUNT]) without a TUN and report");
+        println!("  --analyze FILE             Summarize how a pcap/pcapng capture would be distributed");
+        println!("  --bench-router             Time routing decisions per load balancing mode (--bench-interfaces N)");
+        println!("  --speedtest                Measure throughput per interface and combined (--speedtest-url URL)");
+        println!("  --bench-interface NAME     Measure one interface's download, upload, latency and loss");
+        println!("  --tun-address ADDR/PREFIX  Address of the TUN interface (default 10.0.0.1/24)");
+        println!("  --tun-name NAME            Name of the TUN interface (default NetBoost-TUN)");
+        println!("  --mtu BYTES                MTU of the TUN interface (default 1500)");
+        println!("  --load-balancing-mode MODE One of:");
+        for mode in LoadBalancingMode::ALL {
+            println!("      {:<16} {}", mode.as_str(), mode.description());
+        }
     }
 }
\ No newline at end of file
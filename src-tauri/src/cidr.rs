@@ -0,0 +1,92 @@
+// src-tauri/src/cidr.rs
+use anyhow::{Context, Result};
+use std::net::Ipv4Addr;
+
+/// An IPv4 network such as `192.168.0.0/16`; serialized in that form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ipv4Cidr {
+    network: Ipv4Addr,
+    prefix_len: u8,
+}
+
+impl Ipv4Cidr {
+    pub fn new(address: Ipv4Addr, prefix_len: u8) -> Result<Self> {
+        if prefix_len > 32 {
+            return Err(anyhow::anyhow!("Invalid prefix length /{}", prefix_len));
+        }
+        // Store the network address so equal networks compare equal
+        let network = Ipv4Addr::from(u32::from(address) & Self::mask(prefix_len));
+        Ok(Self { network, prefix_len })
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & Self::mask(self.prefix_len) == u32::from(self.network)
+    }
+
+    /// Parse a CIDR or address, or resolve a host name to one /32 per IPv4
+    /// address it currently has
+    pub async fn resolve(destination: &str) -> Result<Vec<Self>> {
+        if let Ok(cidr) = destination.parse() {
+            return Ok(vec![cidr]);
+        }
+        let mut networks: Vec<Self> = tokio::net::lookup_host((destination, 0))
+            .await
+            .with_context(|| format!("'{}' is neither a CIDR nor a resolvable host name", destination))?
+            .filter_map(|addr| match addr.ip() {
+                std::net::IpAddr::V4(address) => Some(Self { network: address, prefix_len: 32 }),
+                std::net::IpAddr::V6(_) => None,
+            })
+            .collect();
+        networks.dedup();
+        if networks.is_empty() {
+            return Err(anyhow::anyhow!("'{}' has no IPv4 addresses", destination));
+        }
+        Ok(networks)
+    }
+
+    fn mask(prefix_len: u8) -> u32 {
+        u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for Ipv4Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl std::str::FromStr for Ipv4Cidr {
+    type Err = anyhow::Error;
+
+    /// Parse `ADDR/PREFIX`; a bare address is a /32
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (
+                address,
+                prefix_len.parse().with_context(|| format!("Invalid prefix length in '{}'", s))?,
+            ),
+            None => (s, 32),
+        };
+        let address = address.parse().with_context(|| format!("Invalid IPv4 address in '{}'", s))?;
+        Self::new(address, prefix_len)
+    }
+}
+
+impl TryFrom<String> for Ipv4Cidr {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Ipv4Cidr> for String {
+    fn from(cidr: Ipv4Cidr) -> Self {
+        cidr.to_string()
+    }
+}
@@ -0,0 +1,153 @@
+// src-tauri/src/classifier.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::cidr::Ipv4Cidr;
+use crate::packet_router::{FlowKey, TrafficType};
+
+/// Transport protocol a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleProtocol {
+    Tcp,
+    Udp,
+}
+
+impl RuleProtocol {
+    pub fn number(self) -> u8 {
+        match self {
+            RuleProtocol::Tcp => 6,
+            RuleProtocol::Udp => 17,
+        }
+    }
+}
+
+/// Inclusive range of ports, written `443` or `27000-27050`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+impl std::fmt::Display for PortRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl std::str::FromStr for PortRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |port: &str| port.trim().parse::<u16>().with_context(|| format!("Invalid port in '{}'", s));
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => {
+                let port = parse(s)?;
+                (port, port)
+            }
+        };
+        if start > end {
+            return Err(anyhow::anyhow!("Port range '{}' is reversed", s));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl TryFrom<String> for PortRange {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<PortRange> for String {
+    fn from(range: PortRange) -> Self {
+        range.to_string()
+    }
+}
+
+/// Assigns a traffic type to packets matching every condition that is set.
+/// A rule without conditions matches everything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassificationRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<RuleProtocol>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dst_port: Option<PortRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<Ipv4Cidr>,
+    #[serde(default)]
+    pub traffic_type: TrafficType,
+    /// Send matching packets out the interface facing the destination instead
+    /// of load balancing them, e.g. for LAN traffic
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bypass: bool,
+}
+
+impl ClassificationRule {
+    pub fn matches(&self, flow: &FlowKey) -> bool {
+        self.protocol.is_none_or(|protocol| protocol.number() == flow.protocol)
+            && self.dst_port.is_none_or(|ports| ports.contains(flow.dst_port))
+            && self.destination.is_none_or(|cidr| match flow.dst_addr {
+                IpAddr::V4(destination) => cidr.contains(destination),
+                IpAddr::V6(_) => false,
+            })
+    }
+}
+
+/// The first rule in `rules` that matches the packet's flow
+pub fn first_match<'a>(rules: &'a [ClassificationRule], flow: &FlowKey) -> Option<&'a ClassificationRule> {
+    rules.iter().find(|rule| rule.matches(flow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_rules_match_in_declared_order() {
+        let rules: Vec<ClassificationRule> = serde_json::from_str(
+            r#"[
+                {"destination": "192.168.0.0/16", "bypass": true},
+                {"protocol": "udp", "dst_port": "3074", "traffic_type": "gaming"},
+                {"protocol": "tcp", "dst_port": "443", "traffic_type": "web"},
+                {"dst_port": "1-65535", "traffic_type": "file"}
+            ]"#,
+        )
+        .unwrap();
+
+        let flow = |dst_addr: [u8; 4], dst_port: u16, protocol: u8| FlowKey {
+            src_addr: Ipv4Addr::new(10, 0, 0, 2).into(),
+            dst_addr: Ipv4Addr::from(dst_addr).into(),
+            src_port: 40000,
+            dst_port,
+            protocol,
+        };
+
+        let lan = first_match(&rules, &flow([192, 168, 1, 20], 3074, 17)).unwrap();
+        assert!(lan.bypass);
+        assert_eq!(lan.traffic_type, TrafficType::Unknown);
+        assert_eq!(first_match(&rules, &flow([203, 0, 113, 1], 3074, 17)).unwrap().traffic_type, TrafficType::Gaming);
+        assert_eq!(first_match(&rules, &flow([203, 0, 113, 1], 443, 6)).unwrap().traffic_type, TrafficType::Web);
+        assert_eq!(first_match(&rules, &flow([203, 0, 113, 1], 443, 17)).unwrap().traffic_type, TrafficType::File);
+        assert!(first_match(&rules, &flow([203, 0, 113, 1], 0, 1)).is_none());
+
+        assert!("2000-1000".parse::<PortRange>().is_err());
+        assert!("10.0.0.0/33".parse::<Ipv4Cidr>().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+// src-tauri/src/config.rs
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::classifier::ClassificationRule;
+use crate::packet_router::{BypassRoute, LoadBalancingMode};
+use crate::settings::Settings;
+
+/// A setup declared in a TOML file, for managing NetBoost without the GUI.
+/// Sections that are left out keep their saved settings.
+///
+/// ```toml
+/// load_balancing_mode = "active_passive"
+///
+/// [tun]
+/// address = "10.8.0.1"
+/// mtu = 1400
+///
+/// [interfaces.eth0]
+/// weight = 2.0
+///
+/// [interfaces.wwan0]
+/// tier = 2
+/// rate_limit_bytes_per_sec = 1000000
+///
+/// [[classification_rules]]
+/// protocol = "udp"
+/// dst_port = "27000-27050"
+/// traffic_type = "gaming"
+///
+/// [[bypass_routes]]
+/// cidr = "192.168.0.0/16"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetBoostConfig {
+    pub load_balancing_mode: Option<LoadBalancingMode>,
+    pub tun: Option<TunSection>,
+    /// Keyed by interface name
+    #[serde(default)]
+    pub interfaces: HashMap<String, InterfaceSection>,
+    /// Replace the saved rules when present, first match wins
+    pub classification_rules: Option<Vec<ClassificationRule>>,
+    /// Replace the saved bypass routes when present
+    pub bypass_routes: Option<Vec<BypassRoute>>,
+}
+
+/// TUN settings to change; the rest keep their saved values
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TunSection {
+    pub name: Option<String>,
+    pub address: Option<Ipv4Addr>,
+    pub netmask_prefix: Option<u8>,
+    pub mtu: Option<u16>,
+}
+
+/// Per-interface settings to change; the rest keep their saved values
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InterfaceSection {
+    pub weight: Option<f32>,
+    pub enabled: Option<bool>,
+    pub tier: Option<u8>,
+    /// Zero removes the limit
+    pub rate_limit_bytes_per_sec: Option<u64>,
+}
+
+impl NetBoostConfig {
+    /// Read and validate a config file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Parse and validate TOML. Errors name the offending field and where it is.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, interface) in &self.interfaces {
+            if interface.weight.is_some_and(|weight| !weight.is_finite() || weight <= 0.0) {
+                return Err(anyhow::anyhow!("interfaces.{}.weight must be a positive number", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Overlay the config on `settings`, leaving them untouched if the
+    /// resulting TUN settings are invalid
+    pub fn apply_to(&self, settings: &mut Settings) -> Result<()> {
+        let mut tun_config = settings.tun_config.clone();
+        if let Some(tun) = &self.tun {
+            if let Some(name) = &tun.name {
+                tun_config.name = name.clone();
+            }
+            if let Some(address) = tun.address {
+                tun_config.address = address;
+            }
+            if let Some(prefix) = tun.netmask_prefix {
+                tun_config.netmask_prefix = prefix;
+            }
+            if let Some(mtu) = tun.mtu {
+                tun_config.mtu = mtu;
+            }
+        }
+        tun_config.validate().context("Invalid tun section")?;
+        settings.tun_config = tun_config;
+
+        if let Some(mode) = self.load_balancing_mode {
+            settings.load_balancing_mode = mode;
+        }
+        for (name, interface) in &self.interfaces {
+            let saved = settings.interface_mut(name);
+            if let Some(weight) = interface.weight {
+                saved.weight = weight;
+            }
+            if let Some(enabled) = interface.enabled {
+                saved.enabled = enabled;
+            }
+            if let Some(tier) = interface.tier {
+                saved.tier = tier;
+            }
+            if let Some(rate) = interface.rate_limit_bytes_per_sec {
+                saved.rate_limit_bytes_per_sec = (rate > 0).then_some(rate);
+            }
+        }
+        if let Some(rules) = &self.classification_rules {
+            settings.classification_rules = rules.clone();
+        }
+        if let Some(routes) = &self.bypass_routes {
+            settings.bypass_routes = routes.clone();
+        }
+        Ok(())
+    }
+}
+
+/// Editors often save in several writes, so wait this long for the file to settle
+const RELOAD_SETTLE: Duration = Duration::from_millis(250);
+
+/// Yields the new config each time a config file is saved
+pub struct ConfigWatcher {
+    path: PathBuf,
+    changes: mpsc::UnboundedReceiver<()>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Result<Self> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Failed to find config file {}", path.display()))?;
+        // Watch the directory, since many editors replace the file rather than write to it
+        let dir = path.parent().context("Config file has no parent directory")?;
+
+        let (tx, changes) = mpsc::unbounded_channel();
+        let file = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                if !event.kind.is_access() && event.paths.contains(&file) {
+                    let _ = tx.send(());
+                }
+            }
+        })
+        .context("Failed to create config file watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+        Ok(Self { path, changes, _watcher: watcher })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait for the file to change and return the new config. Versions that
+    /// fail to load are logged and skipped, so the previous config stays in
+    /// effect. Returns `None` if the watcher stops.
+    pub async fn next(&mut self) -> Option<NetBoostConfig> {
+        loop {
+            self.changes.recv().await?;
+            tokio::time::sleep(RELOAD_SETTLE).await;
+            while self.changes.try_recv().is_ok() {}
+
+            match NetBoostConfig::load(&self.path) {
+                Ok(config) => return Some(config),
+                Err(e) => error!("Keeping the previous config: {:#}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_router::TrafficType;
+
+    #[test]
+    fn test_config_overlays_saved_settings() {
+        let config = NetBoostConfig::parse(
+            r#"
+            load_balancing_mode = "active_passive"
+
+            [tun]
+            mtu = 1400
+
+            [interfaces.wwan0]
+            tier = 2
+            rate_limit_bytes_per_sec = 1000000
+
+            [[classification_rules]]
+            protocol = "udp"
+            dst_port = "27000-27050"
+            traffic_type = "gaming"
+
+            [[bypass_routes]]
+            cidr = "192.168.0.0/16"
+            "#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        settings.interface_mut("wwan0").weight = 0.5;
+        config.apply_to(&mut settings).unwrap();
+
+        assert_eq!(settings.load_balancing_mode, LoadBalancingMode::ActivePassive);
+        assert_eq!((settings.tun_config.mtu, settings.tun_config.netmask_prefix), (1400, 24));
+        let wwan = &settings.interfaces["wwan0"];
+        assert_eq!((wwan.weight, wwan.tier, wwan.rate_limit_bytes_per_sec), (0.5, 2, Some(1_000_000)));
+        assert_eq!(settings.classification_rules[0].traffic_type, TrafficType::Gaming);
+        assert_eq!(settings.bypass_routes[0].cidr.to_string(), "192.168.0.0/16");
+    }
+
+    #[test]
+    fn test_config_errors_name_the_field() {
+        let err = NetBoostConfig::parse("[interfaces.eth0]\nwieght = 2.0\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("wieght"));
+
+        let err = NetBoostConfig::parse("[interfaces.eth0]\nweight = -1.0\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("interfaces.eth0.weight"));
+
+        let config = NetBoostConfig::parse("[tun]\nmtu = 100\n").unwrap();
+        let mut settings = Settings::default();
+        assert!(config.apply_to(&mut settings).is_err());
+        assert_eq!(settings.tun_config.mtu, 1500);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_skips_invalid_versions() {
+        let dir = std::env::temp_dir().join(format!("netboost-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("netboost.toml");
+        std::fs::write(&path, "load_balancing_mode = \"balanced\"\n").unwrap();
+
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+        let next = tokio::spawn(async move { watcher.next().await });
+
+        std::fs::write(&path, "load_balancing_mode = \"fastest\"\n").unwrap();
+        tokio::time::sleep(RELOAD_SETTLE * 2).await;
+        std::fs::write(&path, "load_balancing_mode = \"round_robin\"\n").unwrap();
+
+        let config = tokio::time::timeout(Duration::from_secs(5), next).await.unwrap().unwrap().unwrap();
+        assert_eq!(config.load_balancing_mode, Some(LoadBalancingMode::RoundRobin));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
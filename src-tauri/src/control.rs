@@ -0,0 +1,319 @@
+// src-tauri/src/control.rs
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+use crate::error::NetBoostError;
+use crate::interface_manager::PhysicalInterface;
+use crate::packet_router::LoadBalancingMode;
+use crate::virtual_adapter::VirtualNetworkInterface;
+
+/// Longest control command accepted; a client sending more is disconnected
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// A command sent to a running service over its control socket, one JSON
+/// object per line, e.g. `{"command":"set_mode","mode":"round_robin"}`.
+/// Each gets one line back: `{"ok":...}` or `{"error":{"code":...,"message":...}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Reply with a [`ControlStatus`]
+    Status,
+    /// Reply with the current `PerformanceStats`
+    Stats,
+    SetMode { mode: LoadBalancingMode },
+    SetInterfaceEnabled { index: u32, enabled: bool },
+    /// Zero the session statistics; the lifetime counters keep accumulating
+    ResetStats,
+    /// Stop routing after flushing the queued packets, ending the service
+    Shutdown,
+}
+
+/// What a running service is doing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub virtual_interface_name: Option<String>,
+    pub uptime_seconds: u64,
+    pub active_interface_count: usize,
+    pub aggregation_enabled: bool,
+    pub load_balancing_mode: LoadBalancingMode,
+    /// The strategy `adaptive` mode is currently following
+    pub effective_load_balancing_mode: Option<LoadBalancingMode>,
+    /// Every interface in the routing pool, including disabled ones
+    pub interfaces: Vec<PhysicalInterface>,
+}
+
+/// Where the CLI service listens unless told otherwise. Controlling the
+/// service needs the same privileges as running it.
+pub fn default_control_path() -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(r"\\.\pipe\netboost-pro")
+    } else if cfg!(target_os = "linux") {
+        PathBuf::from("/run/netboost-pro.sock")
+    } else {
+        std::env::temp_dir().join("netboost-pro.sock")
+    }
+}
+
+/// Accept control commands for `vni` on a Unix domain socket at `path`
+#[cfg(unix)]
+pub async fn start_control_server(path: &Path, vni: Arc<VirtualNetworkInterface>) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::net::{UnixListener, UnixStream};
+
+    // A socket left behind by a service that crashed would make bind fail
+    if UnixStream::connect(path).await.is_ok() {
+        anyhow::bail!("Another NetBoost Pro service is listening on {}", path.display());
+    }
+    let _ = std::fs::remove_file(path);
+
+    let listener = bind_owner_only(path).with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+    let listener = UnixListener::from_std(listener)?;
+
+    info!("Accepting control commands on {}", path.display());
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Control socket accept error: {}", e);
+                    continue;
+                }
+            };
+
+            let vni = Arc::clone(&vni);
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &vni).await {
+                    debug!("Control connection error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(handle)
+}
+
+/// Bind a Unix socket at `path` that only its owner can connect to. The
+/// permissions are set before `listen`, and nobody can connect to a socket
+/// that isn't listening yet, so there is no moment another user could.
+#[cfg(unix)]
+fn bind_owner_only(path: &Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use socket2::{Domain, SockAddr, Socket, Type};
+    use std::os::unix::fs::PermissionsExt;
+
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+    socket.bind(&SockAddr::unix(path)?)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    socket.listen(128)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+/// Accept control commands for `vni` on a named pipe at `path`
+#[cfg(windows)]
+pub async fn start_control_server(path: &Path, vni: Arc<VirtualNetworkInterface>) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let pipe_name = path.as_os_str().to_os_string();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&pipe_name)
+        .with_context(|| format!("Failed to create control pipe {}", path.display()))?;
+
+    info!("Accepting control commands on {}", path.display());
+
+    let handle = tokio::spawn(async move {
+        loop {
+            if let Err(e) = server.connect().await {
+                warn!("Control pipe connect error: {}", e);
+                continue;
+            }
+
+            // Each client gets the connected instance; a fresh one waits for the next
+            let connected = server;
+            server = match ServerOptions::new().create(&pipe_name) {
+                Ok(server) => server,
+                Err(e) => {
+                    warn!("Failed to create control pipe instance, control commands stop: {}", e);
+                    return;
+                }
+            };
+
+            let vni = Arc::clone(&vni);
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(connected, &vni).await {
+                    debug!("Control connection error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(handle)
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(stream: S, vni: &VirtualNetworkInterface) -> Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let len = (&mut reader).take(MAX_LINE_LEN as u64 + 1).read_line(&mut line).await?;
+        if len == 0 {
+            break;
+        }
+        if len > MAX_LINE_LEN {
+            anyhow::bail!("Control command longer than {} bytes", MAX_LINE_LEN);
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => execute(request, vni).await,
+            Err(e) => Err(NetBoostError::InvalidInput(format!("Invalid control command: {}", e))),
+        };
+        let reply = match result {
+            Ok(value) => serde_json::json!({ "ok": value }),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        let mut reply = serde_json::to_vec(&reply)?;
+        reply.push(b'\n');
+        writer.write_all(&reply).await?;
+    }
+    Ok(())
+}
+
+async fn execute(request: ControlRequest, vni: &VirtualNetworkInterface) -> Result<serde_json::Value, NetBoostError> {
+    let value = match request {
+        ControlRequest::Status => serde_json::to_value(status(vni).await),
+        ControlRequest::Stats => serde_json::to_value(vni.get_performance_stats().await),
+        ControlRequest::SetMode { mode } => {
+            vni.set_load_balancing_mode(mode).await;
+            Ok(format!("Load balancing mode set to: {}", mode).into())
+        }
+        ControlRequest::SetInterfaceEnabled { index, enabled } => {
+            let interface = vni.find_interface(index).await.ok_or(NetBoostError::UnknownInterface(index))?;
+            vni.set_interface_enabled(index, enabled).await;
+            Ok(format!("Interface {} {}", interface.name, if enabled { "enabled" } else { "disabled" }).into())
+        }
+        ControlRequest::ResetStats => {
+            vni.reset_performance_stats().await;
+            Ok("Performance statistics reset".into())
+        }
+        ControlRequest::Shutdown => {
+            let report = vni.stop().await.context("NetBoost Pro stopped uncleanly")?;
+            Ok(format!("NetBoost Pro stopped ({} queued packets flushed, {} dropped)", report.drained, report.dropped).into())
+        }
+    };
+    Ok(value.context("Failed to encode control reply")?)
+}
+
+async fn status(vni: &VirtualNetworkInterface) -> ControlStatus {
+    let stats = vni.get_performance_stats().await;
+    ControlStatus {
+        virtual_interface_name: vni.name().ok(),
+        uptime_seconds: stats.uptime.as_secs(),
+        active_interface_count: vni.active_interface_count().await,
+        aggregation_enabled: vni.is_connection_aggregation_enabled().await,
+        load_balancing_mode: vni.get_load_balancing_mode().await,
+        effective_load_balancing_mode: stats.effective_load_balancing_mode,
+        interfaces: vni.interfaces().await,
+    }
+}
+
+#[derive(Deserialize)]
+struct ControlReply {
+    ok: Option<serde_json::Value>,
+    error: Option<ControlError>,
+}
+
+#[derive(Deserialize)]
+struct ControlError {
+    message: String,
+}
+
+/// Send `request` to the service listening at `path` and decode its reply
+pub async fn send_control_request<T: DeserializeOwned>(path: &Path, request: &ControlRequest) -> Result<T> {
+    let stream = connect(path)
+        .await
+        .with_context(|| format!("No NetBoost Pro service is listening on {}", path.display()))?;
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+
+    let reply = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .context("The service closed the connection without replying")?;
+    let reply: ControlReply = serde_json::from_str(&reply).context("Invalid reply from the service")?;
+    match (reply.ok, reply.error) {
+        (_, Some(error)) => Err(anyhow::anyhow!(error.message)),
+        (Some(value), None) => serde_json::from_value(value).context("Unexpected reply from the service"),
+        (None, None) => Err(anyhow::anyhow!("Empty reply from the service")),
+    }
+}
+
+#[cfg(unix)]
+async fn connect(path: &Path) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(windows)]
+async fn connect(path: &Path) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(path)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::performance_monitor::{PerformanceMonitor, PerformanceStats};
+    use crate::simulation::PacketSource;
+
+    #[tokio::test]
+    async fn test_control_socket_reads_and_changes_a_running_service() {
+        let path = std::env::temp_dir().join(format!("netboost-control-{}.sock", std::process::id()));
+        let monitor = Arc::new(PerformanceMonitor::new());
+        let vni = Arc::new(VirtualNetworkInterface::new_simulated(PacketSource::Synthetic { count: 0 }, Arc::clone(&monitor)));
+        let server = start_control_server(&path, Arc::clone(&vni)).await.unwrap();
+        // A second service can't take over the socket
+        assert!(start_control_server(&path, Arc::clone(&vni)).await.is_err());
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let status: ControlStatus = send_control_request(&path, &ControlRequest::Status).await.unwrap();
+        assert_eq!(status.load_balancing_mode, LoadBalancingMode::Balanced);
+        assert_eq!(status.interfaces.len(), vni.interfaces().await.len());
+        let stats: PerformanceStats = send_control_request(&path, &ControlRequest::Stats).await.unwrap();
+        assert_eq!(stats.packets_forwarded, 0);
+
+        monitor.record_packet_forwarded(1, "eth0", crate::packet_router::TrafficType::Web, 1000).await;
+        send_control_request::<String>(&path, &ControlRequest::ResetStats).await.unwrap();
+        assert_eq!(monitor.get_current_stats().await.packets_forwarded, 0);
+        assert_eq!(monitor.get_lifetime_counters().await.packets_forwarded, 1);
+
+        let mode = ControlRequest::SetMode { mode: LoadBalancingMode::RoundRobin };
+        send_control_request::<String>(&path, &mode).await.unwrap();
+        assert_eq!(vni.get_load_balancing_mode().await, LoadBalancingMode::RoundRobin);
+
+        let unknown = ControlRequest::SetInterfaceEnabled { index: 9999, enabled: false };
+        let err = send_control_request::<String>(&path, &unknown).await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown interface index 9999");
+
+        // A client that never ends its line is cut off
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let _ = stream.write_all(&vec![b' '; MAX_LINE_LEN + 2]).await;
+        let mut reply = Vec::new();
+        assert_eq!(stream.read_to_end(&mut reply).await.unwrap_or(0), 0);
+
+        server.abort();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
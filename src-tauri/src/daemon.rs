@@ -0,0 +1,144 @@
+// src-tauri/src/daemon.rs
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+/// Where a background service records its PID unless told otherwise
+pub fn default_pid_file() -> PathBuf {
+    if cfg!(target_os = "linux") {
+        PathBuf::from("/run/netboost-pro.pid")
+    } else {
+        std::env::temp_dir().join("netboost-pro.pid")
+    }
+}
+
+/// Where a background service logs when no log file is configured
+pub fn default_daemon_log_file() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("netboost-pro")
+        .join("netboost.log")
+}
+
+/// The PID of the running service recorded at `path`. A file left behind by
+/// a process that is gone is removed and reads as `None`.
+pub fn read_pid_file(path: &Path) -> Result<Option<u32>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read PID file {}", path.display())),
+    };
+    let pid = contents.trim().parse::<u32>().ok().filter(|pid| process_running(*pid));
+    if pid.is_none() {
+        tracing::info!("Removing stale PID file {}", path.display());
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(pid)
+}
+
+/// Records this process's PID while it lives and removes the file when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Fails if another live process already holds `path`
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(pid) = read_pid_file(path)? {
+            anyhow::bail!("NetBoost Pro is already running (PID {}, recorded in {})", pid, path.display());
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Failed to write PID file {}", path.display()))?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Whether a process with this PID exists
+#[cfg(target_os = "linux")]
+pub fn process_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_running(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(windows)]
+pub fn process_running(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+}
+
+/// Start this executable again with `args`, detached from the terminal and
+/// with no console input or output
+pub fn spawn_detached(args: Vec<OsString>) -> Result<Child> {
+    let exe = std::env::current_exe().context("Failed to locate the CLI executable")?;
+    let mut command = Command::new(exe);
+    command.args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Out of the terminal's process group, so Ctrl-C there doesn't reach it
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    command.spawn().context("Failed to start the background service")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_is_exclusive_and_stale_files_are_replaced() {
+        let path = std::env::temp_dir().join(format!("netboost-pid-{}", std::process::id()));
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(read_pid_file(&path).unwrap(), Some(std::process::id()));
+        assert!(PidFile::create(&path).is_err());
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // Left behind by a process that has exited
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+        std::fs::write(&path, format!("{}\n", exited.id())).unwrap();
+        assert_eq!(read_pid_file(&path).unwrap(), None);
+        assert!(!path.exists());
+
+        std::fs::write(&path, "not a pid\n").unwrap();
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(pid_file.path()).unwrap().trim(), std::process::id().to_string());
+    }
+}
@@ -0,0 +1,204 @@
+// src-tauri/src/datalink.rs
+use anyhow::{Context, Result};
+use pnet_datalink::{Channel, DataLinkSender, NetworkInterface};
+use socket2::{Protocol, SockAddr, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::net_util;
+
+/// Raw socket protocol that sends IP packets with their own header
+const IPPROTO_RAW: i32 = 255;
+
+/// Send errors meaning the interface is gone or down rather than busy
+#[cfg(unix)]
+const LINK_GONE_ERRORS: &[i32] = &[libc::ENXIO, libc::ENODEV, libc::ENETDOWN];
+#[cfg(windows)]
+const LINK_GONE_ERRORS: &[i32] = &[windows_sys::Win32::Networking::WinSock::WSAENETDOWN];
+
+/// What an interface's link carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkLayer {
+    /// IP packets in Ethernet frames addressed to the next hop's MAC
+    Ethernet,
+    /// Bare IP packets, as on cellular modems, PPP links and tunnels
+    RawIp,
+}
+
+impl LinkLayer {
+    /// Point-to-point links and interfaces without a MAC address have no
+    /// Ethernet header, and no next hop to resolve
+    pub fn of(interface: &NetworkInterface) -> Self {
+        if interface.is_point_to_point() || interface.mac.is_none_or(|mac| mac.is_zero()) {
+            LinkLayer::RawIp
+        } else {
+            LinkLayer::Ethernet
+        }
+    }
+}
+
+enum Link {
+    Ethernet(Mutex<Box<dyn DataLinkSender>>),
+    RawIp(Socket),
+}
+
+/// The send half of a channel to one physical interface
+pub struct InterfaceSender {
+    pub interface: NetworkInterface,
+    link: Link,
+}
+
+impl InterfaceSender {
+    pub fn layer(&self) -> LinkLayer {
+        match self.link {
+            Link::Ethernet(_) => LinkLayer::Ethernet,
+            Link::RawIp(_) => LinkLayer::RawIp,
+        }
+    }
+
+    /// Send one Ethernet frame, or one IPv4 packet on a `RawIp` link. A
+    /// channel that can't send at all is reported separately from a failed
+    /// write.
+    pub fn send(&self, frame: &[u8]) -> Result<()> {
+        match &self.link {
+            Link::Ethernet(tx) => {
+                let mut tx = tx.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                match tx.send_to(frame, None) {
+                    Some(Ok(())) => Ok(()),
+                    Some(Err(e)) => Err(anyhow::Error::new(e).context(format!("I/O error sending packet on '{}'", self.interface.name))),
+                    None => Err(anyhow::anyhow!("Interface '{}' does not support sending packets", self.interface.name)),
+                }
+            }
+            Link::RawIp(socket) => {
+                if frame.len() < 20 || frame[0] >> 4 != 4 {
+                    anyhow::bail!("Only IPv4 can be sent over point-to-point interface '{}'", self.interface.name);
+                }
+                let destination = Ipv4Addr::new(frame[16], frame[17], frame[18], frame[19]);
+                socket
+                    .send_to(frame, &SockAddr::from(SocketAddr::from((destination, 0))))
+                    .with_context(|| format!("I/O error sending packet on '{}'", self.interface.name))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether a failed send means the interface went away or down, so its
+/// channel has to be reopened. Transient errors such as ENOBUFS under load
+/// keep the channel.
+pub fn link_is_gone(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .filter_map(std::io::Error::raw_os_error)
+        .any(|code| LINK_GONE_ERRORS.contains(&code))
+}
+
+/// A raw socket that sends IPv4 packets, headers included, out of `interface` only
+fn raw_ip_socket(interface: &NetworkInterface) -> Result<Socket> {
+    let address = interface
+        .ips
+        .iter()
+        .find_map(|ip| match ip.ip() {
+            IpAddr::V4(address) => Some(address),
+            IpAddr::V6(_) => None,
+        })
+        .with_context(|| format!("Interface '{}' has no IPv4 address", interface.name))?;
+    // Without binding to the device the kernel would route the packet, likely
+    // back into the TUN
+    let socket = net_util::bind_socket_to_interface(
+        &interface.name,
+        interface.index,
+        address,
+        Type::RAW,
+        Some(Protocol::from(IPPROTO_RAW)),
+    )?;
+    // Implied by IPPROTO_RAW on Linux and macOS, but not everywhere
+    socket.set_header_included_v4(true)?;
+    Ok(socket)
+}
+
+/// Datalink channels kept open per interface index, so the forwarding path
+/// doesn't enumerate interfaces and open a raw socket for every packet
+#[derive(Default)]
+pub struct DatalinkSenders {
+    senders: RwLock<HashMap<u32, Arc<InterfaceSender>>>,
+}
+
+impl DatalinkSenders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The open channel to an interface, opening one if there is none yet
+    pub fn get(&self, index: u32) -> Result<Arc<InterfaceSender>> {
+        if let Some(sender) = self.senders.read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&index) {
+            return Ok(Arc::clone(sender));
+        }
+        self.open(index)
+    }
+
+    /// Open a fresh channel to an interface, replacing any cached one
+    pub fn open(&self, index: u32) -> Result<Arc<InterfaceSender>> {
+        let interface = pnet_datalink::interfaces()
+            .into_iter()
+            .find(|iface| iface.index == index)
+            .with_context(|| format!("Interface {} no longer exists", index))?;
+
+        let link = match LinkLayer::of(&interface) {
+            LinkLayer::Ethernet => match pnet_datalink::channel(&interface, Default::default()) {
+                Ok(Channel::Ethernet(tx, _)) => Link::Ethernet(Mutex::new(tx)),
+                Ok(_) => return Err(anyhow::anyhow!("Unsupported channel type")),
+                Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to open a channel on '{}'", interface.name))),
+            },
+            LinkLayer::RawIp => Link::RawIp(raw_ip_socket(&interface)?),
+        };
+
+        let sender = Arc::new(InterfaceSender { interface, link });
+        self.senders
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(index, Arc::clone(&sender));
+        Ok(sender)
+    }
+
+    /// Forget the channel to an interface, so the next send reopens it
+    pub fn invalidate(&self, index: u32) {
+        self.senders.write().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_datalink::MacAddr;
+
+    fn interface(mac: Option<MacAddr>) -> NetworkInterface {
+        NetworkInterface {
+            name: "test0".to_string(),
+            description: String::new(),
+            index: 7,
+            mac,
+            ips: Vec::new(),
+            flags: 0,
+        }
+    }
+
+    #[test]
+    fn test_interfaces_without_a_mac_carry_bare_ip() {
+        assert_eq!(LinkLayer::of(&interface(Some(MacAddr::new(2, 0, 0, 0, 0, 1)))), LinkLayer::Ethernet);
+        assert_eq!(LinkLayer::of(&interface(Some(MacAddr::zero()))), LinkLayer::RawIp);
+        assert_eq!(LinkLayer::of(&interface(None)), LinkLayer::RawIp);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_only_a_vanished_link_invalidates_the_channel() {
+        let send_error = |code| anyhow::Error::new(std::io::Error::from_raw_os_error(code)).context("I/O error sending packet");
+        assert!(link_is_gone(&send_error(libc::ENODEV)));
+        assert!(link_is_gone(&send_error(libc::ENETDOWN)));
+        assert!(!link_is_gone(&send_error(libc::ENOBUFS)));
+        assert!(!link_is_gone(&send_error(libc::EAGAIN)));
+    }
+}
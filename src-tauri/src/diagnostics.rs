@@ -0,0 +1,332 @@
+// src-tauri/src/diagnostics.rs
+use anyhow::{Context, Result};
+use pnet_packet::ethernet::{EtherTypes, MutableEthernetPacket};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::arp::ArpCache;
+use crate::datalink::{DatalinkSenders, LinkLayer};
+use crate::health;
+use crate::interface_manager::{InterfaceManager, PhysicalInterface};
+use crate::nat;
+use crate::privileges::check_privileges;
+use crate::settings::Settings;
+use crate::virtual_adapter::{tun_driver_available, DEFAULT_PROBE_TARGET};
+use crate::vpn::{self, VpnPolicy, VpnReport};
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but may cause problems
+    Warn,
+    Fail,
+    /// Not applicable, or blocked by an earlier failure
+    Skipped,
+}
+
+/// The outcome of one diagnostic check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    /// What was checked, e.g. `privileges` or `reachability`
+    pub name: String,
+    /// The interface checked, for per-interface checks
+    pub interface: Option<String>,
+    pub status: CheckStatus,
+    /// Why the check passed or failed, in words a user can act on
+    pub reason: String,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, interface: Option<&PhysicalInterface>, status: CheckStatus, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            interface: interface.map(|interface| interface.name.clone()),
+            status,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Everything that has to work for NetBoost Pro to route traffic, checked
+/// in the order a failure would show up
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+    /// Other VPNs found, and which interface carries internet traffic
+    pub vpn: Option<VpnReport>,
+}
+
+impl DiagnosticsReport {
+    /// Whether no check failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.status != CheckStatus::Fail)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &DiagnosticCheck> {
+        self.checks.iter().filter(|check| check.status == CheckStatus::Fail)
+    }
+}
+
+/// Check privileges, the TUN driver, and whether each interface the
+/// settings allow can reach `probe_target`, resolve its next hop and send
+/// a packet the way the forwarding path does
+pub async fn diagnose(settings: &Settings) -> DiagnosticsReport {
+    let mut report = DiagnosticsReport::default();
+
+    let (privileges, tun_driver) = tokio::task::spawn_blocking(|| (check_privileges(), tun_driver_available()))
+        .await
+        .unwrap_or_else(|e| (Err(e.into()), false));
+    report.checks.push(match privileges {
+        Ok(()) => DiagnosticCheck::new("privileges", None, CheckStatus::Pass, "Allowed to create the TUN interface and send raw packets"),
+        Err(e) => DiagnosticCheck::new("privileges", None, CheckStatus::Fail, e.to_string()),
+    });
+    report.checks.push(if tun_driver {
+        DiagnosticCheck::new("tun_driver", None, CheckStatus::Pass, "The TUN driver is available")
+    } else {
+        DiagnosticCheck::new("tun_driver", None, CheckStatus::Fail, crate::error::NetBoostError::TunDriverMissing.to_string())
+    });
+
+    let tun_config = settings.tun_config.clone();
+    let probe_target = settings.probe_target.parse().unwrap_or(DEFAULT_PROBE_TARGET);
+    let vpn = tokio::task::spawn_blocking(move || vpn::detect(&tun_config, probe_target))
+        .await
+        .unwrap_or_default();
+    report.checks.push(vpn_check(settings.vpn_policy, &vpn));
+    report.vpn = Some(vpn);
+
+    let interfaces = match InterfaceManager::discover_usable(&settings.interface_filter) {
+        Ok(interfaces) => {
+            let names: Vec<&str> = interfaces.iter().map(|interface| interface.name.as_str()).collect();
+            report.checks.push(DiagnosticCheck::new(
+                "interfaces",
+                None,
+                CheckStatus::Pass,
+                format!("{} usable interface(s): {}", interfaces.len(), names.join(", ")),
+            ));
+            interfaces
+        }
+        Err(e) => {
+            report.checks.push(DiagnosticCheck::new("interfaces", None, CheckStatus::Fail, format!("{:#}", e)));
+            return report;
+        }
+    };
+
+    let target = settings.probe_target.parse().unwrap_or(DEFAULT_PROBE_TARGET);
+    let timeout = settings.health.probe_timeout();
+    let arp_cache = Arc::new(ArpCache::new());
+    let senders = Arc::new(DatalinkSenders::new());
+    let mut checks = tokio::task::JoinSet::new();
+    for (position, interface) in interfaces.into_iter().enumerate() {
+        let arp_cache = Arc::clone(&arp_cache);
+        let senders = Arc::clone(&senders);
+        checks.spawn(async move {
+            (position, check_interface(&interface, target, timeout, &arp_cache, &senders).await)
+        });
+    }
+    // Report interfaces in discovery order, however long each took
+    let mut by_interface = Vec::new();
+    while let Some(result) = checks.join_next().await {
+        if let Ok(checked) = result {
+            by_interface.push(checked);
+        }
+    }
+    by_interface.sort_by_key(|(position, _)| *position);
+    report.checks.extend(by_interface.into_iter().flat_map(|(_, checks)| checks));
+    report
+}
+
+/// Whether another VPN would get in the way, given what the policy does about it
+fn vpn_check(policy: VpnPolicy, vpn: &VpnReport) -> DiagnosticCheck {
+    let summary = vpn.summary();
+    if vpn.active_vpn().is_none() {
+        return DiagnosticCheck::new("vpn", None, CheckStatus::Pass, summary);
+    }
+    match policy {
+        VpnPolicy::Refuse => DiagnosticCheck::new(
+            "vpn",
+            None,
+            CheckStatus::Fail,
+            format!("{}; NetBoost Pro won't start while the VPN policy is refuse", summary),
+        ),
+        VpnPolicy::Coexist => DiagnosticCheck::new(
+            "vpn",
+            None,
+            CheckStatus::Warn,
+            format!("{}; traffic may not reach the internet while both run. Disconnect it or set the VPN policy to take_precedence", summary),
+        ),
+        VpnPolicy::TakePrecedence => DiagnosticCheck::new(
+            "vpn",
+            None,
+            CheckStatus::Warn,
+            format!("{}; routed traffic will bypass it", summary),
+        ),
+    }
+}
+
+async fn check_interface(
+    interface: &PhysicalInterface,
+    target: Ipv4Addr,
+    timeout: Duration,
+    arp_cache: &ArpCache,
+    senders: &DatalinkSenders,
+) -> Vec<DiagnosticCheck> {
+    let mut checks = Vec::new();
+
+    let (latency, packet_loss) = health::probe_interface(interface, target, timeout).await;
+    checks.push(match latency {
+        Some(latency) => DiagnosticCheck::new(
+            "reachability",
+            Some(interface),
+            CheckStatus::Pass,
+            format!("Reached {} in {} ms ({:.0}% loss)", target, latency.as_millis(), packet_loss * 100.0),
+        ),
+        None => DiagnosticCheck::new(
+            "reachability",
+            Some(interface),
+            CheckStatus::Fail,
+            format!("No answer from {} through {} within {} ms; check its connection", target, interface.ip_address, timeout.as_millis()),
+        ),
+    });
+
+    let next_hop = interface.next_hop(target);
+    let Some(network_interface) = pnet_datalink::interfaces().into_iter().find(|iface| iface.index == interface.index) else {
+        let reason = "The interface disappeared while being checked";
+        checks.push(DiagnosticCheck::new("next_hop", Some(interface), CheckStatus::Fail, reason));
+        checks.push(DiagnosticCheck::new("send_path", Some(interface), CheckStatus::Skipped, reason));
+        return checks;
+    };
+    let destination_mac = match LinkLayer::of(&network_interface) {
+        LinkLayer::RawIp => {
+            checks.push(DiagnosticCheck::new(
+                "next_hop",
+                Some(interface),
+                CheckStatus::Skipped,
+                "A point-to-point link, so there is no next hop to resolve",
+            ));
+            None
+        }
+        LinkLayer::Ethernet => match arp_cache.resolve(&network_interface, interface.ip_address, next_hop).await {
+            Ok(mac) => {
+                checks.push(DiagnosticCheck::new(
+                    "next_hop",
+                    Some(interface),
+                    CheckStatus::Pass,
+                    format!("Next hop {} is at {}", next_hop, mac),
+                ));
+                Some(mac)
+            }
+            Err(e) => {
+                let what = if interface.gateway == Some(next_hop) { "gateway" } else { "next hop" };
+                checks.push(DiagnosticCheck::new(
+                    "next_hop",
+                    Some(interface),
+                    CheckStatus::Fail,
+                    format!("Couldn't resolve the {} {}: {:#}", what, next_hop, e),
+                ));
+                checks.push(DiagnosticCheck::new(
+                    "send_path",
+                    Some(interface),
+                    CheckStatus::Skipped,
+                    "Needs the next hop's MAC address",
+                ));
+                return checks;
+            }
+        },
+    };
+
+    checks.push(match send_test_packet(interface, target, destination_mac, senders) {
+        Ok(()) => DiagnosticCheck::new(
+            "send_path",
+            Some(interface),
+            CheckStatus::Pass,
+            format!("Sent a test packet to {} the way routed traffic is sent", target),
+        ),
+        Err(e) => DiagnosticCheck::new("send_path", Some(interface), CheckStatus::Fail, format!("{:#}", e)),
+    });
+    checks
+}
+
+/// Send an ICMP echo request to `target` out of `interface`, as the
+/// forwarding path sends routed packets. Ethernet links need the next
+/// hop's MAC address.
+fn send_test_packet(
+    interface: &PhysicalInterface,
+    target: Ipv4Addr,
+    destination_mac: Option<pnet_datalink::MacAddr>,
+    senders: &DatalinkSenders,
+) -> Result<()> {
+    let sender = senders.open(interface.index)?;
+    let packet = echo_request(interface.ip_address, target);
+    let Some(destination_mac) = destination_mac else {
+        return sender.send(&packet);
+    };
+    let source_mac = sender.interface.mac.context("Interface has no MAC address")?;
+    let mut frame = vec![0u8; ETHERNET_HEADER_LEN + packet.len()];
+    let mut ethernet = MutableEthernetPacket::new(&mut frame).context("Failed to build Ethernet frame")?;
+    ethernet.set_destination(destination_mac);
+    ethernet.set_source(source_mac);
+    ethernet.set_ethertype(EtherTypes::Ipv4);
+    ethernet.set_payload(&packet);
+    sender.send(&frame)
+}
+
+/// An IPv4 ICMP echo request from `source` to `destination`
+fn echo_request(source: Ipv4Addr, destination: Ipv4Addr) -> Vec<u8> {
+    let mut packet = vec![0u8; 28];
+    packet[0] = 0x45;
+    packet[2..4].copy_from_slice(&28u16.to_be_bytes());
+    packet[8] = 64;
+    packet[9] = 1;
+    packet[12..16].copy_from_slice(&source.octets());
+    packet[16..20].copy_from_slice(&destination.octets());
+    let checksum = nat::checksum(&packet[..20]);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    let icmp = &mut packet[20..];
+    icmp[0] = ICMP_ECHO_REQUEST;
+    icmp[4..6].copy_from_slice(&(std::process::id() as u16).to_be_bytes());
+    icmp[6..8].copy_from_slice(&1u16.to_be_bytes());
+    let checksum = nat::checksum(icmp);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_packet::icmp::{self, IcmpPacket, IcmpTypes};
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+    use pnet_packet::Packet;
+
+    #[test]
+    fn test_echo_request_is_well_formed() {
+        let packet = echo_request(Ipv4Addr::new(192, 168, 1, 10), Ipv4Addr::new(1, 1, 1, 1));
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        assert_eq!(ip.get_destination(), Ipv4Addr::new(1, 1, 1, 1));
+        let echo = IcmpPacket::new(ip.payload()).unwrap();
+        assert_eq!(echo.get_icmp_type(), IcmpTypes::EchoRequest);
+        assert_eq!(echo.get_checksum(), icmp::checksum(&echo));
+    }
+
+    #[test]
+    fn test_report_fails_if_any_check_fails() {
+        let mut report = DiagnosticsReport::default();
+        report.checks.push(DiagnosticCheck::new("privileges", None, CheckStatus::Pass, "ok"));
+        report.checks.push(DiagnosticCheck::new("send_path", None, CheckStatus::Skipped, "not Ethernet"));
+        assert!(report.passed());
+
+        report.checks.push(vpn_check(VpnPolicy::Coexist, &VpnReport::default()));
+        assert!(report.passed());
+
+        report.checks.push(DiagnosticCheck::new("tun_driver", None, CheckStatus::Fail, "missing"));
+        assert!(!report.passed());
+        assert_eq!(report.failures().map(|check| check.name.as_str()).collect::<Vec<_>>(), ["tun_driver"]);
+    }
+}
@@ -0,0 +1,391 @@
+// src-tauri/src/dns.rs
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::nat;
+
+pub const DNS_PORT: u16 = 53;
+
+const DNS_HEADER_LEN: usize = 12;
+const UDP_HEADER_LEN: usize = 8;
+const UDP_PROTOCOL: u8 = 17;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const RCODE_MASK: u16 = 0x000f;
+/// EDNS pseudo-record, whose TTL field holds flags rather than a TTL
+const TYPE_OPT: u16 = 41;
+/// Answers are never kept longer than this, whatever their TTL says
+const MAX_CACHE_TTL: u32 = 3600;
+
+/// How DNS queries are routed and answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsSettings {
+    /// Send lookups through the lowest-latency interface in every load
+    /// balancing mode; when off, they are balanced like web traffic
+    pub latency_first: bool,
+    /// Send every lookup through this interface while it is available
+    pub interface: Option<String>,
+    /// Answer repeated lookups from earlier replies until their TTL runs out
+    pub cache: bool,
+    /// Most distinct questions the cache holds
+    pub cache_size: usize,
+}
+
+impl Default for DnsSettings {
+    fn default() -> Self {
+        Self {
+            latency_first: true,
+            interface: None,
+            cache: false,
+            cache_size: 1024,
+        }
+    }
+}
+
+impl DnsSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.cache && self.cache_size == 0 {
+            return Err(anyhow::anyhow!("The DNS cache needs room for at least one answer"));
+        }
+        Ok(())
+    }
+}
+
+/// Offset and original value of each record's TTL in a DNS message
+type RecordTtls = Vec<(usize, u32)>;
+
+struct CachedAnswer {
+    message: Vec<u8>,
+    /// Counted down when answering
+    ttls: RecordTtls,
+    stored: Instant,
+    /// Seconds until the shortest-lived record expires
+    lifetime: u32,
+}
+
+impl CachedAnswer {
+    fn expired(&self) -> bool {
+        self.stored.elapsed().as_secs() >= self.lifetime as u64
+    }
+}
+
+/// Answers to recent IPv4 UDP lookups, keyed by question, so repeated
+/// lookups are answered without leaving the machine
+#[derive(Default)]
+pub struct DnsCache {
+    /// Zero while caching is off
+    capacity: AtomicUsize,
+    answers: Mutex<HashMap<Vec<u8>, CachedAnswer>>,
+}
+
+impl DnsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold up to `capacity` answers; 0 turns the cache off and empties it
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut answers = self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if capacity == 0 {
+            answers.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remember the answer in a reply from a DNS server. Truncated,
+    /// failed and empty replies aren't kept.
+    pub fn store(&self, packet: &[u8]) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let Some((src_port, _, message)) = udp_payload(packet) else {
+            return;
+        };
+        let flags = read_u16(message, 2).unwrap_or(0);
+        if src_port != DNS_PORT || flags & FLAG_RESPONSE == 0 || flags & (FLAG_TRUNCATED | RCODE_MASK) != 0 {
+            return;
+        }
+        let Some((key, ttls)) = parse_response(message) else {
+            return;
+        };
+        let Some(lifetime) = ttls.iter().map(|(_, ttl)| *ttl).min().map(|ttl| ttl.min(MAX_CACHE_TTL)).filter(|ttl| *ttl > 0) else {
+            return;
+        };
+
+        let mut answers = self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if answers.len() >= capacity && !answers.contains_key(&key) {
+            answers.retain(|_, answer| !answer.expired());
+        }
+        while answers.len() >= capacity && !answers.contains_key(&key) {
+            // Make room by dropping whichever answer expires soonest
+            let Some(soonest) = answers
+                .iter()
+                .min_by_key(|(_, answer)| answer.stored + std::time::Duration::from_secs(answer.lifetime as u64))
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            answers.remove(&soonest);
+        }
+        answers.insert(key, CachedAnswer {
+            message: message.to_vec(),
+            ttls,
+            stored: Instant::now(),
+            lifetime,
+        });
+    }
+
+    /// A complete reply packet to the lookup in `packet`, if an unexpired
+    /// answer to the same question is cached
+    pub fn answer(&self, packet: &[u8]) -> Option<Vec<u8>> {
+        if self.capacity.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        let (_, dst_port, query) = udp_payload(packet)?;
+        if dst_port != DNS_PORT || read_u16(query, 2)? & FLAG_RESPONSE != 0 {
+            return None;
+        }
+        let (key, _) = question(query)?;
+
+        let mut answers = self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let answer = answers.get(&key)?;
+        if answer.expired() {
+            answers.remove(&key);
+            return None;
+        }
+
+        let elapsed = answer.stored.elapsed().as_secs() as u32;
+        let mut message = answer.message.clone();
+        message[..2].copy_from_slice(&query[..2]);
+        for (offset, ttl) in &answer.ttls {
+            message[*offset..*offset + 4].copy_from_slice(&ttl.saturating_sub(elapsed).to_be_bytes());
+        }
+        Some(reply_packet(packet, &message))
+    }
+}
+
+/// Source port, destination port and payload of an unfragmented IPv4 UDP packet
+fn udp_payload(packet: &[u8]) -> Option<(u16, u16, &[u8])> {
+    if packet.first()? >> 4 != 4 || *packet.get(9)? != UDP_PROTOCOL {
+        return None;
+    }
+    // Neither more fragments to come nor a fragment offset
+    if read_u16(packet, 6)? & 0x3fff != 0 {
+        return None;
+    }
+    let header_len = ((packet[0] & 0x0f) as usize) * 4;
+    let total_len = (read_u16(packet, 2)? as usize).min(packet.len());
+    let udp = packet.get(header_len..total_len)?;
+    let payload = udp.get(UDP_HEADER_LEN..)?;
+    Some((read_u16(udp, 0)?, read_u16(udp, 2)?, payload))
+}
+
+/// The single question of a DNS message, with its name lowercased so
+/// lookups differing only in case share an answer, and where it ends
+fn question(message: &[u8]) -> Option<(Vec<u8>, usize)> {
+    if read_u16(message, 4)? != 1 {
+        return None;
+    }
+    let mut key = Vec::new();
+    let mut offset = DNS_HEADER_LEN;
+    loop {
+        let len = *message.get(offset)? as usize;
+        // Compression pointers don't appear in the question of a query
+        if len & 0xc0 != 0 {
+            return None;
+        }
+        key.extend_from_slice(message.get(offset..offset + 1 + len)?);
+        offset += 1 + len;
+        if len == 0 {
+            break;
+        }
+    }
+    key.make_ascii_lowercase();
+    // Type and class
+    key.extend_from_slice(message.get(offset..offset + 4)?);
+    Some((key, offset + 4))
+}
+
+/// The question of a reply and where the TTL of each of its records is
+fn parse_response(message: &[u8]) -> Option<(Vec<u8>, RecordTtls)> {
+    let (key, mut offset) = question(message)?;
+    if read_u16(message, 6)? == 0 {
+        return None;
+    }
+    let records = read_u16(message, 6)? as usize + read_u16(message, 8)? as usize + read_u16(message, 10)? as usize;
+
+    let mut ttls = Vec::new();
+    for _ in 0..records {
+        offset = skip_name(message, offset)?;
+        let record_type = read_u16(message, offset)?;
+        let ttl_offset = offset + 4;
+        let rdata_len = read_u16(message, offset + 8)? as usize;
+        if record_type != TYPE_OPT {
+            ttls.push((ttl_offset, read_u32(message, ttl_offset)?));
+        }
+        offset += 10 + rdata_len;
+        if offset > message.len() {
+            return None;
+        }
+    }
+    Some((key, ttls))
+}
+
+/// Offset just past the domain name at `offset`
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)? as usize;
+        if len & 0xc0 == 0xc0 {
+            return Some(offset + 2);
+        }
+        offset += 1 + len;
+        if len == 0 {
+            return Some(offset);
+        }
+    }
+}
+
+/// A UDP packet carrying `message` back to the sender of `query`
+fn reply_packet(query: &[u8], message: &[u8]) -> Vec<u8> {
+    let header_len = ((query[0] & 0x0f) as usize) * 4;
+    let udp_len = UDP_HEADER_LEN + message.len();
+    let total_len = 20 + udp_len;
+
+    let mut reply = vec![0u8; total_len];
+    reply[0] = 0x45;
+    reply[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+    reply[8] = 64;
+    reply[9] = UDP_PROTOCOL;
+    reply[12..16].copy_from_slice(&query[16..20]);
+    reply[16..20].copy_from_slice(&query[12..16]);
+    let checksum = nat::checksum(&reply[..20]);
+    reply[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    // Ports swapped
+    reply[20..22].copy_from_slice(&query[header_len + 2..header_len + 4]);
+    reply[22..24].copy_from_slice(&query[header_len..header_len + 2]);
+    reply[24..26].copy_from_slice(&(udp_len as u16).to_be_bytes());
+    reply[28..].copy_from_slice(message);
+
+    let mut pseudo = Vec::with_capacity(12 + udp_len);
+    pseudo.extend_from_slice(&reply[12..20]);
+    pseudo.extend_from_slice(&[0, UDP_PROTOCOL]);
+    pseudo.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    pseudo.extend_from_slice(&reply[20..]);
+    let checksum = match nat::checksum(&pseudo) {
+        // Zero means no checksum in UDP
+        0 => 0xffff,
+        checksum => checksum,
+    };
+    reply[26..28].copy_from_slice(&checksum.to_be_bytes());
+    reply
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+    use pnet_packet::udp::{self, UdpPacket};
+    use pnet_packet::Packet;
+
+    const QUESTION: &[u8] = b"\x07example\x03com\x00\x00\x01\x00\x01";
+
+    fn udp_packet(src: ([u8; 4], u16), dst: ([u8; 4], u16), payload: &[u8]) -> Vec<u8> {
+        let total_len = 28 + payload.len();
+        let mut packet = vec![0u8; total_len];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+        packet[8] = 64;
+        packet[9] = UDP_PROTOCOL;
+        packet[12..16].copy_from_slice(&src.0);
+        packet[16..20].copy_from_slice(&dst.0);
+        packet[20..22].copy_from_slice(&src.1.to_be_bytes());
+        packet[22..24].copy_from_slice(&dst.1.to_be_bytes());
+        packet[24..26].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        packet[28..].copy_from_slice(payload);
+        packet
+    }
+
+    fn query(id: u16, question: &[u8]) -> Vec<u8> {
+        let mut message = id.to_be_bytes().to_vec();
+        message.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        message.extend_from_slice(question);
+        udp_packet(([10, 0, 0, 2], 40000), ([1, 1, 1, 1], DNS_PORT), &message)
+    }
+
+    fn response(id: u16, ttl: u32) -> Vec<u8> {
+        let mut message = id.to_be_bytes().to_vec();
+        message.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+        message.extend_from_slice(QUESTION);
+        // example.com A 93.184.216.34, its name a pointer to the question
+        message.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1]);
+        message.extend_from_slice(&ttl.to_be_bytes());
+        message.extend_from_slice(&[0, 4, 93, 184, 216, 34]);
+        udp_packet(([1, 1, 1, 1], DNS_PORT), ([10, 0, 0, 2], 40000), &message)
+    }
+
+    #[test]
+    fn test_repeated_lookup_is_answered_from_the_cache() {
+        let cache = DnsCache::new();
+        cache.store(&response(1, 300));
+        // Off until given a capacity
+        assert!(cache.is_empty());
+
+        cache.set_capacity(16);
+        cache.store(&response(1, 300));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.answer(&query(2, b"\x07example\x03org\x00\x00\x01\x00\x01")).is_none());
+
+        let reply = cache.answer(&query(0xbeef, b"\x07EXAMPLE\x03com\x00\x00\x01\x00\x01")).unwrap();
+        let ip = Ipv4Packet::new(&reply).unwrap();
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        assert_eq!(ip.get_source().octets(), [1, 1, 1, 1]);
+        assert_eq!(ip.get_destination().octets(), [10, 0, 0, 2]);
+        let udp = UdpPacket::new(ip.payload()).unwrap();
+        assert_eq!((udp.get_source(), udp.get_destination()), (DNS_PORT, 40000));
+        assert_eq!(udp.get_checksum(), udp::ipv4_checksum(&udp, &ip.get_source(), &ip.get_destination()));
+        // Same answer, under the new query's ID
+        assert_eq!(&udp.payload()[..2], &0xbeefu16.to_be_bytes());
+        assert_eq!(&udp.payload()[2..], &response(1, 300)[30..]);
+
+        cache.set_capacity(0);
+        assert!(cache.answer(&query(3, QUESTION)).is_none());
+    }
+
+    #[test]
+    fn test_zero_ttl_answers_and_full_cache() {
+        let cache = DnsCache::new();
+        cache.set_capacity(1);
+        cache.store(&response(1, 0));
+        assert!(cache.is_empty());
+
+        cache.store(&response(1, 60));
+        let mut other = response(1, 60);
+        other[28 + 12 + 1..28 + 12 + 8].copy_from_slice(b"netboos");
+        cache.store(&other);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.answer(&query(2, QUESTION)).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+// src-tauri/src/error.rs
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Failures reported to the frontend. Internal code uses `anyhow`; errors are
+/// mapped to these at the command boundary and serialized as
+/// `{ "code": ..., "message": ... }`, so the UI can react to the code and
+/// localize the message.
+#[derive(Debug, thiserror::Error)]
+pub enum NetBoostError {
+    #[error("NetBoost Pro is already running")]
+    AlreadyRunning,
+    #[error("NetBoost Pro is not running")]
+    NotRunning,
+    /// The setting can only change while the service is stopped
+    #[error("Stop NetBoost Pro before changing the {0}")]
+    StopRequired(&'static str),
+    #[error("{0}")]
+    NoPrivileges(String),
+    #[error("The TUN driver NetBoost Pro needs is not installed")]
+    TunDriverMissing,
+    /// Nothing the service could route over
+    #[error("{0}")]
+    NoInterfaces(String),
+    #[error("Unknown interface index {0}")]
+    UnknownInterface(u32),
+    /// Another VPN is active and the VPN policy is to refuse to start
+    #[error("{0}")]
+    VpnConflict(String),
+    /// A command argument was rejected
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The item to remove doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0:#}")]
+    Internal(#[from] anyhow::Error),
+}
+
+impl NetBoostError {
+    /// Stable identifier the frontend matches on
+    pub fn code(&self) -> &'static str {
+        match self {
+            NetBoostError::AlreadyRunning => "already_running",
+            NetBoostError::NotRunning => "not_running",
+            NetBoostError::StopRequired(_) => "stop_required",
+            NetBoostError::NoPrivileges(_) => "no_privileges",
+            NetBoostError::TunDriverMissing => "tun_driver_missing",
+            NetBoostError::NoInterfaces(_) => "no_interfaces",
+            NetBoostError::UnknownInterface(_) => "unknown_interface",
+            NetBoostError::VpnConflict(_) => "vpn_conflict",
+            NetBoostError::InvalidInput(_) => "invalid_input",
+            NetBoostError::NotFound(_) => "not_found",
+            NetBoostError::Internal(_) => "internal",
+        }
+    }
+
+    /// Reject a command argument with the full context of `error`
+    pub fn invalid_input(error: anyhow::Error) -> Self {
+        NetBoostError::InvalidInput(format!("{:#}", error))
+    }
+}
+
+impl Serialize for NetBoostError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("NetBoostError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_errors_serialize_with_code_and_message() {
+        let json = serde_json::to_value(NetBoostError::UnknownInterface(7)).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "unknown_interface", "message": "Unknown interface index 7" }));
+
+        // Internal errors keep their whole context chain
+        let error: NetBoostError = Err::<(), _>(anyhow::anyhow!("disk full"))
+            .context("Failed to save settings")
+            .unwrap_err()
+            .into();
+        let json = serde_json::to_value(error).unwrap();
+        assert_eq!(json["code"], "internal");
+        assert_eq!(json["message"], "Failed to save settings: disk full");
+    }
+}
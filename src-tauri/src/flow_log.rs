@@ -0,0 +1,231 @@
+// src-tauri/src/flow_log.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::packet_router::{FlowKey, RoutingDecision, TrafficType};
+
+/// Records waiting to be written; more are dropped rather than slow down
+/// routing
+const RECORD_QUEUE_LEN: usize = 1024;
+
+/// Where to record the interface chosen for each new flow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowLogSettings {
+    pub enabled: bool,
+    /// JSON Lines file; `None` uses `flows.jsonl` in the data directory
+    pub file: Option<PathBuf>,
+    /// Size at which the file is rotated to `<file>.1`
+    pub max_file_bytes: u64,
+}
+
+impl Default for FlowLogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: None,
+            max_file_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl FlowLogSettings {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.file
+            .clone()
+            .or_else(|| dirs::data_local_dir().map(|dir| dir.join("netboost-pro").join("flows.jsonl")))
+    }
+}
+
+/// One line of the flow log
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlowRecord {
+    pub timestamp: String,
+    pub protocol: u8,
+    pub src_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_addr: IpAddr,
+    pub dst_port: u16,
+    pub interface: String,
+    pub traffic_type: TrafficType,
+    pub confidence: f32,
+}
+
+/// The open log file, owned by the writer thread
+struct LogFile {
+    path: PathBuf,
+    max_file_bytes: u64,
+    file: File,
+    len: u64,
+}
+
+/// Append-only log of routing decisions for new flows, rotated by size.
+/// Records are written on a thread of their own, so routing never waits on
+/// the disk.
+pub struct FlowLog {
+    path: PathBuf,
+    records: SyncSender<FlowRecord>,
+    writer: JoinHandle<()>,
+}
+
+impl FlowLog {
+    pub fn open(path: &Path, max_file_bytes: u64) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = open_file(path)?;
+        let len = file.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+        let log_file = LogFile {
+            path: path.to_path_buf(),
+            max_file_bytes,
+            file,
+            len,
+        };
+
+        let (records, queued) = mpsc::sync_channel(RECORD_QUEUE_LEN);
+        let writer = std::thread::Builder::new()
+            .name("flow-log".to_string())
+            .spawn(move || log_file.write_records(queued))
+            .context("Failed to start the flow log writer")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            records,
+            writer,
+        })
+    }
+
+    /// Open the log described by `settings`, or `None` if it is disabled
+    pub fn from_settings(settings: &FlowLogSettings) -> Result<Option<Self>> {
+        if !settings.enabled {
+            return Ok(None);
+        }
+        let path = settings.path().context("No data directory available for the flow log")?;
+        Self::open(&path, settings.max_file_bytes).map(Some)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop logging once the records already queued are written. Blocks.
+    pub fn close(self) {
+        drop(self.records);
+        let _ = self.writer.join();
+    }
+
+    /// Queue the decision made for a new flow for writing. The record is
+    /// dropped if the writer has fallen behind.
+    pub fn record(&self, flow: &FlowKey, decision: &RoutingDecision) {
+        let record = FlowRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            protocol: flow.protocol,
+            src_addr: flow.src_addr,
+            src_port: flow.src_port,
+            dst_addr: flow.dst_addr,
+            dst_port: flow.dst_port,
+            interface: decision.interface_name.clone(),
+            traffic_type: decision.traffic_type,
+            confidence: decision.confidence,
+        };
+        if let Err(TrySendError::Full(_)) = self.records.try_send(record) {
+            debug!("Flow log writer is behind; dropping a record");
+        }
+    }
+}
+
+impl LogFile {
+    /// Write records until the log is closed
+    fn write_records(mut self, queued: Receiver<FlowRecord>) {
+        while let Ok(record) = queued.recv() {
+            if let Err(e) = self.write(&record) {
+                warn!("Failed to write flow log {}: {:#}", self.path.display(), e);
+            }
+        }
+    }
+
+    fn write(&mut self, record: &FlowRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        if self.len > 0 && self.len + line.len() as u64 > self.max_file_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    /// Move the current file to `<file>.1`, replacing the previous one
+    fn rotate(&mut self) -> Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        std::fs::rename(&self.path, &rotated)
+            .with_context(|| format!("Failed to rotate flow log {}", self.path.display()))?;
+        self.file = open_file(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+fn open_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open flow log {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_flow_log_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("netboost-flow-log-{}", std::process::id()));
+        let path = dir.join("flows.jsonl");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let flow = FlowKey {
+            src_addr: Ipv4Addr::new(10, 0, 0, 2).into(),
+            dst_addr: Ipv4Addr::new(93, 184, 216, 34).into(),
+            src_port: 50000,
+            dst_port: 443,
+            protocol: 6,
+        };
+        let decision = RoutingDecision {
+            interface_index: 2,
+            interface_name: "eth0".to_string(),
+            confidence: 0.75,
+            reason: String::new(),
+            traffic_type: TrafficType::Web,
+            duplicates: Vec::new(),
+            low_confidence_fallback: false,
+        };
+
+        // Room for two records per file
+        let log = FlowLog::open(&path, 500).unwrap();
+        for _ in 0..3 {
+            log.record(&flow, &decision);
+        }
+        log.close();
+
+        let current = std::fs::read_to_string(&path).unwrap();
+        let rotated = std::fs::read_to_string(dir.join("flows.jsonl.1")).unwrap();
+        assert_eq!((rotated.lines().count(), current.lines().count()), (2, 1));
+
+        let record: FlowRecord = serde_json::from_str(current.lines().next().unwrap()).unwrap();
+        assert_eq!(record.dst_port, 443);
+        assert_eq!(record.interface, "eth0");
+        assert_eq!(record.traffic_type, TrafficType::Web);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
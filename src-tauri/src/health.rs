@@ -0,0 +1,178 @@
+// src-tauri/src/health.rs
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crate::interface_manager::PhysicalInterface;
+use crate::net_util;
+
+/// Port probed on the probe target; HTTPS is open on most public resolvers
+const PROBE_PORT: u16 = 443;
+
+/// Connection attempts per probe round, used to estimate packet loss
+pub const PROBES_PER_ROUND: usize = 3;
+
+/// Thresholds for marking an interface unhealthy and bringing it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub max_latency_ms: u64,
+    pub max_packet_loss: f32,
+    /// Consecutive failing probe rounds before an interface is drained
+    pub failure_threshold: u32,
+    /// Consecutive passing probe rounds before it is used again
+    pub recovery_threshold: u32,
+    pub probe_timeout_ms: u64,
+    /// Consecutive failed sends before an interface is taken out of rotation
+    pub send_failure_threshold: u32,
+    /// How long a tripped interface is skipped before a send is tried again
+    pub send_failure_cooldown_secs: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            max_latency_ms: 500,
+            max_packet_loss: 0.2,
+            failure_threshold: 3,
+            recovery_threshold: 3,
+            probe_timeout_ms: 1000,
+            send_failure_threshold: 5,
+            send_failure_cooldown_secs: 30,
+        }
+    }
+}
+
+impl HealthConfig {
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_millis(self.probe_timeout_ms)
+    }
+
+    pub fn send_failure_cooldown(&self) -> Duration {
+        Duration::from_secs(self.send_failure_cooldown_secs)
+    }
+
+    fn is_failing(&self, latency: Option<Duration>, packet_loss: f32) -> bool {
+        packet_loss > self.max_packet_loss
+            || latency.is_none_or(|latency| latency > Duration::from_millis(self.max_latency_ms))
+    }
+}
+
+/// Probe results for a single interface
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub last_latency_ms: Option<f64>,
+    pub last_packet_loss: f32,
+    /// Standard deviation of recent probe latencies, filled in by the router
+    pub jitter_ms: Option<f64>,
+    /// Skipped after repeated send failures, filled in by the router
+    pub circuit_open: bool,
+}
+
+impl Default for HealthStatus {
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            last_latency_ms: None,
+            last_packet_loss: 0.0,
+            jitter_ms: None,
+            circuit_open: false,
+        }
+    }
+}
+
+impl HealthStatus {
+    /// Fold in one probe round. Returns true if the interface changed state.
+    pub fn record(&mut self, config: &HealthConfig, latency: Option<Duration>, packet_loss: f32) -> bool {
+        self.last_latency_ms = latency.map(|latency| latency.as_secs_f64() * 1000.0);
+        self.last_packet_loss = packet_loss;
+
+        if config.is_failing(latency, packet_loss) {
+            self.consecutive_failures += 1;
+            self.consecutive_successes = 0;
+        } else {
+            self.consecutive_successes += 1;
+            self.consecutive_failures = 0;
+        }
+
+        let was_healthy = self.healthy;
+        if self.healthy && self.consecutive_failures >= config.failure_threshold {
+            self.healthy = false;
+        } else if !self.healthy && self.consecutive_successes >= config.recovery_threshold {
+            self.healthy = true;
+        }
+        was_healthy != self.healthy
+    }
+}
+
+/// Takes an interface out of rotation after repeated send failures, e.g.
+/// when its raw socket can't be opened. Once the cooldown is over the next
+/// send is a trial: success closes the breaker, failure trips it again.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    consecutive_failures: u32,
+    /// Set while tripped; the interface is skipped until then
+    retry_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Whether the interface should be skipped for now
+    pub fn is_open(&self) -> bool {
+        self.retry_at.is_some_and(|retry_at| Instant::now() < retry_at)
+    }
+
+    /// Fold in one send. Returns false if this failure tripped the breaker
+    /// and true if a trial send closed it again.
+    pub fn record(&mut self, config: &HealthConfig, success: bool) -> Option<bool> {
+        if success {
+            self.consecutive_failures = 0;
+            return self.retry_at.take().map(|_| true);
+        }
+
+        self.consecutive_failures += 1;
+        if self.retry_at.is_some() || self.consecutive_failures >= config.send_failure_threshold {
+            // Packets routed before the trip can still fail; they only extend it
+            let tripped = !self.is_open();
+            self.retry_at = Some(Instant::now() + config.send_failure_cooldown());
+            return tripped.then_some(false);
+        }
+        None
+    }
+}
+
+/// Measure round-trip time through an interface with a TCP handshake to
+/// `target`, which needs no raw socket privileges. Returns the average
+/// latency of the successful attempts and the fraction that failed.
+pub async fn probe_interface(interface: &PhysicalInterface, target: Ipv4Addr, timeout: Duration) -> (Option<Duration>, f32) {
+    let mut total = Duration::ZERO;
+    let mut successes = 0u32;
+
+    for _ in 0..PROBES_PER_ROUND {
+        if let Some(rtt) = connect_once(interface, target, timeout).await {
+            total += rtt;
+            successes += 1;
+        }
+    }
+
+    let latency = (successes > 0).then(|| total / successes);
+    let packet_loss = 1.0 - successes as f32 / PROBES_PER_ROUND as f32;
+    (latency, packet_loss)
+}
+
+async fn connect_once(interface: &PhysicalInterface, target: Ipv4Addr, timeout: Duration) -> Option<Duration> {
+    let socket = net_util::tcp_socket(interface).ok()?;
+
+    let start = Instant::now();
+    let stream = tokio::time::timeout(timeout, socket.connect(SocketAddr::new(target.into(), PROBE_PORT)))
+        .await
+        .ok()?
+        .ok()?;
+    let rtt = start.elapsed();
+    drop(stream);
+    Some(rtt)
+}
@@ -0,0 +1,177 @@
+// src-tauri/src/interface_filter.rs
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cidr::Ipv4Cidr;
+use crate::interface_manager::{InterfaceType, PhysicalInterface};
+
+/// Interfaces matching any of these name globs, types or subnets
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceRules {
+    /// Case-insensitive globs where `*` matches any run of characters and
+    /// `?` any single one, e.g. `docker*`
+    pub names: Vec<String>,
+    pub types: Vec<InterfaceType>,
+    /// Networks containing the interface's address
+    pub subnets: Vec<Ipv4Cidr>,
+}
+
+impl InterfaceRules {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.types.is_empty() && self.subnets.is_empty()
+    }
+
+    pub fn matches(&self, interface: &PhysicalInterface) -> bool {
+        self.names.iter().any(|pattern| glob_matches(pattern, &interface.name))
+            || self.types.contains(&interface.interface_type)
+            || self.subnets.iter().any(|subnet| subnet.contains(interface.ip_address))
+    }
+}
+
+/// Which discovered interfaces take part in aggregation, on top of the
+/// up/non-loopback/IPv4 checks discovery always makes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceFilter {
+    /// Only use interfaces matching these; empty allows every interface
+    pub include: InterfaceRules,
+    /// Never use interfaces matching these, even if included
+    pub exclude: InterfaceRules,
+}
+
+impl InterfaceFilter {
+    pub fn allows(&self, interface: &PhysicalInterface) -> bool {
+        (self.include.is_empty() || self.include.matches(interface)) && !self.exclude.matches(interface)
+    }
+
+    pub fn apply(&self, interfaces: Vec<PhysicalInterface>) -> Vec<PhysicalInterface> {
+        interfaces.into_iter().filter(|interface| self.allows(interface)).collect()
+    }
+
+    /// Like `apply`, but fails with a message for the user when no interface
+    /// is left, since nothing could be routed
+    pub fn require_any(&self, interfaces: Vec<PhysicalInterface>) -> Result<Vec<PhysicalInterface>> {
+        let discovered = interfaces.len();
+        let allowed = self.apply(interfaces);
+        if allowed.is_empty() {
+            if discovered > 0 {
+                bail!(
+                    "All {} active network interfaces are excluded by the interface filter; allow at least one and try again",
+                    discovered
+                );
+            }
+            bail!("No active network interfaces found; connect at least one and try again");
+        }
+        Ok(allowed)
+    }
+}
+
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    // Greedy matching that backtracks to the most recent `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn interface(name: &str, interface_type: InterfaceType, ip_address: Ipv4Addr) -> PhysicalInterface {
+        PhysicalInterface {
+            name: name.to_string(),
+            description: String::new(),
+            ip_address,
+            index: 1,
+            link_speed_mbps: None,
+            interface_type,
+            prefix_len: 24,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        }
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("docker*", "docker0"));
+        assert!(glob_matches("*", "eth0"));
+        assert!(glob_matches("tun?", "TUN0"));
+        assert!(glob_matches("*eth*", "veth12ab"));
+        assert!(!glob_matches("tun?", "tun10"));
+        assert!(!glob_matches("eth", "eth0"));
+    }
+
+    #[test]
+    fn test_filter_includes_then_excludes() {
+        let eth = interface("eth0", InterfaceType::Ethernet, Ipv4Addr::new(192, 168, 1, 10));
+        let wifi = interface("wlan0", InterfaceType::WiFi, Ipv4Addr::new(192, 168, 2, 10));
+        let docker = interface("docker0", InterfaceType::Ethernet, Ipv4Addr::new(172, 17, 0, 1));
+        let vpn = interface("tun0", InterfaceType::Virtual, Ipv4Addr::new(10, 8, 0, 2));
+        let all = vec![eth.clone(), wifi.clone(), docker.clone(), vpn.clone()];
+        let names = |interfaces: Vec<PhysicalInterface>| interfaces.into_iter().map(|i| i.name).collect::<Vec<_>>();
+
+        assert_eq!(InterfaceFilter::default().apply(all.clone()).len(), 4);
+
+        let filter = InterfaceFilter {
+            include: InterfaceRules::default(),
+            exclude: InterfaceRules {
+                names: vec!["docker*".to_string()],
+                types: vec![InterfaceType::Virtual],
+                subnets: Vec::new(),
+            },
+        };
+        assert_eq!(names(filter.apply(all.clone())), ["eth0", "wlan0"]);
+
+        let filter = InterfaceFilter {
+            include: InterfaceRules {
+                subnets: vec!["192.168.0.0/16".parse().unwrap()],
+                ..Default::default()
+            },
+            exclude: InterfaceRules {
+                names: vec!["wlan*".to_string()],
+                ..Default::default()
+            },
+        };
+        assert_eq!(names(filter.apply(all)), ["eth0"]);
+    }
+
+    #[test]
+    fn test_require_any_explains_why_nothing_is_left() {
+        let eth = interface("eth0", InterfaceType::Ethernet, Ipv4Addr::new(192, 168, 1, 10));
+        let mut filter = InterfaceFilter::default();
+        assert_eq!(filter.require_any(vec![eth.clone()]).unwrap().len(), 1);
+
+        let error = filter.require_any(Vec::new()).unwrap_err().to_string();
+        assert!(error.starts_with("No active network interfaces found"), "{}", error);
+
+        filter.exclude.types.push(InterfaceType::Ethernet);
+        let error = filter.require_any(vec![eth]).unwrap_err().to_string();
+        assert!(error.contains("excluded by the interface filter"), "{}", error);
+    }
+}
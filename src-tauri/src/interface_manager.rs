@@ -1,5 +1,23 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::net::Ipv4Addr;
+use tracing::{debug, info};
+
+use crate::interface_filter::InterfaceFilter;
+use crate::mtu::DEFAULT_MTU;
+
+#[cfg(unix)]
+use unix_impl as platform;
+#[cfg(windows)]
+use windows_impl as platform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InterfaceType {
+    Ethernet,
+    WiFi,
+    Cellular,
+    Virtual,
+    Unknown,
+}
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PhysicalInterface {
@@ -7,6 +25,51 @@ pub struct PhysicalInterface {
     pub description: String,
     pub ip_address: Ipv4Addr,
     pub index: u32,
+    pub link_speed_mbps: Option<u64>,
+    pub interface_type: InterfaceType,
+    pub prefix_len: u8,
+    /// Default gateway reachable through this interface, if it has one
+    pub gateway: Option<Ipv4Addr>,
+    /// Largest IPv4 packet the link carries without fragmenting
+    pub mtu: u16,
+    /// Whether the OS marks the link as data capped, like a cellular
+    /// connection or a phone hotspot
+    pub is_metered: bool,
+}
+
+impl PhysicalInterface {
+    /// The address to resolve at the link layer when sending to `destination`:
+    /// the destination itself when it is on-link, otherwise the gateway
+    pub fn next_hop(&self, destination: Ipv4Addr) -> Ipv4Addr {
+        match self.gateway {
+            Some(gateway) if !self.is_on_link(destination) => gateway,
+            _ => destination,
+        }
+    }
+
+    /// Whether `destination` is in this interface's subnet
+    pub fn is_on_link(&self, destination: Ipv4Addr) -> bool {
+        let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+        u32::from(self.ip_address) & mask == u32::from(destination) & mask
+    }
+}
+
+/// Traffic counters the OS keeps for an interface since it came up, which
+/// include traffic from every application and not only what we route
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct OsInterfaceStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+}
+
+/// A change in the set of usable interfaces between two discoveries
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InterfaceEvent {
+    Added(PhysicalInterface),
+    Removed(PhysicalInterface),
 }
 
 pub struct InterfaceManager {
@@ -15,65 +78,611 @@ pub struct InterfaceManager {
 
 impl InterfaceManager {
     pub fn new() -> Result<Self> {
-        let mut manager = Self {
-            interfaces: Vec::new(),
+        debug!("Discovering network interfaces");
+
+        let manager = Self {
+            interfaces: Self::discover(),
         };
-        manager.discover_interfaces()?;
+
+        info!("Found {} interfaces", manager.interfaces.len());
+        for iface in &manager.interfaces {
+            debug!(
+                "{}: {}/{} (index {}, {:?}, {}, MTU {}, gateway {}{})",
+                iface.name,
+                iface.ip_address,
+                iface.prefix_len,
+                iface.index,
+                iface.interface_type,
+                iface.link_speed_mbps.map_or("unknown speed".to_string(), |speed| format!("{} Mbps", speed)),
+                iface.mtu,
+                iface.gateway.map_or("none".to_string(), |gateway| gateway.to_string()),
+                if iface.is_metered { ", metered" } else { "" }
+            );
+        }
+
         Ok(manager)
     }
 
-    fn discover_interfaces(&mut self) -> Result<()> {
-        println!("Discovering network interfaces...");
-        
-        self.interfaces = pnet_datalink::interfaces()
+    /// Discover the interfaces `filter` allows, failing if there are none.
+    /// Checked before starting the service, which couldn't route anything.
+    pub fn discover_usable(filter: &InterfaceFilter) -> Result<Vec<PhysicalInterface>> {
+        filter.require_any(Self::discover())
+    }
+
+    /// Read the OS traffic counters of `interfaces`, keyed by interface index.
+    /// Interfaces the OS reports nothing for are missing. On Windows this
+    /// starts PowerShell, so call it off the async runtime.
+    pub fn os_interface_stats(interfaces: &[PhysicalInterface]) -> OsCounters {
+        platform::os_counters(interfaces)
+    }
+
+    /// Enumerate the interfaces that are up, non-loopback and have an IPv4 address
+    pub fn discover() -> Vec<PhysicalInterface> {
+        Self::rediscover(&[])
+    }
+
+    /// Like `discover`, but only look up link speed, gateway, MTU and
+    /// metering for interfaces that are new or whose name or address changed
+    /// since `previous`; the rest are reused as they were. Listing the
+    /// interfaces is cheap, while the lookups start PowerShell on Windows.
+    pub fn rediscover(previous: &[PhysicalInterface]) -> Vec<PhysicalInterface> {
+        let candidates: Vec<(pnet_datalink::NetworkInterface, Ipv4Addr, u8)> = pnet_datalink::interfaces()
             .into_iter()
-            .filter(|iface| iface.is_up() && !iface.is_loopback() && !iface.ips.is_empty())
+            .filter(|iface| iface.is_up() && !iface.is_loopback())
             .filter_map(|iface| {
-                iface.ips.iter().find(|ip| ip.is_ipv4()).map(|ip| {
-                    let ip_addr = match ip.ip() {
-                        std::net::IpAddr::V4(ipv4) => ipv4,
-                        _ => return None, // Should not happen due to filter
-                    };
-                    Some(PhysicalInterface {
-                        name: iface.name.clone(),
-                        description: iface.description.clone(),
-                        ip_address: ip_addr,
-                        index: iface.index,
-                    })
-                }).flatten()
+                let (ip_address, prefix_len) = iface.ips.iter().find_map(|ip| match ip.ip() {
+                    std::net::IpAddr::V4(ipv4) => Some((ipv4, ip.prefix())),
+                    _ => None,
+                })?;
+                Some((iface, ip_address, prefix_len))
+            })
+            .collect();
+        let unchanged = |iface: &pnet_datalink::NetworkInterface, ip_address: Ipv4Addr, prefix_len: u8| {
+            previous.iter().find(|known| {
+                known.index == iface.index && known.name == iface.name && known.ip_address == ip_address && known.prefix_len == prefix_len
             })
+        };
+
+        let changed: Vec<pnet_datalink::NetworkInterface> = candidates
+            .iter()
+            .filter(|(iface, ip_address, prefix_len)| unchanged(iface, *ip_address, *prefix_len).is_none())
+            .map(|(iface, _, _)| iface.clone())
             .collect();
+        let (link_info, gateways, mtus, metered) = if changed.is_empty() {
+            Default::default()
+        } else {
+            (
+                platform::link_info(&changed),
+                platform::default_gateways(&changed),
+                platform::mtus(&changed),
+                platform::metered(&changed),
+            )
+        };
 
-        println!("Found {} interfaces:", self.interfaces.len());
-        for iface in &self.interfaces {
-            println!("  - {}: {} (index {})", iface.name, iface.ip_address, iface.index);
-        }
+        candidates
+            .iter()
+            .map(|(iface, ip_address, prefix_len)| {
+                if let Some(known) = unchanged(iface, *ip_address, *prefix_len) {
+                    return known.clone();
+                }
+                let (link_speed_mbps, interface_type) = link_info
+                    .get(&iface.index)
+                    .copied()
+                    .unwrap_or((None, InterfaceType::Unknown));
+                PhysicalInterface {
+                    name: iface.name.clone(),
+                    description: iface.description.clone(),
+                    ip_address: *ip_address,
+                    index: iface.index,
+                    link_speed_mbps,
+                    interface_type,
+                    prefix_len: *prefix_len,
+                    gateway: gateways.get(&iface.index).copied(),
+                    mtu: mtus.get(&iface.index).copied().unwrap_or(DEFAULT_MTU),
+                    // Cellular links are assumed metered when the OS can't tell
+                    is_metered: metered.get(&iface.index).copied().unwrap_or(interface_type == InterfaceType::Cellular),
+                }
+            })
+            .collect()
+    }
+
+    /// Compare a fresh discovery against the current interfaces. An interface
+    /// whose address changed is reported as removed and re-added, since flows
+    /// through the old address can't survive the change.
+    pub fn diff(&self, interfaces: &[PhysicalInterface]) -> Vec<InterfaceEvent> {
+        let same = |a: &PhysicalInterface, b: &PhysicalInterface| {
+            a.index == b.index && a.name == b.name && a.ip_address == b.ip_address
+        };
+
+        let removed = self
+            .interfaces
+            .iter()
+            .filter(|old| !interfaces.iter().any(|new| same(old, new)))
+            .cloned()
+            .map(InterfaceEvent::Removed);
+        let added = interfaces
+            .iter()
+            .filter(|new| !self.interfaces.iter().any(|old| same(old, new)))
+            .cloned()
+            .map(InterfaceEvent::Added);
 
-        Ok(())
+        removed.chain(added).collect()
     }
 
     pub fn get_primary_interface(&self) -> Option<&PhysicalInterface> {
         self.interfaces.first()
     }
 
+    /// The interface the user chose as primary, or the first one discovered
+    /// if none was chosen or it is currently missing. The choice is kept by
+    /// name since indices change when an adapter is replugged.
+    pub fn get_preferred_primary(&self, name: Option<&str>) -> Option<&PhysicalInterface> {
+        name.and_then(|name| self.interfaces.iter().find(|iface| iface.name == name))
+            .or_else(|| self.get_primary_interface())
+    }
+
+    pub fn get_interface(&self, index: u32) -> Result<&PhysicalInterface> {
+        self.interfaces
+            .iter()
+            .find(|iface| iface.index == index)
+            .with_context(|| format!("No interface with index {}", index))
+    }
+
     pub fn get_all_interfaces(&self) -> &Vec<PhysicalInterface> {
         &self.interfaces
     }
 }
 
-// Future implementation ideas for real interface discovery:
+/// Link speed in Mbps and interface type, keyed by interface index
+type LinkInfo = std::collections::HashMap<u32, (Option<u64>, InterfaceType)>;
+
+/// Default gateway, keyed by interface index
+type Gateways = std::collections::HashMap<u32, Ipv4Addr>;
+
+/// IPv4 MTU, keyed by interface index
+type Mtus = std::collections::HashMap<u32, u16>;
+
+/// Whether the OS considers a link metered, keyed by interface index.
+/// Interfaces the OS has no opinion on are missing.
+type Metered = std::collections::HashMap<u32, bool>;
+
+/// OS traffic counters, keyed by interface index
+pub type OsCounters = std::collections::HashMap<u32, OsInterfaceStats>;
+
 #[cfg(windows)]
 mod windows_impl {
-    // Use Windows API directly:
-    // - GetAdaptersAddresses
-    // - WMI queries
-    // - ipconfig parsing
+    use super::{Gateways, InterfaceType, LinkInfo, Metered, Mtus, OsCounters, OsInterfaceStats, PhysicalInterface};
+    use std::process::Command;
+
+    /// Read each adapter's counters through `Get-NetAdapterStatistics`, which
+    /// reports the same totals as `GetIfEntry2`
+    pub fn os_counters(interfaces: &[PhysicalInterface]) -> OsCounters {
+        let mut counters = OsCounters::new();
+
+        let script = "Get-NetAdapter | ForEach-Object { \
+                $stats = $_ | Get-NetAdapterStatistics; \
+                [pscustomobject]@{ InterfaceIndex = $_.ifIndex; ReceivedBytes = $stats.ReceivedBytes; SentBytes = $stats.SentBytes; \
+                    ReceivedUnicastPackets = $stats.ReceivedUnicastPackets; SentUnicastPackets = $stats.SentUnicastPackets } \
+            } | ConvertTo-Json -Compress";
+        let output = Command::new("powershell").args(["-NoProfile", "-Command", script]).output();
+        let Ok(output) = output else {
+            return counters;
+        };
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return counters;
+        };
+
+        let adapters = match value {
+            serde_json::Value::Array(adapters) => adapters,
+            adapter => vec![adapter],
+        };
+
+        for adapter in adapters {
+            let Some(index) = adapter["InterfaceIndex"].as_u64().map(|index| index as u32) else {
+                continue;
+            };
+            if !interfaces.iter().any(|iface| iface.index == index) {
+                continue;
+            }
+            counters.insert(index, OsInterfaceStats {
+                rx_bytes: adapter["ReceivedBytes"].as_u64().unwrap_or(0),
+                tx_bytes: adapter["SentBytes"].as_u64().unwrap_or(0),
+                rx_packets: adapter["ReceivedUnicastPackets"].as_u64().unwrap_or(0),
+                tx_packets: adapter["SentUnicastPackets"].as_u64().unwrap_or(0),
+            });
+        }
+
+        counters
+    }
+
+    /// Read default routes from the IPv4 forwarding table through `Get-NetRoute`
+    pub fn default_gateways(_interfaces: &[pnet_datalink::NetworkInterface]) -> Gateways {
+        let mut gateways = Gateways::new();
+
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-NetRoute -AddressFamily IPv4 -DestinationPrefix 0.0.0.0/0 | Sort-Object RouteMetric | Select-Object InterfaceIndex, NextHop | ConvertTo-Json -Compress",
+            ])
+            .output();
+        let Ok(output) = output else {
+            return gateways;
+        };
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return gateways;
+        };
+
+        let routes = match value {
+            serde_json::Value::Array(routes) => routes,
+            route => vec![route],
+        };
+
+        for route in routes {
+            let Some(index) = route["InterfaceIndex"].as_u64() else {
+                continue;
+            };
+            let Some(next_hop) = route["NextHop"].as_str().and_then(|hop| hop.parse().ok()) else {
+                continue;
+            };
+            // Routes are sorted by metric, so keep the first one per interface
+            if next_hop != std::net::Ipv4Addr::UNSPECIFIED {
+                gateways.entry(index as u32).or_insert(next_hop);
+            }
+        }
+
+        gateways
+    }
+
+    /// Query the IPv4 MTU of every adapter through `Get-NetIPInterface`
+    pub fn mtus(_interfaces: &[pnet_datalink::NetworkInterface]) -> Mtus {
+        let mut mtus = Mtus::new();
+
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-NetIPInterface -AddressFamily IPv4 | Select-Object InterfaceIndex, NlMtu | ConvertTo-Json -Compress",
+            ])
+            .output();
+        let Ok(output) = output else {
+            return mtus;
+        };
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return mtus;
+        };
+
+        let adapters = match value {
+            serde_json::Value::Array(adapters) => adapters,
+            adapter => vec![adapter],
+        };
+
+        for adapter in adapters {
+            let (Some(index), Some(mtu)) = (adapter["InterfaceIndex"].as_u64(), adapter["NlMtu"].as_u64()) else {
+                continue;
+            };
+            if let Ok(mtu) = u16::try_from(mtu) {
+                mtus.insert(index as u32, mtu);
+            }
+        }
+
+        mtus
+    }
+
+    /// Query the cost of each connection profile through the WinRT
+    /// `NetworkInformation` API. Fixed and variable cost plans are metered.
+    pub fn metered(_interfaces: &[pnet_datalink::NetworkInterface]) -> Metered {
+        let mut metered = Metered::new();
+
+        let script = "$null = [Windows.Networking.Connectivity.NetworkInformation, Windows.Networking.Connectivity, ContentType = WindowsRuntime]; \
+            $adapters = Get-NetAdapter; \
+            [Windows.Networking.Connectivity.NetworkInformation]::GetConnectionProfiles() | Where-Object { $_.NetworkAdapter } | ForEach-Object { \
+                $guid = '{' + $_.NetworkAdapter.NetworkAdapterId.ToString().ToUpper() + '}'; \
+                $adapter = $adapters | Where-Object InterfaceGuid -eq $guid; \
+                if ($adapter) { [pscustomobject]@{ InterfaceIndex = $adapter.ifIndex; NetworkCostType = $_.GetConnectionCost().NetworkCostType.ToString() } } \
+            } | ConvertTo-Json -Compress";
+        let output = Command::new("powershell").args(["-NoProfile", "-Command", script]).output();
+        let Ok(output) = output else {
+            return metered;
+        };
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return metered;
+        };
+
+        let profiles = match value {
+            serde_json::Value::Array(profiles) => profiles,
+            profile => vec![profile],
+        };
+
+        for profile in profiles {
+            let (Some(index), Some(cost)) = (profile["InterfaceIndex"].as_u64(), profile["NetworkCostType"].as_str()) else {
+                continue;
+            };
+            match cost {
+                "Fixed" | "Variable" => metered.insert(index as u32, true),
+                "Unrestricted" => metered.insert(index as u32, false),
+                _ => continue,
+            };
+        }
+
+        metered
+    }
+
+    /// Query link speed and media type for all adapters through `Get-NetAdapter`
+    pub fn link_info(_interfaces: &[pnet_datalink::NetworkInterface]) -> LinkInfo {
+        let mut info = LinkInfo::new();
+
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-NetAdapter | Select-Object InterfaceIndex, ReceiveLinkSpeed, PhysicalMediaType, Virtual | ConvertTo-Json -Compress",
+            ])
+            .output();
+        let Ok(output) = output else {
+            return info;
+        };
+        let Ok(value) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return info;
+        };
+
+        // ConvertTo-Json emits a bare object when there is only one adapter
+        let adapters = match value {
+            serde_json::Value::Array(adapters) => adapters,
+            adapter => vec![adapter],
+        };
+
+        for adapter in adapters {
+            let Some(index) = adapter["InterfaceIndex"].as_u64() else {
+                continue;
+            };
+            let speed = adapter["ReceiveLinkSpeed"]
+                .as_u64()
+                .filter(|bps| *bps > 0)
+                .map(|bps| bps / 1_000_000);
+            let interface_type = if adapter["Virtual"].as_bool().unwrap_or(false) {
+                InterfaceType::Virtual
+            } else {
+                match adapter["PhysicalMediaType"].as_str().unwrap_or("") {
+                    "802.3" => InterfaceType::Ethernet,
+                    "Native 802.11" | "Wireless LAN" => InterfaceType::WiFi,
+                    "Wireless WAN" => InterfaceType::Cellular,
+                    _ => InterfaceType::Unknown,
+                }
+            };
+            info.insert(index as u32, (speed, interface_type));
+        }
+
+        info
+    }
 }
 
 #[cfg(unix)]
 mod unix_impl {
-    // Use Unix-specific methods:
-    // - Parse /proc/net/dev (Linux)
-    // - Use getifaddrs (macOS/BSD)
-    // - Parse ip route show (Linux)
-}
\ No newline at end of file
+    use super::{Gateways, InterfaceType, LinkInfo, Metered, Mtus, OsCounters, OsInterfaceStats, PhysicalInterface};
+    use std::net::Ipv4Addr;
+    use std::path::Path;
+    use std::process::Command;
+
+    const RTF_UP: u32 = 0x1;
+    const RTF_GATEWAY: u32 = 0x2;
+
+    // ARPHRD_* values reported in /sys/class/net/<name>/type
+    const ARPHRD_ETHER: u32 = 1;
+    const ARPHRD_PPP: u32 = 512;
+    const ARPHRD_RAWIP: u32 = 519;
+    const ARPHRD_TUNNEL: u32 = 768;
+    const ARPHRD_IPGRE: u32 = 778;
+    const ARPHRD_NONE: u32 = 65534;
+
+    /// Read link speed and type from sysfs. Only Linux has sysfs; other Unixes
+    /// report unknown speed and type.
+    pub fn link_info(interfaces: &[pnet_datalink::NetworkInterface]) -> LinkInfo {
+        interfaces
+            .iter()
+            .map(|iface| {
+                let base = Path::new("/sys/class/net").join(&iface.name);
+                (iface.index, (read_speed(&base), read_type(&base)))
+            })
+            .collect()
+    }
+
+    /// Read the MTU from sysfs. Other Unixes fall back to the default MTU.
+    pub fn mtus(interfaces: &[pnet_datalink::NetworkInterface]) -> Mtus {
+        interfaces
+            .iter()
+            .filter_map(|iface| {
+                let mtu = std::fs::read_to_string(Path::new("/sys/class/net").join(&iface.name).join("mtu")).ok()?;
+                Some((iface.index, mtu.trim().parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Ask NetworkManager which devices are metered, including its guesses
+    /// (e.g. a hotspot shared by a phone). Without NetworkManager nothing is
+    /// known.
+    pub fn metered(interfaces: &[pnet_datalink::NetworkInterface]) -> Metered {
+        let Ok(output) = Command::new("nmcli")
+            .args(["-t", "-f", "GENERAL.DEVICE,GENERAL.METERED", "device", "show"])
+            .output()
+        else {
+            return Metered::new();
+        };
+        parse_nmcli_metered(&String::from_utf8_lossy(&output.stdout), interfaces)
+    }
+
+    /// Parse `nmcli -t` output, where each device is a `GENERAL.DEVICE:<name>`
+    /// line followed by a `GENERAL.METERED:<yes|no|unknown>[ (guessed)]` line
+    fn parse_nmcli_metered(output: &str, interfaces: &[pnet_datalink::NetworkInterface]) -> Metered {
+        let mut metered = Metered::new();
+        let mut device = None;
+        for line in output.lines() {
+            if let Some(name) = line.strip_prefix("GENERAL.DEVICE:") {
+                device = interfaces.iter().find(|iface| iface.name == name);
+            } else if let (Some(value), Some(iface)) = (line.strip_prefix("GENERAL.METERED:"), device) {
+                if value.starts_with("yes") {
+                    metered.insert(iface.index, true);
+                } else if value.starts_with("no") {
+                    metered.insert(iface.index, false);
+                }
+            }
+        }
+        metered
+    }
+
+    /// Read interface counters from /proc/net/dev. Only Linux has procfs;
+    /// other Unixes report none.
+    pub fn os_counters(interfaces: &[PhysicalInterface]) -> OsCounters {
+        std::fs::read_to_string("/proc/net/dev")
+            .map(|table| parse_proc_net_dev(&table, interfaces))
+            .unwrap_or_default()
+    }
+
+    /// Parse /proc/net/dev, where after two header lines each interface is
+    /// `<name>: <rx bytes> <rx packets> <6 more rx fields> <tx bytes> <tx packets> ...`
+    pub(super) fn parse_proc_net_dev(table: &str, interfaces: &[PhysicalInterface]) -> OsCounters {
+        table
+            .lines()
+            .skip(2)
+            .filter_map(|line| {
+                let (name, fields) = line.split_once(':')?;
+                let iface = interfaces.iter().find(|iface| iface.name == name.trim())?;
+                let fields: Vec<u64> = fields.split_whitespace().map(|field| field.parse().ok()).collect::<Option<_>>()?;
+                if fields.len() < 10 {
+                    return None;
+                }
+                Some((iface.index, OsInterfaceStats {
+                    rx_bytes: fields[0],
+                    rx_packets: fields[1],
+                    tx_bytes: fields[8],
+                    tx_packets: fields[9],
+                }))
+            })
+            .collect()
+    }
+
+    /// Read default routes from /proc/net/route, the table behind `ip route`.
+    /// Only Linux has procfs; other Unixes report no gateways.
+    pub fn default_gateways(interfaces: &[pnet_datalink::NetworkInterface]) -> Gateways {
+        let mut gateways = Gateways::new();
+        let Ok(table) = std::fs::read_to_string("/proc/net/route") else {
+            return gateways;
+        };
+
+        // Columns: Iface Destination Gateway Flags RefCnt Use Metric Mask ...
+        let mut routes: Vec<(&str, Ipv4Addr, u32)> = table
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 8 || fields[1] != "00000000" || fields[7] != "00000000" {
+                    return None;
+                }
+                let flags = u32::from_str_radix(fields[3], 16).ok()?;
+                if flags & (RTF_UP | RTF_GATEWAY) != RTF_UP | RTF_GATEWAY {
+                    return None;
+                }
+                // The kernel prints the raw network-order address as a native integer
+                let gateway = Ipv4Addr::from(u32::from_str_radix(fields[2], 16).ok()?.to_ne_bytes());
+                let metric = fields[6].parse().ok()?;
+                Some((fields[0], gateway, metric))
+            })
+            .collect();
+        routes.sort_by_key(|(_, _, metric)| *metric);
+
+        for (name, gateway, _) in routes {
+            if let Some(iface) = interfaces.iter().find(|iface| iface.name == name) {
+                gateways.entry(iface.index).or_insert(gateway);
+            }
+        }
+
+        gateways
+    }
+
+    fn read_speed(base: &Path) -> Option<u64> {
+        // Drivers report -1 when the speed is unknown (e.g. WiFi, link down)
+        std::fs::read_to_string(base.join("speed"))
+            .ok()
+            .and_then(|speed| speed.trim().parse::<i64>().ok())
+            .filter(|speed| *speed > 0)
+            .map(|speed| speed as u64)
+    }
+
+    fn read_type(base: &Path) -> InterfaceType {
+        if base.join("wireless").exists() || base.join("phy80211").exists() {
+            return InterfaceType::WiFi;
+        }
+
+        let uevent = std::fs::read_to_string(base.join("uevent")).unwrap_or_default();
+        if uevent.lines().any(|line| line == "DEVTYPE=wwan") {
+            return InterfaceType::Cellular;
+        }
+
+        let arp_type = std::fs::read_to_string(base.join("type"))
+            .ok()
+            .and_then(|t| t.trim().parse::<u32>().ok());
+
+        match arp_type {
+            // Bridges, veth pairs and the like have no backing device
+            Some(ARPHRD_ETHER) if base.join("device").exists() => InterfaceType::Ethernet,
+            Some(ARPHRD_ETHER) => InterfaceType::Virtual,
+            // Point-to-point links are usually cellular modems
+            Some(ARPHRD_PPP) | Some(ARPHRD_RAWIP) => InterfaceType::Cellular,
+            Some(ARPHRD_NONE) | Some(ARPHRD_TUNNEL..=ARPHRD_IPGRE) => InterfaceType::Virtual,
+            _ => InterfaceType::Unknown,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rediscover_reuses_unchanged_interfaces() {
+        let mut previous = InterfaceManager::discover();
+        for iface in &mut previous {
+            iface.mtu = 1234;
+        }
+        let interfaces = InterfaceManager::rediscover(&previous);
+        assert_eq!(interfaces.len(), previous.len());
+        assert!(interfaces.iter().all(|iface| iface.mtu == 1234));
+
+        // An interface whose address changed is looked up again
+        for iface in &mut previous {
+            iface.ip_address = Ipv4Addr::UNSPECIFIED;
+        }
+        let interfaces = InterfaceManager::rediscover(&previous);
+        assert!(interfaces.iter().all(|iface| iface.ip_address != Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn test_parse_proc_net_dev() {
+        let eth = PhysicalInterface {
+            name: "eth0".to_string(),
+            description: String::new(),
+            ip_address: Ipv4Addr::new(192, 168, 1, 10),
+            index: 2,
+            link_speed_mbps: None,
+            interface_type: InterfaceType::Ethernet,
+            prefix_len: 24,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        };
+        let table = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     789    0    0    0     0          0         0   123456     789    0    0    0     0       0          0
+  eth0: 9876543210 6543210    0   12    0     0          0      34 1234567890 3210987    0    0    0     0       0          0
+";
+        let counters = unix_impl::parse_proc_net_dev(table, &[eth]);
+        assert_eq!(counters.len(), 1);
+        assert_eq!(counters[&2], OsInterfaceStats {
+            rx_bytes: 9876543210,
+            tx_bytes: 1234567890,
+            rx_packets: 6543210,
+            tx_packets: 3210987,
+        });
+    }
+}
@@ -0,0 +1,174 @@
+// src-tauri/src/kill_switch.rs
+use anyhow::{bail, Context, Result};
+#[cfg(any(target_os = "linux", windows))]
+use std::io::Write;
+#[cfg(any(target_os = "linux", windows))]
+use std::process::{Command, Stdio};
+use tracing::info;
+
+/// Marks the sockets NetBoost Pro opens itself, so the kill-switch lets
+/// probes and relay traffic out of the physical interfaces
+pub const SOCKET_MARK: u32 = 0x4e42;
+
+#[cfg(target_os = "linux")]
+const NFT_TABLE: &str = "netboost_kill_switch";
+
+#[cfg(windows)]
+const FIREWALL_GROUP: &str = "NetBoost Pro Kill Switch";
+
+/// Block all outbound traffic except through the TUN named `tun`, over
+/// loopback, DHCP, and NetBoost Pro's own sockets. The rules outlive the
+/// process, so if the service dies nothing leaks out of the default
+/// interface; only [`disable`] removes them. Replaces any rules left from
+/// an earlier run.
+pub fn enable(tun: &str) -> Result<()> {
+    install(tun)?;
+    info!("Kill-switch on: traffic can only leave through '{}'", tun);
+    Ok(())
+}
+
+/// Remove the kill-switch's rules, restoring normal connectivity. Does
+/// nothing if none are installed.
+pub fn disable() -> Result<()> {
+    remove()?;
+    info!("Kill-switch off");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn install(tun: &str) -> Result<()> {
+    run_with_input("nft", &["-f", "-"], &nft_ruleset(tun)).context("Failed to install the kill-switch's nftables rules")
+}
+
+#[cfg(target_os = "linux")]
+fn remove() -> Result<()> {
+    run_with_input("nft", &["-f", "-"], &format!("table inet {0}\ndelete table inet {0}\n", NFT_TABLE))
+        .context("Failed to remove the kill-switch's nftables rules")
+}
+
+#[cfg(windows)]
+fn install(tun: &str) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to find the NetBoost Pro executable")?;
+    run_with_input("powershell", &["-NoProfile", "-Command", "-"], &firewall_enable_script(tun, &exe.to_string_lossy()))
+        .context("Failed to install the kill-switch's firewall rules")
+}
+
+#[cfg(windows)]
+fn remove() -> Result<()> {
+    run_with_input("powershell", &["-NoProfile", "-Command", "-"], &firewall_disable_script())
+        .context("Failed to remove the kill-switch's firewall rules")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn install(_tun: &str) -> Result<()> {
+    bail!("The kill-switch isn't supported on this platform yet; turn it off to start")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn remove() -> Result<()> {
+    Ok(())
+}
+
+/// Declaring the table before deleting it makes replacing it atomic and
+/// safe when there is none yet
+#[cfg(target_os = "linux")]
+fn nft_ruleset(tun: &str) -> String {
+    format!(
+        r#"table inet {table}
+delete table inet {table}
+table inet {table} {{
+    chain output {{
+        type filter hook output priority 0; policy drop;
+        oifname "lo" accept
+        oifname "{tun}" accept
+        meta mark {mark:#x} accept
+        ct direction reply accept
+        udp sport 68 udp dport 67 accept
+    }}
+}}
+"#,
+        table = NFT_TABLE,
+        tun = tun,
+        mark = SOCKET_MARK
+    )
+}
+
+/// Outbound traffic is blocked by default on every firewall profile, with
+/// exceptions for the TUN, NetBoost Pro itself and DHCP. The profiles'
+/// previous default is kept in the rules' descriptions, so it can be
+/// restored even after a crash.
+#[cfg(windows)]
+fn firewall_enable_script(tun: &str, exe: &str) -> String {
+    format!(
+        r#"$ErrorActionPreference = 'Stop'
+$group = '{group}'
+$existing = Get-NetFirewallRule -Group $group -ErrorAction SilentlyContinue
+if ($existing) {{
+    $previous = ($existing | Select-Object -First 1).Description
+    $existing | Remove-NetFirewallRule
+}} else {{
+    $previous = (Get-NetFirewallProfile | ForEach-Object {{ "$($_.Name)=$($_.DefaultOutboundAction)" }}) -join ','
+}}
+New-NetFirewallRule -Group $group -DisplayName 'NetBoost Pro TUN' -Description $previous -Direction Outbound -InterfaceAlias '{tun}' -Action Allow | Out-Null
+New-NetFirewallRule -Group $group -DisplayName 'NetBoost Pro' -Description $previous -Direction Outbound -Program '{exe}' -Action Allow | Out-Null
+New-NetFirewallRule -Group $group -DisplayName 'NetBoost Pro DHCP' -Description $previous -Direction Outbound -Protocol UDP -LocalPort 68 -RemotePort 67 -Action Allow | Out-Null
+Set-NetFirewallProfile -All -DefaultOutboundAction Block
+"#,
+        group = FIREWALL_GROUP,
+        tun = tun.replace('\'', "''"),
+        exe = exe.replace('\'', "''")
+    )
+}
+
+#[cfg(windows)]
+fn firewall_disable_script() -> String {
+    format!(
+        r#"$ErrorActionPreference = 'Stop'
+$rules = Get-NetFirewallRule -Group '{group}' -ErrorAction SilentlyContinue
+if ($rules) {{
+    foreach ($entry in (($rules | Select-Object -First 1).Description -split ',')) {{
+        $name, $action = $entry -split '='
+        Set-NetFirewallProfile -Name $name -DefaultOutboundAction $action
+    }}
+    $rules | Remove-NetFirewallRule
+}}
+"#,
+        group = FIREWALL_GROUP
+    )
+}
+
+#[cfg(any(target_os = "linux", windows))]
+pub(crate) fn run_with_input(program: &str, args: &[&str], input: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    child
+        .stdin
+        .take()
+        .context("No stdin")?
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ruleset_only_lets_the_tun_and_our_own_sockets_out() {
+        let ruleset = nft_ruleset("netboost0");
+        assert!(ruleset.contains("policy drop;"));
+        assert!(ruleset.contains(r#"oifname "netboost0" accept"#));
+        assert!(ruleset.contains("meta mark 0x4e42 accept"));
+        // Replaces rules left behind by a crash instead of failing on them
+        assert!(ruleset.starts_with("table inet netboost_kill_switch\ndelete table inet netboost_kill_switch\n"));
+    }
+}
@@ -1,23 +1,97 @@
 // src-tauri/src/lib.rs
+mod arp;
+mod cidr;
+mod classifier;
+mod config;
+mod control;
+mod daemon;
+mod datalink;
+mod diagnostics;
+mod dns;
+mod error;
+mod flow_log;
+mod health;
+mod logging;
+mod mtu;
+mod net_util;
 mod virtual_adapter;
 mod packet_router;
 mod performance_monitor;
+mod nat;
+mod packet_batch;
+mod packet_queue;
+mod packet_sampler;
+mod pcap;
+mod privileges;
+mod qos;
+mod rate_limiter;
+mod reassembly;
+mod relay;
+mod routes;
+mod service;
+mod settings;
+mod simulation;
+mod speed_test;
+mod vpn;
+#[cfg(feature = "metrics")]
+mod metrics_server;
+pub mod interface_filter;
+pub mod wire;
 pub mod interface_manager;
+mod kill_switch;
 
 // Re-export commonly used types for easier access
-pub use interface_manager::{InterfaceManager, PhysicalInterface};
-pub use packet_router::LoadBalancingMode;
-pub use performance_monitor::PerformanceStats;
+pub use cidr::Ipv4Cidr;
+pub use classifier::{ClassificationRule, PortRange, RuleProtocol};
+pub use config::{ConfigWatcher, NetBoostConfig};
+pub use control::{default_control_path, send_control_request, start_control_server, ControlRequest, ControlStatus};
+pub use daemon::{default_daemon_log_file, default_pid_file, process_running, read_pid_file, spawn_detached, PidFile};
+pub use diagnostics::{diagnose, CheckStatus, DiagnosticCheck, DiagnosticsReport};
+pub use dns::{DnsCache, DnsSettings};
+pub use error::NetBoostError;
+pub use flow_log::{FlowLog, FlowLogSettings};
+pub use health::{HealthConfig, HealthStatus};
+pub use interface_filter::{InterfaceFilter, InterfaceRules};
+pub use interface_manager::{InterfaceManager, OsInterfaceStats, PhysicalInterface};
+pub use kill_switch::disable as disable_kill_switch;
+pub use logging::{init_logging, LogSettings};
+pub use packet_router::{BypassRoute, InterfaceGroup, LoadBalancingMode, ModeInfo, RoutingDecision};
+pub use packet_sampler::{PacketSampler, SamplerSettings};
+pub use performance_monitor::{DropReason, DropReasons, HistorySample, PerformanceMonitor, PerformanceStats};
+pub use privileges::{check_privileges, has_privileges};
+pub use qos::{DscpConfig, SchedulerConfig, SchedulingPolicy};
+pub use reassembly::{Reassembled, Reassembler};
+pub use relay::RelayConfig;
+#[cfg(feature = "metrics")]
+pub use metrics_server::start_metrics_server;
+#[cfg(windows)]
+pub use service::run_as_windows_service;
+pub use service::{install_service, uninstall_service, ServiceLink, ServiceRequest, SERVICE_NAME};
+pub use settings::{InterfaceSettings, Settings};
+pub use simulation::{benchmark_interface_count, benchmark_router, simulate, ModeBenchmark, PacketSource, SimulationReport};
+pub use speed_test::{run_interface_benchmark, run_speed_test, InterfaceBenchResult, InterfaceSpeed, SpeedTestConfig, SpeedTestResult};
 
+#[cfg(feature = "gui")]
+use anyhow::Context;
+#[cfg(feature = "gui")]
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use virtual_adapter::VirtualNetworkInterface;
+pub use virtual_adapter::{tun_driver_available, ShutdownReport, TunConfig, VirtualNetworkInterface};
+pub use vpn::{check_vpn, TunnelInterface, VpnPolicy, VpnReport};
+#[cfg(feature = "gui")]
 use tauri::Manager;
+#[cfg(feature = "gui")]
+use tauri::Emitter;
 
 // Global state for the application
 pub struct AppState {
-    pub virtual_interface: Arc<RwLock<Option<VirtualNetworkInterface>>>,
+    pub virtual_interface: Arc<RwLock<Option<Arc<VirtualNetworkInterface>>>>,
     pub is_running: Arc<RwLock<bool>>,
+    pub settings: Arc<RwLock<Settings>>,
+    pub performance_monitor: Arc<PerformanceMonitor>,
+    /// Applies edits to the config file loaded last
+    pub config_reloader: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
 }
 
 impl AppState {
@@ -25,8 +99,134 @@ impl AppState {
         Self {
             virtual_interface: Arc::new(RwLock::new(None)),
             is_running: Arc::new(RwLock::new(false)),
+            settings: Arc::new(RwLock::new(Settings::load())),
+            performance_monitor: Arc::new(PerformanceMonitor::new()),
+            config_reloader: Arc::new(RwLock::new(None)),
         }
     }
+
+    /// Persist the current settings, logging rather than failing the caller
+    pub async fn save_settings(&self) {
+        if let Err(e) = self.settings.read().await.save() {
+            tracing::error!("Failed to save settings: {}", e);
+        }
+    }
+
+    /// Start routing traffic with the current settings. The service runs in
+    /// a background task until [`AppState::stop`] is called or it fails; the
+    /// returned interface can be used to subscribe to its events.
+    pub async fn start(&self) -> Result<Arc<VirtualNetworkInterface>, NetBoostError> {
+        // Held until the service is up, so a second start waits and then fails
+        let mut is_running = self.is_running.write().await;
+        if *is_running {
+            return Err(NetBoostError::AlreadyRunning);
+        }
+
+        check_privileges().map_err(|e| NetBoostError::NoPrivileges(e.to_string()))?;
+        if !tun_driver_available() {
+            return Err(NetBoostError::TunDriverMissing);
+        }
+
+        tracing::info!("Starting NetBoost Pro service");
+
+        let mut settings = self.settings.read().await.clone();
+
+        // A VPN already routing traffic can break, or be broken by, our routes
+        let settings = tokio::task::spawn_blocking(move || check_vpn(&mut settings).map(|_| settings))
+            .await
+            .map_err(anyhow::Error::from)?
+            .map_err(|e| NetBoostError::VpnConflict(format!("{:#}", e)))?;
+
+        // Without an interface the service would start but drop every packet
+        let filter = settings.interface_filter.clone();
+        tokio::task::spawn_blocking(move || InterfaceManager::discover_usable(&filter))
+            .await
+            .map_err(anyhow::Error::from)?
+            .map_err(|e| NetBoostError::NoInterfaces(format!("{:#}", e)))?;
+
+        let vni = match VirtualNetworkInterface::new(settings.tun_config.clone(), Arc::clone(&self.performance_monitor)).await {
+            Ok(vni) => Arc::new(vni),
+            Err(e) => {
+                tracing::error!("Failed to start NetBoost Pro: {:#}", e);
+                return Err(e.context("Failed to start NetBoost Pro").into());
+            }
+        };
+        vni.apply_settings(&settings).await;
+
+        *self.virtual_interface.write().await = Some(Arc::clone(&vni));
+        *is_running = true;
+        drop(is_running);
+
+        // Start the virtual interface in a background task
+        let service = Arc::clone(&vni);
+        let vni_state = Arc::clone(&self.virtual_interface);
+        let running_state = Arc::clone(&self.is_running);
+        tokio::spawn(async move {
+            if let Err(e) = service.run().await {
+                tracing::error!("Virtual interface error: {:#}", e);
+            }
+            *running_state.write().await = false;
+            *vni_state.write().await = None;
+        });
+
+        Ok(vni)
+    }
+
+    /// Stop the service, resolving once queued packets have been flushed
+    pub async fn stop(&self) -> Result<ShutdownReport, NetBoostError> {
+        if !*self.is_running.read().await {
+            return Err(NetBoostError::NotRunning);
+        }
+
+        tracing::info!("Stopping NetBoost Pro service");
+
+        let result = match self.virtual_interface.read().await.as_ref() {
+            Some(vni) => vni.stop().await,
+            None => Ok(ShutdownReport::default()),
+        };
+
+        *self.is_running.write().await = false;
+        *self.virtual_interface.write().await = None;
+
+        result.map_err(|e| e.context("NetBoost Pro stopped uncleanly").into())
+    }
+
+    /// The running service's mode, or the saved one while stopped
+    pub async fn load_balancing_mode(&self) -> LoadBalancingMode {
+        match self.virtual_interface.read().await.as_ref() {
+            Some(vni) => vni.get_load_balancing_mode().await,
+            None => self.settings.read().await.load_balancing_mode,
+        }
+    }
+
+    /// Switch modes, applying to the running service if there is one and
+    /// saving the choice for the next start
+    pub async fn set_load_balancing_mode(&self, mode: LoadBalancingMode) {
+        self.settings.write().await.load_balancing_mode = mode;
+        self.save_settings().await;
+
+        if let Some(vni) = self.virtual_interface.read().await.as_ref() {
+            vni.set_load_balancing_mode(mode).await;
+        }
+    }
+
+    /// Statistics for the running service
+    pub async fn performance_stats(&self) -> Result<PerformanceStats, NetBoostError> {
+        if !*self.is_running.read().await {
+            return Err(NetBoostError::NotRunning);
+        }
+
+        match self.virtual_interface.read().await.as_ref() {
+            Some(vni) => Ok(vni.get_performance_stats().await),
+            None => Err(NetBoostError::NotRunning),
+        }
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -38,111 +238,238 @@ fn greet(name: &str) -> String {
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn start_netboost(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let is_running = *state.is_running.read().await;
-    
-    if is_running {
-        return Err("NetBoost Pro is already running".to_string());
-    }
+async fn start_netboost(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let vni = state.start().await?;
 
-    println!("Starting NetBoost Pro service...");
-    
-    match VirtualNetworkInterface::new().await {
-        Ok(vni) => {
-            *state.virtual_interface.write().await = Some(vni);
-            *state.is_running.write().await = true;
-            
-            // Start the virtual interface in a background task
-            let vni_state = Arc::clone(&state.virtual_interface);
-            let running_state = Arc::clone(&state.is_running);
-            
-            tauri::async_runtime::spawn(async move {
-                if let Some(vni) = vni_state.write().await.take() {
-                    if let Err(e) = vni.run().await {
-                        eprintln!("Virtual interface error: {}", e);
+    // Keep the GUI interface list in sync with hotplugged adapters
+    let mut interface_rx = vni.subscribe_interface_events();
+    let interface_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match interface_rx.recv().await {
+                Ok(event) => {
+                    if let Err(e) = interface_app.emit("interfaces-changed", &event) {
+                        tracing::warn!("Failed to emit interface change: {}", e);
                     }
                 }
-                *running_state.write().await = false;
-            });
-            
-            Ok("NetBoost Pro started successfully".to_string())
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
         }
-        Err(e) => {
-            eprintln!("Failed to start NetBoost Pro: {}", e);
-            Err(format!("Failed to start NetBoost Pro: {}", e))
+    });
+
+    // Push stats to the frontend so it doesn't have to poll
+    let mut stats_rx = vni.subscribe_stats();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match stats_rx.recv().await {
+                Ok(stats) => {
+                    if let Err(e) = app.emit("performance-update", &stats) {
+                        tracing::warn!("Failed to emit performance update: {}", e);
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
         }
-    }
+    });
+
+    Ok("NetBoost Pro started successfully".to_string())
 }
 
+/// Remove kill-switch rules left behind when the service died, restoring
+/// connectivity
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn stop_netboost(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let is_running = *state.is_running.read().await;
-    
-    if !is_running {
-        return Err("NetBoost Pro is not running".to_string());
+async fn clear_kill_switch(state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    if *state.is_running.read().await {
+        return Err(NetBoostError::StopRequired("kill-switch"));
     }
+    tokio::task::spawn_blocking(disable_kill_switch).await.map_err(anyhow::Error::from)??;
+    Ok("Kill-switch disabled".to_string())
+}
 
-    println!("Stopping NetBoost Pro service...");
-    
-    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
-        vni.stop().await;
-    }
-    
-    *state.is_running.write().await = false;
-    *state.virtual_interface.write().await = None;
-    
-    Ok("NetBoost Pro stopped successfully".to_string())
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn stop_netboost(state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let report = state.stop().await?;
+    Ok(format!(
+        "NetBoost Pro stopped successfully ({} queued packets flushed, {} dropped)",
+        report.drained, report.dropped
+    ))
 }
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn get_service_status(state: tauri::State<'_, AppState>) -> Result<ServiceStatus, String> {
+async fn get_service_status(state: tauri::State<'_, AppState>) -> Result<ServiceStatus, NetBoostError> {
     let is_running = *state.is_running.read().await;
 
-    let (uptime_seconds, virtual_interface_name) = if is_running {
+    let (uptime_seconds, virtual_interface_name, total_bytes_uploaded, total_bytes_downloaded) = if is_running {
         if let Some(vni) = state.virtual_interface.read().await.as_ref() {
             let stats = vni.get_performance_stats().await;
-            (Some(stats.uptime.as_secs()), vni.name().ok())
+            (Some(stats.uptime.as_secs()), vni.name().ok(), stats.upload_bytes, stats.download_bytes)
         } else {
-            (None, None)
+            (None, None, 0, 0)
         }
     } else {
-        (None, None)
+        (None, None, 0, 0)
+    };
+    let (active_interface_count, aggregation_enabled, load_balancing_mode) = match state.virtual_interface.read().await.as_ref() {
+        Some(vni) => (
+            vni.active_interface_count().await,
+            vni.is_connection_aggregation_enabled().await,
+            vni.get_load_balancing_mode().await,
+        ),
+        None => {
+            let settings = state.settings.read().await;
+            (0, settings.connection_aggregation, settings.load_balancing_mode)
+        }
     };
-    
+
     Ok(ServiceStatus {
         is_running,
         uptime_seconds,
         virtual_interface_name,
+        active_interface_count,
+        aggregation_enabled,
+        load_balancing_mode: load_balancing_mode.to_string(),
+        total_bytes_uploaded,
+        total_bytes_downloaded,
     })
 }
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn get_performance_stats(state: tauri::State<'_, AppState>) -> Result<PerformanceStats, String> {
-    let is_running = *state.is_running.read().await;
-    
-    if !is_running {
-        return Err("NetBoost Pro is not running".to_string());
+async fn get_performance_stats(state: tauri::State<'_, AppState>) -> Result<PerformanceStats, NetBoostError> {
+    state.performance_stats().await
+}
+
+/// Effective combined speed in bits per second
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_aggregate_bandwidth(state: tauri::State<'_, AppState>) -> Result<u64, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
     }
 
     if let Some(vni) = state.virtual_interface.read().await.as_ref() {
-        Ok(vni.get_performance_stats().await)
+        Ok(vni.get_aggregate_bandwidth().await)
     } else {
-        Err("Virtual interface not available".to_string())
+        Err(NetBoostError::NotRunning)
     }
 }
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn get_network_interfaces() -> Result<Vec<PhysicalInterface>, String> {
+async fn reset_performance_stats(state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    // Session counters and the uptime clock restart together so bandwidth stays per-second
+    state.performance_monitor.reset_stats().await;
+    Ok("Performance statistics reset".to_string())
+}
+
+/// Write the recorded performance history to a CSV file, by default a
+/// timestamped one next to the flow log
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn export_performance_history(path: Option<String>, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => dirs::data_local_dir()
+            .context("No data directory to save the report in")?
+            .join("netboost-pro")
+            .join(format!("history-{}.csv", chrono::Local::now().format("%Y%m%d-%H%M%S"))),
+    };
+    let samples = state.performance_monitor.export_history_csv(&path).await?;
+    Ok(format!("Wrote {} samples to {}", samples, path.display()))
+}
+
+/// `[timestamp_ms, upload_bps, download_bps]` points from the last
+/// `window_secs` seconds, to backfill the speed graph
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_throughput_history(window_secs: u64, state: tauri::State<'_, AppState>) -> Result<Vec<(u64, u64, u64)>, NetBoostError> {
+    Ok(state.performance_monitor.get_throughput_history(std::time::Duration::from_secs(window_secs)).await)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_interface_health(state: tauri::State<'_, AppState>) -> Result<HashMap<u32, HealthStatus>, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    match state.virtual_interface.read().await.as_ref() {
+        Some(vni) => Ok(vni.get_interface_health().await),
+        None => Err(NetBoostError::NotRunning),
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_network_interfaces() -> Result<Vec<PhysicalInterface>, NetBoostError> {
     match InterfaceManager::new() {
         Ok(manager) => {
             // Return all discovered interfaces
             Ok(manager.get_all_interfaces().clone())
         }
-        Err(e) => Err(format!("Failed to discover interfaces: {}", e)),
+        Err(e) => Err(e.context("Failed to discover interfaces").into()),
+    }
+}
+
+/// Bytes and packets the OS has counted on each interface since it came
+/// up, across all applications, keyed by interface index
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_os_interface_stats(state: tauri::State<'_, AppState>) -> Result<HashMap<u32, OsInterfaceStats>, NetBoostError> {
+    let interfaces = match state.virtual_interface.read().await.as_ref() {
+        Some(vni) => vni.interfaces().await,
+        None => InterfaceManager::new()
+            .context("Failed to discover interfaces")?
+            .get_all_interfaces()
+            .clone(),
+    };
+    tokio::task::spawn_blocking(move || InterfaceManager::os_interface_stats(&interfaces))
+        .await
+        .context("Failed to read interface counters")
+        .map_err(NetBoostError::from)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_primary_interface(state: tauri::State<'_, AppState>) -> Result<Option<PhysicalInterface>, NetBoostError> {
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        return Ok(vni.primary_interface().await);
+    }
+    let manager = InterfaceManager::new().context("Failed to discover interfaces")?;
+    let settings = state.settings.read().await;
+    Ok(manager.get_preferred_primary(settings.primary_interface.as_deref()).cloned())
+}
+
+/// Choose the primary interface by index, or go back to the first
+/// discovered one with `None`
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_primary_interface(index: Option<u32>, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let vni = state.virtual_interface.read().await.clone();
+    let name = match (index, &vni) {
+        (Some(index), Some(vni)) => Some(vni.set_primary_interface(index).await.map_err(NetBoostError::invalid_input)?.name),
+        (Some(index), None) => {
+            let manager = InterfaceManager::new().context("Failed to discover interfaces")?;
+            Some(manager.get_interface(index).map_err(NetBoostError::invalid_input)?.name.clone())
+        }
+        (None, Some(vni)) => {
+            vni.set_primary_interface_name(None).await;
+            None
+        }
+        (None, None) => None,
+    };
+
+    state.settings.write().await.primary_interface = name.clone();
+    state.save_settings().await;
+
+    match name {
+        Some(name) => Ok(format!("Primary interface set to {}", name)),
+        None => Ok("Primary interface reset to the first discovered interface".to_string()),
     }
 }
 
@@ -151,32 +478,568 @@ async fn get_network_interfaces() -> Result<Vec<PhysicalInterface>, String> {
 async fn set_load_balancing_mode(
     mode: String,
     state: tauri::State<'_, AppState>,
-) -> Result<String, String> {
-    let is_running = *state.is_running.read().await;
-    
-    if !is_running {
-        return Err("NetBoost Pro is not running".to_string());
-    }
+) -> Result<String, NetBoostError> {
+    let balancing_mode: LoadBalancingMode = mode.parse().map_err(NetBoostError::invalid_input)?;
+
+    // Remember the choice even when stopped so it applies on the next start
+    state.set_load_balancing_mode(balancing_mode).await;
+    Ok(format!("Load balancing mode set to: {}", balancing_mode))
+}
+
+/// Apply a TOML config file on top of the saved settings and keep the
+/// result, then keep applying it whenever the file is saved. TUN changes
+/// take effect on the next start.
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn load_config(path: String, app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let config = NetBoostConfig::load(std::path::Path::new(&path)).map_err(NetBoostError::invalid_input)?;
+    let watcher = ConfigWatcher::new(std::path::Path::new(&path))?;
 
-    let balancing_mode = match mode.as_str() {
-        "round_robin" => LoadBalancingMode::RoundRobin,
-        "latency_based" => LoadBalancingMode::LatencyBased,
-        "bandwidth_based" => LoadBalancingMode::BandwidthBased,
-        "balanced" => LoadBalancingMode::Balanced,
-        _ => return Err("Invalid load balancing mode".to_string()),
+    let settings = {
+        let mut settings = state.settings.write().await;
+        config.apply_to(&mut settings).map_err(NetBoostError::invalid_input)?;
+        settings.clone()
     };
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.apply_settings(&settings).await;
+    }
+
+    let reloader = tokio::spawn(reload_config(watcher, app, Arc::clone(&state.settings), Arc::clone(&state.virtual_interface)));
+    if let Some(previous) = state.config_reloader.write().await.replace(reloader) {
+        previous.abort();
+    }
+
+    Ok(format!("Loaded config from {}", path))
+}
+
+/// Apply each valid new version of a config file to the settings and the
+/// running service, and tell the frontend
+#[cfg(feature = "gui")]
+async fn reload_config(
+    mut watcher: ConfigWatcher,
+    app: tauri::AppHandle,
+    settings: Arc<RwLock<Settings>>,
+    virtual_interface: Arc<RwLock<Option<Arc<VirtualNetworkInterface>>>>,
+) {
+    while let Some(config) = watcher.next().await {
+        let (previous, updated) = {
+            let mut settings = settings.write().await;
+            let previous = settings.clone();
+            if let Err(e) = config.apply_to(&mut settings) {
+                tracing::error!("Keeping the previous config: {:#}", e);
+                continue;
+            }
+            (previous, settings.clone())
+        };
+        if let Err(e) = updated.save() {
+            tracing::error!("Failed to save settings: {}", e);
+        }
+        if let Some(vni) = virtual_interface.read().await.as_ref() {
+            vni.apply_config_changes(&previous, &updated).await;
+        }
+        tracing::info!("Reloaded config from {}", watcher.path().display());
+        if let Err(e) = app.emit("settings-reloaded", &updated) {
+            tracing::warn!("Failed to emit settings reload: {}", e);
+        }
+    }
+}
+
+/// Every load balancing mode, in the order they should be offered
+#[cfg(feature = "gui")]
+#[tauri::command]
+fn list_load_balancing_modes() -> Vec<ModeInfo> {
+    LoadBalancingMode::ALL.iter().map(LoadBalancingMode::info).collect()
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_load_balancing_mode(state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    Ok(state.load_balancing_mode().await.to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_interface_enabled(
+    index: u32,
+    enabled: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        let interface = vni.find_interface(index).await
+            .ok_or(NetBoostError::UnknownInterface(index))?;
+        vni.set_interface_enabled(index, enabled).await;
+
+        state.settings.write().await.interface_mut(&interface.name).enabled = enabled;
+        state.save_settings().await;
+
+        Ok(format!("Interface {} {}", interface.name, if enabled { "enabled" } else { "disabled" }))
+    } else {
+        Err(NetBoostError::NotRunning)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_interface_weight(
+    index: u32,
+    weight: f32,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, NetBoostError> {
+    if !weight.is_finite() || weight <= 0.0 {
+        return Err(NetBoostError::InvalidInput("Interface weight must be a positive number".to_string()));
+    }
+
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        let interface = vni.find_interface(index).await
+            .ok_or(NetBoostError::UnknownInterface(index))?;
+        vni.set_interface_weight(index, weight).await;
+
+        state.settings.write().await.interface_mut(&interface.name).weight = weight;
+        state.save_settings().await;
+
+        Ok(format!("Interface {} weight set to {}", interface.name, weight))
+    } else {
+        Err(NetBoostError::NotRunning)
+    }
+}
 
-    if let Some(vni) = state.virtual_interface.write().await.as_mut() {
-        vni.set_load_balancing_mode(balancing_mode).await;
-        Ok(format!("Load balancing mode set to: {}", mode))
+/// Set an interface's failover tier for active-passive mode; tier 1 is
+/// active and higher tiers are standbys, in order
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_interface_tier(
+    index: u32,
+    tier: u8,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        let interface = vni.find_interface(index).await
+            .ok_or(NetBoostError::UnknownInterface(index))?;
+        vni.set_interface_tier(index, tier).await;
+
+        state.settings.write().await.interface_mut(&interface.name).tier = tier;
+        state.save_settings().await;
+
+        Ok(format!("Interface {} moved to tier {}", interface.name, tier))
     } else {
-        Err("Virtual interface not available".to_string())
+        Err(NetBoostError::NotRunning)
     }
 }
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn get_system_info() -> Result<SystemInfo, String> {
+async fn set_interface_rate_limit(
+    index: u32,
+    bytes_per_sec: Option<u64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    // A limit of zero means unlimited
+    let bytes_per_sec = bytes_per_sec.filter(|rate| *rate > 0);
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        let interface = vni.find_interface(index).await
+            .ok_or(NetBoostError::UnknownInterface(index))?;
+        vni.set_interface_rate_limit(index, bytes_per_sec).await;
+
+        state.settings.write().await.interface_mut(&interface.name).rate_limit_bytes_per_sec = bytes_per_sec;
+        state.save_settings().await;
+
+        Ok(match bytes_per_sec {
+            Some(rate) => format!("Interface {} limited to {} bytes/s", interface.name, rate),
+            None => format!("Interface {} rate limit removed", interface.name),
+        })
+    } else {
+        Err(NetBoostError::NotRunning)
+    }
+}
+
+/// Bond interfaces so the balancer treats them as one link and stripes
+/// the packets it sends there across them
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn create_interface_group(
+    indices: Vec<u32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<InterfaceGroup, NetBoostError> {
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        for index in &indices {
+            vni.find_interface(*index).await.ok_or(NetBoostError::UnknownInterface(*index))?;
+        }
+        vni.create_interface_group(&indices).await.map_err(NetBoostError::invalid_input)
+    } else {
+        Err(NetBoostError::NotRunning)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn remove_interface_group(index: u32, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        let group = vni
+            .remove_interface_group(index)
+            .await
+            .map_err(|e| NetBoostError::NotFound(e.to_string()))?;
+        Ok(format!("Interface group {} removed", group.name))
+    } else {
+        Err(NetBoostError::NotRunning)
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn list_interface_groups(state: tauri::State<'_, AppState>) -> Result<Vec<InterfaceGroup>, NetBoostError> {
+    match state.virtual_interface.read().await.as_ref() {
+        Some(vni) => Ok(vni.interface_groups().await),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_tun_config(state: tauri::State<'_, AppState>) -> Result<TunConfig, NetBoostError> {
+    Ok(state.settings.read().await.tun_config.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_tun_config(config: TunConfig, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    if *state.is_running.read().await {
+        return Err(NetBoostError::StopRequired("TUN configuration"));
+    }
+
+    config.validate().map_err(NetBoostError::invalid_input)?;
+    state.settings.write().await.tun_config = config;
+    state.save_settings().await;
+
+    Ok("TUN configuration updated".to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_dscp_config(state: tauri::State<'_, AppState>) -> Result<DscpConfig, NetBoostError> {
+    Ok(state.settings.read().await.dscp.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_dscp_config(config: DscpConfig, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    config.validate().map_err(NetBoostError::invalid_input)?;
+
+    state.settings.write().await.dscp = config.clone();
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_dscp_config(config).await;
+    }
+
+    Ok("DSCP marking updated".to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_scheduler_config(state: tauri::State<'_, AppState>) -> Result<SchedulerConfig, NetBoostError> {
+    Ok(state.settings.read().await.scheduler.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_scheduler_config(config: SchedulerConfig, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    config.validate().map_err(NetBoostError::invalid_input)?;
+
+    state.settings.write().await.scheduler = config.clone();
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_scheduler_config(&config);
+    }
+
+    Ok("Queue scheduling updated".to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_dns_settings(state: tauri::State<'_, AppState>) -> Result<DnsSettings, NetBoostError> {
+    Ok(state.settings.read().await.dns.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_dns_settings(settings: DnsSettings, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    settings.validate().map_err(NetBoostError::invalid_input)?;
+
+    state.settings.write().await.dns = settings.clone();
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_dns_settings(&settings).await;
+    }
+
+    Ok("DNS handling updated".to_string())
+}
+
+/// Seconds between performance stats updates
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_monitoring_interval(secs: u64, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    if !(1..=virtual_adapter::MAX_MONITORING_INTERVAL_SECS).contains(&secs) {
+        return Err(NetBoostError::InvalidInput(format!(
+            "Monitoring interval must be between 1 and {} seconds",
+            virtual_adapter::MAX_MONITORING_INTERVAL_SECS
+        )));
+    }
+
+    state.settings.write().await.monitoring_interval_secs = secs;
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_monitoring_interval(secs).map_err(NetBoostError::invalid_input)?;
+    }
+
+    Ok(format!("Performance stats now update every {}s", secs))
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_classification_rules(state: tauri::State<'_, AppState>) -> Result<Vec<ClassificationRule>, NetBoostError> {
+    Ok(state.settings.read().await.classification_rules.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn add_classification_rule(rule: ClassificationRule, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    state.settings.write().await.classification_rules.push(rule.clone());
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.add_classification_rule(rule).await;
+    }
+
+    Ok("Classification rule added".to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_pinned_destinations(state: tauri::State<'_, AppState>) -> Result<HashMap<std::net::IpAddr, String>, NetBoostError> {
+    Ok(state.settings.read().await.pinned_destinations.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn pin_destination(destination: String, index: u32, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let destination: std::net::IpAddr = destination
+        .parse()
+        .map_err(|_| NetBoostError::InvalidInput(format!("'{}' is not an IP address", destination)))?;
+
+    // Pins are saved by interface name and applied on the next start while stopped
+    let vni = state.virtual_interface.read().await.clone();
+    let name = match &vni {
+        Some(vni) => {
+            let interface = vni.find_interface(index).await
+                .ok_or(NetBoostError::UnknownInterface(index))?;
+            vni.pin_destination(destination, index).await.map_err(NetBoostError::invalid_input)?;
+            interface.name
+        }
+        None => {
+            let manager = InterfaceManager::new().context("Failed to discover interfaces")?;
+            manager.get_interface(index).map_err(|_| NetBoostError::UnknownInterface(index))?.name.clone()
+        }
+    };
+
+    state.settings.write().await.pinned_destinations.insert(destination, name.clone());
+    state.save_settings().await;
+
+    Ok(format!("{} pinned to {}", destination, name))
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn unpin_destination(destination: String, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let destination: std::net::IpAddr = destination
+        .parse()
+        .map_err(|_| NetBoostError::InvalidInput(format!("'{}' is not an IP address", destination)))?;
+
+    if state.settings.write().await.pinned_destinations.remove(&destination).is_none() {
+        return Err(NetBoostError::NotFound(format!("{} is not pinned", destination)));
+    }
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.unpin_destination(destination).await;
+    }
+
+    Ok(format!("{} unpinned", destination))
+}
+
+/// Which interface a packet to `destination`:`port` would leave through
+/// right now, and why, without sending anything. `size` feeds the size
+/// heuristic the classifier falls back on; it defaults to a typical request.
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_routing_decision_preview(
+    protocol: RuleProtocol,
+    destination: String,
+    port: u16,
+    source_port: Option<u16>,
+    size: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<RoutingDecision, NetBoostError> {
+    let destination: std::net::IpAddr = destination
+        .parse()
+        .map_err(|_| NetBoostError::InvalidInput(format!("'{}' is not an IP address", destination)))?;
+    let source = match destination {
+        std::net::IpAddr::V4(_) => std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        std::net::IpAddr::V6(_) => std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+    };
+    let flow = packet_router::FlowKey {
+        src_addr: source,
+        dst_addr: destination,
+        src_port: source_port.unwrap_or(49152),
+        dst_port: port,
+        protocol: protocol.number(),
+    };
+
+    if !*state.is_running.read().await {
+        return Err(NetBoostError::NotRunning);
+    }
+    let vni = state.virtual_interface.read().await;
+    let vni = vni.as_ref().ok_or(NetBoostError::NotRunning)?;
+    Ok(vni.preview_route(&flow, size.unwrap_or(512)).await?)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_bypass_routes(state: tauri::State<'_, AppState>) -> Result<Vec<BypassRoute>, NetBoostError> {
+    Ok(state.settings.read().await.bypass_routes.clone())
+}
+
+/// `destination` is a CIDR, an address or a host name; host names are
+/// resolved now and bypass the addresses they currently have
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn add_bypass_route(destination: String, interface: Option<String>, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let networks = Ipv4Cidr::resolve(&destination).await.map_err(NetBoostError::invalid_input)?;
+
+    {
+        let mut settings = state.settings.write().await;
+        settings.bypass_routes.retain(|route| !networks.contains(&route.cidr));
+        settings.bypass_routes.extend(networks.iter().map(|&cidr| BypassRoute { cidr, interface: interface.clone() }));
+    }
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        for &cidr in &networks {
+            vni.add_bypass_route(cidr, interface.clone()).await;
+        }
+    }
+
+    let networks: Vec<String> = networks.iter().map(ToString::to_string).collect();
+    Ok(format!("Bypassing {}", networks.join(", ")))
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn remove_bypass_route(destination: String, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let networks = Ipv4Cidr::resolve(&destination).await.map_err(NetBoostError::invalid_input)?;
+
+    {
+        let mut settings = state.settings.write().await;
+        let before = settings.bypass_routes.len();
+        settings.bypass_routes.retain(|route| !networks.contains(&route.cidr));
+        if settings.bypass_routes.len() == before {
+            return Err(NetBoostError::NotFound(format!("No bypass route for {}", destination)));
+        }
+    }
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        for &cidr in &networks {
+            vni.remove_bypass_route(cidr).await;
+        }
+    }
+
+    Ok(format!("Removed bypass route for {}", destination))
+}
+
+/// Measure each interface alone and all of them together; `config` defaults
+/// to the saved speed test settings
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn start_speed_test(config: Option<SpeedTestConfig>, state: tauri::State<'_, AppState>) -> Result<SpeedTestResult, NetBoostError> {
+    let config = match config {
+        Some(config) => config,
+        None => state.settings.read().await.speed_test.clone(),
+    };
+    let interfaces = InterfaceManager::discover();
+    Ok(run_speed_test(&config, &interfaces).await?)
+}
+
+/// Measure one interface's real download, upload, latency and loss on its
+/// own, against the saved speed test servers
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn benchmark_interface(
+    index: u32,
+    duration_secs: Option<u64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<InterfaceBenchResult, NetBoostError> {
+    let config = state.settings.read().await.speed_test.clone();
+    let interface = InterfaceManager::discover()
+        .into_iter()
+        .find(|interface| interface.index == index)
+        .ok_or(NetBoostError::UnknownInterface(index))?;
+    let duration = std::time::Duration::from_secs(duration_secs.unwrap_or(config.duration_secs));
+    Ok(run_interface_benchmark(&config, &interface, duration).await?)
+}
+
+/// Check everything routing depends on, for a setup checklist in the GUI
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn run_diagnostics(state: tauri::State<'_, AppState>) -> Result<DiagnosticsReport, NetBoostError> {
+    let settings = state.settings.read().await.clone();
+    Ok(diagnose(&settings).await)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_log_settings(state: tauri::State<'_, AppState>) -> Result<LogSettings, NetBoostError> {
+    Ok(state.settings.read().await.logging.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_log_settings(logging: LogSettings, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    state.settings.write().await.logging = logging;
+    state.save_settings().await;
+
+    // The subscriber is installed once at startup
+    Ok("Log settings saved; they take effect after a restart".to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_system_info() -> Result<SystemInfo, NetBoostError> {
     Ok(SystemInfo {
         os: std::env::consts::OS.to_string(),
         arch: std::env::consts::ARCH.to_string(),
@@ -185,6 +1048,26 @@ async fn get_system_info() -> Result<SystemInfo, String> {
     })
 }
 
+/// Lets the frontend check on launch that the backend is up and able to start
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn ping() -> Result<BackendInfo, NetBoostError> {
+    let (tun_driver, has_privileges) = tokio::task::spawn_blocking(|| (tun_driver_available(), has_privileges()))
+        .await
+        .map_err(anyhow::Error::from)?;
+    let features = [
+        ("gui", cfg!(feature = "gui")),
+        ("metrics", cfg!(feature = "metrics")),
+        ("cbor", cfg!(feature = "cbor")),
+    ];
+    Ok(BackendInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tun_driver,
+        has_privileges,
+        features: features.into_iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name.to_string()).collect(),
+    })
+}
+
 // Data structures for Tauri commands
 #[cfg(feature = "gui")]
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -192,6 +1075,14 @@ struct ServiceStatus {
     is_running: bool,
     uptime_seconds: Option<u64>,
     virtual_interface_name: Option<String>,
+    /// Interfaces new traffic can be routed over right now
+    active_interface_count: usize,
+    aggregation_enabled: bool,
+    load_balancing_mode: String,
+    /// Bytes sent out of the physical interfaces since the start or the last stats reset
+    total_bytes_uploaded: u64,
+    /// Bytes of replies received since the start or the last stats reset
+    total_bytes_downloaded: u64,
 }
 
 #[cfg(feature = "gui")]
@@ -203,22 +1094,40 @@ struct SystemInfo {
     build_date: String,
 }
 
+#[cfg(feature = "gui")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BackendInfo {
+    version: String,
+    /// Whether the TUN driver the service needs is installed
+    tun_driver: bool,
+    /// Whether the process may create the TUN interface
+    has_privileges: bool,
+    /// Cargo features compiled in
+    features: Vec<String>,
+}
+
 #[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logging
-    #[cfg(debug_assertions)]
-    {
-        env_logger::init();
-    }
-
     let app_state = AppState::new();
+    init_logging(&app_state.settings.blocking_read().logging);
+
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = app_state.settings.blocking_read().metrics_address {
+        let performance_monitor = Arc::clone(&app_state.performance_monitor);
+        let virtual_interface = Arc::clone(&app_state.virtual_interface);
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = start_metrics_server(addr, performance_monitor, virtual_interface).await {
+                tracing::error!("Failed to start metrics server: {}", e);
+            }
+        });
+    }
 
     tauri::Builder::default()
         .manage(app_state)
         .setup(|app| {
             // You can perform additional setup here if needed
-            println!("NetBoost Pro GUI initialized");
+            tracing::info!("NetBoost Pro GUI initialized");
             
             #[cfg(debug_assertions)]
             {
@@ -232,14 +1141,65 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .invoke_handler(tauri::generate_handler![
             greet,
+            ping,
+            run_diagnostics,
             start_netboost,
             stop_netboost,
             get_service_status,
             get_performance_stats,
+            get_aggregate_bandwidth,
+            start_speed_test,
+            clear_kill_switch,
+            benchmark_interface,
+            reset_performance_stats,
+            export_performance_history,
+            get_throughput_history,
+            get_interface_health,
             get_network_interfaces,
+            get_os_interface_stats,
+            get_primary_interface,
+            set_primary_interface,
+            get_load_balancing_mode,
+            list_load_balancing_modes,
+            load_config,
             set_load_balancing_mode,
+            set_interface_enabled,
+            set_interface_weight,
+            set_interface_tier,
+            set_interface_rate_limit,
+            create_interface_group,
+            remove_interface_group,
+            list_interface_groups,
             get_system_info,
-            set_connection_aggregation
+            get_tun_config,
+            set_tun_config,
+            get_dscp_config,
+            set_dscp_config,
+            get_scheduler_config,
+            set_scheduler_config,
+            get_dns_settings,
+            set_dns_settings,
+            set_monitoring_interval,
+            get_classification_rules,
+            add_classification_rule,
+            get_pinned_destinations,
+            pin_destination,
+            unpin_destination,
+            get_routing_decision_preview,
+            get_bypass_routes,
+            add_bypass_route,
+            remove_bypass_route,
+            get_log_settings,
+            set_log_settings,
+            set_connection_aggregation,
+            get_avoid_metered,
+            set_avoid_metered,
+            get_flow_log_settings,
+            set_flow_log,
+            get_relay_config,
+            set_relay_config,
+            get_interface_filter,
+            set_interface_filter
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -247,13 +1207,13 @@ pub fn run() {
 
 #[cfg(feature = "gui")]
 #[tauri::command]
-async fn set_connection_aggregation(enabled: bool, state: tauri::State<'_, AppState>) -> Result<String, String> {
-    if !*state.is_running.read().await {
-        return Err("NetBoost Pro is not running".to_string());
-    }
+async fn set_connection_aggregation(enabled: bool, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    state.settings.write().await.connection_aggregation = enabled;
+    state.save_settings().await;
 
-    // In the future, this would enable/disable the aggregation logic
-    // For now, it's just a placeholder
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_connection_aggregation(enabled).await;
+    }
 
     let message = if enabled {
         "Connection aggregation enabled"
@@ -264,9 +1224,115 @@ async fn set_connection_aggregation(enabled: bool, state: tauri::State<'_, AppSt
     Ok(message.to_string())
 }
 
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_avoid_metered(state: tauri::State<'_, AppState>) -> Result<bool, NetBoostError> {
+    Ok(state.settings.read().await.avoid_metered)
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_avoid_metered(avoid: bool, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    state.settings.write().await.avoid_metered = avoid;
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_avoid_metered(avoid).await;
+    }
+
+    let message = if avoid {
+        "File transfers will avoid metered connections"
+    } else {
+        "File transfers may use metered connections"
+    };
+    Ok(message.to_string())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_flow_log_settings(state: tauri::State<'_, AppState>) -> Result<FlowLogSettings, NetBoostError> {
+    Ok(state.settings.read().await.flow_log.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_flow_log(enabled: bool, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    let mut flow_log = state.settings.read().await.flow_log.clone();
+    flow_log.enabled = enabled;
+
+    // A log that can't be opened isn't saved as enabled
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_flow_log(&flow_log).await?;
+    }
+    state.settings.write().await.flow_log.enabled = enabled;
+    state.save_settings().await;
+
+    match flow_log.path().filter(|_| enabled) {
+        Some(path) => Ok(format!("Logging new flows to {}", path.display())),
+        None => Ok("Flow logging disabled".to_string()),
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_relay_config(state: tauri::State<'_, AppState>) -> Result<Option<RelayConfig>, NetBoostError> {
+    Ok(state.settings.read().await.relay.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_relay_config(config: Option<RelayConfig>, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    // A relay that can't be used isn't saved, or it would fail every start
+    if let Some(config) = &config {
+        config.validate().map_err(NetBoostError::invalid_input)?;
+    }
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_relay(config.as_ref()).await?;
+    }
+    state.settings.write().await.relay = config.clone();
+    state.save_settings().await;
+
+    match config {
+        Some(config) => Ok(format!("Bonding interfaces through relay {}", config.endpoint)),
+        None => Ok("Routing each flow directly".to_string()),
+    }
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn get_interface_filter(state: tauri::State<'_, AppState>) -> Result<InterfaceFilter, NetBoostError> {
+    Ok(state.settings.read().await.interface_filter.clone())
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+async fn set_interface_filter(filter: InterfaceFilter, state: tauri::State<'_, AppState>) -> Result<String, NetBoostError> {
+    state.settings.write().await.interface_filter = filter.clone();
+    state.save_settings().await;
+
+    if let Some(vni) = state.virtual_interface.read().await.as_ref() {
+        vni.set_interface_filter(filter).await;
+    }
+    Ok("Interface filter updated".to_string())
+}
+
 #[cfg(not(feature = "gui"))]
 pub fn run() {
     println!("NetBoost Pro - CLI Mode");
     println!("GUI feature not enabled. Use the CLI binary instead.");
     println!("Run with: cargo run --bin cli -- --help");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_control_without_a_running_service() {
+        let state = AppState::new();
+        assert!(matches!(state.stop().await, Err(NetBoostError::NotRunning)));
+        assert!(matches!(state.performance_stats().await, Err(NetBoostError::NotRunning)));
+        let saved = state.settings.read().await.load_balancing_mode;
+        assert_eq!(state.load_balancing_mode().await, saved);
+    }
+}
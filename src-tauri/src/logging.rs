@@ -0,0 +1,78 @@
+// src-tauri/src/logging.rs
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Level used when `RUST_LOG` isn't set; per-packet events are `trace` and stay off
+const DEFAULT_FILTER: &str = "info";
+
+/// Where to persist logs in addition to the console
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Log file path; a date suffix is added as the file rotates daily.
+    /// `None` logs to the console only.
+    pub file: Option<PathBuf>,
+    /// Rotated files to keep before the oldest is deleted
+    pub max_files: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_files: 7,
+        }
+    }
+}
+
+/// Install the global tracing subscriber. Console events go to stderr so
+/// machine-readable CLI output on stdout stays clean, and are also written to
+/// the rolling log file if one is configured. Filtering follows `RUST_LOG`,
+/// e.g. `RUST_LOG=netboost_pro_lib=trace`.
+pub fn init_logging(settings: &LogSettings) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let (appender, file_error) = match settings.file.as_ref().map(|path| file_appender(path, settings.max_files)) {
+        Some(Ok(appender)) => (Some(appender), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    let file_layer = appender.map(|appender| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(appender)
+    });
+
+    // Ignore the error if a subscriber is already installed, as in tests
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .try_init();
+
+    if let Some(e) = file_error {
+        tracing::warn!("Logging to the console only: {:#}", e);
+    }
+}
+
+fn file_appender(path: &std::path::Path, max_files: usize) -> anyhow::Result<RollingFileAppender> {
+    use anyhow::Context;
+
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Log file path {} has no file name", path.display()))?;
+    let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+
+    std::fs::create_dir_all(directory).with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(file_name.to_string_lossy())
+        .max_log_files(max_files.max(1))
+        .build(directory)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
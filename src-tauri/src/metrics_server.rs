@@ -0,0 +1,304 @@
+// src-tauri/src/metrics_server.rs
+use anyhow::{bail, Context, Result};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::performance_monitor::{DropReason, LifetimeCounters, PerformanceMonitor, PerformanceStats};
+use crate::virtual_adapter::VirtualNetworkInterface;
+
+/// Scrape requests are a request line and a few headers
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+/// How long a client gets to send its request, and then to take the response
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latest readings of one interface of the running service
+struct InterfaceGauges {
+    index: u32,
+    name: String,
+    bits_per_second: u64,
+    /// Round trip of the last health probe, if it was answered
+    latency: Option<Duration>,
+}
+
+/// Serve the performance monitor in Prometheus text exposition format at
+/// `/metrics`, with per-interface gauges while `virtual_interface` is running
+pub async fn start_metrics_server(
+    addr: SocketAddr,
+    performance_monitor: Arc<PerformanceMonitor>,
+    virtual_interface: Arc<RwLock<Option<Arc<VirtualNetworkInterface>>>>,
+) -> Result<tokio::task::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind metrics server to {}", addr))?;
+
+    info!("Serving Prometheus metrics on http://{}/metrics", addr);
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Metrics server accept error: {}", e);
+                    continue;
+                }
+            };
+
+            let performance_monitor = Arc::clone(&performance_monitor);
+            let virtual_interface = Arc::clone(&virtual_interface);
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &performance_monitor, &virtual_interface).await {
+                    debug!("Metrics server connection error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(handle)
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    performance_monitor: &PerformanceMonitor,
+    virtual_interface: &RwLock<Option<Arc<VirtualNetworkInterface>>>,
+) -> Result<()> {
+    let request_line = tokio::time::timeout(CLIENT_TIMEOUT, read_request_line(&mut stream))
+        .await
+        .context("Client didn't send a request in time")??;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let response = if path == "/metrics" {
+        let lifetime = performance_monitor.get_lifetime_counters().await;
+        let stats = performance_monitor.get_current_stats().await;
+        let interfaces = interface_gauges(virtual_interface, &stats).await;
+        let body = render_metrics(&lifetime, &stats, &interfaces);
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    tokio::time::timeout(CLIENT_TIMEOUT, async {
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    })
+    .await
+    .context("Client didn't take the response in time")??;
+    Ok(())
+}
+
+/// Throughput and probe latency of each interface of the running service
+async fn interface_gauges(
+    virtual_interface: &RwLock<Option<Arc<VirtualNetworkInterface>>>,
+    stats: &PerformanceStats,
+) -> Vec<InterfaceGauges> {
+    let Some(vni) = virtual_interface.read().await.clone() else {
+        return Vec::new();
+    };
+    let health = vni.get_interface_health().await;
+    let mut interfaces: Vec<InterfaceGauges> = vni
+        .interfaces()
+        .await
+        .into_iter()
+        .map(|interface| InterfaceGauges {
+            bits_per_second: stats.interfaces.get(&interface.index).map_or(0, |iface| iface.current_bps),
+            latency: health
+                .get(&interface.index)
+                .and_then(|status| status.last_latency_ms)
+                .map(|ms| Duration::from_secs_f64(ms / 1000.0)),
+            index: interface.index,
+            name: interface.name,
+        })
+        .collect();
+    interfaces.sort_by_key(|interface| interface.index);
+    interfaces
+}
+
+/// Read the request head, up to `MAX_REQUEST_LEN` bytes, and return its first line
+async fn read_request_line<R: AsyncRead + Unpin>(reader: &mut R) -> Result<String> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buf.len() >= MAX_REQUEST_LEN {
+            bail!("Request is longer than {} bytes", MAX_REQUEST_LEN);
+        }
+        let len = reader.read(&mut chunk).await?;
+        if len == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..len]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    Ok(request.lines().next().unwrap_or("").to_string())
+}
+
+/// Render counters from the lifetime totals and gauges from the current session
+fn render_metrics(lifetime: &LifetimeCounters, stats: &PerformanceStats, readings: &[InterfaceGauges]) -> String {
+    let mut out = String::new();
+
+    let counters = [
+        ("netboost_packets_received_total", "Packets read from the TUN device", lifetime.packets_received),
+        ("netboost_packets_forwarded_total", "Packets forwarded to a physical interface", lifetime.packets_forwarded),
+        ("netboost_packets_duplicated_total", "Extra packet copies sent in redundant mode", lifetime.packets_duplicated),
+        ("netboost_bytes_duplicated_total", "Bytes of extra packet copies sent in redundant mode", lifetime.bytes_duplicated),
+        ("netboost_circuit_breaker_trips_total", "Interfaces taken out of rotation after repeated send failures", lifetime.circuit_breaker_trips),
+        ("netboost_flows_migrated_total", "Flows moved off an interface after it went down", lifetime.flows_migrated),
+        ("netboost_low_confidence_fallbacks_total", "Packets sent to the primary interface because the balancer's pick was below the confidence threshold", lifetime.low_confidence_fallbacks),
+        ("netboost_dns_cache_hits_total", "DNS lookups answered from the cache", lifetime.dns_cache_hits),
+        ("netboost_reassembly_timeouts_total", "Fragmented datagrams dropped before all their fragments arrived", lifetime.reassembly_timeouts),
+        ("netboost_bytes_received_total", "Bytes read from the TUN device", lifetime.bytes_received),
+        ("netboost_bytes_forwarded_total", "Bytes forwarded to a physical interface", lifetime.bytes_forwarded),
+        ("netboost_packets_returned_total", "Reply packets written back into the TUN device", lifetime.packets_returned),
+        ("netboost_bytes_returned_total", "Reply bytes written back into the TUN device", lifetime.bytes_returned),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    let _ = writeln!(out, "# HELP netboost_packets_dropped_total Packets that were not forwarded, by reason");
+    let _ = writeln!(out, "# TYPE netboost_packets_dropped_total counter");
+    for reason in DropReason::ALL {
+        let _ = writeln!(out, "netboost_packets_dropped_total{{reason=\"{}\"}} {}", reason.as_str(), lifetime.packets_dropped.get(reason));
+    }
+
+    let mut interfaces: Vec<_> = lifetime.interfaces.iter().collect();
+    interfaces.sort_by_key(|(index, _)| **index);
+
+    let _ = writeln!(out, "# HELP netboost_interface_packets_forwarded_total Packets forwarded per interface");
+    let _ = writeln!(out, "# TYPE netboost_interface_packets_forwarded_total counter");
+    for (index, iface) in &interfaces {
+        let _ = writeln!(
+            out,
+            "netboost_interface_packets_forwarded_total{{interface=\"{}\",index=\"{}\"}} {}",
+            escape_label(&iface.name), index, iface.packets_forwarded
+        );
+    }
+
+    let _ = writeln!(out, "# HELP netboost_interface_bytes_forwarded_total Bytes forwarded per interface");
+    let _ = writeln!(out, "# TYPE netboost_interface_bytes_forwarded_total counter");
+    for (index, iface) in &interfaces {
+        let _ = writeln!(
+            out,
+            "netboost_interface_bytes_forwarded_total{{interface=\"{}\",index=\"{}\"}} {}",
+            escape_label(&iface.name), index, iface.bytes_forwarded
+        );
+    }
+
+    let mut traffic_types: Vec<_> = lifetime.traffic_types.iter().collect();
+    traffic_types.sort_by_key(|(traffic_type, _)| **traffic_type);
+
+    let _ = writeln!(out, "# HELP netboost_traffic_type_packets_total Packets forwarded per traffic type");
+    let _ = writeln!(out, "# TYPE netboost_traffic_type_packets_total counter");
+    for (traffic_type, by_type) in &traffic_types {
+        let _ = writeln!(out, "netboost_traffic_type_packets_total{{type=\"{}\"}} {}", traffic_type.as_str(), by_type.packets);
+    }
+
+    let _ = writeln!(out, "# HELP netboost_traffic_type_bytes_total Bytes forwarded per traffic type");
+    let _ = writeln!(out, "# TYPE netboost_traffic_type_bytes_total counter");
+    for (traffic_type, by_type) in &traffic_types {
+        let _ = writeln!(out, "netboost_traffic_type_bytes_total{{type=\"{}\"}} {}", traffic_type.as_str(), by_type.bytes);
+    }
+
+    let gauges = [
+        ("netboost_average_latency_seconds", "Average packet processing latency", stats.average_latency.as_secs_f64()),
+        ("netboost_bandwidth_bytes_per_second", "Average forwarded bandwidth this session", stats.bandwidth_usage as f64),
+        ("netboost_upload_bits_per_second", "Upload throughput over the last second", stats.upload_bps as f64),
+        ("netboost_download_bits_per_second", "Download throughput over the last second", stats.download_bps as f64),
+        ("netboost_aggregate_bits_per_second", "Combined throughput of all interfaces over the last second", stats.aggregate_bandwidth_bps as f64),
+        ("netboost_packet_loss_ratio", "Fraction of received packets that were dropped", stats.packet_loss_rate as f64),
+        ("netboost_uptime_seconds", "Time since the service started", stats.uptime.as_secs_f64()),
+        ("netboost_queue_depth", "Packets waiting to be processed", stats.queue_depth as f64),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    let mut queue_depths: Vec<_> = stats.queue_depth_by_type.iter().collect();
+    queue_depths.sort_by_key(|(traffic_type, _)| **traffic_type);
+
+    let _ = writeln!(out, "# HELP netboost_queue_depth_by_type Packets waiting to be processed per traffic type");
+    let _ = writeln!(out, "# TYPE netboost_queue_depth_by_type gauge");
+    for (traffic_type, depth) in queue_depths {
+        let _ = writeln!(out, "netboost_queue_depth_by_type{{type=\"{}\"}} {}", traffic_type.as_str(), depth);
+    }
+
+    let _ = writeln!(out, "# HELP netboost_interface_bits_per_second Throughput per interface over the last second");
+    let _ = writeln!(out, "# TYPE netboost_interface_bits_per_second gauge");
+    for iface in readings {
+        let _ = writeln!(
+            out,
+            "netboost_interface_bits_per_second{{interface=\"{}\",index=\"{}\"}} {}",
+            escape_label(&iface.name), iface.index, iface.bits_per_second
+        );
+    }
+
+    let _ = writeln!(out, "# HELP netboost_interface_latency_seconds Round trip of each interface's last answered health probe");
+    let _ = writeln!(out, "# TYPE netboost_interface_latency_seconds gauge");
+    for iface in readings {
+        if let Some(latency) = iface.latency {
+            let _ = writeln!(
+                out,
+                "netboost_interface_latency_seconds{{interface=\"{}\",index=\"{}\"}} {}",
+                escape_label(&iface.name), iface.index, latency.as_secs_f64()
+            );
+        }
+    }
+
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_reads_are_bounded() {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        client.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        assert_eq!(read_request_line(&mut server).await.unwrap(), "GET /metrics HTTP/1.1");
+
+        // A client that keeps sending headers is cut off rather than buffered
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        client.write_all(b"GET /metrics HTTP/1.1\r\n").await.unwrap();
+        client.write_all(&[b'x'; MAX_REQUEST_LEN * 2]).await.unwrap();
+        assert!(read_request_line(&mut server).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interface_counters_outlive_the_interface() {
+        let monitor = PerformanceMonitor::new();
+        monitor.record_packet_duplicated(2, "wlan0", 1000).await;
+        monitor.forget_interface(2).await;
+
+        let interfaces = [InterfaceGauges {
+            index: 1,
+            name: "eth0".to_string(),
+            bits_per_second: 8000,
+            latency: Some(Duration::from_millis(25)),
+        }];
+        let body = render_metrics(&monitor.get_lifetime_counters().await, &monitor.get_current_stats().await, &interfaces);
+        assert!(body.contains("netboost_interface_bytes_forwarded_total{interface=\"wlan0\",index=\"2\"} 1000\n"));
+        assert!(body.contains("netboost_interface_bits_per_second{interface=\"eth0\",index=\"1\"} 8000\n"));
+        assert!(body.contains("netboost_interface_latency_seconds{interface=\"eth0\",index=\"1\"} 0.025\n"));
+    }
+}
@@ -0,0 +1,212 @@
+// src-tauri/src/mtu.rs
+use anyhow::{Context, Result};
+use std::net::Ipv4Addr;
+
+use crate::nat;
+
+/// MTU assumed for links that don't report one
+pub const DEFAULT_MTU: u16 = 1500;
+
+// Byte offsets into the IPv4 header
+const IPV4_TOTAL_LENGTH_OFFSET: usize = 2;
+const IPV4_FLAGS_OFFSET: usize = 6;
+const IPV4_CHECKSUM_OFFSET: usize = 10;
+
+const FLAG_DONT_FRAGMENT: u16 = 0x4000;
+const FLAG_MORE_FRAGMENTS: u16 = 0x2000;
+const FRAGMENT_OFFSET_MASK: u16 = 0x1fff;
+
+const ICMP_DESTINATION_UNREACHABLE: u8 = 3;
+const ICMP_FRAGMENTATION_NEEDED: u8 = 4;
+
+fn header_len(packet: &[u8]) -> Result<usize> {
+    packet
+        .first()
+        .map(|byte| ((byte & 0x0f) as usize) * 4)
+        .filter(|len| *len >= 20 && *len <= packet.len())
+        .context("Invalid IPv4 header")
+}
+
+fn flags_and_offset(packet: &[u8]) -> u16 {
+    u16::from_be_bytes([packet[IPV4_FLAGS_OFFSET], packet[IPV4_FLAGS_OFFSET + 1]])
+}
+
+fn set_checksum(header: &mut [u8]) {
+    header[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].fill(0);
+    let checksum = nat::checksum(header);
+    header[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// Whether the sender forbade fragmenting the packet. Routers never
+/// fragment IPv6.
+pub fn dont_fragment(packet: &[u8]) -> bool {
+    match packet.first().map(|byte| byte >> 4) {
+        Some(6) => true,
+        _ => packet.len() > IPV4_FLAGS_OFFSET + 1 && flags_and_offset(packet) & FLAG_DONT_FRAGMENT != 0,
+    }
+}
+
+/// The options that must be repeated in every fragment (RFC 791), padded to
+/// a multiple of four bytes
+fn copied_options(options: &[u8]) -> Vec<u8> {
+    let mut copied = Vec::new();
+    let mut i = 0;
+    while i < options.len() {
+        match options[i] {
+            0 => break,
+            1 => i += 1,
+            kind => {
+                let len = options.get(i + 1).map_or(0, |len| *len as usize).max(2);
+                let end = (i + len).min(options.len());
+                if kind & 0x80 != 0 {
+                    copied.extend_from_slice(&options[i..end]);
+                }
+                i = end;
+            }
+        }
+    }
+    copied.resize(copied.len().div_ceil(4) * 4, 0);
+    copied
+}
+
+/// Split an IPv4 packet into fragments of at most `mtu` bytes. Fragments of
+/// an already fragmented packet keep their place in the original.
+pub fn fragment(packet: &[u8], mtu: u16) -> Result<Vec<Vec<u8>>> {
+    let header_len = header_len(packet)?;
+    let first_header = &packet[..header_len];
+    let mut later_header = first_header[..20].to_vec();
+    later_header.extend(copied_options(&first_header[20..]));
+    later_header[0] = 0x40 | (later_header.len() / 4) as u8;
+
+    let original = flags_and_offset(packet);
+    let mut offset = (original & FRAGMENT_OFFSET_MASK) as usize * 8;
+    let payload = &packet[header_len..];
+
+    let mut fragments = Vec::new();
+    let mut position = 0;
+    while position < payload.len() {
+        let header = if position == 0 { first_header } else { &later_header[..] };
+        // Every fragment but the last carries a multiple of eight bytes
+        let room = (mtu as usize).saturating_sub(header.len()) & !7;
+        if room == 0 {
+            return Err(anyhow::anyhow!("MTU {} is too small to fragment into", mtu));
+        }
+        let len = room.min(payload.len() - position);
+        let last = position + len == payload.len();
+
+        let mut fragment = Vec::with_capacity(header.len() + len);
+        fragment.extend_from_slice(header);
+        fragment.extend_from_slice(&payload[position..position + len]);
+
+        let total_len = fragment.len() as u16;
+        fragment[IPV4_TOTAL_LENGTH_OFFSET..IPV4_TOTAL_LENGTH_OFFSET + 2].copy_from_slice(&total_len.to_be_bytes());
+        let more = if last { original & FLAG_MORE_FRAGMENTS } else { FLAG_MORE_FRAGMENTS };
+        let flags = (original & FLAG_DONT_FRAGMENT) | more | (offset / 8) as u16;
+        fragment[IPV4_FLAGS_OFFSET..IPV4_FLAGS_OFFSET + 2].copy_from_slice(&flags.to_be_bytes());
+        set_checksum(&mut fragment[..header.len()]);
+
+        fragments.push(fragment);
+        position += len;
+        offset += len;
+    }
+    Ok(fragments)
+}
+
+/// An ICMP "fragmentation needed" error telling the sender of `packet` to
+/// send at most `mtu` bytes (RFC 1191). Returns `None` for packets that
+/// mustn't be answered with an ICMP error, and for IPv6, which would need
+/// an ICMPv6 Packet Too Big instead.
+pub fn fragmentation_needed(packet: &[u8], mtu: u16, source: Ipv4Addr) -> Option<Vec<u8>> {
+    if packet.first()? >> 4 != 4 {
+        return None;
+    }
+    let header_len = header_len(packet).ok()?;
+    // Only the first fragment is answered, and never another ICMP error
+    if flags_and_offset(packet) & FRAGMENT_OFFSET_MASK != 0 || is_icmp_error(packet, header_len) {
+        return None;
+    }
+
+    let quoted = &packet[..(header_len + 8).min(packet.len())];
+    let total_len = 20 + 8 + quoted.len();
+    let mut reply = vec![0u8; total_len];
+    reply[0] = 0x45;
+    reply[IPV4_TOTAL_LENGTH_OFFSET..IPV4_TOTAL_LENGTH_OFFSET + 2].copy_from_slice(&(total_len as u16).to_be_bytes());
+    reply[8] = 64;
+    reply[9] = 1;
+    reply[12..16].copy_from_slice(&source.octets());
+    reply[16..20].copy_from_slice(&packet[12..16]);
+    set_checksum(&mut reply[..20]);
+
+    let icmp = &mut reply[20..];
+    icmp[0] = ICMP_DESTINATION_UNREACHABLE;
+    icmp[1] = ICMP_FRAGMENTATION_NEEDED;
+    icmp[6..8].copy_from_slice(&mtu.to_be_bytes());
+    icmp[8..].copy_from_slice(quoted);
+    let checksum = nat::checksum(icmp);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+    Some(reply)
+}
+
+fn is_icmp_error(packet: &[u8], header_len: usize) -> bool {
+    // Echo request and reply are the only queries that can get this big
+    packet[9] == 1 && packet.get(header_len).is_some_and(|kind| !matches!(kind, 0 | 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+
+    fn udp_packet(payload_len: usize, flags: u16) -> Vec<u8> {
+        let total_len = 28 + payload_len;
+        let mut packet = vec![0u8; total_len];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+        packet[4..6].copy_from_slice(&0x1234u16.to_be_bytes());
+        packet[6..8].copy_from_slice(&flags.to_be_bytes());
+        packet[8] = 64;
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&[10, 0, 0, 2]);
+        packet[16..20].copy_from_slice(&[93, 184, 216, 34]);
+        for (i, byte) in packet[20..].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        set_checksum(&mut packet[..20]);
+        packet
+    }
+
+    #[test]
+    fn test_fragments_fit_mtu_and_reassemble() {
+        let packet = udp_packet(3000, 0);
+        let fragments = fragment(&packet, 1400).unwrap();
+        assert_eq!(fragments.len(), 3);
+
+        let mut payload = Vec::new();
+        for (i, fragment) in fragments.iter().enumerate() {
+            assert!(fragment.len() <= 1400);
+            let ip = Ipv4Packet::new(fragment).unwrap();
+            assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+            assert_eq!(ip.get_identification(), 0x1234);
+            assert_eq!(ip.get_fragment_offset() as usize * 8, payload.len());
+            assert_eq!(ip.get_flags() & 1 != 0, i < fragments.len() - 1);
+            payload.extend_from_slice(&fragment[20..]);
+        }
+        assert_eq!(payload, packet[20..]);
+        assert!(fragment(&packet, 24).is_err());
+    }
+
+    #[test]
+    fn test_fragmentation_needed_quotes_the_packet() {
+        let packet = udp_packet(2000, FLAG_DONT_FRAGMENT);
+        assert!(dont_fragment(&packet));
+
+        let reply = fragmentation_needed(&packet, 1400, Ipv4Addr::new(192, 168, 1, 10)).unwrap();
+        let ip = Ipv4Packet::new(&reply).unwrap();
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        assert_eq!(ip.get_destination(), Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(&reply[20..22], &[ICMP_DESTINATION_UNREACHABLE, ICMP_FRAGMENTATION_NEEDED]);
+        assert_eq!(u16::from_be_bytes([reply[26], reply[27]]), 1400);
+        assert_eq!(nat::checksum(&reply[20..]), 0);
+        assert_eq!(&reply[28..], &packet[..28]);
+    }
+}
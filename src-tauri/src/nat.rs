@@ -0,0 +1,489 @@
+// src-tauri/src/nat.rs
+use anyhow::{bail, Context, Result};
+use pnet_packet::ip::IpNextHeaderProtocols;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::info;
+
+#[cfg(target_os = "linux")]
+use crate::kill_switch::run_with_input;
+use crate::packet_router::FlowKey;
+
+/// Source ports outbound TCP and UDP flows are translated to. Linux gives
+/// its own sockets ephemeral ports below these (32768-60999 by default), so
+/// replies to translated flows can be kept from the host's stack.
+pub const NAT_PORTS: RangeInclusive<u16> = 61000..=65535;
+
+#[cfg(target_os = "linux")]
+const NFT_TABLE: &str = "netboost_nat";
+
+// Byte offsets into the IPv4 header
+const IPV4_CHECKSUM_OFFSET: usize = 10;
+const IPV4_SOURCE_OFFSET: usize = 12;
+const IPV4_DESTINATION_OFFSET: usize = 16;
+
+// Checksum offsets from the start of the transport header
+const TCP_CHECKSUM_OFFSET: usize = 16;
+const UDP_CHECKSUM_OFFSET: usize = 6;
+
+struct NatEntry {
+    /// The flow's key before translation
+    original: FlowKey,
+    last_seen: Instant,
+}
+
+#[derive(Default)]
+struct Mappings {
+    /// Keyed by the interface a flow left through and the key its replies carry
+    inbound: HashMap<(u32, FlowKey), NatEntry>,
+    /// The source port each flow was given, keyed by interface and original key
+    outbound: HashMap<(u32, FlowKey), u16>,
+    /// Where the search for a free port starts, so ports aren't reused sooner than needed
+    next_port: u16,
+}
+
+impl Mappings {
+    /// A port in `NAT_PORTS` no other flow to the same destination uses on this interface
+    fn allocate_port(&mut self, interface_index: u32, translated: FlowKey) -> Result<u16> {
+        let len = NAT_PORTS.len() as u16;
+        for attempt in 0..len {
+            let port = NAT_PORTS.start() + (self.next_port.wrapping_add(attempt) % len);
+            let reply_key = FlowKey { src_port: port, ..translated }.reversed();
+            if !self.inbound.contains_key(&(interface_index, reply_key)) {
+                self.next_port = (port - NAT_PORTS.start() + 1) % len;
+                return Ok(port);
+            }
+        }
+        bail!("Every NAT port to {} is in use", translated.dst_addr)
+    }
+
+    fn retain(&mut self, keep: impl Fn(&(u32, FlowKey), &NatEntry) -> bool) {
+        let outbound = &mut self.outbound;
+        self.inbound.retain(|key, entry| {
+            let kept = keep(key, entry);
+            if !kept {
+                outbound.remove(&(key.0, entry.original));
+            }
+            kept
+        });
+    }
+}
+
+/// Source NAT for packets forwarded out of a physical interface. Outbound
+/// packets take the interface's address and, for TCP and UDP, a port from
+/// `NAT_PORTS`; replies are mapped back to the original TUN-side address
+/// and port.
+pub struct NatTable {
+    mappings: RwLock<Mappings>,
+}
+
+impl Default for NatTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NatTable {
+    pub fn new() -> Self {
+        Self {
+            mappings: RwLock::new(Mappings::default()),
+        }
+    }
+
+    /// Rewrite the source of an outbound packet to `interface_address` and
+    /// the flow's NAT port, and remember the mapping for the return path
+    pub async fn translate_outbound(&self, packet: &mut [u8], interface_index: u32, interface_address: Ipv4Addr) -> Result<()> {
+        let original = FlowKey::from_packet(packet).context("Not an IP packet")?;
+        // Interfaces only have IPv4 addresses to translate to, so IPv6 keeps its source
+        let IpAddr::V4(original_source) = original.src_addr else {
+            return Ok(());
+        };
+        if original_source == interface_address {
+            return Ok(());
+        }
+        let has_ports = has_ports(original.protocol);
+
+        let port = {
+            let mut mappings = self.mappings.write().await;
+            let translated = FlowKey { src_addr: IpAddr::V4(interface_address), ..original };
+            let port = match mappings.outbound.get(&(interface_index, original)) {
+                Some(&port) => port,
+                None if has_ports => mappings.allocate_port(interface_index, translated)?,
+                None => original.src_port,
+            };
+            let reply_key = FlowKey { src_port: port, ..translated }.reversed();
+            mappings.outbound.insert((interface_index, original), port);
+            mappings.inbound.insert((interface_index, reply_key), NatEntry { original, last_seen: Instant::now() });
+            port
+        };
+
+        rewrite_source(packet, interface_address)?;
+        if has_ports {
+            rewrite_source_port(packet, port)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the original destination of a reply received on `interface_index`.
+    /// Returns false if the packet doesn't belong to a translated flow.
+    pub async fn translate_inbound(&self, packet: &mut [u8], interface_index: u32) -> bool {
+        let Some(key) = FlowKey::from_packet(packet) else {
+            return false;
+        };
+
+        let original = match self.mappings.write().await.inbound.get_mut(&(interface_index, key)) {
+            Some(entry) => {
+                entry.last_seen = Instant::now();
+                entry.original
+            }
+            None => return false,
+        };
+        let IpAddr::V4(original_source) = original.src_addr else {
+            return false;
+        };
+
+        if has_ports(original.protocol) && rewrite_destination_port(packet, original.src_port).is_err() {
+            return false;
+        }
+        rewrite_destination(packet, original_source).is_ok()
+    }
+
+    /// Remove the mappings of an interface that has gone away; no reply can
+    /// arrive on it any more
+    pub async fn forget_interface(&self, interface_index: u32) {
+        self.mappings.write().await.retain(|(index, _), _| *index != interface_index);
+    }
+
+    /// Remove mappings that haven't seen traffic for `idle_timeout`
+    pub async fn expire(&self, idle_timeout: Duration) {
+        self.mappings
+            .write()
+            .await
+            .retain(|_, entry| entry.last_seen.elapsed() < idle_timeout);
+    }
+}
+
+/// Keep the host's own stack from answering replies to translated flows.
+/// They arrive on the physical interfaces for ports no local socket has, so
+/// Linux would reset the TCP connections and refuse the UDP ones. The packet
+/// readers still see the replies, since they capture them before the rules
+/// drop them. Replaces any rules left from an earlier run.
+pub fn block_host_replies(tun: &str) -> Result<()> {
+    install_reply_rules(tun)?;
+    info!("Replies to NAT ports {}-{} are left to NetBoost Pro", NAT_PORTS.start(), NAT_PORTS.end());
+    Ok(())
+}
+
+/// Remove the rules `block_host_replies` installed. Does nothing if none are.
+pub fn unblock_host_replies() -> Result<()> {
+    remove_reply_rules()
+}
+
+#[cfg(target_os = "linux")]
+fn install_reply_rules(tun: &str) -> Result<()> {
+    run_with_input("nft", &["-f", "-"], &nft_ruleset(tun)).context("Failed to install the NAT's nftables rules")
+}
+
+#[cfg(target_os = "linux")]
+fn remove_reply_rules() -> Result<()> {
+    run_with_input("nft", &["-f", "-"], &format!("table ip {0}\ndelete table ip {0}\n", NFT_TABLE))
+        .context("Failed to remove the NAT's nftables rules")
+}
+
+// Windows Firewall drops unsolicited inbound traffic by default, which
+// covers the replies there; other platforms aren't covered yet
+#[cfg(not(target_os = "linux"))]
+fn install_reply_rules(_tun: &str) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn remove_reply_rules() -> Result<()> {
+    Ok(())
+}
+
+/// Drops before connection tracking, which would otherwise log the
+/// replies as invalid, and only traffic addressed to the host itself
+#[cfg(target_os = "linux")]
+fn nft_ruleset(tun: &str) -> String {
+    format!(
+        r#"table ip {table}
+delete table ip {table}
+table ip {table} {{
+    chain prerouting {{
+        type filter hook prerouting priority raw; policy accept;
+        iifname "{tun}" accept
+        meta l4proto {{ tcp, udp }} th dport {first}-{last} fib daddr type local drop
+    }}
+}}
+"#,
+        table = NFT_TABLE,
+        tun = tun,
+        first = NAT_PORTS.start(),
+        last = NAT_PORTS.end()
+    )
+}
+
+fn has_ports(protocol: u8) -> bool {
+    protocol == IpNextHeaderProtocols::Tcp.0 || protocol == IpNextHeaderProtocols::Udp.0
+}
+
+/// Replace the IPv4 source address, fixing up the IP and transport checksums
+pub fn rewrite_source(packet: &mut [u8], address: Ipv4Addr) -> Result<()> {
+    rewrite_address(packet, IPV4_SOURCE_OFFSET, address)
+}
+
+/// Replace the IPv4 destination address, fixing up the IP and transport checksums
+pub fn rewrite_destination(packet: &mut [u8], address: Ipv4Addr) -> Result<()> {
+    rewrite_address(packet, IPV4_DESTINATION_OFFSET, address)
+}
+
+/// Replace the TCP or UDP source port, fixing up the transport checksum
+pub fn rewrite_source_port(packet: &mut [u8], port: u16) -> Result<()> {
+    rewrite_port(packet, 0, port)
+}
+
+/// Replace the TCP or UDP destination port, fixing up the transport checksum
+pub fn rewrite_destination_port(packet: &mut [u8], port: u16) -> Result<()> {
+    rewrite_port(packet, 2, port)
+}
+
+fn rewrite_address(packet: &mut [u8], offset: usize, address: Ipv4Addr) -> Result<()> {
+    let header_len = ipv4_header_len(packet)?;
+
+    let old = [packet[offset], packet[offset + 1], packet[offset + 2], packet[offset + 3]];
+    let new = address.octets();
+    packet[offset..offset + 4].copy_from_slice(&new);
+
+    // The header is small, so recompute its checksum outright
+    packet[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].fill(0);
+    let checksum = checksum(&packet[..header_len]);
+    packet[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].copy_from_slice(&checksum.to_be_bytes());
+
+    // The transport checksum covers the addresses through the pseudo-header
+    if let Some(checksum_offset) = transport_checksum_offset(packet, header_len) {
+        adjust_transport_checksum(packet, checksum_offset, &old, &new);
+    }
+    Ok(())
+}
+
+/// `offset` is from the start of the transport header, where TCP and UDP
+/// both keep their ports
+fn rewrite_port(packet: &mut [u8], offset: usize, port: u16) -> Result<()> {
+    let header_len = ipv4_header_len(packet)?;
+    let checksum_offset = transport_checksum_offset(packet, header_len).context("Packet has no TCP or UDP header")?;
+
+    let offset = header_len + offset;
+    let old = [packet[offset], packet[offset + 1]];
+    let new = port.to_be_bytes();
+    packet[offset..offset + 2].copy_from_slice(&new);
+
+    adjust_transport_checksum(packet, checksum_offset, &old, &new);
+    Ok(())
+}
+
+fn ipv4_header_len(packet: &[u8]) -> Result<usize> {
+    packet
+        .first()
+        .map(|byte| ((byte & 0x0f) as usize) * 4)
+        .filter(|len| *len >= 20 && *len <= packet.len())
+        .context("Invalid IPv4 header")
+}
+
+/// Where the TCP or UDP checksum is, if the packet carries a whole enough
+/// transport header to have one. Non-first fragments carry none.
+fn transport_checksum_offset(packet: &[u8], header_len: usize) -> Option<usize> {
+    let fragment_offset = u16::from_be_bytes([packet[6], packet[7]]) & 0x1fff;
+    if fragment_offset != 0 {
+        return None;
+    }
+    let protocol = packet[9];
+    let checksum_offset = if protocol == IpNextHeaderProtocols::Tcp.0 {
+        header_len + TCP_CHECKSUM_OFFSET
+    } else if protocol == IpNextHeaderProtocols::Udp.0 {
+        header_len + UDP_CHECKSUM_OFFSET
+    } else {
+        return None;
+    };
+    (checksum_offset + 2 <= packet.len()).then_some(checksum_offset)
+}
+
+/// Adjust the transport checksum for a field that changed from `old` to
+/// `new`. Done incrementally, since a first fragment doesn't hold the whole
+/// payload the checksum was computed over.
+fn adjust_transport_checksum(packet: &mut [u8], checksum_offset: usize, old: &[u8], new: &[u8]) {
+    let udp = packet[9] == IpNextHeaderProtocols::Udp.0;
+    let old_checksum = u16::from_be_bytes([packet[checksum_offset], packet[checksum_offset + 1]]);
+    // A zero UDP checksum means the sender didn't compute one
+    if udp && old_checksum == 0 {
+        return;
+    }
+
+    let mut new_checksum = adjust_checksum(old_checksum, old, new);
+    if udp && new_checksum == 0 {
+        new_checksum = 0xffff;
+    }
+    packet[checksum_offset..checksum_offset + 2].copy_from_slice(&new_checksum.to_be_bytes());
+}
+
+/// Internet checksum (RFC 1071) of `data`
+pub(crate) fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Update a checksum for a changed field of whole 16-bit words without
+/// touching the rest of the data (RFC 1624)
+fn adjust_checksum(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    let mut sum = (!checksum) as u32;
+    for (old, new) in old.chunks(2).zip(new.chunks(2)) {
+        sum += (!u16::from_be_bytes([old[0], old[1]])) as u32;
+        sum += u16::from_be_bytes([new[0], new[1]]) as u32;
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+    use pnet_packet::tcp::{self, TcpPacket};
+    use pnet_packet::udp::{self, UdpPacket};
+    use pnet_packet::Packet;
+
+    // 10.0.0.2:54321 -> 93.184.216.34:53, UDP payload "netboost"
+    const UDP_FIXTURE: [u8; 36] = [
+        0x45, 0x00, 0x00, 0x24, 0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0xe8, 0xb8, 0x0a, 0x00, 0x00, 0x02,
+        0x5d, 0xb8, 0xd8, 0x22, 0xd4, 0x31, 0x00, 0x35, 0x00, 0x10, 0x25, 0xdf, 0x6e, 0x65, 0x74, 0x62,
+        0x6f, 0x6f, 0x73, 0x74,
+    ];
+
+    // 10.0.0.2:40000 -> 93.184.216.34:443, TCP SYN
+    const TCP_FIXTURE: [u8; 40] = [
+        0x45, 0x00, 0x00, 0x28, 0x12, 0x34, 0x40, 0x00, 0x40, 0x06, 0xe8, 0xbf, 0x0a, 0x00, 0x00, 0x02,
+        0x5d, 0xb8, 0xd8, 0x22, 0x9c, 0x40, 0x01, 0xbb, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0x50, 0x02, 0xfa, 0xf0, 0xd7, 0x18, 0x00, 0x00,
+    ];
+
+    const INTERFACE_ADDRESS: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+
+    #[test]
+    fn test_udp_source_rewrite_checksums() {
+        let mut packet = UDP_FIXTURE;
+        rewrite_source(&mut packet, INTERFACE_ADDRESS).unwrap();
+
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        assert_eq!(ip.get_source(), INTERFACE_ADDRESS);
+        assert_eq!(ip.get_checksum(), 0x3108);
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+
+        let udp = UdpPacket::new(ip.payload()).unwrap();
+        assert_eq!(udp.get_checksum(), 0x6e2e);
+        assert_eq!(udp.get_checksum(), udp::ipv4_checksum(&udp, &ip.get_source(), &ip.get_destination()));
+    }
+
+    #[test]
+    fn test_tcp_source_rewrite_checksums() {
+        let mut packet = TCP_FIXTURE;
+        rewrite_source(&mut packet, INTERFACE_ADDRESS).unwrap();
+
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        assert_eq!(ip.get_source(), INTERFACE_ADDRESS);
+        assert_eq!(ip.get_checksum(), 0x310f);
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+
+        let tcp = TcpPacket::new(ip.payload()).unwrap();
+        assert_eq!(tcp.get_checksum(), 0x1f68);
+        assert_eq!(tcp.get_checksum(), tcp::ipv4_checksum(&tcp, &ip.get_source(), &ip.get_destination()));
+    }
+
+    #[test]
+    fn test_port_rewrite_checksums() {
+        let mut packet = TCP_FIXTURE;
+        rewrite_source_port(&mut packet, 61000).unwrap();
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        let tcp = TcpPacket::new(ip.payload()).unwrap();
+        assert_eq!(tcp.get_source(), 61000);
+        assert_eq!(tcp.get_checksum(), tcp::ipv4_checksum(&tcp, &ip.get_source(), &ip.get_destination()));
+
+        let mut packet = UDP_FIXTURE;
+        rewrite_destination_port(&mut packet, 5353).unwrap();
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        let udp = UdpPacket::new(ip.payload()).unwrap();
+        assert_eq!(udp.get_destination(), 5353);
+        assert_eq!(udp.get_checksum(), udp::ipv4_checksum(&udp, &ip.get_source(), &ip.get_destination()));
+    }
+
+    #[tokio::test]
+    async fn test_nat_round_trip() {
+        let nat = NatTable::new();
+        let mut outbound = UDP_FIXTURE;
+        nat.translate_outbound(&mut outbound, 1, INTERFACE_ADDRESS).await.unwrap();
+        // The host's stack never hands out ports in the NAT range
+        let port = UdpPacket::new(&outbound[20..]).unwrap().get_source();
+        assert!(NAT_PORTS.contains(&port));
+        // Later packets of the flow keep its port
+        let mut again = UDP_FIXTURE;
+        nat.translate_outbound(&mut again, 1, INTERFACE_ADDRESS).await.unwrap();
+        assert_eq!(again, outbound);
+
+        // Build the reply by swapping addresses and ports
+        let mut reply = outbound;
+        reply[12..20].copy_from_slice(&[outbound[16], outbound[17], outbound[18], outbound[19], outbound[12], outbound[13], outbound[14], outbound[15]]);
+        reply[20..24].copy_from_slice(&[outbound[22], outbound[23], outbound[20], outbound[21]]);
+
+        // Replies on another interface don't belong to the mapping
+        assert!(!nat.translate_inbound(&mut reply.clone(), 2).await);
+        nat.forget_interface(2).await;
+        assert!(nat.translate_inbound(&mut reply, 1).await);
+
+        let ip = Ipv4Packet::new(&reply).unwrap();
+        assert_eq!(ip.get_destination(), Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        let udp = UdpPacket::new(ip.payload()).unwrap();
+        assert_eq!(udp.get_destination(), 54321);
+        assert_eq!(udp.get_checksum(), udp::ipv4_checksum(&udp, &ip.get_source(), &ip.get_destination()));
+
+        // Once the interface is gone, so are its mappings
+        nat.forget_interface(1).await;
+        let mappings = nat.mappings.read().await;
+        assert!(mappings.inbound.is_empty() && mappings.outbound.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flows_sharing_a_source_port_get_their_own_nat_ports() {
+        let nat = NatTable::new();
+        let mut first = UDP_FIXTURE;
+        nat.translate_outbound(&mut first, 1, INTERFACE_ADDRESS).await.unwrap();
+        // Another TUN-side host using the same port for the same destination
+        let mut second = UDP_FIXTURE;
+        rewrite_source(&mut second, Ipv4Addr::new(10, 0, 0, 3)).unwrap();
+        nat.translate_outbound(&mut second, 1, INTERFACE_ADDRESS).await.unwrap();
+        assert_ne!(first[20..22], second[20..22]);
+
+        nat.expire(Duration::ZERO).await;
+        let mappings = nat.mappings.read().await;
+        assert!(mappings.inbound.is_empty() && mappings.outbound.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_ruleset_drops_host_replies_to_nat_ports_only() {
+        let ruleset = nft_ruleset("netboost0");
+        assert!(ruleset.contains(r#"iifname "netboost0" accept"#));
+        assert!(ruleset.contains("th dport 61000-65535 fib daddr type local drop"));
+        assert!(ruleset.starts_with("table ip netboost_nat\ndelete table ip netboost_nat\n"));
+    }
+}
@@ -0,0 +1,137 @@
+// src-tauri/src/net_util.rs
+use anyhow::{Context, Result};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::{TcpSocket, UdpSocket};
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use tracing::debug;
+
+use crate::interface_manager::PhysicalInterface;
+#[cfg(target_os = "linux")]
+use crate::kill_switch::SOCKET_MARK;
+
+/// An IPv4 socket of `socket_type` whose traffic leaves through the interface
+/// with this name, index and address only, whatever the routing table says.
+/// It is always bound to the interface's address; pinning it to the device
+/// as well (SO_BINDTODEVICE on Linux, IP_BOUND_IF on macOS, IP_UNICAST_IF on
+/// Windows) is best effort, since Linux needs CAP_NET_RAW for it. Raw sockets
+/// need that anyway and would otherwise be routed, so for them it must work.
+pub fn bind_socket_to_interface(
+    name: &str,
+    index: u32,
+    address: Ipv4Addr,
+    socket_type: Type,
+    protocol: Option<Protocol>,
+) -> Result<Socket> {
+    let socket = Socket::new(Domain::IPV4, socket_type, protocol)
+        .with_context(|| format!("Failed to open a socket on '{}'", name))?;
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    let required = socket_type == Type::RAW;
+
+    #[cfg(target_os = "linux")]
+    if let Err(e) = socket.bind_device(Some(name.as_bytes())) {
+        if required {
+            return Err(anyhow::Error::new(e).context(format!("Failed to bind socket to '{}'", name)));
+        }
+        debug!("Could not bind socket to {}: {}", name, e);
+    }
+    // Let it past the kill-switch
+    #[cfg(target_os = "linux")]
+    if let Err(e) = socket.set_mark(SOCKET_MARK) {
+        if required {
+            return Err(anyhow::Error::new(e).context(format!("Failed to mark socket on '{}'", name)));
+        }
+        debug!("Could not mark socket on {}: {}", name, e);
+    }
+    #[cfg(target_os = "macos")]
+    if let Err(e) = socket.bind_device_by_index_v4(std::num::NonZeroU32::new(index)) {
+        if required {
+            return Err(anyhow::Error::new(e).context(format!("Failed to bind socket to '{}'", name)));
+        }
+        debug!("Could not bind socket to {}: {}", name, e);
+    }
+    #[cfg(windows)]
+    set_unicast_interface(&socket, index)
+        .with_context(|| format!("Failed to bind socket to '{}'", name))?;
+    // Only macOS and Windows pin by index
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let _ = index;
+
+    socket
+        .bind(&SockAddr::from(SocketAddr::new(address.into(), 0)))
+        .with_context(|| format!("Failed to bind to {}", address))?;
+    Ok(socket)
+}
+
+/// A TCP socket, ready to connect, that can only leave through `interface`
+pub fn tcp_socket(interface: &PhysicalInterface) -> Result<TcpSocket> {
+    let socket = bind_socket_to_interface(&interface.name, interface.index, interface.ip_address, Type::STREAM, None)?;
+    socket.set_nonblocking(true)?;
+    Ok(TcpSocket::from_std_stream(socket.into()))
+}
+
+/// A UDP socket that can only send through `interface`
+pub fn udp_socket(interface: &PhysicalInterface) -> Result<UdpSocket> {
+    let socket = bind_socket_to_interface(&interface.name, interface.index, interface.ip_address, Type::DGRAM, None)?;
+    socket.set_nonblocking(true)?;
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+/// Windows' weak host model would otherwise send from the bound address out
+/// of whichever interface the route picks
+#[cfg(windows)]
+fn set_unicast_interface(socket: &Socket, index: u32) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawSocket;
+    use windows_sys::Win32::Networking::WinSock::{setsockopt, IPPROTO_IP, IP_UNICAST_IF, SOCKET, SOCKET_ERROR};
+
+    // IPv4 takes the index in network byte order
+    let index = index.to_be();
+    let result = unsafe {
+        setsockopt(
+            socket.as_raw_socket() as SOCKET,
+            IPPROTO_IP,
+            IP_UNICAST_IF,
+            &index as *const u32 as *const u8,
+            std::mem::size_of::<u32>() as i32,
+        )
+    };
+    if result == SOCKET_ERROR {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface_manager::InterfaceType;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_sockets_are_bound_to_the_interface_address() {
+        let loopback = PhysicalInterface {
+            name: "lo".to_string(),
+            description: "Loopback".to_string(),
+            ip_address: Ipv4Addr::LOCALHOST,
+            index: 1,
+            link_speed_mbps: None,
+            interface_type: InterfaceType::Virtual,
+            prefix_len: 8,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        };
+
+        let receiver = udp_socket(&loopback).unwrap();
+        let sender = udp_socket(&loopback).unwrap();
+        assert_eq!(sender.local_addr().unwrap().ip(), Ipv4Addr::LOCALHOST);
+        sender.send_to(b"ping", receiver.local_addr().unwrap()).await.unwrap();
+        let mut buf = [0u8; 4];
+        let (_, from) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, sender.local_addr().unwrap());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = tcp_socket(&loopback).unwrap().connect(listener.local_addr().unwrap()).await.unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), Ipv4Addr::LOCALHOST);
+    }
+}
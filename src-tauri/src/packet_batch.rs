@@ -0,0 +1,50 @@
+// src-tauri/src/packet_batch.rs
+use std::ops::Range;
+
+/// Packets stored back to back in a single reusable buffer, so a batch read
+/// from the TUN costs one allocation for its lifetime rather than one per packet
+pub struct PacketBatch {
+    buf: Vec<u8>,
+    packets: Vec<Range<usize>>,
+    used: usize,
+    max_packet_size: usize,
+}
+
+impl PacketBatch {
+    /// A batch with room for `max_packets` packets of up to `max_packet_size` bytes
+    pub fn new(max_packets: usize, max_packet_size: usize) -> Self {
+        Self {
+            buf: vec![0u8; max_packets * max_packet_size],
+            packets: Vec::with_capacity(max_packets),
+            used: 0,
+            max_packet_size,
+        }
+    }
+
+    /// Empty the batch for reuse, keeping its buffer
+    pub fn clear(&mut self) {
+        self.packets.clear();
+        self.used = 0;
+    }
+
+    /// Space for the next packet, or `None` if the batch is full
+    pub fn next_slot(&mut self) -> Option<&mut [u8]> {
+        let end = self.used + self.max_packet_size;
+        self.buf.get_mut(self.used..end)
+    }
+
+    /// Commit `len` bytes written into the slot returned by `next_slot`
+    pub fn commit(&mut self, len: usize) {
+        let len = len.min(self.max_packet_size);
+        self.packets.push(self.used..self.used + len);
+        self.used += len;
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.packets.iter().map(|range| &self.buf[range.clone()])
+    }
+}
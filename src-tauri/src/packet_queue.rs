@@ -0,0 +1,265 @@
+// src-tauri/src/packet_queue.rs
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// What happened to a packet offered to the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueued {
+    Queued,
+    /// The queue was full and a lower-priority packet was dropped to make room
+    DisplacedLower,
+}
+
+/// Bytes a level with weight 1 may send per round of fair queueing, about
+/// one full-size packet so every backlogged level gets a turn each round
+const DRR_QUANTUM: usize = 1500;
+
+struct QueueState {
+    /// One FIFO per priority level, lowest priority first
+    levels: Vec<VecDeque<Vec<u8>>>,
+    len: usize,
+    /// Bytes each level may send per round; `None` serves strictly by priority
+    quanta: Option<Vec<usize>>,
+    /// Unspent allowance of each level in the current round
+    deficits: Vec<usize>,
+    /// Level being served, visited from the highest priority down
+    turn: usize,
+    /// Whether `turn` has received its quantum this visit
+    credited: bool,
+    /// Buffers of processed packets, reused for new ones
+    free: Vec<Vec<u8>>,
+    closed: bool,
+}
+
+impl QueueState {
+    /// Take the next packet and the level it was queued at
+    fn take_next(&mut self) -> Option<(usize, Vec<u8>)> {
+        if self.len == 0 {
+            return None;
+        }
+        if self.quanta.is_none() {
+            return self.levels.iter_mut().enumerate().rev().find_map(|(level, queue)| queue.pop_front().map(|packet| (level, packet)));
+        }
+
+        // Deficit round robin: each visit tops up a level's allowance by its
+        // quantum and it sends packets until the next one doesn't fit
+        loop {
+            let level = self.turn;
+            match self.levels[level].front().map(Vec::len) {
+                Some(size) => {
+                    if !self.credited {
+                        self.deficits[level] += self.quanta.as_ref()?[level];
+                        self.credited = true;
+                    }
+                    if size <= self.deficits[level] {
+                        self.deficits[level] -= size;
+                        let packet = self.levels[level].pop_front()?;
+                        if self.levels[level].is_empty() {
+                            self.deficits[level] = 0;
+                            self.advance();
+                        }
+                        return Some((level, packet));
+                    }
+                }
+                // Idle levels don't bank allowance for later
+                None => self.deficits[level] = 0,
+            }
+            self.advance();
+        }
+    }
+
+    fn advance(&mut self) {
+        self.turn = self.turn.checked_sub(1).unwrap_or(self.levels.len() - 1);
+        self.credited = false;
+    }
+}
+
+/// Bounded queue between the TUN reader and the processing loop. It serves
+/// higher-priority packets first, or shares the processing loop between
+/// levels in proportion to their weights once `set_weights` is called. When
+/// it is full, an incoming packet displaces the oldest queued packet of lower
+/// priority if there is one; otherwise the producer waits for space.
+pub struct PacketQueue {
+    state: Mutex<QueueState>,
+    /// Mirror of the queue length, readable without taking the lock
+    depth: AtomicUsize,
+    /// Mirror of each level's length
+    level_depths: Vec<AtomicUsize>,
+    capacity: usize,
+    space_available: Notify,
+    packets_available: Notify,
+}
+
+impl PacketQueue {
+    pub fn new(capacity: usize, priority_levels: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                levels: (0..priority_levels).map(|_| VecDeque::new()).collect(),
+                len: 0,
+                quanta: None,
+                deficits: vec![0; priority_levels],
+                turn: priority_levels - 1,
+                credited: false,
+                free: Vec::new(),
+                closed: false,
+            }),
+            depth: AtomicUsize::new(0),
+            level_depths: (0..priority_levels).map(|_| AtomicUsize::new(0)).collect(),
+            capacity,
+            space_available: Notify::new(),
+            packets_available: Notify::new(),
+        }
+    }
+
+    /// Copy `packet` into the queue at `priority`, waiting for space if needed
+    pub async fn push(&self, packet: &[u8], priority: u8) -> Enqueued {
+        loop {
+            {
+                let mut guard = self.state.lock().unwrap();
+                let state = &mut *guard;
+                let level = (priority as usize).min(state.levels.len() - 1);
+
+                let outcome = if state.len < self.capacity {
+                    state.len += 1;
+                    Some(Enqueued::Queued)
+                } else if let Some((lower, displaced)) = state.levels[..level]
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(lower, queue)| queue.pop_front().map(|packet| (lower, packet)))
+                {
+                    state.free.push(displaced);
+                    self.level_depths[lower].store(state.levels[lower].len(), Ordering::Relaxed);
+                    Some(Enqueued::DisplacedLower)
+                } else {
+                    None
+                };
+
+                if let Some(outcome) = outcome {
+                    let mut buf = state.free.pop().unwrap_or_default();
+                    buf.clear();
+                    buf.extend_from_slice(packet);
+                    state.levels[level].push_back(buf);
+                    self.depth.store(state.len, Ordering::Relaxed);
+                    self.level_depths[level].store(state.levels[level].len(), Ordering::Relaxed);
+                    drop(guard);
+                    self.packets_available.notify_one();
+                    return outcome;
+                }
+            }
+            self.space_available.notified().await;
+        }
+    }
+
+    /// Take the next packet to process, waiting if the queue is empty.
+    /// Returns `None` once the queue is closed and drained.
+    pub async fn pop(&self) -> Option<Vec<u8>> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some((level, packet)) = state.take_next() {
+                    state.len -= 1;
+                    self.depth.store(state.len, Ordering::Relaxed);
+                    self.level_depths[level].store(state.levels[level].len(), Ordering::Relaxed);
+                    drop(state);
+                    self.space_available.notify_one();
+                    return Some(packet);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.packets_available.notified().await;
+        }
+    }
+
+    /// Return a buffer from `pop` so its allocation can be reused
+    pub fn recycle(&self, buf: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        if state.free.len() < self.capacity {
+            state.free.push(buf);
+        }
+    }
+
+    /// Wake the consumer so it can drain what's left and stop
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.packets_available.notify_one();
+    }
+
+    /// Number of packets waiting to be processed
+    pub fn len(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// Number of packets waiting at each priority level, lowest first
+    pub fn depths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.level_depths.iter().map(|depth| depth.load(Ordering::Relaxed))
+    }
+
+    /// Share the processing loop between levels by weight, lowest priority
+    /// first, using deficit round robin; `None` goes back to strict priority.
+    /// A zero weight counts as one so no level is starved.
+    pub fn set_weights(&self, weights: Option<Vec<u32>>) {
+        let mut state = self.state.lock().unwrap();
+        state.quanta = weights.map(|weights| {
+            (0..state.levels.len())
+                .map(|level| weights.get(level).copied().unwrap_or(1).max(1) as usize * DRR_QUANTUM)
+                .collect()
+        });
+        state.deficits.iter_mut().for_each(|deficit| *deficit = 0);
+        state.turn = state.levels.len() - 1;
+        state.credited = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_high_priority_served_first_and_displaces_bulk() {
+        let queue = PacketQueue::new(2, 5);
+
+        assert_eq!(queue.push(b"bulk-1", 1).await, Enqueued::Queued);
+        assert_eq!(queue.push(b"bulk-2", 1).await, Enqueued::Queued);
+
+        // Full: a gaming packet pushes out the oldest bulk packet
+        assert_eq!(queue.push(b"game", 4).await, Enqueued::DisplacedLower);
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.pop().await.unwrap(), b"game");
+        assert_eq!(queue.pop().await.unwrap(), b"bulk-2");
+
+        queue.close();
+        assert!(queue.pop().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fair_queueing_shares_by_weight_without_starving_bulk() {
+        let queue = PacketQueue::new(100, 3);
+        queue.set_weights(Some(vec![1, 2, 4]));
+
+        let packet = [0u8; DRR_QUANTUM];
+        for _ in 0..20 {
+            queue.push(&packet, 0).await;
+            queue.push(&packet, 1).await;
+            queue.push(&packet, 2).await;
+        }
+        assert_eq!(queue.depths().collect::<Vec<_>>(), [20, 20, 20]);
+
+        // One round serves levels in proportion to their weights, highest first
+        for _ in 0..7 {
+            queue.pop().await.unwrap();
+        }
+        assert_eq!(queue.depths().collect::<Vec<_>>(), [19, 18, 16]);
+
+        // Without weights, the top level is drained before anything else
+        queue.set_weights(None);
+        for _ in 0..16 {
+            queue.pop().await.unwrap();
+        }
+        assert_eq!(queue.depths().collect::<Vec<_>>(), [19, 18, 0]);
+    }
+}
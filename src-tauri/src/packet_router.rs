@@ -1,54 +1,601 @@
 use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::net::Ipv4Addr;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{Duration, Instant};
+use pnet_packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet_packet::ipv4::Ipv4Packet;
+use pnet_packet::ipv6::Ipv6Packet;
+use pnet_packet::tcp::TcpPacket;
+use pnet_packet::udp::UdpPacket;
+use pnet_packet::Packet;
 
-use crate::interface_manager::{PhysicalInterface, InterfaceManager};
+use crate::cidr::Ipv4Cidr;
+use crate::classifier::{self, ClassificationRule};
+use crate::dns::{DnsSettings, DNS_PORT};
+use crate::flow_log::FlowLog;
+use crate::health::{CircuitBreaker, HealthConfig, HealthStatus};
+use crate::interface_filter::InterfaceFilter;
+use crate::interface_manager::{InterfaceEvent, InterfaceManager, PhysicalInterface};
+use crate::nat;
+use crate::rate_limiter::TokenBucket;
+use crate::settings::InterfaceSettings;
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct PacketMetrics {
     pub latency: Duration,
+    /// Standard deviation of the recent latency samples
+    pub jitter: Duration,
     pub bandwidth_usage: u64,
     pub packet_loss: f32,
     pub last_updated: Instant,
 }
 
-#[derive(Debug, Clone)]
+/// Interfaces bonded into one link. The balancer sees the group as a single
+/// candidate, and packets it is picked for go to its members in turn.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InterfaceGroup {
+    /// Stands in for the members when choosing an interface
+    pub index: u32,
+    pub name: String,
+    pub members: Vec<u32>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct RoutingDecision {
     pub interface_index: u32,
     pub interface_name: String,
     pub confidence: f32, // 0.0 to 1.0
     pub reason: String,
+    pub traffic_type: TrafficType,
+    /// Further interfaces to send a copy through, in `Redundant` mode
+    pub duplicates: Vec<u32>,
+    /// The balancer's pick was below the confidence threshold, so the
+    /// packet went to the primary interface instead
+    pub low_confidence_fallback: bool,
+}
+
+/// How long interface metrics are trusted by default
+pub const DEFAULT_METRICS_MAX_AGE: Duration = Duration::from_secs(15);
+
+/// Milliseconds of latency a millisecond of jitter counts as by default
+pub const DEFAULT_JITTER_WEIGHT: f32 = 1.0;
+
+/// Failover tier of interfaces that haven't been given one
+pub const DEFAULT_TIER: u8 = 1;
+
+/// Confidence in a decision made on metrics older than the staleness threshold
+const STALE_CONFIDENCE: f32 = 0.3;
+
+/// Latency samples per interface that jitter is measured over
+const JITTER_WINDOW: usize = 10;
+
+/// How much more jitter counts against an interface for gaming traffic,
+/// where late packets are as bad as lost ones
+const GAMING_JITTER_EMPHASIS: f32 = 4.0;
+
+/// Link speed assumed for interfaces that don't report one
+const DEFAULT_LINK_SPEED_MBPS: u64 = 100;
+
+/// Indices from here up stand for interface groups rather than real interfaces
+const GROUP_INDEX_BASE: u32 = 0x8000_0000;
+
+/// Share of recent packets a traffic type needs before `Adaptive` mode
+/// switches to the strategy that suits it
+const ADAPTIVE_ENTER_SHARE: f64 = 0.6;
+
+/// Share below which `Adaptive` mode gives up a strategy again. The gap to
+/// `ADAPTIVE_ENTER_SHARE` keeps a mixed workload from flapping between them.
+const ADAPTIVE_EXIT_SHARE: f64 = 0.4;
+
+/// Packets needed in an observation before `Adaptive` mode acts on it
+const ADAPTIVE_MIN_PACKETS: u64 = 100;
+
+/// Identifies a transport-level flow by its 5-tuple. IPv4 and IPv6 flows to
+/// the same host are distinct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub src_addr: IpAddr,
+    pub dst_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+}
+
+impl FlowKey {
+    /// Extract the flow key from a raw IPv4 or IPv6 packet, if it is one
+    pub fn from_packet(packet_data: &[u8]) -> Option<Self> {
+        match packet_data.first()? >> 4 {
+            4 => Self::from_ipv4(packet_data),
+            6 => Self::from_ipv6(packet_data),
+            _ => None,
+        }
+    }
+
+    fn from_ipv4(packet_data: &[u8]) -> Option<Self> {
+        let ip = Ipv4Packet::new(packet_data)?;
+        if ip.get_header_length() < 5 {
+            return None;
+        }
+
+        let protocol = ip.get_next_level_protocol();
+        // Only the first fragment carries the transport header
+        let (src_port, dst_port) = if ip.get_fragment_offset() != 0 {
+            (0, 0)
+        } else {
+            Self::ports(protocol, ip.payload())
+        };
+
+        Some(Self {
+            src_addr: IpAddr::V4(ip.get_source()),
+            dst_addr: IpAddr::V4(ip.get_destination()),
+            src_port,
+            dst_port,
+            protocol: protocol.0,
+        })
+    }
+
+    fn from_ipv6(packet_data: &[u8]) -> Option<Self> {
+        let ip = Ipv6Packet::new(packet_data)?;
+
+        // Walk the extension headers to reach the transport header
+        let mut protocol = ip.get_next_header();
+        let mut payload = ip.payload();
+        let mut first_fragment = true;
+        loop {
+            match protocol {
+                IpNextHeaderProtocols::Hopopt | IpNextHeaderProtocols::Ipv6Route | IpNextHeaderProtocols::Ipv6Opts => {
+                    let len = (*payload.get(1)? as usize + 1) * 8;
+                    protocol = IpNextHeaderProtocol(*payload.first()?);
+                    payload = payload.get(len..)?;
+                }
+                IpNextHeaderProtocols::Ipv6Frag => {
+                    first_fragment = u16::from_be_bytes([*payload.get(2)?, *payload.get(3)?]) >> 3 == 0;
+                    protocol = IpNextHeaderProtocol(*payload.first()?);
+                    payload = payload.get(8..)?;
+                }
+                _ => break,
+            }
+        }
+        let (src_port, dst_port) = if first_fragment { Self::ports(protocol, payload) } else { (0, 0) };
+
+        Some(Self {
+            src_addr: IpAddr::V6(ip.get_source()),
+            dst_addr: IpAddr::V6(ip.get_destination()),
+            src_port,
+            dst_port,
+            protocol: protocol.0,
+        })
+    }
+
+    fn ports(protocol: IpNextHeaderProtocol, transport: &[u8]) -> (u16, u16) {
+        match protocol {
+            IpNextHeaderProtocols::Tcp => TcpPacket::new(transport)
+                .map(|tcp| (tcp.get_source(), tcp.get_destination()))
+                .unwrap_or((0, 0)),
+            IpNextHeaderProtocols::Udp => UdpPacket::new(transport)
+                .map(|udp| (udp.get_source(), udp.get_destination()))
+                .unwrap_or((0, 0)),
+            _ => (0, 0),
+        }
+    }
+
+    /// The key carried by packets travelling the opposite direction of this flow
+    pub fn reversed(&self) -> Self {
+        Self {
+            src_addr: self.dst_addr,
+            dst_addr: self.src_addr,
+            src_port: self.dst_port,
+            dst_port: self.src_port,
+            protocol: self.protocol,
+        }
+    }
+
+    /// Which of `shards` this flow belongs to, the same for every packet of
+    /// the flow for the life of the process
+    pub fn shard(&self, shards: usize) -> usize {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.hash(&mut hasher);
+        (hasher.finish() % shards.max(1) as u64) as usize
+    }
+
+    /// A packet of `size` bytes carrying this flow, padded with zeros. It is
+    /// made bigger if the headers alone don't fit.
+    pub fn build_packet(&self, size: usize) -> Result<Vec<u8>> {
+        let transport_len = match IpNextHeaderProtocol(self.protocol) {
+            IpNextHeaderProtocols::Tcp => 20,
+            IpNextHeaderProtocols::Udp => 8,
+            _ => 0,
+        };
+        let mut packet = match (self.src_addr, self.dst_addr) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                let len = size.max(20 + transport_len);
+                let mut packet = vec![0u8; len];
+                packet[0] = 0x45;
+                packet[2..4].copy_from_slice(&(len as u16).to_be_bytes());
+                packet[8] = 64;
+                packet[9] = self.protocol;
+                packet[12..16].copy_from_slice(&src.octets());
+                packet[16..20].copy_from_slice(&dst.octets());
+                let checksum = nat::checksum(&packet[..20]);
+                packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+                packet
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                let len = size.max(40 + transport_len);
+                let mut packet = vec![0u8; len];
+                packet[0] = 0x60;
+                packet[4..6].copy_from_slice(&((len - 40) as u16).to_be_bytes());
+                packet[6] = self.protocol;
+                packet[7] = 64;
+                packet[8..24].copy_from_slice(&src.octets());
+                packet[24..40].copy_from_slice(&dst.octets());
+                packet
+            }
+            _ => anyhow::bail!("The source and destination of a flow must both be IPv4 or both IPv6"),
+        };
+
+        let header_len = if self.dst_addr.is_ipv4() { 20 } else { 40 };
+        let payload_len = packet.len() - header_len;
+        let transport = &mut packet[header_len..];
+        if transport_len > 0 {
+            transport[0..2].copy_from_slice(&self.src_port.to_be_bytes());
+            transport[2..4].copy_from_slice(&self.dst_port.to_be_bytes());
+        }
+        match IpNextHeaderProtocol(self.protocol) {
+            // Data offset of a header without options
+            IpNextHeaderProtocols::Tcp => transport[12] = 5 << 4,
+            IpNextHeaderProtocols::Udp => transport[4..6].copy_from_slice(&(payload_len as u16).to_be_bytes()),
+            _ => {}
+        }
+        Ok(packet)
+    }
+}
+
+/// Destinations that skip aggregation and always leave through one interface
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BypassRoute {
+    pub cidr: Ipv4Cidr,
+    /// Interface name to send through; `None` uses the interface facing the
+    /// destination, or the primary interface
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
 }
 
+/// Returned by `route_packet` when every candidate interface is over its rate limit
 #[derive(Debug, Clone, Copy)]
+pub struct RateLimited;
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "All available interfaces are at their rate limit")
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Why a packet was rejected as malformed before routing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPacket {
+    Truncated(usize),
+    UnsupportedVersion(u8),
+    BadHeaderLength(usize),
+    BadTotalLength(usize),
+    BadChecksum,
+}
+
+impl std::fmt::Display for InvalidPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPacket::Truncated(len) => write!(f, "{} bytes is too short for an IP header", len),
+            InvalidPacket::UnsupportedVersion(version) => write!(f, "IP version {} is not supported", version),
+            InvalidPacket::BadHeaderLength(len) => write!(f, "Invalid IPv4 header length {}", len),
+            InvalidPacket::BadTotalLength(len) => write!(f, "IP total length {} doesn't fit the packet", len),
+            InvalidPacket::BadChecksum => write!(f, "IPv4 header checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPacket {}
+
+/// Minimum IPv4 header length in bytes
+const IPV4_MIN_HEADER_LEN: usize = 20;
+
+/// Fixed IPv6 header length in bytes
+const IPV6_HEADER_LEN: usize = 40;
+
+/// Check that a packet is well-formed IPv4 or IPv6 before it is classified
+/// and routed. Trailing bytes past the total length, such as Ethernet
+/// padding in captures, are allowed.
+pub fn validate_packet(packet_data: &[u8]) -> Result<(), InvalidPacket> {
+    if packet_data.len() < IPV4_MIN_HEADER_LEN {
+        return Err(InvalidPacket::Truncated(packet_data.len()));
+    }
+    match packet_data[0] >> 4 {
+        4 => validate_ipv4(packet_data),
+        6 => validate_ipv6(packet_data),
+        version => Err(InvalidPacket::UnsupportedVersion(version)),
+    }
+}
+
+fn validate_ipv6(packet_data: &[u8]) -> Result<(), InvalidPacket> {
+    if packet_data.len() < IPV6_HEADER_LEN {
+        return Err(InvalidPacket::Truncated(packet_data.len()));
+    }
+    let total_len = IPV6_HEADER_LEN + u16::from_be_bytes([packet_data[4], packet_data[5]]) as usize;
+    if total_len > packet_data.len() {
+        return Err(InvalidPacket::BadTotalLength(total_len));
+    }
+    Ok(())
+}
+
+fn validate_ipv4(packet_data: &[u8]) -> Result<(), InvalidPacket> {
+    let header_len = ((packet_data[0] & 0x0f) as usize) * 4;
+    if header_len < IPV4_MIN_HEADER_LEN || header_len > packet_data.len() {
+        return Err(InvalidPacket::BadHeaderLength(header_len));
+    }
+    let total_len = u16::from_be_bytes([packet_data[2], packet_data[3]]) as usize;
+    if total_len < header_len || total_len > packet_data.len() {
+        return Err(InvalidPacket::BadTotalLength(total_len));
+    }
+    // Summing a valid header including its checksum gives zero
+    if nat::checksum(&packet_data[..header_len]) != 0 {
+        return Err(InvalidPacket::BadChecksum);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct FlowEntry {
+    interface_index: u32,
+    last_seen: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 #[allow(dead_code)]
 pub enum TrafficType {
+    Dns,         // Lowest latency, holds up whatever follows it
     Gaming,      // Low latency priority
     Streaming,   // High bandwidth priority  
     File,        // Best effort
     Web,         // Balanced
+    #[default]
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl TrafficType {
+    /// Number of distinct values returned by `priority`
+    pub const PRIORITY_LEVELS: usize = 6;
+
+    pub const ALL: [TrafficType; 6] = [
+        TrafficType::Dns,
+        TrafficType::Gaming,
+        TrafficType::Streaming,
+        TrafficType::Web,
+        TrafficType::File,
+        TrafficType::Unknown,
+    ];
+
+    /// Name used in settings and metrics labels
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TrafficType::Dns => "dns",
+            TrafficType::Gaming => "gaming",
+            TrafficType::Streaming => "streaming",
+            TrafficType::File => "file",
+            TrafficType::Web => "web",
+            TrafficType::Unknown => "unknown",
+        }
+    }
+
+    /// Scheduling priority; higher values are served first when queues back up
+    pub fn priority(self) -> u8 {
+        match self {
+            TrafficType::Dns => 5,
+            TrafficType::Gaming => 4,
+            TrafficType::Streaming => 3,
+            TrafficType::Web => 2,
+            TrafficType::File => 1,
+            TrafficType::Unknown => 0,
+        }
+    }
+
+    /// Guess the traffic type from the packet alone
+    pub fn classify(packet_data: &[u8]) -> Self {
+        if FlowKey::from_packet(packet_data).is_some_and(|flow| flow.src_port == DNS_PORT || flow.dst_port == DNS_PORT) {
+            return TrafficType::Dns;
+        }
+        // For development, we'll do basic analysis based on packet size and patterns
+        match packet_data.len() {
+            0..=64 => TrafficType::Gaming,        // Small packets often gaming/VoIP
+            65..=512 => TrafficType::Web,         // Medium packets often web traffic
+            513..=1500 => TrafficType::Streaming, // Large packets often streaming
+            _ => TrafficType::File,               // Very large packets often file transfer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LoadBalancingMode {
     RoundRobin,
     LatencyBased,
     BandwidthBased,
     Balanced,
+    Weighted,
+    /// Like `Balanced`, but gaming packets are also copied out of every other
+    /// interface so the relay can forward whichever copy arrives first
+    Redundant,
+    /// Balance over the lowest-tier interfaces only; higher tiers are hot
+    /// standbys that carry traffic once every lower-tier link is down
+    ActivePassive,
+    /// Follow the recent traffic mix: latency-based while gaming dominates,
+    /// bandwidth-based while streaming and downloads do, balanced otherwise
+    Adaptive,
+    /// Pick at random, each interface in proportion to its score, so traffic
+    /// shifts gradually as metrics change instead of flipping between links
+    Proportional,
+}
+
+impl LoadBalancingMode {
+    pub const ALL: [LoadBalancingMode; 9] = [
+        LoadBalancingMode::RoundRobin,
+        LoadBalancingMode::LatencyBased,
+        LoadBalancingMode::BandwidthBased,
+        LoadBalancingMode::Balanced,
+        LoadBalancingMode::Weighted,
+        LoadBalancingMode::Redundant,
+        LoadBalancingMode::ActivePassive,
+        LoadBalancingMode::Adaptive,
+        LoadBalancingMode::Proportional,
+    ];
+
+    /// The name used by the settings file, the CLI and the GUI; matches the serde form
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadBalancingMode::RoundRobin => "round_robin",
+            LoadBalancingMode::LatencyBased => "latency_based",
+            LoadBalancingMode::BandwidthBased => "bandwidth_based",
+            LoadBalancingMode::Balanced => "balanced",
+            LoadBalancingMode::Weighted => "weighted",
+            LoadBalancingMode::Redundant => "redundant",
+            LoadBalancingMode::ActivePassive => "active_passive",
+            LoadBalancingMode::Adaptive => "adaptive",
+            LoadBalancingMode::Proportional => "proportional",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            LoadBalancingMode::RoundRobin => "Round Robin",
+            LoadBalancingMode::LatencyBased => "Latency Based",
+            LoadBalancingMode::BandwidthBased => "Bandwidth Based",
+            LoadBalancingMode::Balanced => "Balanced",
+            LoadBalancingMode::Weighted => "Weighted",
+            LoadBalancingMode::Redundant => "Redundant (Gaming)",
+            LoadBalancingMode::ActivePassive => "Active/Passive",
+            LoadBalancingMode::Adaptive => "Adaptive",
+            LoadBalancingMode::Proportional => "Proportional",
+        }
+    }
+
+    /// One line on what the mode optimizes for
+    pub fn description(&self) -> &'static str {
+        match self {
+            LoadBalancingMode::RoundRobin => "Spreads new flows evenly across interfaces in turn",
+            LoadBalancingMode::LatencyBased => "Sends new flows over the interface with the lowest, steadiest latency",
+            LoadBalancingMode::BandwidthBased => "Sends new flows over the least loaded interface",
+            LoadBalancingMode::Balanced => "Picks per traffic type: latency for gaming, spare bandwidth for streaming and downloads",
+            LoadBalancingMode::Weighted => "Spreads new flows in proportion to each interface's weight",
+            LoadBalancingMode::Redundant => "Like Balanced, but copies gaming packets over every interface through the relay",
+            LoadBalancingMode::ActivePassive => "Uses only the lowest-tier interfaces, failing over to the next tier when they are all down",
+            LoadBalancingMode::Adaptive => "Switches between latency and bandwidth based selection as the traffic mix changes",
+            LoadBalancingMode::Proportional => "Shares new flows out in proportion to each interface's score, shifting gradually as links change",
+        }
+    }
+
+    pub fn info(&self) -> ModeInfo {
+        ModeInfo {
+            name: self.as_str(),
+            display_name: self.display_name(),
+            description: self.description(),
+        }
+    }
+}
+
+/// A load balancing mode as presented to users
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModeInfo {
+    /// Accepted by `LoadBalancingMode::from_str`
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub description: &'static str,
+}
+
+impl std::fmt::Display for LoadBalancingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LoadBalancingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Invalid load balancing mode '{}'", s))
+    }
 }
 
 #[allow(dead_code)]
 pub struct PacketRouter {
     interface_manager: Arc<InterfaceManager>,
     interface_metrics: Arc<RwLock<HashMap<u32, PacketMetrics>>>,
-    routing_table: Arc<RwLock<HashMap<Ipv4Addr, u32>>>,
+    /// Most recent latency samples of each interface, for jitter
+    latency_samples: Arc<RwLock<HashMap<u32, VecDeque<Duration>>>>,
+    /// Bytes forwarded through each interface since the rates were last taken
+    forwarded: Arc<RwLock<HashMap<u32, AtomicU64>>>,
+    forwarded_since: Arc<std::sync::Mutex<Instant>>,
+    routing_table: Arc<RwLock<HashMap<IpAddr, u32>>>,
+    flow_table: Arc<RwLock<HashMap<FlowKey, FlowEntry>>>,
+    disabled_interfaces: Arc<RwLock<HashSet<u32>>>,
+    interface_weights: Arc<RwLock<HashMap<u32, f32>>>,
+    /// Failover tier of each interface in `ActivePassive` mode, lowest first
+    interface_tiers: Arc<RwLock<HashMap<u32, u8>>>,
+    rate_limits: Arc<RwLock<HashMap<u32, TokenBucket>>>,
+    weighted_round_robin: Arc<RwLock<HashMap<u32, f64>>>,
+    /// Last applied per-interface settings, kept for interfaces that appear later
+    interface_settings: Arc<RwLock<HashMap<String, InterfaceSettings>>>,
+    interface_health: Arc<RwLock<HashMap<u32, HealthStatus>>>,
+    /// Send failures per interface, see `CircuitBreaker`
+    circuit_breakers: Arc<RwLock<HashMap<u32, CircuitBreaker>>>,
+    /// Interface name each pinned destination was pinned to, for interfaces that appear later
+    destination_pins: Arc<RwLock<HashMap<IpAddr, String>>>,
+    /// Consulted in order before falling back to the size heuristic
+    classification_rules: Arc<RwLock<Vec<ClassificationRule>>>,
+    bypass_routes: Arc<RwLock<Vec<BypassRoute>>>,
+    health_config: HealthConfig,
+    /// Metrics older than this are treated as unknown
+    metrics_max_age: Duration,
+    /// Milliseconds of latency each millisecond of jitter counts as when scoring
+    jitter_weight: f32,
+    /// Balancer picks less confident than this go to the primary interface
+    min_confidence: f32,
     load_balancing_mode: LoadBalancingMode,
-    round_robin_counter: Arc<RwLock<usize>>,
+    /// When off, every packet goes through the primary interface
+    aggregation_enabled: bool,
+    /// Keep bulk file transfers off metered interfaces when others are available
+    avoid_metered: bool,
+    /// Send DNS through the lowest-latency interface whatever the mode
+    dns_latency_first: bool,
+    /// Interface DNS goes through while it is available
+    dns_interface: Option<String>,
+    /// Keep each flow on one interface. Only a relay that reassembles the
+    /// flow on the far side can do without it.
+    flow_affinity: bool,
+    /// Name and address of our own TUN device. It shows up in discovery like
+    /// any other interface, but routing into it would loop packets forever.
+    own_tun: Option<(String, Ipv4Addr)>,
+    /// Which discovered interfaces are used at all
+    interface_filter: InterfaceFilter,
+    /// Name of the interface the user chose as primary
+    primary_interface: Option<String>,
+    /// Interface index picked last by round-robin
+    round_robin_last: Arc<RwLock<Option<u32>>>,
+    interface_groups: Arc<RwLock<Vec<InterfaceGroup>>>,
+    /// Member each interface group sent through last, by group index
+    group_members_last: Arc<RwLock<HashMap<u32, u32>>>,
+    /// Strategy `Adaptive` mode currently follows, see `observe_traffic_mix`
+    adaptive_strategy: Arc<RwLock<LoadBalancingMode>>,
+    /// Records the decision made for each new flow
+    flow_log: Option<Arc<FlowLog>>,
 }
 
 impl PacketRouter {
@@ -56,116 +603,623 @@ impl PacketRouter {
         Self {
             interface_manager: Arc::new(interface_manager),
             interface_metrics: Arc::new(RwLock::new(HashMap::new())),
+            latency_samples: Arc::new(RwLock::new(HashMap::new())),
+            forwarded: Arc::new(RwLock::new(HashMap::new())),
+            forwarded_since: Arc::new(std::sync::Mutex::new(Instant::now())),
             routing_table: Arc::new(RwLock::new(HashMap::new())),
+            flow_table: Arc::new(RwLock::new(HashMap::new())),
+            disabled_interfaces: Arc::new(RwLock::new(HashSet::new())),
+            interface_weights: Arc::new(RwLock::new(HashMap::new())),
+            interface_tiers: Arc::new(RwLock::new(HashMap::new())),
+            rate_limits: Arc::new(RwLock::new(HashMap::new())),
+            weighted_round_robin: Arc::new(RwLock::new(HashMap::new())),
+            interface_settings: Arc::new(RwLock::new(HashMap::new())),
+            interface_health: Arc::new(RwLock::new(HashMap::new())),
+            circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
+            destination_pins: Arc::new(RwLock::new(HashMap::new())),
+            classification_rules: Arc::new(RwLock::new(Vec::new())),
+            bypass_routes: Arc::new(RwLock::new(Vec::new())),
+            health_config: HealthConfig::default(),
+            metrics_max_age: DEFAULT_METRICS_MAX_AGE,
+            jitter_weight: DEFAULT_JITTER_WEIGHT,
+            min_confidence: 0.0,
             load_balancing_mode: LoadBalancingMode::Balanced,
-            round_robin_counter: Arc::new(RwLock::new(0)),
+            aggregation_enabled: true,
+            avoid_metered: false,
+            dns_latency_first: true,
+            dns_interface: None,
+            flow_affinity: true,
+            own_tun: None,
+            interface_filter: InterfaceFilter::default(),
+            primary_interface: None,
+            round_robin_last: Arc::new(RwLock::new(None)),
+            interface_groups: Arc::new(RwLock::new(Vec::new())),
+            group_members_last: Arc::new(RwLock::new(HashMap::new())),
+            adaptive_strategy: Arc::new(RwLock::new(LoadBalancingMode::Balanced)),
+            flow_log: None,
+        }
+    }
+
+    /// A router whose interfaces already have the given metrics
+    #[cfg(test)]
+    pub(crate) fn with_metrics(interface_manager: InterfaceManager, metrics: HashMap<u32, PacketMetrics>) -> Self {
+        let router = Self::new(interface_manager);
+        Self {
+            interface_metrics: Arc::new(RwLock::new(metrics)),
+            ..router
         }
     }
 
     /// Analyze incoming packet and determine optimal routing
     pub async fn route_packet(&self, packet_data: &[u8]) -> Result<RoutingDecision> {
-        // Simplified packet analysis for development
-        let traffic_info = self.analyze_packet_simple(packet_data)?;
+        self.decide(packet_data, true).await
+    }
+
+    /// The decision `route_packet` would make for a packet of `size` bytes on
+    /// `flow`, without consuming rate limits, advancing round-robin turns or
+    /// recording the flow
+    pub async fn preview_route(&self, flow: &FlowKey, size: usize) -> Result<RoutingDecision> {
+        self.decide(&flow.build_packet(size)?, false).await
+    }
+
+    /// Route a packet. Unless `commit` is set, nothing the decision depends
+    /// on is changed, so the same packet gets the same answer again.
+    async fn decide(&self, packet_data: &[u8], commit: bool) -> Result<RoutingDecision> {
+        let traffic_info = self.analyze_packet(packet_data).await?;
         
         // Get current interface metrics
         let metrics = self.interface_metrics.read().await;
         let available_interfaces = self.get_available_interfaces().await;
 
         if available_interfaces.is_empty() {
-            return Err(anyhow::anyhow!("No available interfaces for routing"));
+            return Err(anyhow::anyhow!(self.unavailable_reason().await));
+        }
+
+        // Standby tiers stay idle, even when the active tier is rate limited
+        let available_interfaces = if self.load_balancing_mode == LoadBalancingMode::ActivePassive {
+            self.active_tier(available_interfaces).await
+        } else {
+            available_interfaces
+        };
+
+        // Skip interfaces that have used up their rate limit
+        let available_interfaces: Vec<PhysicalInterface> = {
+            let mut rate_limits = self.rate_limits.write().await;
+            available_interfaces
+                .into_iter()
+                .filter(|iface| {
+                    rate_limits
+                        .get_mut(&iface.index)
+                        .is_none_or(|bucket| bucket.has_capacity(packet_data.len()))
+                })
+                .collect()
+        };
+
+        if available_interfaces.is_empty() {
+            return Err(RateLimited.into());
+        }
+
+        // Manual overrides, and the primary interface while aggregation is
+        // off, skip flow pinning and load balancing entirely
+        let flow_key = FlowKey::from_packet(packet_data);
+        let forced = if self.aggregation_enabled {
+            self.select_override(&available_interfaces, &traffic_info).await
+        } else {
+            Some((self.select_primary(&available_interfaces), "Connection aggregation disabled".to_string()))
+        };
+        if let Some((interface, reason)) = forced {
+            if commit {
+                self.charge_rate_limit(interface.index, packet_data.len()).await?;
+            }
+            let decision = RoutingDecision {
+                interface_index: interface.index,
+                interface_name: interface.name.clone(),
+                confidence: self.calculate_confidence(interface, &metrics).await,
+                reason,
+                traffic_type: traffic_info.traffic_type,
+                duplicates: Vec::new(),
+                low_confidence_fallback: false,
+            };
+            if let Some(key) = flow_key.filter(|_| commit) {
+                self.record_flow(key, &decision).await;
+            }
+            return Ok(decision);
+        }
+
+        // Bulk transfers stay off data-capped links unless nothing else is up
+        let available_interfaces = if self.avoid_metered
+            && traffic_info.traffic_type == TrafficType::File
+            && available_interfaces.iter().any(|iface| !iface.is_metered)
+        {
+            available_interfaces.into_iter().filter(|iface| !iface.is_metered).collect()
+        } else {
+            available_interfaces
+        };
+
+        // Keep established flows on the interface they started on so the
+        // return path can match replies against the same flow table
+        if let Some(key) = flow_key.filter(|_| self.flow_affinity) {
+            let mut flows = self.flow_table.write().await;
+            if let Some(entry) = flows.get_mut(&key) {
+                if let Some(interface) = available_interfaces.iter().find(|i| i.index == entry.interface_index) {
+                    if commit {
+                        self.charge_rate_limit(interface.index, packet_data.len()).await?;
+                        entry.last_seen = Instant::now();
+                    }
+                    return Ok(RoutingDecision {
+                        interface_index: interface.index,
+                        interface_name: interface.name.clone(),
+                        confidence: self.calculate_confidence(interface, &metrics).await,
+                        reason: "Pinned to existing flow".to_string(),
+                        traffic_type: traffic_info.traffic_type,
+                        duplicates: self.redundant_copies(&available_interfaces, interface.index, traffic_info.traffic_type, packet_data.len()).await,
+                        low_confidence_fallback: false,
+                    });
+                }
+            }
         }
 
-        // Apply load balancing strategy
-        let selected_interface = match self.load_balancing_mode {
+        // Each interface group competes as one candidate
+        let grouped = self.collapse_groups(&available_interfaces, &metrics).await;
+        let (candidates, candidate_metrics) = match &grouped {
+            Some((candidates, group_metrics)) => (candidates.as_slice(), group_metrics),
+            None => (available_interfaces.as_slice(), &*metrics),
+        };
+
+        // Apply load balancing strategy. Lookups hold up the connections
+        // that follow them, so they take the fastest path.
+        let strategy = if traffic_info.traffic_type == TrafficType::Dns && self.dns_latency_first {
+            LoadBalancingMode::LatencyBased
+        } else {
+            self.effective_mode().await
+        };
+        let selected_interface = match strategy {
             LoadBalancingMode::RoundRobin => {
-                self.select_round_robin(&available_interfaces).await
+                self.select_round_robin(candidates, commit).await
             }
             LoadBalancingMode::LatencyBased => {
-                self.select_by_latency(&available_interfaces, &metrics).await
+                self.select_by_latency(candidates, candidate_metrics, self.jitter_weight).await
             }
             LoadBalancingMode::BandwidthBased => {
-                self.select_by_bandwidth(&available_interfaces, &metrics).await
+                self.select_by_bandwidth(candidates, candidate_metrics).await
+            }
+            LoadBalancingMode::Balanced
+            | LoadBalancingMode::Redundant
+            | LoadBalancingMode::ActivePassive
+            | LoadBalancingMode::Adaptive => {
+                self.select_balanced(candidates, candidate_metrics, traffic_info.traffic_type).await
             }
-            LoadBalancingMode::Balanced => {
-                self.select_balanced(&available_interfaces, &metrics, traffic_info.traffic_type).await
+            LoadBalancingMode::Weighted => {
+                self.select_weighted_round_robin(candidates, commit).await
+            }
+            LoadBalancingMode::Proportional => {
+                self.select_proportional(candidates, candidate_metrics).await
             }
         };
 
-        let interface = selected_interface.context("Failed to select interface")?;
-        
-        Ok(RoutingDecision {
+        let selected = selected_interface.context("Failed to select interface")?;
+        let mut confidence = self.calculate_confidence(&selected, candidate_metrics).await;
+        let (mut interface, mut reason) = if selected.index >= GROUP_INDEX_BASE {
+            let member = self
+                .next_group_member(selected.index, &available_interfaces, commit)
+                .await
+                .context("Interface group has no available members")?;
+            (member, format!("Selected group {} based on {:?} strategy", selected.name, strategy))
+        } else {
+            (selected, format!("Selected based on {:?} strategy", strategy))
+        };
+
+        // Rather than gamble on stale or poor metrics, use the primary interface
+        let primary = self.select_primary(&available_interfaces);
+        let low_confidence_fallback = confidence < self.min_confidence && primary.index != interface.index;
+        if low_confidence_fallback {
+            reason = format!("{} had confidence {:.2}, below {:.2}; using the primary interface", interface.name, confidence, self.min_confidence);
+            interface = primary.clone();
+            confidence = self.calculate_confidence(&interface, &metrics).await;
+        }
+
+        if commit {
+            self.charge_rate_limit(interface.index, packet_data.len()).await?;
+        }
+
+        let decision = RoutingDecision {
             interface_index: interface.index,
             interface_name: interface.name.clone(),
-            confidence: self.calculate_confidence(&interface, &metrics).await,
-            reason: format!("Selected based on {:?} strategy", self.load_balancing_mode),
-        })
+            confidence,
+            reason,
+            traffic_type: traffic_info.traffic_type,
+            duplicates: self.redundant_copies(&available_interfaces, interface.index, traffic_info.traffic_type, packet_data.len()).await,
+            low_confidence_fallback,
+        };
+        if let Some(key) = flow_key.filter(|_| commit) {
+            self.record_flow(key, &decision).await;
+        }
+        Ok(decision)
+    }
+
+    /// `interfaces` with the members of each interface group replaced by one
+    /// candidate for the whole group, and metrics with the group's added.
+    /// `None` when no group has an available member.
+    async fn collapse_groups(
+        &self,
+        interfaces: &[PhysicalInterface],
+        metrics: &HashMap<u32, PacketMetrics>,
+    ) -> Option<(Vec<PhysicalInterface>, HashMap<u32, PacketMetrics>)> {
+        let groups = self.interface_groups.read().await;
+        let mut candidates = Vec::new();
+        let mut group_metrics = HashMap::new();
+        let mut grouped = HashSet::new();
+
+        for group in groups.iter() {
+            let members: Vec<&PhysicalInterface> = interfaces
+                .iter()
+                .filter(|iface| group.members.contains(&iface.index))
+                .collect();
+            let Some(first) = members.first() else {
+                continue;
+            };
+            grouped.extend(members.iter().map(|iface| iface.index));
+
+            candidates.push(PhysicalInterface {
+                name: group.name.clone(),
+                index: group.index,
+                link_speed_mbps: Some(members.iter().map(|iface| iface.link_speed_mbps.unwrap_or(DEFAULT_LINK_SPEED_MBPS)).sum()),
+                mtu: members.iter().map(|iface| iface.mtu).min().unwrap_or(first.mtu),
+                is_metered: members.iter().any(|iface| iface.is_metered),
+                ..(*first).clone()
+            });
+            if let Some(combined) = combine_metrics(members.iter().filter_map(|iface| metrics.get(&iface.index))) {
+                group_metrics.insert(group.index, combined);
+            }
+        }
+
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.extend(interfaces.iter().filter(|iface| !grouped.contains(&iface.index)).cloned());
+        group_metrics.extend(metrics.iter().map(|(index, metrics)| (*index, metrics.clone())));
+        Some((candidates, group_metrics))
+    }
+
+    /// The available member of a group after the one it sent through last,
+    /// rotating by index like round-robin. The rotation only moves on if
+    /// `commit` is set.
+    async fn next_group_member(&self, group_index: u32, interfaces: &[PhysicalInterface], commit: bool) -> Option<PhysicalInterface> {
+        let groups = self.interface_groups.read().await;
+        let group = groups.iter().find(|group| group.index == group_index)?;
+        let members: Vec<&PhysicalInterface> = interfaces
+            .iter()
+            .filter(|iface| group.members.contains(&iface.index))
+            .collect();
+
+        let mut last_members = self.group_members_last.write().await;
+        let last = last_members.get(&group_index).copied();
+        let next = members
+            .iter()
+            .filter(|iface| last.is_none_or(|last| iface.index > last))
+            .min_by_key(|iface| iface.index)
+            .or_else(|| members.iter().min_by_key(|iface| iface.index))?;
+        if commit {
+            last_members.insert(group_index, next.index);
+        }
+        Some((*next).clone())
+    }
+
+    /// Bond the interfaces with the given indices into a group the balancer
+    /// treats as one link
+    pub async fn create_interface_group(&self, indices: &[u32]) -> Result<InterfaceGroup> {
+        let mut members: Vec<u32> = indices.to_vec();
+        members.sort_unstable();
+        members.dedup();
+        if members.len() < 2 {
+            anyhow::bail!("An interface group needs at least two interfaces");
+        }
+
+        let mut names = Vec::new();
+        for index in &members {
+            let interface = self.find_interface(*index).with_context(|| format!("Unknown interface index {}", index))?;
+            names.push(interface.name);
+        }
+
+        let mut groups = self.interface_groups.write().await;
+        if let Some(group) = groups.iter().find(|group| group.members.iter().any(|index| members.contains(index))) {
+            anyhow::bail!("Interfaces can only belong to one group, and group {} already has some of these", group.name);
+        }
+
+        let group = InterfaceGroup {
+            index: groups.iter().map(|group| group.index + 1).max().unwrap_or(GROUP_INDEX_BASE),
+            name: names.join("+"),
+            members,
+        };
+        groups.push(group.clone());
+        Ok(group)
+    }
+
+    /// Split a group back into its interfaces
+    pub async fn remove_interface_group(&self, index: u32) -> Result<InterfaceGroup> {
+        let mut groups = self.interface_groups.write().await;
+        let position = groups
+            .iter()
+            .position(|group| group.index == index)
+            .with_context(|| format!("Unknown interface group {}", index))?;
+        self.group_members_last.write().await.remove(&index);
+        Ok(groups.remove(position))
+    }
+
+    pub async fn interface_groups(&self) -> Vec<InterfaceGroup> {
+        self.interface_groups.read().await.clone()
+    }
+
+    /// In `Redundant` mode, the other interfaces a gaming packet sent through
+    /// `chosen` could also be copied to, as far as their rate limits allow.
+    /// Nothing is charged until a copy is actually sent.
+    async fn redundant_copies(
+        &self,
+        interfaces: &[PhysicalInterface],
+        chosen: u32,
+        traffic_type: TrafficType,
+        len: usize,
+    ) -> Vec<u32> {
+        if self.load_balancing_mode != LoadBalancingMode::Redundant || traffic_type != TrafficType::Gaming {
+            return Vec::new();
+        }
+        let mut rate_limits = self.rate_limits.write().await;
+        interfaces
+            .iter()
+            .filter(|iface| iface.index != chosen)
+            .filter(|iface| {
+                rate_limits
+                    .get_mut(&iface.index)
+                    .is_none_or(|bucket| bucket.has_capacity(len))
+            })
+            .map(|iface| iface.index)
+            .collect()
+    }
+
+    /// Take `len` bytes from the rate limit of interface `index`, if it has
+    /// one. Fails with `RateLimited` if another packet used up the capacity
+    /// since the interface was picked.
+    pub async fn charge_rate_limit(&self, index: u32, len: usize) -> Result<()> {
+        let mut rate_limits = self.rate_limits.write().await;
+        if rate_limits.get_mut(&index).is_some_and(|bucket| !bucket.try_consume(len)) {
+            return Err(RateLimited.into());
+        }
+        Ok(())
+    }
+
+    /// Remember the interface a flow was sent through, logging flows that
+    /// are new or have moved to another interface. Without flow affinity
+    /// flows move all the time, so only new ones are logged.
+    async fn record_flow(&self, key: FlowKey, decision: &RoutingDecision) {
+        let previous = self.flow_table.write().await.insert(key, FlowEntry {
+            interface_index: decision.interface_index,
+            last_seen: Instant::now(),
+        });
+        if previous.is_none_or(|entry| self.flow_affinity && entry.interface_index != decision.interface_index) {
+            if let Some(flow_log) = &self.flow_log {
+                flow_log.record(&key, decision);
+            }
+        }
+    }
+
+    /// Classify a packet by the first matching rule, falling back to the
+    /// size heuristic when no rule matches
+    async fn analyze_packet(&self, packet_data: &[u8]) -> Result<TrafficInfo> {
+        let Some(flow) = FlowKey::from_packet(packet_data) else {
+            return self.analyze_packet_simple(packet_data);
+        };
+
+        let rules = self.classification_rules.read().await;
+        match classifier::first_match(&rules, &flow) {
+            Some(rule) => Ok(TrafficInfo {
+                traffic_type: rule.traffic_type,
+                priority: rule.traffic_type.priority(),
+                estimated_size: packet_data.len() as u64,
+                destination: Some(flow.dst_addr),
+                bypass: rule.bypass,
+            }),
+            None => self.analyze_packet_simple(packet_data),
+        }
     }
 
     /// Simplified packet analysis without deep packet inspection
     fn analyze_packet_simple(&self, packet_data: &[u8]) -> Result<TrafficInfo> {
-        // For development, we'll do basic analysis based on packet size and patterns
-        let packet_size = packet_data.len() as u64;
-        
-        let (traffic_type, priority) = match packet_size {
-            0..=64 => (TrafficType::Gaming, 4),      // Small packets often gaming/VoIP
-            65..=512 => (TrafficType::Web, 2),       // Medium packets often web traffic
-            513..=1500 => (TrafficType::Streaming, 3), // Large packets often streaming
-            _ => (TrafficType::File, 1),             // Very large packets often file transfer
-        };
+        let traffic_type = TrafficType::classify(packet_data);
 
         Ok(TrafficInfo {
             traffic_type,
-            priority,
-            estimated_size: packet_size,
-            destination: None, // Would need actual packet parsing for this
+            priority: traffic_type.priority(),
+            estimated_size: packet_data.len() as u64,
+            destination: FlowKey::from_packet(packet_data).map(|flow| flow.dst_addr),
+            bypass: false,
         })
     }
 
-    /// Round-robin interface selection
-    async fn select_round_robin(&self, interfaces: &[PhysicalInterface]) -> Option<PhysicalInterface> {
-        let mut counter = self.round_robin_counter.write().await;
-        let index = *counter % interfaces.len();
-        *counter += 1;
-        interfaces.get(index).cloned()
+    /// The traffic type `route_packet` would assign to a packet
+    pub async fn classify(&self, packet_data: &[u8]) -> TrafficType {
+        self.analyze_packet(packet_data)
+            .await
+            .map(|info| info.traffic_type)
+            .unwrap_or_default()
+    }
+
+    /// The primary interface, or the first usable one if the primary is
+    /// disabled, unhealthy or rate limited. `interfaces` must not be empty.
+    fn select_primary<'a>(&self, interfaces: &'a [PhysicalInterface]) -> &'a PhysicalInterface {
+        self.interface_manager
+            .get_preferred_primary(self.primary_interface.as_deref())
+            .and_then(|primary| interfaces.iter().find(|iface| iface.index == primary.index))
+            .unwrap_or(&interfaces[0])
+    }
+
+    /// A manual routing choice for the packet: a pinned destination, then a
+    /// bypass route, then a bypass classification rule
+    async fn select_override<'a>(
+        &self,
+        interfaces: &'a [PhysicalInterface],
+        traffic_info: &TrafficInfo,
+    ) -> Option<(&'a PhysicalInterface, String)> {
+        if let Some(destination) = traffic_info.destination {
+            // A pin to an unavailable interface falls back to normal routing
+            let pinned = self.routing_table.read().await.get(&destination).copied();
+            if let Some(interface) = pinned.and_then(|index| interfaces.iter().find(|iface| iface.index == index)) {
+                return Some((interface, format!("Destination {} pinned", destination)));
+            }
+            if let Some(route) = self.find_bypass_route(destination).await {
+                let interface = self.select_bypass(interfaces, Some(destination), route.interface.as_deref())?;
+                return Some((interface, format!("Matched bypass route {}", route.cidr)));
+            }
+        }
+        if traffic_info.bypass {
+            let interface = self.select_bypass(interfaces, traffic_info.destination, None)?;
+            return Some((interface, "Bypassed by classification rule".to_string()));
+        }
+        if traffic_info.traffic_type == TrafficType::Dns {
+            let pinned = self.dns_interface.as_deref().and_then(|name| interfaces.iter().find(|iface| iface.name == name));
+            if let Some(interface) = pinned {
+                return Some((interface, format!("DNS pinned to {}", interface.name)));
+            }
+        }
+        None
+    }
+
+    /// The named interface if it is a candidate, else the one whose subnet
+    /// holds the destination, otherwise the primary interface
+    fn select_bypass<'a>(
+        &self,
+        interfaces: &'a [PhysicalInterface],
+        destination: Option<IpAddr>,
+        interface_name: Option<&str>,
+    ) -> Option<&'a PhysicalInterface> {
+        let on_link = |destination: IpAddr| match destination {
+            IpAddr::V4(destination) => interfaces.iter().find(|iface| iface.is_on_link(destination)),
+            IpAddr::V6(_) => None,
+        };
+        interface_name
+            .and_then(|name| interfaces.iter().find(|iface| iface.name == name))
+            .or_else(|| destination.and_then(on_link))
+            .or_else(|| (!interfaces.is_empty()).then(|| self.select_primary(interfaces)))
+    }
+
+    /// The most specific bypass route covering `destination`. Bypass routes
+    /// are IPv4 networks, so IPv6 destinations never match.
+    async fn find_bypass_route(&self, destination: IpAddr) -> Option<BypassRoute> {
+        let IpAddr::V4(destination) = destination else {
+            return None;
+        };
+        self.bypass_routes
+            .read()
+            .await
+            .iter()
+            .filter(|route| route.cidr.contains(destination))
+            .max_by_key(|route| route.cidr.prefix_len())
+            .cloned()
+    }
+
+    /// Round-robin interface selection. The turn only passes on if `commit` is set.
+    async fn select_round_robin(&self, interfaces: &[PhysicalInterface], commit: bool) -> Option<PhysicalInterface> {
+        // Rotate by interface index rather than list position, so interfaces
+        // coming and going don't shift everyone else's turn
+        let mut last = self.round_robin_last.write().await;
+        let next = interfaces
+            .iter()
+            .filter(|iface| last.is_none_or(|last| iface.index > last))
+            .min_by_key(|iface| iface.index)
+            .or_else(|| interfaces.iter().min_by_key(|iface| iface.index))?;
+        if commit {
+            *last = Some(next.index);
+        }
+        Some(next.clone())
+    }
+
+    /// Smooth weighted round-robin: each interface gets a share of packets
+    /// proportional to its link speed multiplied by its user-assigned weight.
+    /// Without `commit` the running weights are left as they were.
+    async fn select_weighted_round_robin(&self, interfaces: &[PhysicalInterface], commit: bool) -> Option<PhysicalInterface> {
+        let user_weights = self.interface_weights.read().await;
+        let mut running = self.weighted_round_robin.write().await;
+        let mut scratch;
+        let current = if commit {
+            &mut *running
+        } else {
+            scratch = running.clone();
+            &mut scratch
+        };
+
+        let mut total = 0.0;
+        let mut selected: Option<&PhysicalInterface> = None;
+        let mut selected_weight = f64::MIN;
+
+        for interface in interfaces {
+            let speed = interface.link_speed_mbps.unwrap_or(DEFAULT_LINK_SPEED_MBPS) as f64;
+            let weight = speed * user_weights.get(&interface.index).copied().unwrap_or(1.0) as f64;
+            total += weight;
+
+            let entry = current.entry(interface.index).or_insert(0.0);
+            *entry += weight;
+            if *entry > selected_weight {
+                selected_weight = *entry;
+                selected = Some(interface);
+            }
+        }
+
+        let selected = selected?;
+        if let Some(entry) = current.get_mut(&selected.index) {
+            *entry -= total;
+        }
+        Some(selected.clone())
+    }
+
+    /// The metrics of an interface, unless they are missing or too old to trust
+    fn fresh_metrics<'a>(&self, metrics: &'a HashMap<u32, PacketMetrics>, index: u32) -> Option<&'a PacketMetrics> {
+        metrics
+            .get(&index)
+            .filter(|metric| metric.last_updated.elapsed() <= self.metrics_max_age)
     }
 
-    /// Select interface with lowest latency
-    async fn select_by_latency(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>) -> Option<PhysicalInterface> {
+    /// Select interface with lowest latency, counting each millisecond of
+    /// jitter as `jitter_weight` milliseconds of latency
+    async fn select_by_latency(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>, jitter_weight: f32) -> Option<PhysicalInterface> {
         interfaces.iter()
             .min_by(|a, b| {
-                let latency_a = metrics.get(&a.index)
-                    .map(|m| m.latency)
-                    .unwrap_or(Duration::from_millis(9999));
-                let latency_b = metrics.get(&b.index)
-                    .map(|m| m.latency)
-                    .unwrap_or(Duration::from_millis(9999));
-                latency_a.cmp(&latency_b)
+                let latency_a = self.fresh_metrics(metrics, a.index)
+                    .map_or(9999.0, |m| effective_latency_ms(m, jitter_weight));
+                let latency_b = self.fresh_metrics(metrics, b.index)
+                    .map_or(9999.0, |m| effective_latency_ms(m, jitter_weight));
+                latency_a.partial_cmp(&latency_b).unwrap_or(std::cmp::Ordering::Equal)
             })
             .cloned()
     }
 
-    /// Select interface with highest available bandwidth
+    /// Select the interface with the least bandwidth in use. Ties go to the
+    /// faster link, then to the lowest index, so the choice is deterministic.
     async fn select_by_bandwidth(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>) -> Option<PhysicalInterface> {
-        interfaces.iter()
-            .max_by(|a, b| {
-                let usage_a = metrics.get(&a.index)
-                    .map(|m| m.bandwidth_usage)
-                    .unwrap_or(u64::MAX);
-                let usage_b = metrics.get(&b.index)
-                    .map(|m| m.bandwidth_usage)
-                    .unwrap_or(u64::MAX);
-                // Lower usage = higher available bandwidth
-                usage_b.cmp(&usage_a)
+        interfaces
+            .iter()
+            .min_by_key(|iface| {
+                let speed = iface.link_speed_mbps.unwrap_or(DEFAULT_LINK_SPEED_MBPS);
+                (self.bandwidth_usage(metrics, iface.index), std::cmp::Reverse(speed), iface.index)
             })
             .cloned()
     }
 
+    /// Bandwidth in use on an interface, as `select_by_bandwidth` sees it. An
+    /// interface that hasn't been measured yet counts as idle, so a new link
+    /// gets traffic straight away. One whose metrics have gone stale counts
+    /// as saturated, since measuring it has stopped working.
+    fn bandwidth_usage(&self, metrics: &HashMap<u32, PacketMetrics>, index: u32) -> u64 {
+        match metrics.get(&index) {
+            None => 0,
+            Some(_) => self.fresh_metrics(metrics, index).map_or(u64::MAX, |m| m.bandwidth_usage),
+        }
+    }
+
     /// Balanced selection based on traffic type
     async fn select_balanced(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>, traffic_type: TrafficType) -> Option<PhysicalInterface> {
         match traffic_type {
             TrafficType::Gaming => {
-                // Prioritize latency for gaming
-                self.select_by_latency(interfaces, metrics).await
+                // Prioritize steady, low latency for gaming
+                self.select_by_latency(interfaces, metrics, self.jitter_weight * GAMING_JITTER_EMPHASIS).await
             }
             TrafficType::Streaming => {
                 // Prioritize bandwidth for streaming
@@ -175,7 +1229,7 @@ impl PacketRouter {
                 // Use least loaded interface for file transfers
                 self.select_by_bandwidth(interfaces, metrics).await
             }
-            TrafficType::Web | TrafficType::Unknown => {
+            TrafficType::Dns | TrafficType::Web | TrafficType::Unknown => {
                 // Balanced approach for web traffic
                 self.select_weighted_best(interfaces, metrics).await
             }
@@ -184,60 +1238,644 @@ impl PacketRouter {
 
     /// Weighted selection considering both latency and bandwidth
     async fn select_weighted_best(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>) -> Option<PhysicalInterface> {
+        let weights = self.interface_weights.read().await;
         interfaces.iter()
             .max_by(|a, b| {
-                let score_a = self.calculate_interface_score(a, metrics);
-                let score_b = self.calculate_interface_score(b, metrics);
+                let score_a = self.calculate_interface_score(a, metrics) * weights.get(&a.index).copied().unwrap_or(1.0);
+                let score_b = self.calculate_interface_score(b, metrics) * weights.get(&b.index).copied().unwrap_or(1.0);
                 score_a.partial_cmp(&score_b).unwrap_or(std::cmp::Ordering::Equal)
             })
             .cloned()
     }
 
-    /// Calculate a composite score for interface selection
-    fn calculate_interface_score(&self, interface: &PhysicalInterface, metrics: &HashMap<u32, PacketMetrics>) -> f32 {
-        if let Some(metric) = metrics.get(&interface.index) {
-            let latency_score = 1000.0 / (metric.latency.as_millis() as f32 + 1.0);
-            let bandwidth_score = 1.0 / (metric.bandwidth_usage as f32 + 1.0);
-            let reliability_score = 1.0 - metric.packet_loss;
-            
+    /// Sample an interface with probability proportional to its score times
+    /// its user weight, so one scoring twice another gets about twice the
+    /// flows. Until any interface has fresh metrics they share equally.
+    async fn select_proportional(&self, interfaces: &[PhysicalInterface], metrics: &HashMap<u32, PacketMetrics>) -> Option<PhysicalInterface> {
+        let weights = self.interface_weights.read().await;
+        let mut scores: Vec<f32> = interfaces
+            .iter()
+            .map(|iface| self.calculate_interface_score(iface, metrics) * weights.get(&iface.index).copied().unwrap_or(1.0))
+            .map(|score| score.max(0.0))
+            .collect();
+        if scores.iter().sum::<f32>() <= 0.0 {
+            scores.fill(1.0);
+        }
+
+        let mut point = rand::random::<f32>() * scores.iter().sum::<f32>();
+        for (interface, score) in interfaces.iter().zip(&scores) {
+            if point < *score {
+                return Some(interface.clone());
+            }
+            point -= score;
+        }
+        // Rounding can leave the point just past the end
+        interfaces.iter().zip(&scores).rev().find(|(_, score)| **score > 0.0).map(|(iface, _)| iface.clone())
+    }
+
+    /// Calculate a composite score for interface selection
+    fn calculate_interface_score(&self, interface: &PhysicalInterface, metrics: &HashMap<u32, PacketMetrics>) -> f32 {
+        if let Some(metric) = self.fresh_metrics(metrics, interface.index) {
+            let latency_score = 1000.0 / (effective_latency_ms(metric, self.jitter_weight) + 1.0);
+            let bandwidth_score = 1.0 / (metric.bandwidth_usage as f32 + 1.0);
+            let reliability_score = 1.0 - metric.packet_loss;
+            
             // Weighted combination
             (latency_score * 0.4) + (bandwidth_score * 0.4) + (reliability_score * 0.2)
         } else {
-            0.0 // No metrics available
+            0.0 // No metrics available, or too old to trust
+        }
+    }
+
+    pub(crate) async fn get_available_interfaces(&self) -> Vec<PhysicalInterface> {
+        // Return all interfaces from the interface manager that haven't been disabled
+        let disabled = self.disabled_interfaces.read().await;
+        let enabled: Vec<PhysicalInterface> = self.interface_manager.get_all_interfaces()
+            .iter()
+            .filter(|iface| !disabled.contains(&iface.index) && !self.is_own_tun(iface))
+            .cloned()
+            .collect();
+
+        // Drain unhealthy links and ones that keep failing to send, but keep
+        // routing over them if nothing else is left
+        let health = self.interface_health.read().await;
+        let breakers = self.circuit_breakers.read().await;
+        let healthy: Vec<PhysicalInterface> = enabled
+            .iter()
+            .filter(|iface| health.get(&iface.index).is_none_or(|status| status.healthy))
+            .filter(|iface| breakers.get(&iface.index).is_none_or(|breaker| !breaker.is_open()))
+            .cloned()
+            .collect();
+
+        if healthy.is_empty() { enabled } else { healthy }
+    }
+
+    /// Why no interface is available, naming what is wrong with each one
+    async fn unavailable_reason(&self) -> String {
+        let disabled = self.disabled_interfaces.read().await;
+        let health = self.interface_health.read().await;
+        let breakers = self.circuit_breakers.read().await;
+        let reasons: Vec<(&str, &str)> = self
+            .interface_manager
+            .get_all_interfaces()
+            .iter()
+            .filter(|iface| !self.is_own_tun(iface))
+            .map(|iface| {
+                let reason = if disabled.contains(&iface.index) {
+                    "disabled"
+                } else if breakers.get(&iface.index).is_some_and(|breaker| breaker.is_open()) {
+                    "failing to send"
+                } else if health.get(&iface.index).is_some_and(|status| !status.healthy) {
+                    "unhealthy"
+                } else {
+                    "unavailable"
+                };
+                (iface.name.as_str(), reason)
+            })
+            .collect();
+
+        if reasons.is_empty() {
+            return "No available interfaces for routing".to_string();
         }
+        let described: Vec<String> = reasons.iter().map(|(name, reason)| format!("{} is {}", name, reason)).collect();
+        let hint = if reasons.iter().all(|(_, reason)| *reason == "disabled") {
+            "; enable at least one to route traffic"
+        } else {
+            ""
+        };
+        format!("No interface can route traffic: {}{}", described.join(", "), hint)
+    }
+
+    /// The interfaces of the lowest tier present. `interfaces` are the
+    /// available ones, so a tier is passed over once all of its links are
+    /// disabled, unhealthy or failing to send.
+    async fn active_tier(&self, interfaces: Vec<PhysicalInterface>) -> Vec<PhysicalInterface> {
+        let tiers = self.interface_tiers.read().await;
+        let tier_of = |iface: &PhysicalInterface| tiers.get(&iface.index).copied().unwrap_or(DEFAULT_TIER);
+        let Some(active) = interfaces.iter().map(tier_of).min() else {
+            return interfaces;
+        };
+        interfaces.into_iter().filter(|iface| tier_of(iface) == active).collect()
+    }
+
+    fn is_own_tun(&self, interface: &PhysicalInterface) -> bool {
+        self.own_tun
+            .as_ref()
+            .is_some_and(|(name, address)| interface.name == *name || interface.ip_address == *address)
+    }
+
+    /// Never route over the TUN device with this name and address, i.e. the
+    /// one packets are read from. It is dropped from the interfaces now and
+    /// on every rediscovery, so no return path is opened on it either.
+    pub fn exclude_tun(&mut self, name: String, address: Ipv4Addr) {
+        self.own_tun = Some((name, address));
+        let interfaces = self.get_all_interfaces().into_iter().filter(|iface| !self.is_own_tun(iface)).collect();
+        self.interface_manager = Arc::new(InterfaceManager { interfaces });
+    }
+
+    /// Number of interfaces that new traffic can currently be routed over
+    pub async fn active_interface_count(&self) -> usize {
+        let available = self.get_available_interfaces().await.len();
+        if self.aggregation_enabled { available } else { available.min(1) }
     }
 
-    async fn get_available_interfaces(&self) -> Vec<PhysicalInterface> {
-        // Return all interfaces from the interface manager
+    /// Every discovered interface, including disabled ones
+    pub fn get_all_interfaces(&self) -> Vec<PhysicalInterface> {
         self.interface_manager.get_all_interfaces().clone()
     }
 
+    /// Exclude an interface from routing until it is re-enabled
+    pub async fn disable_interface(&self, index: u32) {
+        self.disabled_interfaces.write().await.insert(index);
+    }
+
+    /// Return a previously disabled interface to the routing pool
+    pub async fn enable_interface(&self, index: u32) {
+        self.disabled_interfaces.write().await.remove(&index);
+    }
+
     async fn calculate_confidence(&self, interface: &PhysicalInterface, metrics: &HashMap<u32, PacketMetrics>) -> f32 {
-        if let Some(metric) = metrics.get(&interface.index) {
-            // Base confidence on metrics quality
-            let latency_confidence = if metric.latency.as_millis() < 50 { 0.9 } else { 0.6 };
-            let loss_confidence = 1.0 - metric.packet_loss;
-            (latency_confidence + loss_confidence) / 2.0
-        } else {
-            0.5 // Medium confidence when no metrics available
+        match (metrics.get(&interface.index), self.fresh_metrics(metrics, interface.index)) {
+            (_, Some(metric)) => {
+                // Base confidence on metrics quality
+                let latency_confidence = if metric.latency.as_millis() < 50 { 0.9 } else { 0.6 };
+                let loss_confidence = 1.0 - metric.packet_loss;
+                (latency_confidence + loss_confidence) / 2.0
+            }
+            // The link hasn't been measured for a while and may have changed
+            (Some(_), None) => STALE_CONFIDENCE,
+            (None, None) => 0.5, // Medium confidence when no metrics available
         }
     }
 
-    /// Update metrics for an interface
+    /// Update metrics for an interface, folding `latency` into its jitter
     pub async fn update_interface_metrics(&self, interface_index: u32, latency: Duration, bandwidth_usage: u64, packet_loss: f32) {
+        let jitter = {
+            let mut latency_samples = self.latency_samples.write().await;
+            let samples = latency_samples.entry(interface_index).or_default();
+            if samples.len() == JITTER_WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back(latency);
+            standard_deviation(samples)
+        };
+
         let mut metrics = self.interface_metrics.write().await;
         metrics.insert(interface_index, PacketMetrics {
             latency,
+            jitter,
             bandwidth_usage,
             packet_loss,
             last_updated: Instant::now(),
         });
     }
 
+    /// Count `bytes` sent out of an interface. Only the interface's first
+    /// packet takes a write lock.
+    pub async fn record_forwarded(&self, interface_index: u32, bytes: usize) {
+        if let Some(counter) = self.forwarded.read().await.get(&interface_index) {
+            counter.fetch_add(bytes as u64, Ordering::Relaxed);
+            return;
+        }
+        self.forwarded
+            .write()
+            .await
+            .entry(interface_index)
+            .or_default()
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes per second forwarded through each interface since the last
+    /// call, restarting the count. The probes feed these into the metrics as
+    /// `bandwidth_usage`, so `Bandwidth` mode follows the traffic actually
+    /// forwarded rather than the service's total.
+    pub async fn take_forwarded_rates(&self) -> HashMap<u32, u64> {
+        let elapsed = {
+            let mut since = self.forwarded_since.lock().unwrap_or_else(|e| e.into_inner());
+            let elapsed = since.elapsed();
+            *since = Instant::now();
+            elapsed
+        };
+        let seconds = elapsed.as_secs_f64().max(0.001);
+        self.forwarded
+            .read()
+            .await
+            .iter()
+            .map(|(index, counter)| (*index, (counter.swap(0, Ordering::Relaxed) as f64 / seconds) as u64))
+            .collect()
+    }
+
+    /// Set the relative weight of an interface when scoring candidates
+    pub async fn set_interface_weight(&self, index: u32, weight: f32) {
+        self.interface_weights.write().await.insert(index, weight);
+    }
+
+    /// Set the failover tier of an interface for `ActivePassive` mode
+    pub async fn set_interface_tier(&self, index: u32, tier: u8) {
+        self.interface_tiers.write().await.insert(index, tier);
+    }
+
+    /// Cap an interface at `bytes_per_sec`, or remove its limit with `None`
+    pub async fn set_interface_rate_limit(&self, index: u32, bytes_per_sec: Option<u64>) {
+        let mut rate_limits = self.rate_limits.write().await;
+        match bytes_per_sec {
+            Some(rate) if rate > 0 => {
+                rate_limits.insert(index, TokenBucket::new(rate));
+            }
+            _ => {
+                rate_limits.remove(&index);
+            }
+        }
+    }
+
+    /// Look up a discovered interface by index
+    pub fn find_interface(&self, index: u32) -> Option<PhysicalInterface> {
+        self.interface_manager.get_all_interfaces()
+            .iter()
+            .find(|iface| iface.index == index)
+            .cloned()
+    }
+
+    /// Apply persisted per-interface settings to the discovered interfaces
+    pub async fn apply_interface_settings(&self, settings: &HashMap<String, InterfaceSettings>) {
+        *self.interface_settings.write().await = settings.clone();
+        for interface in self.interface_manager.get_all_interfaces() {
+            if let Some(interface_settings) = settings.get(&interface.name) {
+                self.apply_settings_to(interface.index, interface_settings).await;
+            }
+        }
+    }
+
+    async fn apply_settings_to(&self, index: u32, interface_settings: &InterfaceSettings) {
+        self.set_interface_weight(index, interface_settings.weight).await;
+        self.set_interface_tier(index, interface_settings.tier).await;
+        self.set_interface_rate_limit(index, interface_settings.rate_limit_bytes_per_sec).await;
+        if interface_settings.enabled {
+            self.enable_interface(index).await;
+        } else {
+            self.disable_interface(index).await;
+        }
+    }
+
+    /// Replace the routing pool with a fresh discovery. State belonging to
+    /// removed interfaces is dropped, apart from their flows, which are left
+    /// for `reroute_flows`, and saved settings are applied to new ones.
+    pub async fn update_interfaces(&mut self, interfaces: Vec<PhysicalInterface>) -> Vec<InterfaceEvent> {
+        let interfaces: Vec<PhysicalInterface> = self
+            .interface_filter
+            .apply(interfaces)
+            .into_iter()
+            .filter(|iface| !self.is_own_tun(iface))
+            .collect();
+        let events = self.interface_manager.diff(&interfaces);
+
+        for event in &events {
+            match event {
+                InterfaceEvent::Removed(interface) => {
+                    let index = interface.index;
+                    self.interface_metrics.write().await.remove(&index);
+                    self.latency_samples.write().await.remove(&index);
+                    self.forwarded.write().await.remove(&index);
+                    self.disabled_interfaces.write().await.remove(&index);
+                    self.interface_weights.write().await.remove(&index);
+                    self.interface_tiers.write().await.remove(&index);
+                    self.rate_limits.write().await.remove(&index);
+                    self.weighted_round_robin.write().await.remove(&index);
+                    self.interface_health.write().await.remove(&index);
+                    self.circuit_breakers.write().await.remove(&index);
+                    self.routing_table.write().await.retain(|_, pinned| *pinned != index);
+                }
+                InterfaceEvent::Added(interface) => {
+                    let saved = self.interface_settings.read().await.get(&interface.name).cloned();
+                    if let Some(interface_settings) = saved {
+                        self.apply_settings_to(interface.index, &interface_settings).await;
+                    }
+                    let pins = self.destination_pins.read().await;
+                    let mut routing_table = self.routing_table.write().await;
+                    for (destination, _) in pins.iter().filter(|(_, name)| **name == interface.name) {
+                        routing_table.insert(*destination, interface.index);
+                    }
+                }
+            }
+        }
+
+        // Always swap in the new list so speed and gateway changes are picked up too
+        self.interface_manager = Arc::new(InterfaceManager { interfaces });
+        events
+    }
+
+    /// Move the flows pinned to `index` onto the other available interfaces in
+    /// turn, once it has gone down. Their connections may reset, but new
+    /// packets get through instead of being blackholed. Returns how many
+    /// flows moved, which is none if no other interface is available.
+    pub async fn reroute_flows(&self, index: u32) -> usize {
+        let mut targets: Vec<PhysicalInterface> = self
+            .get_available_interfaces()
+            .await
+            .into_iter()
+            .filter(|iface| iface.index != index)
+            .collect();
+        if self.load_balancing_mode == LoadBalancingMode::ActivePassive {
+            targets = self.active_tier(targets).await;
+        }
+        if targets.is_empty() {
+            return 0;
+        }
+
+        let mut flows = self.flow_table.write().await;
+        let mut moved = 0;
+        for entry in flows.values_mut().filter(|entry| entry.interface_index == index) {
+            entry.interface_index = targets[moved % targets.len()].index;
+            moved += 1;
+        }
+        moved
+    }
+
+    /// Match a packet received on a physical interface against the outbound flows.
+    /// Returns true if the packet is a reply to a flow pinned to that interface.
+    pub async fn match_return_flow(&self, reply_key: &FlowKey, interface_index: u32) -> bool {
+        let mut flows = self.flow_table.write().await;
+        match flows.get_mut(&reply_key.reversed()) {
+            Some(entry) if entry.interface_index == interface_index => {
+                entry.last_seen = Instant::now();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record a probe round for an interface. Returns the new health state if
+    /// the interface just became unhealthy or recovered.
+    pub async fn record_probe(&self, index: u32, latency: Option<Duration>, packet_loss: f32) -> Option<bool> {
+        let mut health = self.interface_health.write().await;
+        let status = health.entry(index).or_default();
+        status
+            .record(&self.health_config, latency, packet_loss)
+            .then_some(status.healthy)
+    }
+
+    /// Record whether a packet could be sent out of an interface. Returns
+    /// false if the interface was just taken out of rotation and true if it
+    /// was just returned to it.
+    pub async fn record_send_result(&self, index: u32, success: bool) -> Option<bool> {
+        let mut breakers = self.circuit_breakers.write().await;
+        if success && !breakers.contains_key(&index) {
+            return None;
+        }
+        breakers.entry(index).or_default().record(&self.health_config, success)
+    }
+
+    /// Current health of every probed interface
+    pub async fn get_interface_health(&self) -> HashMap<u32, HealthStatus> {
+        let mut health = self.interface_health.read().await.clone();
+        let metrics = self.interface_metrics.read().await;
+        for (index, status) in health.iter_mut() {
+            status.jitter_ms = self
+                .fresh_metrics(&metrics, *index)
+                .map(|metric| metric.jitter.as_secs_f64() * 1000.0);
+        }
+        for (index, breaker) in self.circuit_breakers.read().await.iter() {
+            if breaker.is_open() {
+                health.entry(*index).or_default().circuit_open = true;
+            }
+        }
+        health
+    }
+
+    pub fn health_config(&self) -> &HealthConfig {
+        &self.health_config
+    }
+
+    pub fn set_health_config(&mut self, config: HealthConfig) {
+        self.health_config = config;
+    }
+
+    /// Count each millisecond of jitter as `weight` milliseconds of latency
+    /// when comparing interfaces; gaming traffic weighs it more heavily still
+    pub fn set_jitter_weight(&mut self, weight: f32) {
+        self.jitter_weight = weight.max(0.0);
+    }
+
+    /// Treat interface metrics older than `max_age` as unknown
+    pub fn set_metrics_max_age(&mut self, max_age: Duration) {
+        self.metrics_max_age = max_age;
+    }
+
+    /// Send packets to the primary interface when the balancer's pick has a
+    /// confidence below `min_confidence`; zero trusts every pick
+    pub fn set_min_confidence(&mut self, min_confidence: f32) {
+        self.min_confidence = min_confidence.clamp(0.0, 1.0);
+    }
+
+    /// Forget flows that have been idle for longer than `max_idle`
+    pub async fn expire_flows(&self, max_idle: Duration) -> usize {
+        let mut flows = self.flow_table.write().await;
+        let before = flows.len();
+        flows.retain(|_, entry| entry.last_seen.elapsed() < max_idle);
+        before - flows.len()
+    }
+
+    /// Always send packets for `destination` out the given interface while it is available
+    pub async fn pin_destination(&self, destination: IpAddr, interface_index: u32) -> Result<()> {
+        let interface = self
+            .find_interface(interface_index)
+            .with_context(|| format!("Interface {} not found", interface_index))?;
+        self.routing_table.write().await.insert(destination, interface_index);
+        self.destination_pins.write().await.insert(destination, interface.name);
+        Ok(())
+    }
+
+    /// Returns false if `destination` wasn't pinned
+    pub async fn unpin_destination(&self, destination: IpAddr) -> bool {
+        self.routing_table.write().await.remove(&destination);
+        self.destination_pins.write().await.remove(&destination).is_some()
+    }
+
+    /// Apply persisted pins, which name interfaces since indices aren't
+    /// stable; pins to missing interfaces take effect when they appear
+    pub async fn apply_destination_pins(&self, pins: &HashMap<IpAddr, String>) {
+        *self.destination_pins.write().await = pins.clone();
+        let mut routing_table = self.routing_table.write().await;
+        routing_table.clear();
+        for (destination, name) in pins {
+            if let Some(interface) = self.interface_manager.get_all_interfaces().iter().find(|iface| &iface.name == name) {
+                routing_table.insert(*destination, interface.index);
+            }
+        }
+    }
+
+    /// Send traffic for `cidr` out `interface`, or out the direct route when
+    /// `None`, instead of load balancing it. Replaces any route for the same network.
+    pub async fn add_bypass_route(&self, cidr: Ipv4Cidr, interface: Option<String>) {
+        let mut routes = self.bypass_routes.write().await;
+        routes.retain(|route| route.cidr != cidr);
+        routes.push(BypassRoute { cidr, interface });
+    }
+
+    /// Returns false if there was no route for `cidr`
+    pub async fn remove_bypass_route(&self, cidr: Ipv4Cidr) -> bool {
+        let mut routes = self.bypass_routes.write().await;
+        let before = routes.len();
+        routes.retain(|route| route.cidr != cidr);
+        routes.len() != before
+    }
+
+    /// Replace every bypass route
+    pub async fn set_bypass_routes(&self, routes: Vec<BypassRoute>) {
+        *self.bypass_routes.write().await = routes;
+    }
+
+    /// Append a rule; earlier rules take precedence
+    pub async fn add_classification_rule(&self, rule: ClassificationRule) {
+        self.classification_rules.write().await.push(rule);
+    }
+
+    /// Replace every classification rule
+    pub async fn set_classification_rules(&self, rules: Vec<ClassificationRule>) {
+        *self.classification_rules.write().await = rules;
+    }
+
     /// Set load balancing mode
     pub fn set_load_balancing_mode(&mut self, mode: LoadBalancingMode) {
         self.load_balancing_mode = mode;
     }
+
+    pub fn get_load_balancing_mode(&self) -> LoadBalancingMode {
+        self.load_balancing_mode
+    }
+
+    /// The strategy new flows are routed with: the configured mode, or in
+    /// `Adaptive` mode the one the traffic mix currently calls for
+    pub async fn effective_mode(&self) -> LoadBalancingMode {
+        match self.load_balancing_mode {
+            LoadBalancingMode::Adaptive => *self.adaptive_strategy.read().await,
+            mode => mode,
+        }
+    }
+
+    /// Feed `Adaptive` mode the packets of each traffic type seen since the
+    /// last call. Returns the new strategy if it changed.
+    pub async fn observe_traffic_mix(&self, packets: &HashMap<TrafficType, u64>) -> Option<LoadBalancingMode> {
+        let mut strategy = self.adaptive_strategy.write().await;
+        let next = adaptive_strategy(*strategy, packets);
+        (next != *strategy).then(|| {
+            *strategy = next;
+            next
+        })
+    }
+
+    /// Switch between balancing across every interface and passing all
+    /// traffic through the primary one
+    pub fn set_aggregation_enabled(&mut self, enabled: bool) {
+        self.aggregation_enabled = enabled;
+    }
+
+    pub fn is_aggregation_enabled(&self) -> bool {
+        self.aggregation_enabled
+    }
+
+    /// Use the interface with the given index as primary, for passthrough
+    /// and bypass routing
+    pub fn set_primary_interface(&mut self, index: u32) -> Result<PhysicalInterface> {
+        let primary = self.interface_manager.get_interface(index)?.clone();
+        self.primary_interface = Some(primary.name.clone());
+        Ok(primary)
+    }
+
+    /// Use the named interface as primary whenever it is present, or the
+    /// first discovered one with `None`
+    pub fn set_primary_interface_name(&mut self, name: Option<String>) {
+        self.primary_interface = name;
+    }
+
+    pub fn primary_interface(&self) -> Option<PhysicalInterface> {
+        self.interface_manager.get_preferred_primary(self.primary_interface.as_deref()).cloned()
+    }
+
+    /// Choose which discovered interfaces are used. Interfaces the filter
+    /// rejects are dropped by the next `update_interfaces`.
+    pub fn set_interface_filter(&mut self, filter: InterfaceFilter) {
+        self.interface_filter = filter;
+    }
+
+    /// Keep file transfers off metered interfaces, while latency-sensitive
+    /// traffic can still use them
+    pub fn set_avoid_metered(&mut self, avoid: bool) {
+        self.avoid_metered = avoid;
+    }
+
+    /// Choose how DNS lookups are routed. Answering them from the cache is
+    /// up to the caller.
+    pub fn set_dns_settings(&mut self, settings: &DnsSettings) {
+        self.dns_latency_first = settings.latency_first;
+        self.dns_interface = settings.interface.clone();
+    }
+
+    /// Whether packets of one flow stay on one interface, or are spread
+    /// across interfaces like unrelated packets
+    pub fn set_flow_affinity(&mut self, enabled: bool) {
+        self.flow_affinity = enabled;
+    }
+
+    /// Start or stop recording new flows
+    pub fn set_flow_log(&mut self, flow_log: Option<Arc<FlowLog>>) {
+        self.flow_log = flow_log;
+    }
+}
+
+/// Metrics of an interface group: the mean latency, jitter and loss of its
+/// members, their combined bandwidth use, and the newest sample time
+fn combine_metrics<'a>(members: impl Iterator<Item = &'a PacketMetrics>) -> Option<PacketMetrics> {
+    let members: Vec<&PacketMetrics> = members.collect();
+    let count = members.len() as u32;
+    if count == 0 {
+        return None;
+    }
+    Some(PacketMetrics {
+        latency: members.iter().map(|metrics| metrics.latency).sum::<Duration>() / count,
+        jitter: members.iter().map(|metrics| metrics.jitter).sum::<Duration>() / count,
+        bandwidth_usage: members.iter().map(|metrics| metrics.bandwidth_usage).sum(),
+        packet_loss: members.iter().map(|metrics| metrics.packet_loss).sum::<f32>() / count as f32,
+        last_updated: members.iter().map(|metrics| metrics.last_updated).max()?,
+    })
+}
+
+/// The strategy that suits a traffic mix, sticking with `current` until the
+/// traffic it was chosen for falls below `ADAPTIVE_EXIT_SHARE`
+fn adaptive_strategy(current: LoadBalancingMode, packets: &HashMap<TrafficType, u64>) -> LoadBalancingMode {
+    let total: u64 = packets.values().sum();
+    if total < ADAPTIVE_MIN_PACKETS {
+        return current;
+    }
+    let share = |types: &[TrafficType]| {
+        types.iter().filter_map(|traffic_type| packets.get(traffic_type)).sum::<u64>() as f64 / total as f64
+    };
+    let gaming = share(&[TrafficType::Gaming]);
+    let bulk = share(&[TrafficType::Streaming, TrafficType::File]);
+
+    match current {
+        LoadBalancingMode::LatencyBased if gaming >= ADAPTIVE_EXIT_SHARE => current,
+        LoadBalancingMode::BandwidthBased if bulk >= ADAPTIVE_EXIT_SHARE => current,
+        _ if gaming >= ADAPTIVE_ENTER_SHARE => LoadBalancingMode::LatencyBased,
+        _ if bulk >= ADAPTIVE_ENTER_SHARE => LoadBalancingMode::BandwidthBased,
+        _ => LoadBalancingMode::Balanced,
+    }
+}
+
+/// Latency in milliseconds with `jitter_weight` milliseconds added per
+/// millisecond of jitter
+fn effective_latency_ms(metric: &PacketMetrics, jitter_weight: f32) -> f32 {
+    (metric.latency.as_secs_f32() + metric.jitter.as_secs_f32() * jitter_weight) * 1000.0
+}
+
+fn standard_deviation(samples: &VecDeque<Duration>) -> Duration {
+    if samples.len() < 2 {
+        return Duration::ZERO;
+    }
+    let count = samples.len() as f64;
+    let mean = samples.iter().map(Duration::as_secs_f64).sum::<f64>() / count;
+    let variance = samples.iter().map(|sample| (sample.as_secs_f64() - mean).powi(2)).sum::<f64>() / count;
+    Duration::from_secs_f64(variance.sqrt())
 }
 
 #[derive(Debug)]
@@ -246,12 +1884,14 @@ struct TrafficInfo {
     traffic_type: TrafficType,
     priority: u8,
     estimated_size: u64,
-    destination: Option<Ipv4Addr>,
+    destination: Option<IpAddr>,
+    bypass: bool,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interface_manager::InterfaceType;
     use std::net::Ipv4Addr;
 
     fn create_mock_interfaces() -> Vec<PhysicalInterface> {
@@ -261,12 +1901,24 @@ mod tests {
                 description: "Mock Ethernet".to_string(),
                 ip_address: Ipv4Addr::new(192, 168, 1, 1),
                 index: 1,
+                link_speed_mbps: Some(1000),
+                interface_type: InterfaceType::Ethernet,
+                prefix_len: 24,
+                gateway: Some(Ipv4Addr::new(192, 168, 1, 254)),
+                mtu: 1500,
+                is_metered: false,
             },
             PhysicalInterface {
                 name: "wifi0".to_string(),
                 description: "Mock WiFi".to_string(),
                 ip_address: Ipv4Addr::new(192, 168, 1, 2),
                 index: 2,
+                link_speed_mbps: Some(100),
+                interface_type: InterfaceType::WiFi,
+                prefix_len: 24,
+                gateway: None,
+                mtu: 1500,
+                is_metered: false,
             },
         ]
     }
@@ -290,6 +1942,606 @@ mod tests {
         assert_eq!(decision3.interface_index, 1);
     }
 
+    #[test]
+    fn test_flows_keep_their_shard_and_spread_across_shards() {
+        let flow = |src_port| FlowKey {
+            src_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            dst_addr: IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9)),
+            src_port,
+            dst_port: 443,
+            protocol: 6,
+        };
+        assert_eq!(flow(40000).shard(4), flow(40000).shard(4));
+        assert_eq!(flow(40000).shard(1), 0);
+        assert_eq!(flow(40000).shard(0), 0);
+
+        let shards: HashSet<usize> = (40000..40064).map(|port| flow(port).shard(4)).collect();
+        assert_eq!(shards.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_preview_matches_routing_without_changing_anything() {
+        let mut router = PacketRouter::new(InterfaceManager{ interfaces: create_mock_interfaces() });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.set_interface_rate_limit(1, Some(1)).await;
+        let flow = FlowKey {
+            src_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            dst_addr: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            src_port: 49152,
+            dst_port: 53,
+            protocol: 17,
+        };
+
+        // Asking twice gives the same answer, though a packet this size only
+        // fits through interface 1's rate limit once, and the real decision agrees
+        let preview = router.preview_route(&flow, 3000).await.unwrap();
+        assert_eq!(preview.traffic_type, TrafficType::Dns);
+        assert_eq!(router.preview_route(&flow, 3000).await.unwrap().interface_index, preview.interface_index);
+        let packet = flow.build_packet(3000).unwrap();
+        assert_eq!(FlowKey::from_packet(&packet), Some(flow));
+        assert!(validate_packet(&packet).is_ok());
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, preview.interface_index);
+
+        // Only the real decision used up the rate limit and took the turn
+        assert_eq!(preview.interface_index, 1);
+        assert_eq!(router.preview_route(&flow, 3000).await.unwrap().interface_index, 2);
+        assert_eq!(router.flow_table.read().await.len(), 1);
+    }
+
+    fn mock_metrics(entries: &[(u32, u64, u64, f32)]) -> HashMap<u32, PacketMetrics> {
+        entries
+            .iter()
+            .map(|&(index, latency_ms, bandwidth_usage, packet_loss)| {
+                (index, PacketMetrics {
+                    latency: Duration::from_millis(latency_ms),
+                    jitter: Duration::ZERO,
+                    bandwidth_usage,
+                    packet_loss,
+                    last_updated: Instant::now(),
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_send_failures_trip_and_recover() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_health_config(HealthConfig {
+            send_failure_threshold: 3,
+            ..HealthConfig::default()
+        });
+
+        assert_eq!(router.record_send_result(1, false).await, None);
+        assert_eq!(router.record_send_result(1, true).await, None);
+        for _ in 0..2 {
+            assert_eq!(router.record_send_result(1, false).await, None);
+        }
+        assert_eq!(router.record_send_result(1, false).await, Some(false));
+        let available: Vec<u32> = router.get_available_interfaces().await.iter().map(|i| i.index).collect();
+        assert_eq!(available, [2]);
+        assert!(router.get_interface_health().await[&1].circuit_open);
+
+        // Sends routed before the trip only extend it. Once the cooldown is
+        // over, a failed trial trips it again and a successful send returns
+        // the interface to rotation.
+        router.set_health_config(HealthConfig {
+            send_failure_cooldown_secs: 0,
+            ..HealthConfig::default()
+        });
+        assert_eq!(router.record_send_result(1, false).await, None);
+        assert_eq!(router.get_available_interfaces().await.len(), 2);
+        assert_eq!(router.record_send_result(1, false).await, Some(false));
+        assert_eq!(router.record_send_result(1, true).await, Some(true));
+        assert!(!router.get_interface_health().await.contains_key(&1));
+    }
+
+    #[tokio::test]
+    async fn test_gaming_prefers_the_stable_interface() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let router = PacketRouter::new(im);
+
+        // Interface 1 last measured 5ms but swings between 5 and 35;
+        // interface 2 holds steady at 25ms
+        for latency_ms in [35, 5, 35, 5, 35, 5] {
+            router.update_interface_metrics(1, Duration::from_millis(latency_ms), 0, 0.0).await;
+            router.update_interface_metrics(2, Duration::from_millis(25), 0, 0.0).await;
+        }
+        let metrics = router.interface_metrics.read().await.clone();
+        assert!((metrics[&1].jitter.as_secs_f64() * 1000.0 - 15.0).abs() < 0.001);
+        assert_eq!(metrics[&2].jitter, Duration::ZERO);
+
+        let gaming = router.select_balanced(&create_mock_interfaces(), &metrics, TrafficType::Gaming).await.unwrap();
+        assert_eq!(gaming.index, 2);
+
+        // Without the jitter penalty the lower latency wins
+        let latency_only = router.select_by_latency(&create_mock_interfaces(), &metrics, 0.0).await.unwrap();
+        assert_eq!(latency_only.index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_latency_mode_picks_lowest_latency() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let metrics = mock_metrics(&[(1, 80, 100, 0.0), (2, 10, 5000, 0.0)]);
+
+        let mut router = PacketRouter::with_metrics(im, metrics);
+        router.set_load_balancing_mode(LoadBalancingMode::LatencyBased);
+
+        let decision = router.route_packet(&[0u8; 100]).await.unwrap();
+        assert_eq!(decision.interface_index, 2);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_mode_picks_least_used() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let metrics = mock_metrics(&[(1, 80, 100, 0.0), (2, 10, 5000, 0.0)]);
+
+        let mut router = PacketRouter::with_metrics(im, metrics);
+        router.set_load_balancing_mode(LoadBalancingMode::BandwidthBased);
+
+        let decision = router.route_packet(&[0u8; 100]).await.unwrap();
+        assert_eq!(decision.interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_traffic_updates_measured_usage() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let metrics = mock_metrics(&[(1, 10, 100, 0.0), (2, 10, 5000, 0.0)]);
+
+        let mut router = PacketRouter::with_metrics(im, metrics);
+        router.set_load_balancing_mode(LoadBalancingMode::BandwidthBased);
+        assert_eq!(router.route_packet(&[0u8; 100]).await.unwrap().interface_index, 1);
+
+        // Sending 10 KB within a second through the idle-looking link makes
+        // it the busier one once the next probe publishes the rate
+        for _ in 0..10 {
+            router.record_forwarded(1, 1000).await;
+        }
+        let rates = router.take_forwarded_rates().await;
+        assert!(rates[&1] >= 10_000);
+        router.update_interface_metrics(1, Duration::from_millis(10), rates[&1], 0.0).await;
+        assert_eq!(router.route_packet(&[0u8; 100]).await.unwrap().interface_index, 2);
+
+        // Each rate covers only what was forwarded since it was last taken
+        assert_eq!(router.take_forwarded_rates().await[&1], 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_metrics_are_deprioritized() {
+        let interfaces = create_mock_interfaces();
+        // Interface 2 looks better on every measure, but was last measured a minute ago
+        let mut metrics = mock_metrics(&[(1, 80, 5000, 0.1), (2, 10, 100, 0.0)]);
+        metrics.get_mut(&2).unwrap().last_updated = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
+
+        let mut router = PacketRouter::with_metrics(InterfaceManager{ interfaces: interfaces.clone() }, metrics.clone());
+        router.set_load_balancing_mode(LoadBalancingMode::LatencyBased);
+        let decision = router.route_packet(&[0u8; 100]).await.unwrap();
+        assert_eq!(decision.interface_index, 1);
+        assert_eq!(router.select_by_bandwidth(&interfaces, &metrics).await.unwrap().index, 1);
+        assert_eq!(router.calculate_interface_score(&interfaces[1], &metrics), 0.0);
+        assert_eq!(router.calculate_confidence(&interfaces[1], &metrics).await, STALE_CONFIDENCE);
+
+        // A longer threshold trusts the old reading again
+        router.set_metrics_max_age(Duration::from_secs(120));
+        assert_eq!(router.route_packet(&[0u8; 100]).await.unwrap().interface_index, 2);
+        assert!(router.calculate_confidence(&interfaces[1], &metrics).await > STALE_CONFIDENCE);
+    }
+
+    #[tokio::test]
+    async fn test_low_confidence_picks_fall_back_to_primary() {
+        // Interface 2 is fastest but its last reading is too old to trust
+        let mut metrics = mock_metrics(&[(1, 80, 5000, 0.0), (2, 10, 100, 0.0)]);
+        metrics.get_mut(&2).unwrap().last_updated = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
+
+        let mut router = PacketRouter::with_metrics(InterfaceManager{ interfaces: create_mock_interfaces() }, metrics);
+        router.set_load_balancing_mode(LoadBalancingMode::LatencyBased);
+        router.set_metrics_max_age(Duration::from_secs(120));
+        router.set_min_confidence(0.5);
+        let decision = router.route_packet(&[0u8; 100]).await.unwrap();
+        assert_eq!((decision.interface_index, decision.low_confidence_fallback), (2, false));
+
+        router.set_metrics_max_age(Duration::from_secs(30));
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        // Once stale, round robin's turns on interface 2 go to the primary instead
+        let mut picks = Vec::new();
+        for _ in 0..2 {
+            let decision = router.route_packet(&[0u8; 100]).await.unwrap();
+            picks.push((decision.interface_index, decision.low_confidence_fallback));
+        }
+        picks.sort();
+        assert_eq!(picks, [(1, false), (1, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_selection_without_metrics_and_on_ties() {
+        let interfaces = create_mock_interfaces();
+        let router = PacketRouter::new(InterfaceManager{ interfaces: interfaces.clone() });
+
+        // Neither measured yet: both count as idle and the faster link wins, every time
+        for _ in 0..3 {
+            assert_eq!(router.select_by_bandwidth(&interfaces, &HashMap::new()).await.unwrap().index, 1);
+        }
+
+        // A link that hasn't been measured yet is preferred to a busy one
+        let metrics = mock_metrics(&[(1, 10, 5000, 0.0)]);
+        assert_eq!(router.select_by_bandwidth(&interfaces, &metrics).await.unwrap().index, 2);
+
+        // Equal usage goes to the faster link
+        let metrics = mock_metrics(&[(1, 10, 100, 0.0), (2, 10, 100, 0.0)]);
+        assert_eq!(router.select_by_bandwidth(&interfaces, &metrics).await.unwrap().index, 1);
+
+        // and between equally fast links, to the lowest index
+        let same_speed: Vec<PhysicalInterface> = interfaces
+            .iter()
+            .map(|iface| PhysicalInterface { link_speed_mbps: Some(100), ..iface.clone() })
+            .rev()
+            .collect();
+        assert_eq!(router.select_by_bandwidth(&same_speed, &metrics).await.unwrap().index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_proportional_selection_follows_the_scores() {
+        let interfaces = create_mock_interfaces();
+        let metrics = mock_metrics(&[(1, 10, 100, 0.0), (2, 30, 100, 0.0)]);
+        let router = PacketRouter::with_metrics(InterfaceManager{ interfaces: interfaces.clone() }, metrics.clone());
+
+        let share = |picks: &[u32]| picks.iter().filter(|&&index| index == 1).count() as f32 / picks.len() as f32;
+        let mut picks = Vec::new();
+        for _ in 0..20_000 {
+            picks.push(router.select_proportional(&interfaces, &metrics).await.unwrap().index);
+        }
+        let first = router.calculate_interface_score(&interfaces[0], &metrics);
+        let second = router.calculate_interface_score(&interfaces[1], &metrics);
+        let expected = first / (first + second);
+        assert!((share(&picks) - expected).abs() < 0.02, "got {}, expected {}", share(&picks), expected);
+
+        // Interface weights scale the odds too
+        router.set_interface_weight(1, 0.5).await;
+        picks.clear();
+        for _ in 0..20_000 {
+            picks.push(router.select_proportional(&interfaces, &metrics).await.unwrap().index);
+        }
+        let expected = first * 0.5 / (first * 0.5 + second);
+        assert!((share(&picks) - expected).abs() < 0.02, "got {}, expected {}", share(&picks), expected);
+
+        // Nothing measured yet: an even split
+        picks.clear();
+        for _ in 0..20_000 {
+            picks.push(router.select_proportional(&interfaces, &HashMap::new()).await.unwrap().index);
+        }
+        assert!((share(&picks) - 0.5).abs() < 0.02);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_best_picks_highest_composite_score() {
+        let interfaces = create_mock_interfaces();
+        let metrics = mock_metrics(&[(1, 80, 100, 0.0), (2, 10, 5000, 0.5)]);
+        let router = PacketRouter::with_metrics(InterfaceManager{ interfaces: interfaces.clone() }, metrics.clone());
+
+        // Latency dominates the score, so the faster link wins despite its loss
+        assert!(router.calculate_interface_score(&interfaces[1], &metrics) > router.calculate_interface_score(&interfaces[0], &metrics));
+        assert_eq!(router.select_weighted_best(&interfaces, &metrics).await.unwrap().index, 2);
+
+        // An interface without metrics scores zero
+        let partial = mock_metrics(&[(1, 500, 100_000, 0.9)]);
+        assert_eq!(router.select_weighted_best(&interfaces, &partial).await.unwrap().index, 1);
+
+        // Interface weights scale the score
+        router.set_interface_weight(2, 0.1).await;
+        assert_eq!(router.select_weighted_best(&interfaces, &metrics).await.unwrap().index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_stays_fair_when_interfaces_change() {
+        let mut interfaces = create_mock_interfaces();
+        interfaces.push(PhysicalInterface {
+            name: "eth1".to_string(),
+            description: "Mock Ethernet".to_string(),
+            ip_address: Ipv4Addr::new(10, 0, 0, 2),
+            index: 3,
+            link_speed_mbps: Some(1000),
+            interface_type: InterfaceType::Ethernet,
+            prefix_len: 24,
+            gateway: Some(Ipv4Addr::new(10, 0, 0, 1)),
+            mtu: 1500,
+            is_metered: false,
+        });
+        let mut router = PacketRouter::new(InterfaceManager{ interfaces: interfaces.clone() });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        let packet = vec![0u8; 100];
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 1);
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 2);
+
+        // Interface 3 was next and keeps its turn
+        router.update_interfaces(interfaces[1..].to_vec()).await;
+        let mut picks = Vec::new();
+        for _ in 0..10 {
+            picks.push(router.route_packet(&packet).await.unwrap().interface_index);
+        }
+        assert_eq!(picks[0], 3);
+        assert!(picks.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(picks.iter().filter(|index| **index == 2).count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_aggregation_uses_primary_interface() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        assert_eq!(router.active_interface_count().await, 2);
+        router.set_aggregation_enabled(false);
+        assert_eq!(router.active_interface_count().await, 1);
+
+        let packet = vec![0u8; 100];
+        for _ in 0..3 {
+            let decision = router.route_packet(&packet).await.unwrap();
+            assert_eq!(decision.interface_index, 1);
+        }
+
+        // A disabled primary falls back to the next interface
+        router.disable_interface(1).await;
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 2);
+        router.enable_interface(1).await;
+
+        router.set_aggregation_enabled(true);
+        let first = router.route_packet(&packet).await.unwrap().interface_index;
+        let second = router.route_packet(&packet).await.unwrap().interface_index;
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_primary_override_survives_rediscovery() {
+        let interfaces = create_mock_interfaces();
+        let mut router = PacketRouter::new(InterfaceManager{ interfaces: interfaces.clone() });
+        router.set_aggregation_enabled(false);
+        assert!(router.set_primary_interface(7).is_err());
+        assert_eq!(router.set_primary_interface(2).unwrap().name, "wifi0");
+
+        let packet = vec![0u8; 100];
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 2);
+
+        // Rediscovery keeps the choice, following the interface by name
+        let mut rediscovered = interfaces;
+        rediscovered.reverse();
+        rediscovered[0].index = 5;
+        router.update_interfaces(rediscovered).await;
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 5);
+
+        router.set_primary_interface_name(None);
+        assert_eq!(router.primary_interface().unwrap().name, "wifi0");
+        router.set_primary_interface_name(Some("eth0".to_string()));
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_interfaces_are_skipped() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        let packet = vec![0u8; 100];
+
+        router.disable_interface(1).await;
+        for _ in 0..3 {
+            let decision = router.route_packet(&packet).await.unwrap();
+            assert_eq!(decision.interface_index, 2);
+        }
+
+        router.disable_interface(2).await;
+        let err = router.route_packet(&packet).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No interface can route traffic: eth0 is disabled, wifi0 is disabled; enable at least one to route traffic"
+        );
+
+        router.enable_interface(1).await;
+        let decision = router.route_packet(&packet).await.unwrap();
+        assert_eq!(decision.interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_interface_is_skipped() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        // The bucket starts with a 4096 byte burst, enough for four packets
+        router.set_interface_rate_limit(1, Some(1)).await;
+
+        let packet = vec![0u8; 1000];
+        let mut routed_to_first = 0;
+        for _ in 0..20 {
+            let decision = router.route_packet(&packet).await.unwrap();
+            if decision.interface_index == 1 {
+                routed_to_first += 1;
+            }
+        }
+        assert_eq!(routed_to_first, 4);
+
+        router.set_interface_rate_limit(2, Some(1)).await;
+        for _ in 0..4 {
+            router.route_packet(&packet).await.unwrap();
+        }
+        let err = router.route_packet(&packet).await.unwrap_err();
+        assert!(err.is::<RateLimited>());
+    }
+
+    #[tokio::test]
+    async fn test_weighted_mode_follows_link_speed() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::Weighted);
+
+        let packet = vec![0u8; 100];
+        let mut counts = HashMap::new();
+        for _ in 0..110 {
+            let decision = router.route_packet(&packet).await.unwrap();
+            *counts.entry(decision.interface_index).or_insert(0) += 1;
+        }
+
+        // 1000 Mbps vs 100 Mbps
+        assert_eq!(counts[&1], 100);
+        assert_eq!(counts[&2], 10);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_interface_is_drained() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let router = PacketRouter::new(im);
+        let threshold = router.health_config().failure_threshold;
+
+        // wifi0 latency spikes for enough consecutive probes
+        for round in 1..=threshold {
+            let transition = router.record_probe(2, Some(Duration::from_millis(2000)), 0.0).await;
+            assert_eq!(transition, (round == threshold).then_some(false));
+        }
+        let available = router.get_available_interfaces().await;
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].index, 1);
+
+        // If every link is unhealthy, traffic still flows
+        for _ in 0..threshold {
+            router.record_probe(1, None, 1.0).await;
+        }
+        assert_eq!(router.get_available_interfaces().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_flows_move_off_an_interface_that_goes_down() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        let packets: Vec<Vec<u8>> = (40000u16..40004)
+            .map(|src_port| {
+                let mut packet = vec![0u8; 28];
+                packet[..20].copy_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 198, 51, 100, 7]);
+                packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+                packet[22..24].copy_from_slice(&443u16.to_be_bytes());
+                packet
+            })
+            .collect();
+        let mut on_wifi = 0;
+        for packet in &packets {
+            if router.route_packet(packet).await.unwrap().interface_index == 2 {
+                on_wifi += 1;
+            }
+        }
+        assert_eq!(on_wifi, 2);
+
+        for _ in 0..router.health_config().failure_threshold {
+            router.record_probe(2, None, 1.0).await;
+        }
+        assert_eq!(router.reroute_flows(2).await, on_wifi);
+        for packet in &packets {
+            let decision = router.route_packet(packet).await.unwrap();
+            assert_eq!((decision.interface_index, decision.reason.as_str()), (1, "Pinned to existing flow"));
+        }
+
+        // With nowhere else to go, flows stay put
+        assert_eq!(router.reroute_flows(1).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_standby_tier_is_idle_until_active_tier_fails() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::ActivePassive);
+        router.set_flow_affinity(false);
+        router.set_interface_tier(2, 2).await;
+
+        for _ in 0..20 {
+            assert_eq!(router.route_packet(&[0u8; 300]).await.unwrap().interface_index, 1);
+        }
+
+        // eth0 goes down and wifi0 takes over
+        let threshold = router.health_config().failure_threshold;
+        for _ in 0..threshold {
+            router.record_probe(1, None, 1.0).await;
+        }
+        assert_eq!(router.route_packet(&[0u8; 300]).await.unwrap().interface_index, 2);
+
+        // and hands back once eth0 recovers
+        for _ in 0..router.health_config().recovery_threshold {
+            router.record_probe(1, Some(Duration::from_millis(10)), 0.0).await;
+        }
+        assert_eq!(router.route_packet(&[0u8; 300]).await.unwrap().interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_interfaces_reports_changes() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_interface_rate_limit(2, Some(1000)).await;
+
+        // wifi0 disconnects
+        let events = router.update_interfaces(create_mock_interfaces()[..1].to_vec()).await;
+        assert!(matches!(events.as_slice(), [InterfaceEvent::Removed(iface)] if iface.index == 2));
+        assert!(router.find_interface(2).is_none());
+        assert!(!router.rate_limits.read().await.contains_key(&2));
+
+        // and comes back
+        let events = router.update_interfaces(create_mock_interfaces()).await;
+        assert!(matches!(events.as_slice(), [InterfaceEvent::Added(iface)] if iface.index == 2));
+        assert_eq!(router.get_available_interfaces().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_own_tun_is_never_a_candidate() {
+        let tun = |name: &str, index| PhysicalInterface {
+            name: name.to_string(),
+            description: "NetBoost Pro".to_string(),
+            ip_address: Ipv4Addr::new(10, 0, 0, 1),
+            index,
+            link_speed_mbps: None,
+            interface_type: InterfaceType::Virtual,
+            prefix_len: 24,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        };
+        let mut interfaces = create_mock_interfaces();
+        interfaces.push(tun("NetBoost-TUN", 3));
+        // e.g. a macOS utun, whose name we don't choose
+        interfaces.push(tun("utun4", 4));
+
+        let mut router = PacketRouter::new(InterfaceManager{ interfaces: interfaces.clone() });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.exclude_tun("NetBoost-TUN".to_string(), Ipv4Addr::new(10, 0, 0, 1));
+
+        let available: Vec<u32> = router.get_available_interfaces().await.iter().map(|iface| iface.index).collect();
+        assert_eq!(available, vec![1, 2]);
+        for _ in 0..8 {
+            assert!(router.route_packet(&[0u8; 300]).await.unwrap().interface_index <= 2);
+        }
+
+        // Rediscovery doesn't bring it back
+        assert!(router.update_interfaces(interfaces).await.is_empty());
+        assert_eq!(router.get_all_interfaces().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_interface_filter_applies_on_rediscovery() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+
+        let mut filter = InterfaceFilter::default();
+        filter.exclude.names.push("wifi*".to_string());
+        router.set_interface_filter(filter);
+        let events = router.update_interfaces(create_mock_interfaces()).await;
+        assert!(matches!(events.as_slice(), [InterfaceEvent::Removed(iface)] if iface.index == 2));
+        assert_eq!(router.route_packet(&[0u8; 300]).await.unwrap().interface_index, 1);
+
+        router.set_interface_filter(InterfaceFilter::default());
+        let events = router.update_interfaces(create_mock_interfaces()).await;
+        assert!(matches!(events.as_slice(), [InterfaceEvent::Added(iface)] if iface.index == 2));
+    }
+
     #[tokio::test]
     async fn test_packet_classification() {
         let im = InterfaceManager{ interfaces: create_mock_interfaces() };
@@ -312,4 +2564,410 @@ mod tests {
         let file_info = router.analyze_packet_simple(&file_packet).unwrap();
         assert!(matches!(file_info.traffic_type, TrafficType::File));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_avoid_metered_keeps_file_transfers_off_metered_links() {
+        let mut interfaces = create_mock_interfaces();
+        interfaces[0].is_metered = true;
+        let mut router = PacketRouter::new(InterfaceManager { interfaces });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.set_avoid_metered(true);
+
+        let file_packet = vec![0u8; 2000];
+        for _ in 0..4 {
+            assert_eq!(router.route_packet(&file_packet).await.unwrap().interface_index, 2);
+        }
+
+        // Gaming traffic still uses the metered link
+        let gaming_packet = vec![0u8; 60];
+        let first = router.route_packet(&gaming_packet).await.unwrap().interface_index;
+        let second = router.route_packet(&gaming_packet).await.unwrap().interface_index;
+        assert_ne!(first, second);
+
+        // With only metered links up, file transfers fall back to them
+        router.disable_interface(2).await;
+        assert_eq!(router.route_packet(&file_packet).await.unwrap().interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_classification_rules_override_size_heuristic() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.set_classification_rules(serde_json::from_str(r#"[
+            {"destination": "192.168.0.0/16", "bypass": true},
+            {"protocol": "udp", "dst_port": "3074", "traffic_type": "gaming"}
+        ]"#).unwrap()).await;
+
+        // A large UDP packet from port `src_port` to `dst`:3074
+        let udp_packet = |dst: [u8; 4], src_port: u16| {
+            let mut packet = vec![0u8; 1000];
+            packet[..20].copy_from_slice(&[0x45, 0, 0x03, 0xe8, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, dst[0], dst[1], dst[2], dst[3]]);
+            packet[20..24].copy_from_slice(&[(src_port >> 8) as u8, src_port as u8, 0x0c, 0x02]);
+            packet
+        };
+
+        let decision = router.route_packet(&udp_packet([203, 0, 113, 1], 40000)).await.unwrap();
+        assert_eq!(decision.traffic_type, TrafficType::Gaming);
+        // Without a matching rule the size heuristic still applies
+        assert_eq!(router.classify(&vec![0u8; 1000]).await, TrafficType::Streaming);
+
+        // LAN flows all stay on the interface facing them instead of alternating
+        for src_port in 40001..40005 {
+            let decision = router.route_packet(&udp_packet([192, 168, 1, 20], src_port)).await.unwrap();
+            assert_eq!(decision.interface_index, 1);
+            assert_eq!(decision.traffic_type, TrafficType::Unknown);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_goes_latency_first_or_to_its_pinned_interface() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::with_metrics(im, mock_metrics(&[(1, 50, 0, 0.0), (2, 10, 0, 0.0)]));
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        // A UDP packet from `src_port` to 1.1.1.1:`dst_port`
+        let udp_packet = |src_port: u16, dst_port: u16| {
+            let mut packet = vec![0u8; 60];
+            packet[..20].copy_from_slice(&[0x45, 0, 0, 60, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 1, 1, 1, 1]);
+            packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+            packet[22..24].copy_from_slice(&dst_port.to_be_bytes());
+            packet
+        };
+
+        let mut picks = Vec::new();
+        for src_port in 40000..40004 {
+            let decision = router.route_packet(&udp_packet(src_port, DNS_PORT)).await.unwrap();
+            assert_eq!(decision.traffic_type, TrafficType::Dns);
+            picks.push(decision.interface_index);
+        }
+        assert_eq!(picks, [2, 2, 2, 2]);
+        assert_eq!(router.classify(&udp_packet(40000, 3074)).await, TrafficType::Gaming);
+
+        // Balanced like everything else
+        router.set_dns_settings(&DnsSettings { latency_first: false, ..DnsSettings::default() });
+        let picks = [
+            router.route_packet(&udp_packet(40010, DNS_PORT)).await.unwrap().interface_index,
+            router.route_packet(&udp_packet(40011, DNS_PORT)).await.unwrap().interface_index,
+        ];
+        assert_ne!(picks[0], picks[1]);
+
+        router.set_dns_settings(&DnsSettings { interface: Some("eth0".to_string()), ..DnsSettings::default() });
+        let decision = router.route_packet(&udp_packet(40020, DNS_PORT)).await.unwrap();
+        assert_eq!(decision.interface_index, 1);
+        assert_eq!(decision.reason, "DNS pinned to eth0");
+    }
+
+    #[tokio::test]
+    async fn test_bypass_routes_use_longest_prefix() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.add_bypass_route("10.20.0.0/16".parse().unwrap(), Some("eth0".to_string())).await;
+        router.add_bypass_route("10.20.30.40/32".parse().unwrap(), Some("wifi0".to_string())).await;
+
+        // An ICMP packet to `dst`
+        let icmp_packet = |dst: [u8; 4]| {
+            let mut packet = vec![0u8; 100];
+            packet[..20].copy_from_slice(&[0x45, 0, 0, 100, 0, 0, 0, 0, 64, 1, 0, 0, 10, 0, 0, 2, dst[0], dst[1], dst[2], dst[3]]);
+            packet
+        };
+
+        for _ in 0..3 {
+            assert_eq!(router.route_packet(&icmp_packet([10, 20, 1, 1])).await.unwrap().interface_index, 1);
+            let decision = router.route_packet(&icmp_packet([10, 20, 30, 40])).await.unwrap();
+            assert_eq!(decision.interface_index, 2);
+            assert!(decision.reason.contains("10.20.30.40/32"));
+        }
+
+        assert!(router.remove_bypass_route("10.20.30.40/32".parse().unwrap()).await);
+        assert!(!router.remove_bypass_route("10.20.30.40/32".parse().unwrap()).await);
+        // The flow stays where it was until it expires
+        router.expire_flows(Duration::ZERO).await;
+        assert_eq!(router.route_packet(&icmp_packet([10, 20, 30, 40])).await.unwrap().interface_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_destination_overrides_balancer() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        // Distinct UDP flows to 198.51.100.7
+        let udp_packet = |src_port: u16| {
+            let mut packet = vec![0u8; 100];
+            packet[..20].copy_from_slice(&[0x45, 0, 0, 100, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 198, 51, 100, 7]);
+            packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+            packet
+        };
+        let destination = IpAddr::from([198, 51, 100, 7]);
+
+        router.pin_destination(destination, 2).await.unwrap();
+        assert!(router.pin_destination(destination, 9).await.is_err());
+        for src_port in 40000..40004 {
+            assert_eq!(router.route_packet(&udp_packet(src_port)).await.unwrap().interface_index, 2);
+        }
+
+        // A pinned interface that goes away takes its pin back when it returns
+        router.update_interfaces(create_mock_interfaces()[..1].to_vec()).await;
+        assert_eq!(router.route_packet(&udp_packet(40010)).await.unwrap().interface_index, 1);
+        router.update_interfaces(create_mock_interfaces()).await;
+        assert_eq!(router.route_packet(&udp_packet(40011)).await.unwrap().interface_index, 2);
+
+        assert!(router.unpin_destination(destination).await);
+        let mut indices = HashSet::new();
+        for src_port in 40020..40024 {
+            indices.insert(router.route_packet(&udp_packet(src_port)).await.unwrap().interface_index);
+        }
+        assert_eq!(indices.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_packet_rejects_malformed_headers() {
+        // 10.0.0.2 -> 93.184.216.34, UDP
+        let mut packet = vec![
+            0x45, 0x00, 0x00, 0x1c, 0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x02,
+            0x5d, 0xb8, 0xd8, 0x22, 0xd4, 0x31, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+        ];
+        let checksum = nat::checksum(&packet[..20]);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(validate_packet(&packet), Ok(()));
+
+        // Ethernet padding after the packet is fine
+        let mut padded = packet.clone();
+        padded.extend_from_slice(&[0; 18]);
+        assert_eq!(validate_packet(&padded), Ok(()));
+
+        assert_eq!(validate_packet(&packet[..12]), Err(InvalidPacket::Truncated(12)));
+        assert_eq!(validate_packet(&packet[..27]), Err(InvalidPacket::BadTotalLength(28)));
+        assert_eq!(validate_packet(&[0u8; 100]), Err(InvalidPacket::UnsupportedVersion(0)));
+
+        let mut ipv6 = packet.clone();
+        ipv6[0] = 0x60;
+        assert_eq!(validate_packet(&ipv6), Err(InvalidPacket::Truncated(28)));
+
+        let mut long_header = packet.clone();
+        long_header[0] = 0x4f;
+        assert_eq!(validate_packet(&long_header), Err(InvalidPacket::BadHeaderLength(60)));
+
+        let mut corrupted = packet.clone();
+        corrupted[8] = 1;
+        assert_eq!(validate_packet(&corrupted), Err(InvalidPacket::BadChecksum));
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_flows_are_routed_separately_from_ipv4() {
+        // 2001:db8::2 -> 2001:db8::7, hop-by-hop options then UDP 40000 -> 443
+        let mut ipv6 = vec![0u8; 40 + 8 + 8 + 20];
+        ipv6[0] = 0x60;
+        ipv6[4..6].copy_from_slice(&36u16.to_be_bytes());
+        ipv6[6] = 0;
+        ipv6[7] = 64;
+        ipv6[8..24].copy_from_slice(&"2001:db8::2".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        ipv6[24..40].copy_from_slice(&"2001:db8::7".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        ipv6[40] = 17;
+        ipv6[48..50].copy_from_slice(&40000u16.to_be_bytes());
+        ipv6[50..52].copy_from_slice(&443u16.to_be_bytes());
+        assert_eq!(validate_packet(&ipv6), Ok(()));
+        assert_eq!(validate_packet(&ipv6[..70]), Err(InvalidPacket::BadTotalLength(76)));
+
+        let flow = FlowKey::from_packet(&ipv6).unwrap();
+        assert_eq!(flow.dst_addr, "2001:db8::7".parse::<IpAddr>().unwrap());
+        assert_eq!((flow.src_port, flow.dst_port, flow.protocol), (40000, 443, 17));
+
+        // The same ports over IPv4 make a different flow
+        let mut ipv4 = vec![0u8; 28];
+        ipv4[..20].copy_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 198, 51, 100, 7]);
+        ipv4[20..22].copy_from_slice(&40000u16.to_be_bytes());
+        ipv4[22..24].copy_from_slice(&443u16.to_be_bytes());
+        assert_ne!(FlowKey::from_packet(&ipv4).unwrap(), flow);
+
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        let v6_interface = router.route_packet(&ipv6).await.unwrap().interface_index;
+        let v4_interface = router.route_packet(&ipv4).await.unwrap().interface_index;
+        assert_ne!(v6_interface, v4_interface);
+        for _ in 0..3 {
+            assert_eq!(router.route_packet(&ipv6).await.unwrap().interface_index, v6_interface);
+            assert_eq!(router.route_packet(&ipv4).await.unwrap().interface_index, v4_interface);
+        }
+
+        router.expire_flows(Duration::ZERO).await;
+        router.pin_destination(flow.dst_addr, 2).await.unwrap();
+        assert_eq!(router.route_packet(&ipv6).await.unwrap().interface_index, 2);
+    }
+
+    #[tokio::test]
+    async fn test_flow_log_records_only_new_flows() {
+        let path = std::env::temp_dir().join(format!("netboost-router-flows-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        let flow_log = Arc::new(FlowLog::open(&path, u64::MAX).unwrap());
+        router.set_flow_log(Some(Arc::clone(&flow_log)));
+
+        for src_port in [40000u16, 40001] {
+            let mut packet = vec![0u8; 28];
+            packet[..20].copy_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 198, 51, 100, 7]);
+            packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+            packet[22..24].copy_from_slice(&53u16.to_be_bytes());
+            for _ in 0..5 {
+                router.route_packet(&packet).await.unwrap();
+            }
+        }
+        router.set_flow_log(None);
+        Arc::into_inner(flow_log).unwrap().close();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.contains("\"src_port\":40001"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_interface_group_is_one_candidate_striped_across_members() {
+        let mut interfaces = create_mock_interfaces();
+        let mut wifi1 = interfaces[1].clone();
+        wifi1.name = "wifi1".to_string();
+        wifi1.index = 3;
+        interfaces.push(wifi1);
+        let mut router = PacketRouter::new(InterfaceManager { interfaces });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.set_flow_affinity(false);
+
+        assert!(router.create_interface_group(&[2]).await.is_err());
+        assert!(router.create_interface_group(&[2, 99]).await.is_err());
+        let group = router.create_interface_group(&[3, 2]).await.unwrap();
+        assert_eq!(group.name, "wifi0+wifi1");
+        assert!(router.create_interface_group(&[1, 3]).await.is_err());
+
+        // eth0 and the group take turns, and the group's turns alternate between its members
+        let packet = vec![0u8; 100];
+        let mut picks = Vec::new();
+        for _ in 0..4 {
+            picks.push(router.route_packet(&packet).await.unwrap().interface_index);
+        }
+        assert_eq!(picks, [1, 2, 1, 3]);
+
+        router.remove_interface_group(group.index).await.unwrap();
+        assert!(router.interface_groups().await.is_empty());
+        let picks = [
+            router.route_packet(&packet).await.unwrap().interface_index,
+            router.route_packet(&packet).await.unwrap().interface_index,
+            router.route_packet(&packet).await.unwrap().interface_index,
+        ];
+        assert_eq!(picks, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_flow_is_split_without_affinity() {
+        let mut packet = vec![0u8; 28];
+        packet[..20].copy_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 198, 51, 100, 7]);
+        packet[20..22].copy_from_slice(&40000u16.to_be_bytes());
+        packet[22..24].copy_from_slice(&443u16.to_be_bytes());
+
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+
+        let first = router.route_packet(&packet).await.unwrap().interface_index;
+        assert_eq!(router.route_packet(&packet).await.unwrap().interface_index, first);
+
+        router.set_flow_affinity(false);
+        let picks = [
+            router.route_packet(&packet).await.unwrap().interface_index,
+            router.route_packet(&packet).await.unwrap().interface_index,
+        ];
+        assert_ne!(picks[0], picks[1]);
+    }
+
+    #[tokio::test]
+    async fn test_redundant_mode_copies_gaming_packets() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::Redundant);
+
+        let gaming = router.route_packet(&[0u8; 60]).await.unwrap();
+        assert_eq!(gaming.traffic_type, TrafficType::Gaming);
+        let mut sent = gaming.duplicates.clone();
+        sent.push(gaming.interface_index);
+        sent.sort();
+        assert_eq!(sent, vec![1, 2]);
+
+        // Other traffic is balanced as usual
+        assert!(router.route_packet(&[0u8; 300]).await.unwrap().duplicates.is_empty());
+
+        // So is gaming traffic in other modes
+        router.set_load_balancing_mode(LoadBalancingMode::Balanced);
+        assert!(router.route_packet(&[0u8; 60]).await.unwrap().duplicates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_redundant_copies_are_not_charged_until_sent() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::Redundant);
+        router.set_interface_rate_limit(1, Some(1)).await;
+        router.set_interface_rate_limit(2, Some(1)).await;
+
+        // Each gaming packet is charged to the interface it goes out of, and
+        // nothing more without a relay sending the copies
+        let packet = [0u8; 64];
+        for _ in 0..2 * (4096 / packet.len()) {
+            let decision = router.route_packet(&packet).await.unwrap();
+            assert_eq!(decision.traffic_type, TrafficType::Gaming);
+        }
+        assert!(router.route_packet(&packet).await.unwrap_err().is::<RateLimited>());
+    }
+
+    #[test]
+    fn test_next_hop_uses_gateway_off_link() {
+        let interfaces = create_mock_interfaces();
+
+        // On-link destinations are resolved directly
+        assert_eq!(interfaces[0].next_hop(Ipv4Addr::new(192, 168, 1, 20)), Ipv4Addr::new(192, 168, 1, 20));
+        // Everything else goes through the gateway
+        assert_eq!(interfaces[0].next_hop(Ipv4Addr::new(8, 8, 8, 8)), Ipv4Addr::new(192, 168, 1, 254));
+        // Without a gateway the destination is the best we can do
+        assert_eq!(interfaces[1].next_hop(Ipv4Addr::new(8, 8, 8, 8)), Ipv4Addr::new(8, 8, 8, 8));
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_mode_follows_traffic_mix_with_hysteresis() {
+        let im = InterfaceManager{ interfaces: create_mock_interfaces() };
+        let mut router = PacketRouter::new(im);
+        router.set_load_balancing_mode(LoadBalancingMode::Adaptive);
+        let mix = |gaming: u64, streaming: u64, web: u64| {
+            HashMap::from([(TrafficType::Gaming, gaming), (TrafficType::Streaming, streaming), (TrafficType::Web, web)])
+        };
+
+        assert_eq!(router.effective_mode().await, LoadBalancingMode::Balanced);
+        // Too little traffic to judge
+        assert_eq!(router.observe_traffic_mix(&mix(50, 0, 0)).await, None);
+
+        assert_eq!(router.observe_traffic_mix(&mix(70, 10, 20)).await, Some(LoadBalancingMode::LatencyBased));
+        assert_eq!(router.effective_mode().await, LoadBalancingMode::LatencyBased);
+        // Gaming falling to half the traffic isn't enough to switch back
+        assert_eq!(router.observe_traffic_mix(&mix(50, 30, 20)).await, None);
+        assert_eq!(router.observe_traffic_mix(&mix(20, 70, 10)).await, Some(LoadBalancingMode::BandwidthBased));
+        assert_eq!(router.observe_traffic_mix(&mix(20, 30, 50)).await, Some(LoadBalancingMode::Balanced));
+
+        // Other modes ignore the mix
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        router.observe_traffic_mix(&mix(100, 0, 0)).await;
+        assert_eq!(router.effective_mode().await, LoadBalancingMode::RoundRobin);
+    }
+
+    #[test]
+    fn test_load_balancing_mode_names_round_trip() {
+        for mode in LoadBalancingMode::ALL {
+            assert_eq!(mode.as_str().parse::<LoadBalancingMode>().unwrap(), mode);
+            assert_eq!(serde_json::to_value(mode).unwrap(), mode.as_str());
+            assert_eq!(mode.info().name.parse::<LoadBalancingMode>().unwrap(), mode);
+        }
+        assert!("fastest".parse::<LoadBalancingMode>().is_err());
+    }
+}
@@ -0,0 +1,334 @@
+// src-tauri/src/packet_sampler.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+use crate::packet_router::RoutingDecision;
+use crate::pcap::{LINKTYPE_RAW, PCAPNG_BYTE_ORDER_MAGIC, PCAPNG_ENHANCED_PACKET, PCAPNG_INTERFACE_DESCRIPTION, PCAPNG_SECTION_HEADER};
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const IF_NAME: u16 = 2;
+const IF_DESCRIPTION: u16 = 3;
+
+/// Samples waiting to be written; more are dropped rather than slow down
+/// forwarding
+const SAMPLE_QUEUE_LEN: usize = 1024;
+
+/// Where and how often to copy routed packets for external analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplerSettings {
+    pub enabled: bool,
+    /// Copy one in this many routed packets; 1 copies every packet
+    pub rate: u32,
+    /// pcapng file, replaced each time sampling starts; `None` uses
+    /// `samples.pcapng` in the data directory
+    pub file: Option<PathBuf>,
+    /// Send samples to this UDP collector instead of a file
+    pub collector: Option<SocketAddr>,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 100,
+            file: None,
+            collector: None,
+        }
+    }
+}
+
+impl SamplerSettings {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.file
+            .clone()
+            .or_else(|| dirs::data_local_dir().map(|dir| dir.join("netboost-pro").join("samples.pcapng")))
+    }
+}
+
+enum Sink {
+    /// Each interface routed to gets its own pcapng interface, named after it
+    Pcapng {
+        writer: BufWriter<File>,
+        interface_ids: HashMap<u32, u32>,
+    },
+    /// Each sample is one datagram: the interface index as 4 big-endian
+    /// bytes, then the raw IP packet
+    Udp(UdpSocket, SocketAddr),
+}
+
+/// A routed packet copied for the writer
+struct Sample {
+    packet: Vec<u8>,
+    interface_index: u32,
+    interface_name: String,
+    comment: String,
+}
+
+/// Copies one in every `rate` routed packets, with the interface it was
+/// routed to, to a pcapng file or a UDP collector. Samples are written on a
+/// thread of their own, so forwarding never waits on the disk.
+pub struct PacketSampler {
+    rate: u64,
+    seen: AtomicU64,
+    destination: String,
+    settings: SamplerSettings,
+    samples: SyncSender<Sample>,
+    writer: JoinHandle<()>,
+}
+
+impl PacketSampler {
+    /// Start a new pcapng file at `path`
+    pub fn to_file(path: &Path, rate: u32) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = File::create(path).with_context(|| format!("Failed to create sample file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        write_block(&mut writer, PCAPNG_SECTION_HEADER, |body| {
+            body.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+            body.extend_from_slice(&1u16.to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes());
+            // Section length unknown
+            body.extend_from_slice(&(-1i64).to_le_bytes());
+        })?;
+        writer.flush()?;
+
+        Self::new(rate, path.display().to_string(), Sink::Pcapng { writer, interface_ids: HashMap::new() })
+    }
+
+    pub fn to_collector(collector: SocketAddr, rate: u32) -> Result<Self> {
+        let bind: SocketAddr = if collector.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(bind).context("Failed to open a socket for the sample collector")?;
+        socket.set_nonblocking(true)?;
+        Self::new(rate, format!("udp://{}", collector), Sink::Udp(socket, collector))
+    }
+
+    /// The sampler described by `settings`, or `None` if sampling is off
+    pub fn from_settings(settings: &SamplerSettings) -> Result<Option<Self>> {
+        if !settings.enabled {
+            return Ok(None);
+        }
+        let mut sampler = match settings.collector {
+            Some(collector) => Self::to_collector(collector, settings.rate)?,
+            None => {
+                let path = settings.path().context("No data directory available for packet samples")?;
+                Self::to_file(&path, settings.rate)?
+            }
+        };
+        sampler.settings = settings.clone();
+        Ok(Some(sampler))
+    }
+
+    fn new(rate: u32, destination: String, sink: Sink) -> Result<Self> {
+        let (samples, queued) = mpsc::sync_channel(SAMPLE_QUEUE_LEN);
+        let writer = {
+            let destination = destination.clone();
+            std::thread::Builder::new()
+                .name("packet-sampler".to_string())
+                .spawn(move || write_samples(sink, queued, &destination))
+                .context("Failed to start the packet sample writer")?
+        };
+        Ok(Self {
+            rate: rate.max(1) as u64,
+            seen: AtomicU64::new(0),
+            destination,
+            settings: SamplerSettings { enabled: true, rate, ..Default::default() },
+            samples,
+            writer,
+        })
+    }
+
+    /// The file or collector samples go to
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// The settings the sampler was started with
+    pub fn settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    /// Stop sampling once the samples already queued are written. Blocks.
+    pub fn close(self) {
+        drop(self.samples);
+        let _ = self.writer.join();
+    }
+
+    /// Count a routed packet and queue a copy if it is due. The copy is
+    /// dropped if the writer has fallen behind.
+    pub fn offer(&self, packet: &[u8], decision: &RoutingDecision) {
+        if !self.seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.rate) {
+            return;
+        }
+        let sample = Sample {
+            packet: packet.to_vec(),
+            interface_index: decision.interface_index,
+            interface_name: decision.interface_name.clone(),
+            comment: format!(
+                "traffic_type={} confidence={:.2} reason={}",
+                decision.traffic_type.as_str(), decision.confidence, decision.reason
+            ),
+        };
+        if let Err(TrySendError::Full(_)) = self.samples.try_send(sample) {
+            debug!("Packet sample writer is behind; dropping a sample");
+        }
+    }
+}
+
+/// Write samples until the sampler is closed, flushing whenever the queue
+/// runs dry so the file stays readable while the service runs
+fn write_samples(mut sink: Sink, queued: Receiver<Sample>, destination: &str) {
+    while let Ok(sample) = queued.recv() {
+        for sample in std::iter::once(sample).chain(queued.try_iter()) {
+            if let Err(e) = sink.write(&sample) {
+                warn!("Failed to write packet sample to {}: {:#}", destination, e);
+            }
+        }
+        if let Sink::Pcapng { writer, .. } = &mut sink {
+            if let Err(e) = writer.flush() {
+                warn!("Failed to write packet samples to {}: {}", destination, e);
+            }
+        }
+    }
+}
+
+impl Sink {
+    fn write(&mut self, sample: &Sample) -> Result<()> {
+        match self {
+            Sink::Pcapng { writer, interface_ids } => {
+                let next_id = interface_ids.len() as u32;
+                let interface_id = match interface_ids.get(&sample.interface_index) {
+                    Some(id) => *id,
+                    None => {
+                        write_block(writer, PCAPNG_INTERFACE_DESCRIPTION, |body| {
+                            body.extend_from_slice(&(LINKTYPE_RAW as u16).to_le_bytes());
+                            body.extend_from_slice(&0u16.to_le_bytes());
+                            // No snapshot length limit
+                            body.extend_from_slice(&0u32.to_le_bytes());
+                            push_option(body, IF_NAME, sample.interface_name.as_bytes());
+                            push_option(body, IF_DESCRIPTION, format!("index {}", sample.interface_index).as_bytes());
+                            push_option(body, OPT_END, &[]);
+                        })?;
+                        interface_ids.insert(sample.interface_index, next_id);
+                        next_id
+                    }
+                };
+
+                let micros = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+                let packet = &sample.packet;
+                write_block(writer, PCAPNG_ENHANCED_PACKET, |body| {
+                    body.extend_from_slice(&interface_id.to_le_bytes());
+                    body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+                    body.extend_from_slice(&(micros as u32).to_le_bytes());
+                    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+                    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+                    body.extend_from_slice(packet);
+                    pad(body);
+                    push_option(body, OPT_COMMENT, sample.comment.as_bytes());
+                    push_option(body, OPT_END, &[]);
+                })?;
+            }
+            Sink::Udp(socket, collector) => {
+                let mut datagram = Vec::with_capacity(4 + sample.packet.len());
+                datagram.extend_from_slice(&sample.interface_index.to_be_bytes());
+                datagram.extend_from_slice(&sample.packet);
+                socket.send_to(&datagram, *collector)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write a pcapng block: type, total length, body, total length again
+fn write_block(writer: &mut impl Write, block_type: u32, fill: impl FnOnce(&mut Vec<u8>)) -> Result<()> {
+    let mut body = Vec::new();
+    fill(&mut body);
+    let total_len = (body.len() + 12) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_len.to_le_bytes())?;
+    writer.write_all(&body)?;
+    writer.write_all(&total_len.to_le_bytes())?;
+    Ok(())
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+/// Pad to a 32-bit boundary, as every pcapng field must be
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().div_ceil(4) * 4, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_router::TrafficType;
+
+    fn decision(interface_index: u32, interface_name: &str) -> RoutingDecision {
+        RoutingDecision {
+            interface_index,
+            interface_name: interface_name.to_string(),
+            confidence: 0.8,
+            reason: "test".to_string(),
+            traffic_type: TrafficType::Web,
+            duplicates: Vec::new(),
+            low_confidence_fallback: false,
+        }
+    }
+
+    fn ipv4_packet(id: u8) -> Vec<u8> {
+        let mut packet = vec![0u8; 21];
+        packet[0] = 0x45;
+        packet[3] = 21;
+        packet[20] = id;
+        packet
+    }
+
+    #[test]
+    fn test_samples_one_in_n_to_pcapng() {
+        let path = std::env::temp_dir().join(format!("netboost-samples-{}.pcapng", std::process::id()));
+        let sampler = PacketSampler::to_file(&path, 2).unwrap();
+        for id in 0..5 {
+            let routed = if id % 4 == 0 { decision(2, "eth0") } else { decision(3, "wlan0") };
+            sampler.offer(&ipv4_packet(id), &routed);
+        }
+        sampler.close();
+
+        // Packets 0, 2 and 4, over two interface blocks
+        let packets = crate::pcap::read_ipv4_packets(&path).unwrap();
+        let ids: Vec<u8> = packets.iter().map(|packet| packet[20]).collect();
+        assert_eq!(ids, [0, 2, 4]);
+        let contents = std::fs::read(&path).unwrap();
+        assert!(contents.windows(5).any(|window| window == b"wlan0"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_samples_go_to_a_udp_collector() {
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        collector.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        let sampler = PacketSampler::to_collector(collector.local_addr().unwrap(), 1).unwrap();
+        sampler.offer(&ipv4_packet(7), &decision(2, "eth0"));
+
+        let mut buf = [0u8; 64];
+        let len = collector.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..4], &2u32.to_be_bytes());
+        assert_eq!(&buf[4..len], ipv4_packet(7).as_slice());
+    }
+}
@@ -1,67 +1,613 @@
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+use crate::packet_router::{LoadBalancingMode, TrafficType};
+
+/// Span over which `current_bandwidth_bps` is measured
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Granularity of the throughput window
+const THROUGHPUT_BUCKET: Duration = Duration::from_millis(100);
+
+/// Buckets making up the throughput window
+const THROUGHPUT_BUCKETS: usize = (THROUGHPUT_WINDOW.as_millis() / THROUGHPUT_BUCKET.as_millis()) as usize;
+
+/// How much performance history is kept by default
+pub const DEFAULT_HISTORY_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Time between performance history samples by default
+pub const DEFAULT_HISTORY_RESOLUTION: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PerformanceStats {
     pub packets_received: u64,
     pub packets_forwarded: u64,
-    pub packets_dropped: u64,
+    pub packets_dropped: DropReasons,
+    /// Bytes of the packets dropped by interface rate limits
+    pub bytes_throttled: u64,
+    /// Extra copies sent in `Redundant` mode, not counted in `packets_forwarded`
+    pub packets_duplicated: u64,
+    pub bytes_duplicated: u64,
+    /// Times an interface was taken out of rotation after repeated send failures
+    pub circuit_breaker_trips: u64,
+    /// Flows moved off an interface after it went down
+    pub flows_migrated: u64,
+    /// Packets sent to the primary interface because the balancer's pick
+    /// was below the confidence threshold
+    pub low_confidence_fallbacks: u64,
+    /// DNS lookups answered from the cache without leaving the machine
+    pub dns_cache_hits: u64,
+    /// Fragmented datagrams dropped because the rest of their fragments
+    /// didn't arrive in time or the reassembly buffer was full
+    pub reassembly_timeouts: u64,
+    /// Packets waiting to be processed
+    pub queue_depth: u64,
+    /// Packets waiting to be processed, by traffic type
+    pub queue_depth_by_type: HashMap<TrafficType, u64>,
+    /// Average forwarded bytes per second since the session started
     pub bandwidth_usage: u64,
+    /// Bits per second in both directions over the last second
+    pub current_bandwidth_bps: u64,
+    /// Bytes sent out of the physical interfaces this session
+    pub upload_bytes: u64,
+    /// Bytes of replies written back into the TUN this session
+    pub download_bytes: u64,
+    pub upload_bps: u64,
+    pub download_bps: u64,
+    /// Effective combined speed: the sum of each interface's measured
+    /// throughput over the last second, so idle links don't inflate it
+    pub aggregate_bandwidth_bps: u64,
     pub average_latency: Duration,
     pub packet_loss_rate: f32,
     pub uptime: Duration,
+    pub interfaces: HashMap<u32, InterfaceStats>,
+    /// Forwarded traffic by how it was classified, for the traffic mix
+    pub traffic_types: HashMap<TrafficType, TrafficTypeStats>,
+    /// Strategy new flows are routed with, which `Adaptive` mode changes
+    /// with the traffic mix. Filled in by the running service.
+    pub effective_load_balancing_mode: Option<LoadBalancingMode>,
+}
+
+/// Traffic forwarded through a single physical interface
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct InterfaceStats {
+    pub name: String,
+    pub packets_forwarded: u64,
+    pub bytes_forwarded: u64,
+    /// Bits per second in both directions over the last second
+    pub current_bps: u64,
+}
+
+/// Why a packet was dropped instead of forwarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// No usable interface could be chosen for it
+    NoInterface,
+    /// The chosen interface was gone or failed to send it
+    SendError,
+    /// Pushed out of the full processing queue by a higher-priority packet
+    QueueFull,
+    /// Malformed, caught before routing
+    InvalidPacket,
+    /// Every interface was over its rate limit
+    RateLimited,
+    /// Still queued when the service stopped without draining
+    Shutdown,
+    /// IPv6, which the host routes itself instead of through the TUN
+    Ipv6,
+}
+
+impl DropReason {
+    pub const ALL: [DropReason; 7] = [
+        DropReason::NoInterface,
+        DropReason::SendError,
+        DropReason::QueueFull,
+        DropReason::InvalidPacket,
+        DropReason::RateLimited,
+        DropReason::Shutdown,
+        DropReason::Ipv6,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::NoInterface => "no_interface",
+            DropReason::SendError => "send_error",
+            DropReason::QueueFull => "queue_full",
+            DropReason::InvalidPacket => "invalid_packet",
+            DropReason::RateLimited => "rate_limited",
+            DropReason::Shutdown => "shutdown",
+            DropReason::Ipv6 => "ipv6",
+        }
+    }
+}
+
+/// Dropped packets by `DropReason`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DropReasons {
+    pub no_interface: u64,
+    pub send_error: u64,
+    pub queue_full: u64,
+    pub invalid_packet: u64,
+    pub rate_limited: u64,
+    pub shutdown: u64,
+    pub ipv6: u64,
+    /// Packets dropped for any reason
+    pub total: u64,
+}
+
+impl DropReasons {
+    fn from_counters(counters: &[AtomicU64; DropReason::ALL.len()]) -> Self {
+        let count = |reason: DropReason| counters[reason as usize].load(Ordering::Relaxed);
+        let mut drops = Self {
+            no_interface: count(DropReason::NoInterface),
+            send_error: count(DropReason::SendError),
+            queue_full: count(DropReason::QueueFull),
+            invalid_packet: count(DropReason::InvalidPacket),
+            rate_limited: count(DropReason::RateLimited),
+            shutdown: count(DropReason::Shutdown),
+            ipv6: count(DropReason::Ipv6),
+            total: 0,
+        };
+        drops.total = DropReason::ALL.iter().map(|&reason| drops.get(reason)).sum();
+        drops
+    }
+
+    pub fn get(&self, reason: DropReason) -> u64 {
+        match reason {
+            DropReason::NoInterface => self.no_interface,
+            DropReason::SendError => self.send_error,
+            DropReason::QueueFull => self.queue_full,
+            DropReason::InvalidPacket => self.invalid_packet,
+            DropReason::RateLimited => self.rate_limited,
+            DropReason::Shutdown => self.shutdown,
+            DropReason::Ipv6 => self.ipv6,
+        }
+    }
+}
+
+/// Traffic forwarded of a single `TrafficType`
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct TrafficTypeStats {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// Counters that are never reset, so external monitoring systems see
+/// monotonic values for the lifetime of the process
+#[derive(Debug, Clone, Default)]
+pub struct LifetimeCounters {
+    pub packets_received: u64,
+    pub packets_forwarded: u64,
+    pub packets_dropped: DropReasons,
+    pub packets_duplicated: u64,
+    pub bytes_duplicated: u64,
+    pub circuit_breaker_trips: u64,
+    pub flows_migrated: u64,
+    pub low_confidence_fallbacks: u64,
+    pub dns_cache_hits: u64,
+    pub reassembly_timeouts: u64,
+    pub bytes_received: u64,
+    pub bytes_forwarded: u64,
+    pub packets_returned: u64,
+    pub bytes_returned: u64,
+    pub interfaces: HashMap<u32, InterfaceStats>,
+    pub traffic_types: HashMap<TrafficType, TrafficTypeStats>,
+}
+
+/// One point of the performance history
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistorySample {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub upload_bps: u64,
+    pub download_bps: u64,
+    pub aggregate_bandwidth_bps: u64,
+    pub average_latency_ms: f64,
+    pub packet_loss_rate: f32,
+}
+
+impl HistorySample {
+    const CSV_HEADER: &'static str = "timestamp,upload_bps,download_bps,aggregate_bandwidth_bps,average_latency_ms,packet_loss_percent";
+
+    fn from_stats(timestamp_ms: u64, stats: &PerformanceStats) -> Self {
+        Self {
+            timestamp_ms,
+            upload_bps: stats.upload_bps,
+            download_bps: stats.download_bps,
+            aggregate_bandwidth_bps: stats.aggregate_bandwidth_bps,
+            average_latency_ms: stats.average_latency.as_secs_f64() * 1000.0,
+            packet_loss_rate: stats.packet_loss_rate,
+        }
+    }
+
+    fn write_csv_row(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let timestamp = chrono::DateTime::from_timestamp_millis(self.timestamp_ms as i64)
+            .unwrap_or_default()
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        writeln!(
+            out,
+            "{},{},{},{},{:.3},{:.3}",
+            timestamp,
+            self.upload_bps,
+            self.download_bps,
+            self.aggregate_bandwidth_bps,
+            self.average_latency_ms,
+            self.packet_loss_rate * 100.0
+        )
+    }
+}
+
+/// Recent `HistorySample`s, oldest first, covering at most `retention`
+#[derive(Debug)]
+struct History {
+    samples: VecDeque<HistorySample>,
+    retention: Duration,
+}
+
+impl History {
+    fn expire(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.retention.as_millis() as u64);
+        while self.samples.front().is_some_and(|sample| sample.timestamp_ms < cutoff) {
+            self.samples.pop_front();
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
 }
 
 pub struct PerformanceMonitor {
+    /// Per-session counters, updated without locking on the packet path
+    session: Counters,
+    /// Never-reset counters, see `LifetimeCounters`
+    lifetime: Counters,
+    /// Packets waiting to be processed, indexed by `TrafficType::priority`
+    queue_depths: [AtomicU64; TrafficType::PRIORITY_LEVELS],
     stats: Arc<RwLock<InternalStats>>,
-    start_time: Instant,
+    /// Only written to when an interface is seen for the first time
+    interfaces: RwLock<HashMap<u32, InterfaceTraffic>>,
+    recent_upload: ThroughputWindow,
+    recent_download: ThroughputWindow,
+    history: RwLock<History>,
+}
+
+/// Simple counters shared by the per-session and lifetime totals
+#[derive(Debug, Default)]
+struct Counters {
+    packets_received: AtomicU64,
+    packets_forwarded: AtomicU64,
+    /// Indexed by `DropReason`
+    packets_dropped: [AtomicU64; DropReason::ALL.len()],
+    packets_duplicated: AtomicU64,
+    bytes_duplicated: AtomicU64,
+    circuit_breaker_trips: AtomicU64,
+    flows_migrated: AtomicU64,
+    low_confidence_fallbacks: AtomicU64,
+    dns_cache_hits: AtomicU64,
+    reassembly_timeouts: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_forwarded: AtomicU64,
+    bytes_throttled: AtomicU64,
+    packets_returned: AtomicU64,
+    bytes_returned: AtomicU64,
+    /// Indexed by `TrafficType`
+    type_packets: [AtomicU64; TrafficType::ALL.len()],
+    type_bytes: [AtomicU64; TrafficType::ALL.len()],
+}
+
+impl Counters {
+    fn reset(&self) {
+        for counter in [
+            &self.packets_received,
+            &self.packets_forwarded,
+            &self.packets_duplicated,
+            &self.bytes_duplicated,
+            &self.circuit_breaker_trips,
+            &self.flows_migrated,
+            &self.low_confidence_fallbacks,
+            &self.dns_cache_hits,
+            &self.reassembly_timeouts,
+            &self.bytes_received,
+            &self.bytes_forwarded,
+            &self.bytes_throttled,
+            &self.packets_returned,
+            &self.bytes_returned,
+        ]
+        .into_iter()
+        .chain(&self.packets_dropped)
+        .chain(&self.type_packets)
+        .chain(&self.type_bytes)
+        {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn traffic_types(&self) -> HashMap<TrafficType, TrafficTypeStats> {
+        TrafficType::ALL
+            .into_iter()
+            .filter_map(|traffic_type| {
+                let packets = self.type_packets[traffic_type as usize].load(Ordering::Relaxed);
+                (packets > 0).then(|| {
+                    let bytes = self.type_bytes[traffic_type as usize].load(Ordering::Relaxed);
+                    (traffic_type, TrafficTypeStats { packets, bytes })
+                })
+            })
+            .collect()
+    }
+}
+
+/// Traffic sent out of one interface for the current session and the
+/// process lifetime, plus its recent throughput in both directions
+#[derive(Debug, Default)]
+struct InterfaceTraffic {
+    /// Set by the first packet forwarded; replies don't carry a name
+    name: OnceLock<String>,
+    session_packets: AtomicU64,
+    session_bytes: AtomicU64,
+    lifetime_packets: AtomicU64,
+    lifetime_bytes: AtomicU64,
+    recent: ThroughputWindow,
+}
+
+impl InterfaceTraffic {
+    fn add_sent(&self, name: &str, bytes: usize) {
+        self.name.get_or_init(|| name.to_string());
+        self.session_packets.fetch_add(1, Ordering::Relaxed);
+        self.session_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.lifetime_packets.fetch_add(1, Ordering::Relaxed);
+        self.lifetime_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.recent.record(bytes);
+    }
+
+    /// Session totals, or `None` if nothing was sent this session
+    fn session_stats(&self) -> Option<InterfaceStats> {
+        let packets_forwarded = self.session_packets.load(Ordering::Relaxed);
+        (packets_forwarded > 0).then(|| InterfaceStats {
+            name: self.name.get().cloned().unwrap_or_default(),
+            packets_forwarded,
+            bytes_forwarded: self.session_bytes.load(Ordering::Relaxed),
+            current_bps: self.recent.bits_per_second(),
+        })
+    }
+
+    fn lifetime_stats(&self) -> Option<InterfaceStats> {
+        let packets_forwarded = self.lifetime_packets.load(Ordering::Relaxed);
+        (packets_forwarded > 0).then(|| InterfaceStats {
+            name: self.name.get().cloned().unwrap_or_default(),
+            packets_forwarded,
+            bytes_forwarded: self.lifetime_bytes.load(Ordering::Relaxed),
+            current_bps: 0,
+        })
+    }
+}
+
+/// Bytes in fixed-size time buckets covering `THROUGHPUT_WINDOW`, kept in a
+/// ring so recording needs no lock. Bytes recorded just as a bucket is
+/// reused may be lost, which only blurs the rate a little.
+#[derive(Debug)]
+struct ThroughputWindow {
+    origin: Instant,
+    /// (bucket number since `origin`, bytes in that bucket), at the bucket
+    /// number modulo the ring size
+    buckets: [(AtomicU64, AtomicU64); THROUGHPUT_BUCKETS],
 }
 
+impl Default for ThroughputWindow {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+            buckets: Default::default(),
+        }
+    }
+}
+
+impl ThroughputWindow {
+    fn current_bucket(&self) -> u64 {
+        (self.origin.elapsed().as_millis() / THROUGHPUT_BUCKET.as_millis()) as u64
+    }
+
+    fn record(&self, bytes: usize) {
+        let bucket = self.current_bucket();
+        let (number, total) = &self.buckets[bucket as usize % THROUGHPUT_BUCKETS];
+        let previous = number.load(Ordering::Acquire);
+        // Whoever moves the slot on to this bucket clears what it held
+        if previous != bucket && number.compare_exchange(previous, bucket, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            total.store(0, Ordering::Release);
+        }
+        total.fetch_add(bytes as u64, Ordering::AcqRel);
+    }
+
+    fn clear(&self) {
+        for (_, total) in &self.buckets {
+            total.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn bits_per_second(&self) -> u64 {
+        let current = self.current_bucket();
+        let bytes: u64 = self
+            .buckets
+            .iter()
+            .filter(|(number, _)| number.load(Ordering::Acquire) + THROUGHPUT_BUCKETS as u64 > current)
+            .map(|(_, bytes)| bytes.load(Ordering::Acquire))
+            .sum();
+        (bytes as f64 * 8.0 / THROUGHPUT_WINDOW.as_secs_f64()) as u64
+    }
+}
+
+/// Processing latencies kept for the average
+const LATENCY_SAMPLE_WINDOW: usize = 1000;
+
 #[derive(Debug)]
 struct InternalStats {
-    packets_received: u64,
-    packets_forwarded: u64,
-    packets_dropped: u64,
-    total_bytes_received: u64,
-    total_bytes_forwarded: u64,
     total_processing_time: Duration,
-    latency_samples: Vec<Duration>,
+    latency_samples: VecDeque<Duration>,
     max_latency_samples: usize,
+    start_time: Instant,
+}
+
+impl InternalStats {
+    fn new() -> Self {
+        Self {
+            total_processing_time: Duration::new(0, 0),
+            latency_samples: VecDeque::new(),
+            max_latency_samples: LATENCY_SAMPLE_WINDOW,
+            start_time: Instant::now(),
+        }
+    }
+}
+
+impl Default for PerformanceMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PerformanceMonitor {
     pub fn new() -> Self {
         Self {
-            stats: Arc::new(RwLock::new(InternalStats {
-                packets_received: 0,
-                packets_forwarded: 0,
-                packets_dropped: 0,
-                total_bytes_received: 0,
-                total_bytes_forwarded: 0,
-                total_processing_time: Duration::new(0, 0),
-                latency_samples: Vec::new(),
-                max_latency_samples: 1000, // Keep last 1000 samples
-            })),
-            start_time: Instant::now(),
+            session: Counters::default(),
+            lifetime: Counters::default(),
+            queue_depths: Default::default(),
+            stats: Arc::new(RwLock::new(InternalStats::new())),
+            interfaces: RwLock::new(HashMap::new()),
+            recent_upload: ThroughputWindow::default(),
+            recent_download: ThroughputWindow::default(),
+            history: RwLock::new(History { samples: VecDeque::new(), retention: DEFAULT_HISTORY_RETENTION }),
         }
     }
 
     pub async fn record_packet_received(&self, bytes: usize) {
-        let mut stats = self.stats.write().await;
-        stats.packets_received += 1;
-        stats.total_bytes_received += bytes as u64;
+        for counters in [&self.session, &self.lifetime] {
+            counters.packets_received.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
     }
 
-    pub async fn record_packet_forwarded(&self, bytes: usize) {
-        let mut stats = self.stats.write().await;
-        stats.packets_forwarded += 1;
-        stats.total_bytes_forwarded += bytes as u64;
+    pub async fn record_packet_forwarded(&self, interface_index: u32, interface_name: &str, traffic_type: TrafficType, bytes: usize) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.packets_forwarded.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_forwarded.fetch_add(bytes as u64, Ordering::Relaxed);
+            counters.type_packets[traffic_type as usize].fetch_add(1, Ordering::Relaxed);
+            counters.type_bytes[traffic_type as usize].fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+        self.recent_upload.record(bytes);
+        self.with_interface(interface_index, |interface| interface.add_sent(interface_name, bytes)).await;
     }
 
-    pub async fn record_packet_dropped(&self) {
-        let mut stats = self.stats.write().await;
-        stats.packets_dropped += 1;
+    /// Record an extra copy of a forwarded packet sent out of `interface_index`.
+    /// It counts towards that interface's traffic and the upload rate, but
+    /// not towards the forwarded totals.
+    pub async fn record_packet_duplicated(&self, interface_index: u32, interface_name: &str, bytes: usize) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.packets_duplicated.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_duplicated.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+        self.recent_upload.record(bytes);
+        self.with_interface(interface_index, |interface| interface.add_sent(interface_name, bytes)).await;
+    }
+
+    /// Record a reply that arrived on `interface_index` and was written back into the TUN device
+    pub async fn record_packet_returned(&self, interface_index: u32, bytes: usize) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.packets_returned.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_returned.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+        self.recent_download.record(bytes);
+        self.with_interface(interface_index, |interface| interface.recent.record(bytes)).await;
+    }
+
+    /// Run `update` on the traffic of interface `index`, only taking the
+    /// write lock the first time the interface is seen
+    async fn with_interface(&self, index: u32, update: impl FnOnce(&InterfaceTraffic)) {
+        if let Some(interface) = self.interfaces.read().await.get(&index) {
+            update(interface);
+            return;
+        }
+        update(self.interfaces.write().await.entry(index).or_default());
+    }
+
+    /// Stop showing an interface that has gone away in the session stats
+    /// and the current throughput. Its lifetime counters stay, so exported
+    /// counters never go backwards.
+    pub async fn forget_interface(&self, index: u32) {
+        if let Some(interface) = self.interfaces.read().await.get(&index) {
+            interface.session_packets.store(0, Ordering::Relaxed);
+            interface.session_bytes.store(0, Ordering::Relaxed);
+            interface.recent.clear();
+        }
+    }
+
+    /// Combined throughput of every interface over the last second
+    pub async fn aggregate_bandwidth_bps(&self) -> u64 {
+        Self::aggregate_bps(&*self.interfaces.read().await)
+    }
+
+    fn aggregate_bps(interfaces: &HashMap<u32, InterfaceTraffic>) -> u64 {
+        interfaces.values().map(|interface| interface.recent.bits_per_second()).sum()
+    }
+
+    pub async fn record_packet_dropped(&self, reason: DropReason) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.packets_dropped[reason as usize].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a packet dropped because every interface was over its rate limit
+    pub async fn record_packet_throttled(&self, bytes: usize) {
+        self.record_packet_dropped(DropReason::RateLimited).await;
+        for counters in [&self.session, &self.lifetime] {
+            counters.bytes_throttled.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Record an interface taken out of rotation after repeated send failures
+    pub async fn record_circuit_breaker_trip(&self) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.circuit_breaker_trips.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record flows moved off an interface that went down
+    pub async fn record_flows_migrated(&self, count: usize) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.flows_migrated.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a packet sent to the primary interface for lack of confidence
+    pub async fn record_low_confidence_fallback(&self) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.low_confidence_fallbacks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a DNS lookup answered from the cache
+    pub async fn record_dns_cache_hit(&self) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.dns_cache_hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record fragmented datagrams dropped before they were complete
+    pub async fn record_reassembly_timeouts(&self, count: usize) {
+        for counters in [&self.session, &self.lifetime] {
+            counters.reassembly_timeouts.fetch_add(count as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Record how many packets are waiting to be processed at each priority level
+    pub fn record_queue_depth(&self, depths: impl IntoIterator<Item = usize>) {
+        for (counter, depth) in self.queue_depths.iter().zip(depths) {
+            counter.store(depth as u64, Ordering::Relaxed);
+        }
     }
 
     pub async fn record_processing_latency(&self, latency: Duration) {
@@ -69,15 +615,19 @@ impl PerformanceMonitor {
         stats.total_processing_time += latency;
         
         // Add latency sample and maintain a rolling window
-        stats.latency_samples.push(latency);
+        stats.latency_samples.push_back(latency);
         if stats.latency_samples.len() > stats.max_latency_samples {
-            stats.latency_samples.remove(0);
+            stats.latency_samples.pop_front();
         }
     }
 
     pub async fn get_current_stats(&self) -> PerformanceStats {
         let stats = self.stats.read().await;
-        let uptime = self.start_time.elapsed();
+        let uptime = stats.start_time.elapsed();
+
+        let packets_received = self.session.packets_received.load(Ordering::Relaxed);
+        let packets_dropped = DropReasons::from_counters(&self.session.packets_dropped);
+        let bytes_forwarded = self.session.bytes_forwarded.load(Ordering::Relaxed);
 
         // Calculate average latency from samples
         let average_latency = if !stats.latency_samples.is_empty() {
@@ -88,42 +638,275 @@ impl PerformanceMonitor {
         };
 
         // Calculate packet loss rate
-        let packet_loss_rate = if stats.packets_received > 0 {
-            stats.packets_dropped as f32 / stats.packets_received as f32
+        let packet_loss_rate = if packets_received > 0 {
+            packets_dropped.total as f32 / packets_received as f32
         } else {
             0.0
         };
 
         // Calculate bandwidth usage (bytes per second)
         let bandwidth_usage = if uptime.as_secs() > 0 {
-            stats.total_bytes_forwarded / uptime.as_secs()
+            bytes_forwarded / uptime.as_secs()
         } else {
             0
         };
 
+        let upload_bps = self.recent_upload.bits_per_second();
+        let download_bps = self.recent_download.bits_per_second();
+        let traffic = self.interfaces.read().await;
+        let interfaces = traffic
+            .iter()
+            .filter_map(|(index, interface)| Some((*index, interface.session_stats()?)))
+            .collect();
         PerformanceStats {
-            packets_received: stats.packets_received,
-            packets_forwarded: stats.packets_forwarded,
-            packets_dropped: stats.packets_dropped,
+            packets_received,
+            packets_forwarded: self.session.packets_forwarded.load(Ordering::Relaxed),
+            packets_dropped,
+            bytes_throttled: self.session.bytes_throttled.load(Ordering::Relaxed),
+            packets_duplicated: self.session.packets_duplicated.load(Ordering::Relaxed),
+            bytes_duplicated: self.session.bytes_duplicated.load(Ordering::Relaxed),
+            circuit_breaker_trips: self.session.circuit_breaker_trips.load(Ordering::Relaxed),
+            flows_migrated: self.session.flows_migrated.load(Ordering::Relaxed),
+            low_confidence_fallbacks: self.session.low_confidence_fallbacks.load(Ordering::Relaxed),
+            dns_cache_hits: self.session.dns_cache_hits.load(Ordering::Relaxed),
+            reassembly_timeouts: self.session.reassembly_timeouts.load(Ordering::Relaxed),
+            queue_depth: self.queue_depths.iter().map(|depth| depth.load(Ordering::Relaxed)).sum(),
+            queue_depth_by_type: TrafficType::ALL
+                .into_iter()
+                .map(|traffic_type| (traffic_type, self.queue_depths[traffic_type.priority() as usize].load(Ordering::Relaxed)))
+                .collect(),
             bandwidth_usage,
             average_latency,
             packet_loss_rate,
             uptime,
+            current_bandwidth_bps: upload_bps + download_bps,
+            upload_bytes: bytes_forwarded + self.session.bytes_duplicated.load(Ordering::Relaxed),
+            download_bytes: self.session.bytes_returned.load(Ordering::Relaxed),
+            upload_bps,
+            download_bps,
+            aggregate_bandwidth_bps: Self::aggregate_bps(&traffic),
+            interfaces,
+            traffic_types: self.session.traffic_types(),
+            effective_load_balancing_mode: None,
+        }
+    }
+
+    /// Counters accumulated since the process started, unaffected by `reset_stats`
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub async fn get_lifetime_counters(&self) -> LifetimeCounters {
+        let traffic = self.interfaces.read().await;
+        LifetimeCounters {
+            packets_received: self.lifetime.packets_received.load(Ordering::Relaxed),
+            packets_forwarded: self.lifetime.packets_forwarded.load(Ordering::Relaxed),
+            packets_dropped: DropReasons::from_counters(&self.lifetime.packets_dropped),
+            packets_duplicated: self.lifetime.packets_duplicated.load(Ordering::Relaxed),
+            bytes_duplicated: self.lifetime.bytes_duplicated.load(Ordering::Relaxed),
+            circuit_breaker_trips: self.lifetime.circuit_breaker_trips.load(Ordering::Relaxed),
+            flows_migrated: self.lifetime.flows_migrated.load(Ordering::Relaxed),
+            low_confidence_fallbacks: self.lifetime.low_confidence_fallbacks.load(Ordering::Relaxed),
+            dns_cache_hits: self.lifetime.dns_cache_hits.load(Ordering::Relaxed),
+            reassembly_timeouts: self.lifetime.reassembly_timeouts.load(Ordering::Relaxed),
+            bytes_received: self.lifetime.bytes_received.load(Ordering::Relaxed),
+            bytes_forwarded: self.lifetime.bytes_forwarded.load(Ordering::Relaxed),
+            packets_returned: self.lifetime.packets_returned.load(Ordering::Relaxed),
+            bytes_returned: self.lifetime.bytes_returned.load(Ordering::Relaxed),
+            interfaces: traffic
+                .iter()
+                .filter_map(|(index, interface)| Some((*index, interface.lifetime_stats()?)))
+                .collect(),
+            traffic_types: self.lifetime.traffic_types(),
         }
     }
 
-    #[allow(dead_code)]
+    /// Reset the per-session statistics, including the uptime clock
     pub async fn reset_stats(&self) {
         let mut stats = self.stats.write().await;
         *stats = InternalStats {
-            packets_received: 0,
-            packets_forwarded: 0,
-            packets_dropped: 0,
-            total_bytes_received: 0,
-            total_bytes_forwarded: 0,
-            total_processing_time: Duration::new(0, 0),
-            latency_samples: Vec::new(),
             max_latency_samples: stats.max_latency_samples,
+            ..InternalStats::new()
         };
+        self.session.reset();
+        self.recent_upload.clear();
+        self.recent_download.clear();
+        for interface in self.interfaces.read().await.values() {
+            interface.session_packets.store(0, Ordering::Relaxed);
+            interface.session_bytes.store(0, Ordering::Relaxed);
+            interface.recent.clear();
+        }
+    }
+
+    /// Add `stats` to the performance history, unless recording is off
+    pub async fn record_history_sample(&self, stats: &PerformanceStats) {
+        let now = unix_millis();
+        let mut history = self.history.write().await;
+        if history.retention.is_zero() {
+            return;
+        }
+        history.samples.push_back(HistorySample::from_stats(now, stats));
+        history.expire(now);
+    }
+
+    /// Keep this much performance history; zero stops recording and clears it
+    pub async fn set_history_retention(&self, retention: Duration) {
+        let mut history = self.history.write().await;
+        history.retention = retention;
+        if retention.is_zero() {
+            history.samples.clear();
+        }
+        history.expire(unix_millis());
+    }
+
+    /// The recorded performance history, oldest first
+    pub async fn history(&self) -> Vec<HistorySample> {
+        self.history.read().await.samples.iter().copied().collect()
+    }
+
+    /// `(timestamp_ms, upload_bps, download_bps)` for each history sample
+    /// taken within the last `window`, oldest first, for drawing a graph
+    pub async fn get_throughput_history(&self, window: Duration) -> Vec<(u64, u64, u64)> {
+        let since = unix_millis().saturating_sub(window.as_millis() as u64);
+        self.history
+            .read()
+            .await
+            .samples
+            .iter()
+            .filter(|sample| sample.timestamp_ms >= since)
+            .map(|sample| (sample.timestamp_ms, sample.upload_bps, sample.download_bps))
+            .collect()
+    }
+
+    /// Write the performance history to a CSV file with a header row.
+    /// Returns the number of samples written.
+    pub async fn export_history_csv(&self, path: &Path) -> Result<usize> {
+        let samples = self.history().await;
+        let mut out = Vec::new();
+        write_history_csv(&samples, &mut out)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, out).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(samples.len())
+    }
+}
+
+fn write_history_csv(samples: &[HistorySample], out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "{}", HistorySample::CSV_HEADER)?;
+    for sample in samples {
+        sample.write_csv_row(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_totals_reset_but_lifetime_totals_do_not() {
+        let monitor = PerformanceMonitor::new();
+        monitor.record_packet_forwarded(1, "eth0", TrafficType::Web, 1000).await;
+        monitor.record_packet_duplicated(2, "wlan0", 1000).await;
+        monitor.record_packet_returned(1, 500).await;
+
+        let stats = monitor.get_current_stats().await;
+        assert_eq!((stats.upload_bytes, stats.download_bytes), (2000, 500));
+
+        monitor.reset_stats().await;
+        let stats = monitor.get_current_stats().await;
+        assert_eq!((stats.upload_bytes, stats.download_bytes), (0, 0));
+        let lifetime = monitor.get_lifetime_counters().await;
+        assert_eq!((lifetime.bytes_forwarded, lifetime.bytes_returned), (1000, 500));
+
+        // A removed interface leaves the session stats but keeps its lifetime counters
+        monitor.record_packet_duplicated(2, "wlan0", 1000).await;
+        monitor.forget_interface(2).await;
+        assert!(!monitor.get_current_stats().await.interfaces.contains_key(&2));
+        let lifetime = monitor.get_lifetime_counters().await;
+        assert_eq!(lifetime.interfaces[&2].bytes_forwarded, 2000);
+        assert_eq!(lifetime.bytes_duplicated, 2000);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_bounded_and_exports_as_csv() {
+        let monitor = PerformanceMonitor::new();
+        let stats = monitor.get_current_stats().await;
+        {
+            // An hour-old sample that is about to age out
+            let mut history = monitor.history.write().await;
+            let old = unix_millis() - DEFAULT_HISTORY_RETENTION.as_millis() as u64 + 100;
+            history.samples.push_back(HistorySample { upload_bps: 8000, ..HistorySample::from_stats(old, &stats) });
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        monitor.record_history_sample(&stats).await;
+        let history = monitor.history().await;
+        assert_eq!(history.len(), 1);
+
+        let mut csv = Vec::new();
+        write_history_csv(&history, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HistorySample::CSV_HEADER);
+        assert!(lines[1].ends_with(",0,0,0,0.000,0.000"), "{}", lines[1]);
+
+        assert_eq!(monitor.get_throughput_history(Duration::from_secs(60)).await, vec![(history[0].timestamp_ms, 0, 0)]);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(monitor.get_throughput_history(Duration::from_millis(10)).await.is_empty());
+
+        monitor.set_history_retention(Duration::ZERO).await;
+        monitor.record_history_sample(&stats).await;
+        assert!(monitor.history().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drops_are_counted_by_reason() {
+        let monitor = PerformanceMonitor::new();
+        for _ in 0..4 {
+            monitor.record_packet_received(100).await;
+        }
+        monitor.record_packet_dropped(DropReason::SendError).await;
+        monitor.record_packet_dropped(DropReason::SendError).await;
+        monitor.record_packet_throttled(100).await;
+
+        let stats = monitor.get_current_stats().await;
+        assert_eq!(stats.packets_dropped, DropReasons { send_error: 2, rate_limited: 1, total: 3, ..Default::default() });
+        assert_eq!(stats.bytes_throttled, 100);
+        assert!((stats.packet_loss_rate - 0.75).abs() < f32::EPSILON);
+
+        monitor.reset_stats().await;
+        assert_eq!(monitor.get_current_stats().await.packets_dropped.total, 0);
+        assert_eq!(monitor.get_lifetime_counters().await.packets_dropped.send_error, 2);
+    }
+
+    // Recording into a full window used to shift a Vec; run with
+    // `cargo test --release -- --ignored --nocapture` to compare
+    #[test]
+    #[ignore]
+    fn bench_latency_window() {
+        const SAMPLES: usize = 1_000_000;
+        let latency = |i: usize| Duration::from_micros((i % 500) as u64);
+
+        let mut shifting: Vec<Duration> = (0..LATENCY_SAMPLE_WINDOW).map(latency).collect();
+        let start = Instant::now();
+        for i in 0..SAMPLES {
+            shifting.push(std::hint::black_box(latency(i)));
+            shifting.remove(0);
+        }
+        let before = start.elapsed();
+        std::hint::black_box(&shifting);
+
+        let mut ring: VecDeque<Duration> = (0..LATENCY_SAMPLE_WINDOW).map(latency).collect();
+        let start = Instant::now();
+        for i in 0..SAMPLES {
+            ring.push_back(std::hint::black_box(latency(i)));
+            ring.pop_front();
+        }
+        let after = start.elapsed();
+        std::hint::black_box(&ring);
+
+        println!(
+            "shifting Vec {:.1} ns/sample, ring buffer {:.1} ns/sample",
+            before.as_nanos() as f64 / SAMPLES as f64,
+            after.as_nanos() as f64 / SAMPLES as f64
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,102 @@
+// src-tauri/src/privileges.rs
+use anyhow::Result;
+
+/// Capability bits for creating TUN devices and opening the raw sockets
+/// packets are sent through
+#[cfg(target_os = "linux")]
+const CAP_NET_ADMIN: u32 = 12;
+#[cfg(target_os = "linux")]
+const CAP_NET_RAW: u32 = 13;
+
+/// Whether the process may create the TUN interface
+pub fn has_privileges() -> bool {
+    platform::has_privileges()
+}
+
+/// Fail early with an actionable message if the process can't create the TUN
+/// interface, instead of letting the device builder fail with an opaque error
+pub fn check_privileges() -> Result<()> {
+    if has_privileges() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(platform::ELEVATION_HINT))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{CAP_NET_ADMIN, CAP_NET_RAW};
+
+    pub const ELEVATION_HINT: &str = "NetBoost Pro needs the CAP_NET_ADMIN and CAP_NET_RAW capabilities to create its TUN interface. \
+        Re-run it with sudo, or grant them once with `sudo setcap cap_net_admin,cap_net_raw+ep <path to executable>`.";
+
+    pub fn has_privileges() -> bool {
+        let required = (1 << CAP_NET_ADMIN) | (1 << CAP_NET_RAW);
+        // If the capability set can't be read, let interface creation report the real error
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| super::effective_capabilities(&status))
+            .is_none_or(|caps| caps & required == required)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod platform {
+    use std::process::Command;
+
+    pub const ELEVATION_HINT: &str = "NetBoost Pro must run as root to create its TUN interface. Re-run it with sudo.";
+
+    pub fn has_privileges() -> bool {
+        Command::new("id")
+            .arg("-u")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::process::Command;
+
+    pub const ELEVATION_HINT: &str = "NetBoost Pro needs administrator rights to create its Wintun adapter. \
+        Right-click it and choose \"Run as administrator\", or start it from an elevated terminal.";
+
+    pub fn has_privileges() -> bool {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "([Security.Principal.WindowsPrincipal][Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)",
+            ])
+            .output();
+        match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim().eq_ignore_ascii_case("true"),
+            Err(_) => true,
+        }
+    }
+}
+
+/// Parse the effective capability mask from the contents of /proc/self/status
+#[cfg(target_os = "linux")]
+fn effective_capabilities(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_capabilities_parsing() {
+        let root = "Name:\tcli\nCapInh:\t0000000000000000\nCapEff:\t000001ffffffffff\n";
+        let user = "Name:\tcli\nCapEff:\t0000000000000000\n";
+
+        assert!(effective_capabilities(root).unwrap() & (1 << CAP_NET_ADMIN) != 0);
+        assert_eq!(effective_capabilities(user), Some(0));
+        assert_eq!(effective_capabilities("Name:\tcli\n"), None);
+    }
+}
@@ -0,0 +1,242 @@
+// src-tauri/src/qos.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::nat;
+use crate::packet_router::TrafficType;
+
+// Byte offsets into the IPv4 header
+const IPV4_TOS_OFFSET: usize = 1;
+const IPV4_CHECKSUM_OFFSET: usize = 10;
+
+/// Expedited Forwarding, for latency-critical traffic (RFC 3246)
+pub const DSCP_EF: u8 = 46;
+/// Assured Forwarding class 4, low drop precedence (RFC 2597)
+pub const DSCP_AF41: u8 = 34;
+/// Best effort
+pub const DSCP_DEFAULT: u8 = 0;
+
+/// DSCP code point written into outbound packets of each traffic type.
+/// `None` leaves whatever marking the application chose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DscpConfig {
+    pub enabled: bool,
+    pub dns: Option<u8>,
+    pub gaming: Option<u8>,
+    pub streaming: Option<u8>,
+    pub web: Option<u8>,
+    pub file: Option<u8>,
+    pub unknown: Option<u8>,
+}
+
+impl Default for DscpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dns: None,
+            gaming: Some(DSCP_EF),
+            streaming: Some(DSCP_AF41),
+            web: None,
+            file: Some(DSCP_DEFAULT),
+            unknown: None,
+        }
+    }
+}
+
+impl DscpConfig {
+    /// Check that every code point fits in the six DSCP bits
+    pub fn validate(&self) -> Result<()> {
+        for (name, dscp) in [
+            ("dns", self.dns),
+            ("gaming", self.gaming),
+            ("streaming", self.streaming),
+            ("web", self.web),
+            ("file", self.file),
+            ("unknown", self.unknown),
+        ] {
+            if let Some(dscp) = dscp.filter(|dscp| *dscp > 63) {
+                return Err(anyhow::anyhow!("DSCP value {} for {} traffic is above 63", dscp, name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The code point to mark `traffic_type` with, if marking applies to it
+    pub fn dscp_for(&self, traffic_type: TrafficType) -> Option<u8> {
+        if !self.enabled {
+            return None;
+        }
+        match traffic_type {
+            TrafficType::Dns => self.dns,
+            TrafficType::Gaming => self.gaming,
+            TrafficType::Streaming => self.streaming,
+            TrafficType::Web => self.web,
+            TrafficType::File => self.file,
+            TrafficType::Unknown => self.unknown,
+        }
+    }
+}
+
+/// Largest weight a traffic type can be given relative to the others
+const MAX_WEIGHT: u32 = 100;
+
+/// How the processing queue is shared between traffic types when it backs up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPolicy {
+    /// Always serve the highest-priority traffic first
+    #[default]
+    StrictPriority,
+    /// Serve each traffic type in proportion to its weight, so bulk
+    /// transfers keep a share under saturation
+    FairQueueing,
+}
+
+/// Weights used by `SchedulingPolicy::FairQueueing`; a type with weight 4
+/// gets four times the share of one with weight 1 while both are backlogged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub policy: SchedulingPolicy,
+    pub dns: u32,
+    pub gaming: u32,
+    pub streaming: u32,
+    pub web: u32,
+    pub file: u32,
+    pub unknown: u32,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            policy: SchedulingPolicy::StrictPriority,
+            dns: 8,
+            gaming: 8,
+            streaming: 4,
+            web: 2,
+            file: 1,
+            unknown: 1,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Check that every weight is between 1 and `MAX_WEIGHT`
+    pub fn validate(&self) -> Result<()> {
+        for traffic_type in TrafficType::ALL {
+            let weight = self.weight_for(traffic_type);
+            if !(1..=MAX_WEIGHT).contains(&weight) {
+                return Err(anyhow::anyhow!(
+                    "Weight {} for {} traffic must be between 1 and {}",
+                    weight, traffic_type.as_str(), MAX_WEIGHT
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn weight_for(&self, traffic_type: TrafficType) -> u32 {
+        match traffic_type {
+            TrafficType::Dns => self.dns,
+            TrafficType::Gaming => self.gaming,
+            TrafficType::Streaming => self.streaming,
+            TrafficType::Web => self.web,
+            TrafficType::File => self.file,
+            TrafficType::Unknown => self.unknown,
+        }
+    }
+
+    /// Weights indexed by `TrafficType::priority`, or `None` under strict priority
+    pub fn queue_weights(&self) -> Option<Vec<u32>> {
+        if self.policy == SchedulingPolicy::StrictPriority {
+            return None;
+        }
+        let mut weights = vec![1; TrafficType::PRIORITY_LEVELS];
+        for traffic_type in TrafficType::ALL {
+            weights[traffic_type.priority() as usize] = self.weight_for(traffic_type);
+        }
+        Some(weights)
+    }
+}
+
+/// Set the DSCP bits of an IPv4 or IPv6 packet, keeping its ECN bits, and
+/// fix up the IPv4 header checksum
+pub fn mark_dscp(packet: &mut [u8], dscp: u8) -> Result<()> {
+    if packet.first().is_some_and(|byte| byte >> 4 == 6) {
+        return mark_dscp_ipv6(packet, dscp);
+    }
+
+    let header_len = packet
+        .first()
+        .map(|byte| ((byte & 0x0f) as usize) * 4)
+        .filter(|len| *len >= 20 && *len <= packet.len())
+        .context("Invalid IPv4 header")?;
+
+    let tos = ((dscp & 0x3f) << 2) | (packet[IPV4_TOS_OFFSET] & 0x03);
+    if packet[IPV4_TOS_OFFSET] == tos {
+        return Ok(());
+    }
+    packet[IPV4_TOS_OFFSET] = tos;
+
+    packet[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].fill(0);
+    let checksum = nat::checksum(&packet[..header_len]);
+    packet[IPV4_CHECKSUM_OFFSET..IPV4_CHECKSUM_OFFSET + 2].copy_from_slice(&checksum.to_be_bytes());
+
+    Ok(())
+}
+
+/// IPv6 carries DSCP in the traffic class, which straddles the first two
+/// bytes; there is no header checksum to fix
+fn mark_dscp_ipv6(packet: &mut [u8], dscp: u8) -> Result<()> {
+    if packet.len() < 2 {
+        return Err(anyhow::anyhow!("Invalid IPv6 header"));
+    }
+    let traffic_class = ((dscp & 0x3f) << 2) | ((packet[1] >> 4) & 0x03);
+    packet[0] = (packet[0] & 0xf0) | (traffic_class >> 4);
+    packet[1] = (packet[1] & 0x0f) | (traffic_class << 4);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+
+    #[test]
+    fn test_mark_dscp_keeps_ecn_and_checksum_valid() {
+        // 10.0.0.2 -> 93.184.216.34, UDP, ECT(0) set
+        let mut packet = [
+            0x45, 0x02, 0x00, 0x1c, 0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x02,
+            0x5d, 0xb8, 0xd8, 0x22, 0xd4, 0x31, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+        ];
+
+        let config = DscpConfig::default();
+        mark_dscp(&mut packet, config.dscp_for(TrafficType::Gaming).unwrap()).unwrap();
+
+        let ip = Ipv4Packet::new(&packet).unwrap();
+        assert_eq!(ip.get_dscp(), DSCP_EF);
+        assert_eq!(ip.get_ecn(), 0x02);
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+
+        assert_eq!(config.dscp_for(TrafficType::Unknown), None);
+
+        // IPv6 traffic class 0x02 (ECT(0)), flow label 0xabcde
+        let mut ipv6 = [0x60, 0x2a, 0xbc, 0xde];
+        mark_dscp(&mut ipv6, DSCP_EF).unwrap();
+        assert_eq!(ipv6, [0x6b, 0xaa, 0xbc, 0xde]);
+        assert!(DscpConfig { gaming: Some(64), ..DscpConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_scheduler_weights_follow_priority_levels() {
+        let mut config = SchedulerConfig::default();
+        assert_eq!(config.queue_weights(), None);
+
+        config.policy = SchedulingPolicy::FairQueueing;
+        assert_eq!(config.queue_weights(), Some(vec![1, 1, 2, 4, 8, 8]));
+
+        config.file = 0;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+/// Smallest burst a bucket allows, so that a full-size packet can always pass
+/// eventually even when the configured rate is below the MTU
+const MIN_BURST_BYTES: f64 = 4096.0;
+
+/// Token bucket limiting the number of bytes sent per second
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    bytes_per_sec: u64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        // Allow up to one second of traffic to burst
+        let capacity = (bytes_per_sec as f64).max(MIN_BURST_BYTES);
+        Self {
+            bytes_per_sec,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec as f64).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Whether `bytes` could be sent right now without exceeding the limit
+    pub fn has_capacity(&mut self, bytes: usize) -> bool {
+        self.refill();
+        self.tokens >= bytes as f64
+    }
+
+    /// Take `bytes` worth of tokens, returning false if not enough are available
+    pub fn try_consume(&mut self, bytes: usize) -> bool {
+        if self.has_capacity(bytes) {
+            self.tokens -= bytes as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// src-tauri/src/reassembly.rs
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::nat;
+
+/// How long the fragments of a datagram are held waiting for the rest,
+/// as RFC 791 suggests
+pub const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fragment payload held across all incomplete datagrams at once
+pub const MAX_REASSEMBLY_BYTES: usize = 4 * 1024 * 1024;
+
+const MORE_FRAGMENTS: u16 = 0x2000;
+const FRAGMENT_OFFSET_MASK: u16 = 0x1fff;
+const MAX_DATAGRAM_LEN: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FragmentKey {
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    id: u16,
+    protocol: u8,
+}
+
+struct PartialDatagram {
+    /// IP header of the first fragment, once it has arrived
+    header: Option<Vec<u8>>,
+    /// Payload of each fragment by byte offset
+    fragments: BTreeMap<usize, Vec<u8>>,
+    /// Payload length, known once the last fragment has arrived
+    total_len: Option<usize>,
+    bytes: usize,
+    first_seen: Instant,
+}
+
+impl PartialDatagram {
+    /// The whole datagram, if every byte of it has arrived
+    fn assemble(&self) -> Option<Vec<u8>> {
+        let header = self.header.as_ref()?;
+        let total_len = self.total_len?;
+        let mut covered = 0;
+        for (offset, payload) in &self.fragments {
+            if *offset > covered {
+                return None;
+            }
+            covered = covered.max(offset + payload.len());
+        }
+        if covered < total_len {
+            return None;
+        }
+
+        let mut datagram = header.clone();
+        datagram.resize(header.len() + total_len, 0);
+        // Overlapping fragments can start past the end the last one declared
+        for (offset, payload) in self.fragments.range(..total_len) {
+            let end = (offset + payload.len()).min(total_len);
+            datagram[header.len() + offset..header.len() + end].copy_from_slice(&payload[..end - offset]);
+        }
+        let len = datagram.len() as u16;
+        datagram[2..4].copy_from_slice(&len.to_be_bytes());
+        // No longer a fragment
+        datagram[6..8].copy_from_slice(&0u16.to_be_bytes());
+        datagram[10..12].copy_from_slice(&[0, 0]);
+        let checksum = nat::checksum(&datagram[..header.len()]);
+        datagram[10..12].copy_from_slice(&checksum.to_be_bytes());
+        Some(datagram)
+    }
+}
+
+/// What became of a packet handed to [`Reassembler::reassemble`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reassembled {
+    /// Not a fragment; route the packet as it is
+    Unfragmented,
+    /// Held until the rest of its datagram arrives
+    Incomplete,
+    /// The last missing fragment arrived; route this whole datagram instead
+    Complete(Vec<u8>),
+}
+
+struct State {
+    datagrams: HashMap<FragmentKey, PartialDatagram>,
+    bytes: usize,
+    /// Incomplete datagrams dropped since the last `expire`
+    discarded: usize,
+}
+
+/// Collects IPv4 fragments into whole datagrams, so a datagram is
+/// classified and routed as one packet rather than fragment by fragment.
+/// IPv6 packets pass through untouched.
+pub struct Reassembler {
+    state: Mutex<State>,
+    timeout: Duration,
+    max_bytes: usize,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(REASSEMBLY_TIMEOUT, MAX_REASSEMBLY_BYTES)
+    }
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration, max_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                datagrams: HashMap::new(),
+                bytes: 0,
+                discarded: 0,
+            }),
+            timeout,
+            max_bytes,
+        }
+    }
+
+    /// Take in a validated IP packet. Fragments are held until their
+    /// datagram is complete; when memory runs short the oldest incomplete
+    /// datagrams are dropped to make room.
+    pub fn reassemble(&self, packet: &[u8]) -> Reassembled {
+        if packet.first().is_none_or(|byte| byte >> 4 != 4) || packet.len() < 20 {
+            return Reassembled::Unfragmented;
+        }
+        let flags_and_offset = u16::from_be_bytes([packet[6], packet[7]]);
+        if flags_and_offset & (MORE_FRAGMENTS | FRAGMENT_OFFSET_MASK) == 0 {
+            return Reassembled::Unfragmented;
+        }
+
+        let header_len = ((packet[0] & 0x0f) as usize) * 4;
+        let total_len = (u16::from_be_bytes([packet[2], packet[3]]) as usize).min(packet.len());
+        let payload = &packet[header_len.min(total_len)..total_len];
+        let offset = (flags_and_offset & FRAGMENT_OFFSET_MASK) as usize * 8;
+        let key = FragmentKey {
+            src: Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]),
+            dst: Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]),
+            id: u16::from_be_bytes([packet[4], packet[5]]),
+            protocol: packet[9],
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // A datagram that would be too big can never be sent whole
+        if header_len + offset + payload.len() > MAX_DATAGRAM_LEN {
+            if let Some(partial) = state.datagrams.remove(&key) {
+                state.bytes -= partial.bytes;
+                state.discarded += 1;
+            }
+            return Reassembled::Incomplete;
+        }
+
+        // Make room, oldest first, but never by dropping this datagram's own fragments
+        while state.bytes + payload.len() > self.max_bytes {
+            let oldest = state
+                .datagrams
+                .iter()
+                .filter(|(other, _)| **other != key)
+                .min_by_key(|(_, partial)| partial.first_seen)
+                .map(|(other, _)| *other);
+            let Some(oldest) = oldest else {
+                break;
+            };
+            if let Some(partial) = state.datagrams.remove(&oldest) {
+                state.bytes -= partial.bytes;
+                state.discarded += 1;
+            }
+        }
+        if state.bytes + payload.len() > self.max_bytes {
+            return Reassembled::Incomplete;
+        }
+
+        let partial = state.datagrams.entry(key).or_insert_with(|| PartialDatagram {
+            header: None,
+            fragments: BTreeMap::new(),
+            total_len: None,
+            bytes: 0,
+            first_seen: Instant::now(),
+        });
+        if offset == 0 {
+            partial.header = Some(packet[..header_len].to_vec());
+        }
+        if flags_and_offset & MORE_FRAGMENTS == 0 {
+            partial.total_len = Some(offset + payload.len());
+        }
+        let added = payload.len();
+        let replaced = partial.fragments.insert(offset, payload.to_vec()).map_or(0, |old| old.len());
+        partial.bytes = partial.bytes + added - replaced;
+
+        let assembled = partial.assemble();
+        state.bytes = state.bytes + added - replaced;
+        match assembled {
+            Some(datagram) => {
+                if let Some(partial) = state.datagrams.remove(&key) {
+                    state.bytes -= partial.bytes;
+                }
+                Reassembled::Complete(datagram)
+            }
+            None => Reassembled::Incomplete,
+        }
+    }
+
+    /// Drop datagrams that have waited longer than the timeout. Returns how
+    /// many incomplete datagrams were dropped since the last call, including
+    /// those dropped to make room.
+    pub fn expire(&self) -> usize {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let timeout = self.timeout;
+        let mut expired_bytes = 0;
+        let mut expired = 0;
+        state.datagrams.retain(|_, partial| {
+            let keep = partial.first_seen.elapsed() < timeout;
+            if !keep {
+                expired_bytes += partial.bytes;
+                expired += 1;
+            }
+            keep
+        });
+        state.bytes -= expired_bytes;
+        std::mem::take(&mut state.discarded) + expired
+    }
+
+    /// Bytes of fragment payload currently held
+    pub fn buffered_bytes(&self) -> usize {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mtu;
+    use pnet_packet::ipv4::{self, Ipv4Packet};
+
+    fn udp_datagram(id: u16, payload_len: usize) -> Vec<u8> {
+        let total_len = 28 + payload_len;
+        let mut packet = vec![0u8; total_len];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+        packet[4..6].copy_from_slice(&id.to_be_bytes());
+        packet[8] = 64;
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&[10, 0, 0, 2]);
+        packet[16..20].copy_from_slice(&[198, 51, 100, 7]);
+        packet[20..22].copy_from_slice(&40000u16.to_be_bytes());
+        packet[22..24].copy_from_slice(&9000u16.to_be_bytes());
+        packet[24..26].copy_from_slice(&((8 + payload_len) as u16).to_be_bytes());
+        for (i, byte) in packet[28..].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let checksum = nat::checksum(&packet[..20]);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_fragments_in_any_order_reassemble_into_the_datagram() {
+        let datagram = udp_datagram(7, 3000);
+        let mut fragments = mtu::fragment(&datagram, 1280).unwrap();
+        assert_eq!(fragments.len(), 3);
+        fragments.reverse();
+
+        let reassembler = Reassembler::default();
+        assert_eq!(reassembler.reassemble(&udp_datagram(8, 100)), Reassembled::Unfragmented);
+        assert_eq!(reassembler.reassemble(&fragments[0]), Reassembled::Incomplete);
+        // Duplicates don't confuse it
+        assert_eq!(reassembler.reassemble(&fragments[0]), Reassembled::Incomplete);
+        assert_eq!(reassembler.reassemble(&fragments[1]), Reassembled::Incomplete);
+        let Reassembled::Complete(whole) = reassembler.reassemble(&fragments[2]) else {
+            panic!("datagram should be complete");
+        };
+        assert_eq!(whole, datagram);
+        let ip = Ipv4Packet::new(&whole).unwrap();
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        assert_eq!(reassembler.buffered_bytes(), 0);
+        assert_eq!(reassembler.expire(), 0);
+    }
+
+    fn fragment(offset: usize, payload_len: usize, more: bool) -> Vec<u8> {
+        let mut packet = udp_datagram(9, payload_len.saturating_sub(8));
+        packet.truncate(20 + payload_len);
+        packet[2..4].copy_from_slice(&((20 + payload_len) as u16).to_be_bytes());
+        let flags = if more { MORE_FRAGMENTS } else { 0 };
+        packet[6..8].copy_from_slice(&(flags | (offset / 8) as u16).to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_overlapping_fragments_past_the_end_are_ignored() {
+        let reassembler = Reassembler::default();
+        assert_eq!(reassembler.reassemble(&fragment(0, 200, true)), Reassembled::Incomplete);
+        assert_eq!(reassembler.reassemble(&fragment(40, 10, true)), Reassembled::Incomplete);
+        // The last fragment ends the datagram before the one at 40 starts
+        let Reassembled::Complete(whole) = reassembler.reassemble(&fragment(8, 8, false)) else {
+            panic!("datagram should be complete");
+        };
+        assert_eq!(whole.len(), 20 + 16);
+        assert_eq!(reassembler.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn test_incomplete_datagrams_time_out_and_memory_is_bounded() {
+        let first = mtu::fragment(&udp_datagram(1, 3000), 1280).unwrap();
+        let second = mtu::fragment(&udp_datagram(2, 3000), 1280).unwrap();
+
+        let reassembler = Reassembler::new(Duration::ZERO, 2000);
+        assert_eq!(reassembler.reassemble(&first[0]), Reassembled::Incomplete);
+        // No room for both, so the older datagram goes
+        assert_eq!(reassembler.reassemble(&second[0]), Reassembled::Incomplete);
+        assert!(reassembler.buffered_bytes() <= 2000);
+        assert_eq!(reassembler.expire(), 2);
+        assert_eq!(reassembler.buffered_bytes(), 0);
+    }
+}
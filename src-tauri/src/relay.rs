@@ -0,0 +1,813 @@
+// src-tauri/src/relay.rs
+use anyhow::{bail, Context, Result};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use sha2::Sha256;
+use tracing::{debug, trace, warn};
+
+use crate::interface_manager::PhysicalInterface;
+use crate::net_util;
+use crate::packet_router::TrafficType;
+
+const MAGIC: [u8; 2] = *b"NB";
+const VERSION: u8 = 2;
+
+/// Bytes the relay header adds in front of every tunneled packet
+pub const HEADER_LEN: usize = 20;
+
+/// Header flag marking one of several copies of a packet sent over
+/// different links; the relay forwards the first and drops the rest
+pub const FLAG_DUPLICATE: u8 = 0x01;
+
+/// Header flag marking a packet whose encrypted payload starts with an
+/// order number, so the far end can deliver sequenced packets in the order
+/// they were sent
+pub const FLAG_SEQUENCED: u8 = 0x02;
+
+/// Bytes the order number adds to a sequenced packet
+pub const SEQUENCING_LEN: usize = 8;
+
+/// Bytes of the Poly1305 tag after every encrypted packet
+pub const TAG_LEN: usize = 16;
+
+/// Bytes tunneling adds to a packet on the wire: the relay header and
+/// authentication tag plus the outer IPv4 and UDP headers
+pub const OVERHEAD: usize = HEADER_LEN + TAG_LEN + 20 + 8;
+
+/// How far behind the newest sequence number a packet may arrive before it
+/// is treated as a replay. Links with different latencies reorder packets,
+/// so this has to cover the spread between the fastest and slowest link.
+const REPLAY_WINDOW: u64 = 1024;
+
+const KDF_SALT: &[u8] = b"netboost-pro relay v2";
+
+/// Largest datagram read from the relay
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// Sequenced packets held back at most, however short their wait so far
+const MAX_HELD_PACKETS: usize = 512;
+
+fn default_reorder_hold_ms() -> u64 {
+    50
+}
+
+/// A relay server that reassembles traffic split across interfaces and
+/// forwards it to the internet, so even a single download uses every link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayConfig {
+    /// `host:port` of the relay's UDP listener
+    pub endpoint: String,
+    /// Shared secret identifying this client to the relay, from which the
+    /// tunnel's encryption keys are derived
+    pub auth_token: String,
+    /// Traffic types whose packets are put back in order at the far end
+    /// after taking links of different latency. Each sequenced packet costs
+    /// `SEQUENCING_LEN` more bytes, and a lost one holds up those behind it
+    /// for up to `reorder_hold_ms`. Needs a relay that supports sequencing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequenced: Vec<TrafficType>,
+    /// Longest a sequenced packet waits for the ones sent before it
+    #[serde(default = "default_reorder_hold_ms")]
+    pub reorder_hold_ms: u64,
+}
+
+impl RelayConfig {
+    /// A relay that sequences nothing
+    pub fn new(endpoint: String, auth_token: String) -> Self {
+        Self {
+            endpoint,
+            auth_token,
+            sequenced: Vec::new(),
+            reorder_hold_ms: default_reorder_hold_ms(),
+        }
+    }
+
+    pub fn reorder_hold(&self) -> Duration {
+        Duration::from_millis(self.reorder_hold_ms)
+    }
+
+    /// Reject configs that could never connect, before they are saved
+    pub fn validate(&self) -> Result<()> {
+        let Some((host, port)) = self.endpoint.rsplit_once(':') else {
+            bail!("Relay endpoint '{}' needs a port, as in host:port", self.endpoint);
+        };
+        if host.is_empty() || port.parse::<u16>().is_err() {
+            bail!("Relay endpoint '{}' is not a valid host:port", self.endpoint);
+        }
+        if self.auth_token.is_empty() {
+            bail!("The relay needs an auth token");
+        }
+        Ok(())
+    }
+}
+
+/// The header in front of every packet exchanged with the relay:
+///
+/// ```text
+/// 0      2         3        4                    12                   20
+/// | "NB" | version | flags  | session tag        | sequence number    |
+/// ```
+///
+/// The session tag is drawn at random for each connection so the relay can
+/// tell sessions apart; it is neither secret nor proof of anything, since
+/// the cipher authenticates every datagram. Keys are derived per session,
+/// so sequence numbers can start from zero on every connection without
+/// reusing a nonce. Sequence numbers run
+/// across all links and make each packet's nonce; packets flagged
+/// `FLAG_SEQUENCED` also carry an order number for the far end to restore
+/// their original order by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayHeader {
+    pub flags: u8,
+    pub session_tag: u64,
+    pub sequence: u64,
+}
+
+impl RelayHeader {
+    pub fn encode(&self, packet: &[u8]) -> Vec<u8> {
+        let mut datagram = Vec::with_capacity(HEADER_LEN + packet.len());
+        datagram.extend_from_slice(&MAGIC);
+        datagram.push(VERSION);
+        datagram.push(self.flags);
+        datagram.extend_from_slice(&self.session_tag.to_be_bytes());
+        datagram.extend_from_slice(&self.sequence.to_be_bytes());
+        datagram.extend_from_slice(packet);
+        datagram
+    }
+
+    /// Split a datagram from the relay into its header and the packet inside
+    pub fn decode(datagram: &[u8]) -> Result<(Self, &[u8])> {
+        if datagram.len() < HEADER_LEN {
+            bail!("{} byte datagram is shorter than the relay header", datagram.len());
+        }
+        if datagram[..2] != MAGIC {
+            bail!("Not a relay datagram");
+        }
+        if datagram[2] != VERSION {
+            bail!("Unsupported relay protocol version {}", datagram[2]);
+        }
+        let header = Self {
+            flags: datagram[3],
+            session_tag: u64::from_be_bytes(datagram[4..12].try_into()?),
+            sequence: u64::from_be_bytes(datagram[12..20].try_into()?),
+        };
+        Ok((header, &datagram[HEADER_LEN..]))
+    }
+}
+
+/// Which end of the tunnel a key encrypts for. Each direction has its own
+/// key, so both ends can count sequence numbers from zero without ever
+/// reusing a nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToRelay,
+    ToClient,
+}
+
+impl Direction {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Direction::ToRelay => b"client to relay",
+            Direction::ToClient => b"relay to client",
+        }
+    }
+}
+
+/// ChaCha20-Poly1305 keys derived from the auth token and session tag with
+/// HKDF-SHA256. The header is authenticated along with the packet, and its
+/// sequence number is the nonce.
+pub struct RelayCipher {
+    seal: ChaCha20Poly1305,
+    open: ChaCha20Poly1305,
+}
+
+impl RelayCipher {
+    /// Keys for the end of session `session_tag` that sends in direction
+    /// `outbound`
+    pub fn new(auth_token: &str, session_tag: u64, outbound: Direction) -> Self {
+        let kdf = Hkdf::<Sha256>::new(Some(KDF_SALT), auth_token.as_bytes());
+        let key = |direction: Direction| {
+            let mut key = Key::default();
+            kdf.expand_multi_info(&[direction.label(), &session_tag.to_be_bytes()], &mut key)
+                .expect("32 bytes is a valid HKDF-SHA256 length");
+            ChaCha20Poly1305::new(&key)
+        };
+        let inbound = match outbound {
+            Direction::ToRelay => Direction::ToClient,
+            Direction::ToClient => Direction::ToRelay,
+        };
+        Self { seal: key(outbound), open: key(inbound) }
+    }
+
+    fn nonce(sequence: u64) -> Nonce {
+        let mut nonce = Nonce::default();
+        nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+        nonce
+    }
+
+    /// Encrypt `packet` into a complete datagram behind `header`
+    pub fn seal(&self, header: &RelayHeader, packet: &[u8]) -> Result<Vec<u8>> {
+        let mut datagram = header.encode(&[]);
+        let ciphertext = self
+            .seal
+            .encrypt(&Self::nonce(header.sequence), Payload { msg: packet, aad: &datagram })
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt relay packet"))?;
+        datagram.extend_from_slice(&ciphertext);
+        Ok(datagram)
+    }
+
+    /// Authenticate and decrypt a datagram from the other end of the tunnel
+    pub fn open(&self, datagram: &[u8]) -> Result<(RelayHeader, Vec<u8>)> {
+        let (header, ciphertext) = RelayHeader::decode(datagram)?;
+        let packet = self
+            .open
+            .decrypt(&Self::nonce(header.sequence), Payload { msg: ciphertext, aad: &datagram[..HEADER_LEN] })
+            .map_err(|_| anyhow::anyhow!("Relay datagram failed authentication"))?;
+        Ok((header, packet))
+    }
+}
+
+/// Sliding window of the sequence numbers already received, so a captured
+/// datagram can't be played back (RFC 6479)
+#[derive(Debug, Default)]
+pub struct ReplayWindow {
+    /// One past the highest sequence number seen, 0 before the first
+    next: u64,
+    seen: [u64; (REPLAY_WINDOW / 64) as usize],
+}
+
+impl ReplayWindow {
+    /// Record `sequence`, returning false if it was seen before or is too old
+    /// to tell
+    pub fn accept(&mut self, sequence: u64) -> bool {
+        if sequence >= self.next {
+            // Senders never use the last sequence number, so the window can't wrap
+            let Some(next) = sequence.checked_add(1) else {
+                return false;
+            };
+            // Forget the slots the window slides past
+            let advance = (next - self.next).min(REPLAY_WINDOW);
+            for stale in self.next..self.next + advance {
+                self.clear(stale);
+            }
+            self.next = next;
+        } else if self.next - sequence > REPLAY_WINDOW || self.is_set(sequence) {
+            return false;
+        }
+        self.set(sequence);
+        true
+    }
+
+    fn slot(sequence: u64) -> (usize, u64) {
+        let bit = sequence % REPLAY_WINDOW;
+        ((bit / 64) as usize, 1 << (bit % 64))
+    }
+
+    fn is_set(&self, sequence: u64) -> bool {
+        let (word, mask) = Self::slot(sequence);
+        self.seen[word] & mask != 0
+    }
+
+    fn set(&mut self, sequence: u64) {
+        let (word, mask) = Self::slot(sequence);
+        self.seen[word] |= mask;
+    }
+
+    fn clear(&mut self, sequence: u64) {
+        let (word, mask) = Self::slot(sequence);
+        self.seen[word] &= !mask;
+    }
+}
+
+/// Puts sequenced packets back in the order they were sent, counting from
+/// 0 like the sender. A packet waits for the ones before it until it has
+/// been held for `max_hold`, after which the missing ones are given up on.
+pub struct ReorderBuffer<T> {
+    /// Order number of the next packet to deliver
+    next: u64,
+    held: BTreeMap<u64, (Instant, T)>,
+    max_hold: Duration,
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn new(max_hold: Duration) -> Self {
+        Self { next: 0, held: BTreeMap::new(), max_hold }
+    }
+
+    /// Take in packet number `order`, returning the packets now ready in
+    /// order. Packets arriving after their turn was given up on are
+    /// delivered straight away.
+    pub fn push(&mut self, order: u64, packet: T) -> Vec<T> {
+        if order < self.next {
+            return vec![packet];
+        }
+        self.held.insert(order, (Instant::now(), packet));
+
+        let mut ready = Vec::new();
+        self.release(&mut ready);
+        if self.held.len() > MAX_HELD_PACKETS {
+            self.skip_gap(&mut ready);
+        }
+        ready
+    }
+
+    /// Packets that have waited too long, with those in order behind them
+    pub fn expire(&mut self) -> Vec<T> {
+        let mut ready = Vec::new();
+        while self.held.values().any(|(arrived, _)| arrived.elapsed() >= self.max_hold) {
+            self.skip_gap(&mut ready);
+        }
+        ready
+    }
+
+    /// Give up on the missing packets before the first one held
+    fn skip_gap(&mut self, ready: &mut Vec<T>) {
+        if let Some(&first) = self.held.keys().next() {
+            self.next = first;
+            self.release(ready);
+        }
+    }
+
+    fn release(&mut self, ready: &mut Vec<T>) {
+        while let Some((_, packet)) = self.held.remove(&self.next) {
+            ready.push(packet);
+            self.next += 1;
+        }
+    }
+}
+
+/// Received packets waiting for their turn, with the interface each came in on
+type Reorder = Arc<Mutex<ReorderBuffer<(u32, Vec<u8>)>>>;
+
+/// A UDP socket to the relay through one physical interface, and the task
+/// reading what the relay sends back over it
+struct RelayLink {
+    socket: Arc<UdpSocket>,
+    reader: JoinHandle<()>,
+}
+
+impl Drop for RelayLink {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Client side of the relay tunnel. Packets are encrypted and sent to the
+/// relay through whichever interface the router picked, and packets the
+/// relay returns on any link are passed to `inbound` with the index of the
+/// interface they arrived on.
+pub struct RelayTunnel {
+    endpoint: SocketAddr,
+    session_tag: u64,
+    sequence: AtomicU64,
+    /// Order number of the next sequenced packet sent
+    order: AtomicU64,
+    sequenced: HashSet<TrafficType>,
+    cipher: Arc<RelayCipher>,
+    /// Shared by every link, since sequence numbers run across all of them
+    replay_window: Arc<Mutex<ReplayWindow>>,
+    /// Shared by every link, like the replay window
+    reorder: Reorder,
+    /// Delivers held packets once they have waited long enough
+    flusher: JoinHandle<()>,
+    links: Mutex<HashMap<u32, RelayLink>>,
+    inbound: mpsc::Sender<(u32, Vec<u8>)>,
+}
+
+impl Drop for RelayTunnel {
+    fn drop(&mut self) {
+        self.flusher.abort();
+    }
+}
+
+impl RelayTunnel {
+    /// Resolve the relay endpoint. Links are opened on first use.
+    pub async fn connect(config: &RelayConfig, inbound: mpsc::Sender<(u32, Vec<u8>)>) -> Result<Self> {
+        // Physical interfaces only have IPv4 addresses
+        let endpoint = tokio::net::lookup_host(&config.endpoint)
+            .await
+            .with_context(|| format!("Failed to resolve relay {}", config.endpoint))?
+            .find(SocketAddr::is_ipv4)
+            .with_context(|| format!("Relay {} has no IPv4 address", config.endpoint))?;
+
+        // Random, so sessions can't be linked to each other or the token, and
+        // long enough that a session's keys are never reused
+        let session_tag = rand::random();
+        let reorder = Arc::new(Mutex::new(ReorderBuffer::new(config.reorder_hold())));
+        let flusher = tokio::spawn(Self::flush_reordered(Arc::clone(&reorder), config.reorder_hold(), inbound.clone()));
+
+        Ok(Self {
+            endpoint,
+            session_tag,
+            sequence: AtomicU64::new(0),
+            order: AtomicU64::new(0),
+            sequenced: config.sequenced.iter().copied().collect(),
+            cipher: Arc::new(RelayCipher::new(&config.auth_token, session_tag, Direction::ToRelay)),
+            replay_window: Arc::new(Mutex::new(ReplayWindow::default())),
+            reorder,
+            flusher,
+            links: Mutex::new(HashMap::new()),
+            inbound,
+        })
+    }
+
+    pub fn endpoint(&self) -> SocketAddr {
+        self.endpoint
+    }
+
+    fn next_header(&self, flags: u8) -> Result<RelayHeader> {
+        // Wrapping around would reuse nonces under this session's keys
+        let sequence = self
+            .sequence
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sequence| sequence.checked_add(1))
+            .map_err(|_| anyhow::anyhow!("The relay session has used up its sequence numbers; reconnect to start a new one"))?;
+        Ok(RelayHeader {
+            flags,
+            session_tag: self.session_tag,
+            sequence,
+        })
+    }
+
+    /// Whether packets of `traffic_type` are sent with `send_sequenced`
+    pub fn sequences(&self, traffic_type: TrafficType) -> bool {
+        self.sequenced.contains(&traffic_type)
+    }
+
+    /// Tunnel one packet to the relay through `interface`
+    pub async fn send(&self, interface: &PhysicalInterface, packet: &[u8]) -> Result<()> {
+        let datagram = self.cipher.seal(&self.next_header(0)?, packet)?;
+        self.send_datagram(interface, &datagram).await
+    }
+
+    /// Tunnel one packet with the next order number, so the relay delivers
+    /// it after the sequenced packets sent before it
+    pub async fn send_sequenced(&self, interface: &PhysicalInterface, packet: &[u8]) -> Result<()> {
+        let order = self.order.fetch_add(1, Ordering::Relaxed);
+        let sequenced = [&order.to_be_bytes()[..], packet].concat();
+        let datagram = self.cipher.seal(&self.next_header(FLAG_SEQUENCED)?, &sequenced)?;
+        self.send_datagram(interface, &datagram).await
+    }
+
+    /// Send the same packet through every one of `interfaces` under a single
+    /// sequence number, so the relay can forward whichever copy arrives first
+    /// and drop the rest. Returns the interfaces a copy went out of.
+    pub async fn send_redundant(&self, interfaces: &[PhysicalInterface], packet: &[u8]) -> Result<Vec<u32>> {
+        let datagram = self.cipher.seal(&self.next_header(FLAG_DUPLICATE)?, packet)?;
+        let mut sent = Vec::with_capacity(interfaces.len());
+        for interface in interfaces {
+            match self.send_datagram(interface, &datagram).await {
+                Ok(()) => sent.push(interface.index),
+                Err(e) => debug!("Dropped a redundant copy: {:#}", e),
+            }
+        }
+        if sent.is_empty() {
+            bail!("No copy of the packet reached any relay link");
+        }
+        Ok(sent)
+    }
+
+    async fn send_datagram(&self, interface: &PhysicalInterface, datagram: &[u8]) -> Result<()> {
+        let socket = self.link(interface).await?;
+        socket
+            .send(datagram)
+            .await
+            .with_context(|| format!("Failed to send to the relay through '{}'", interface.name))?;
+        Ok(())
+    }
+
+    /// Close the link through an interface, e.g. because it went away
+    pub fn close(&self, index: u32) {
+        self.links.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&index);
+    }
+
+    async fn link(&self, interface: &PhysicalInterface) -> Result<Arc<UdpSocket>> {
+        if let Some(link) = self.links.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&interface.index) {
+            return Ok(Arc::clone(&link.socket));
+        }
+
+        let socket = net_util::udp_socket(interface)?;
+        socket
+            .connect(self.endpoint)
+            .await
+            .with_context(|| format!("Failed to connect to relay {}", self.endpoint))?;
+        let socket = Arc::new(socket);
+
+        let reader = tokio::spawn(Self::read_link(
+            Arc::clone(&socket),
+            interface.index,
+            self.session_tag,
+            Arc::clone(&self.cipher),
+            Arc::clone(&self.replay_window),
+            Arc::clone(&self.reorder),
+            self.inbound.clone(),
+        ));
+        debug!("Opened relay link through '{}'", interface.name);
+
+        // Another packet may have opened the link meanwhile; keep the first
+        let mut links = self.links.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let link = links.entry(interface.index).or_insert(RelayLink { socket, reader });
+        Ok(Arc::clone(&link.socket))
+    }
+
+    async fn read_link(
+        socket: Arc<UdpSocket>,
+        index: u32,
+        session_tag: u64,
+        cipher: Arc<RelayCipher>,
+        replay_window: Arc<Mutex<ReplayWindow>>,
+        reorder: Reorder,
+        inbound: mpsc::Sender<(u32, Vec<u8>)>,
+    ) {
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let len = match socket.recv(&mut buffer).await {
+                Ok(len) => len,
+                Err(e) => {
+                    // The relay being unreachable surfaces here as a refused connection
+                    trace!("Relay link on interface {} failed to receive: {}", index, e);
+                    continue;
+                }
+            };
+            let (header, packet) = match cipher.open(&buffer[..len]) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    warn!("Dropping relay datagram: {:#}", e);
+                    continue;
+                }
+            };
+            if header.session_tag != session_tag {
+                trace!("Dropping relay datagram for another session");
+                continue;
+            }
+            // Only checked once authenticated, so forgeries can't move the window
+            if !replay_window.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).accept(header.sequence) {
+                debug!("Dropping replayed relay datagram {}", header.sequence);
+                continue;
+            }
+            let ready = if header.flags & FLAG_SEQUENCED == 0 {
+                vec![(index, packet)]
+            } else if packet.len() < SEQUENCING_LEN {
+                debug!("Dropping sequenced relay datagram {} without an order number", header.sequence);
+                continue;
+            } else {
+                let order = u64::from_be_bytes(packet[..SEQUENCING_LEN].try_into().expect("length checked"));
+                let packet = packet[SEQUENCING_LEN..].to_vec();
+                reorder.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(order, (index, packet))
+            };
+            for packet in ready {
+                if inbound.send(packet).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Deliver held packets once the ones before them are given up on
+    async fn flush_reordered(reorder: Reorder, max_hold: Duration, inbound: mpsc::Sender<(u32, Vec<u8>)>) {
+        let mut interval = tokio::time::interval((max_hold / 4).max(Duration::from_millis(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let ready = reorder.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).expire();
+            if !ready.is_empty() {
+                trace!("Delivering {} relay packet(s) past a gap", ready.len());
+            }
+            for packet in ready {
+                if inbound.send(packet).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface_manager::InterfaceType;
+    use std::net::Ipv4Addr;
+
+    const SESSION: u64 = 0x1234_5678_9abc_def0;
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = RelayHeader { flags: 0, session_tag: SESSION, sequence: 42 };
+        let datagram = header.encode(&[0x45, 1, 2, 3]);
+        assert_eq!(datagram.len(), HEADER_LEN + 4);
+
+        let (decoded, packet) = RelayHeader::decode(&datagram).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(packet, &[0x45, 1, 2, 3]);
+
+        assert!(RelayHeader::decode(&datagram[..HEADER_LEN - 1]).is_err());
+        let mut wrong_version = datagram.clone();
+        wrong_version[2] = 9;
+        assert!(RelayHeader::decode(&wrong_version).is_err());
+    }
+
+    #[test]
+    fn test_cipher_round_trip() {
+        let client = RelayCipher::new("secret", SESSION, Direction::ToRelay);
+        let relay = RelayCipher::new("secret", SESSION, Direction::ToClient);
+        let header = RelayHeader { flags: 0, session_tag: SESSION, sequence: 7 };
+
+        let datagram = client.seal(&header, b"payload").unwrap();
+        assert_eq!(datagram.len(), HEADER_LEN + 7 + TAG_LEN);
+        assert!(!datagram.windows(7).any(|window| window == b"payload"));
+        assert_eq!(relay.open(&datagram).unwrap(), (header, b"payload".to_vec()));
+
+        // A sender can't decrypt its own direction, nor can another token or
+        // session, so sequence numbers restarting in a new session are safe
+        assert!(client.open(&datagram).is_err());
+        assert!(RelayCipher::new("other", SESSION, Direction::ToClient).open(&datagram).is_err());
+        assert!(RelayCipher::new("secret", SESSION + 1, Direction::ToClient).open(&datagram).is_err());
+
+        // Tampering with the header or the ciphertext is detected
+        for position in [10, HEADER_LEN + 1] {
+            let mut tampered = datagram.clone();
+            tampered[position] ^= 1;
+            assert!(relay.open(&tampered).is_err());
+        }
+    }
+
+    #[test]
+    fn test_replay_window() {
+        let mut window = ReplayWindow::default();
+        assert!(window.accept(0));
+        assert!(window.accept(5));
+        // Reordered packets are fine, repeats are not
+        assert!(window.accept(3));
+        assert!(!window.accept(3));
+        assert!(!window.accept(5));
+
+        assert!(window.accept(5 + REPLAY_WINDOW));
+        assert!(!window.accept(5), "fell out of the window");
+        // The oldest sequence number still inside the window
+        assert!(window.accept(6));
+        assert!(!window.accept(6));
+        assert!(!window.accept(u64::MAX));
+    }
+
+    #[test]
+    fn test_reorder_buffer_restores_order_and_gives_up_on_gaps() {
+        let mut reorder = ReorderBuffer::new(Duration::from_secs(60));
+        assert!(reorder.push(1, 'b').is_empty());
+        assert_eq!(reorder.push(0, 'a'), vec!['a', 'b']);
+        assert!(reorder.push(3, 'd').is_empty());
+        assert_eq!(reorder.push(2, 'c'), vec!['c', 'd']);
+        assert!(reorder.held.is_empty());
+        // Already delivered past, so there's nothing to wait for
+        assert_eq!(reorder.push(1, 'z'), vec!['z']);
+
+        assert!(reorder.push(5, 'f').is_empty());
+        assert!(reorder.expire().is_empty());
+        let mut reorder = ReorderBuffer { max_hold: Duration::ZERO, ..reorder };
+        assert_eq!(reorder.expire(), vec!['f']);
+        assert_eq!(reorder.push(4, 'e'), vec!['e']);
+        assert_eq!(reorder.push(6, 'g'), vec!['g']);
+    }
+
+    #[test]
+    fn test_relay_config_validation() {
+        assert!(RelayConfig::new("relay.example.com:7000".to_string(), "secret".to_string()).validate().is_ok());
+        assert!(RelayConfig::new("relay.example.com".to_string(), "secret".to_string()).validate().is_err());
+        assert!(RelayConfig::new(":7000".to_string(), "secret".to_string()).validate().is_err());
+        assert!(RelayConfig::new("relay.example.com:70000".to_string(), "secret".to_string()).validate().is_err());
+        assert!(RelayConfig::new("relay.example.com:7000".to_string(), String::new()).validate().is_err());
+    }
+
+    fn loopback() -> PhysicalInterface {
+        PhysicalInterface {
+            name: "lo".to_string(),
+            description: "Loopback".to_string(),
+            ip_address: Ipv4Addr::LOCALHOST,
+            index: 1,
+            link_speed_mbps: None,
+            interface_type: InterfaceType::Virtual,
+            prefix_len: 8,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_round_trip_over_loopback() {
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let config = RelayConfig::new(relay.local_addr().unwrap().to_string(), "secret".to_string());
+        let (inbound_tx, mut inbound_rx) = mpsc::channel(4);
+        let tunnel = RelayTunnel::connect(&config, inbound_tx).await.unwrap();
+        let cipher = RelayCipher::new("secret", tunnel.session_tag, Direction::ToClient);
+
+        let loopback = loopback();
+        tunnel.send(&loopback, b"first").await.unwrap();
+        tunnel.send(&loopback, b"second").await.unwrap();
+
+        let mut buffer = [0u8; 64];
+        let mut client = None;
+        for (expected_sequence, expected) in [(0, &b"first"[..]), (1, &b"second"[..])] {
+            let (len, from) = relay.recv_from(&mut buffer).await.unwrap();
+            let (header, packet) = cipher.open(&buffer[..len]).unwrap();
+            assert_eq!((header.sequence, &packet[..]), (expected_sequence, expected));
+            assert_eq!(header.session_tag, tunnel.session_tag);
+            client = Some(from);
+        }
+
+        // Replies under another key, in plain text or played back are ignored
+        let client = client.unwrap();
+        let reply = RelayHeader { flags: 0, session_tag: tunnel.session_tag, sequence: 0 };
+        let other = RelayCipher::new("other", tunnel.session_tag, Direction::ToClient);
+        relay.send_to(&other.seal(&reply, b"ignored").unwrap(), client).await.unwrap();
+        relay.send_to(&reply.encode(b"ignored"), client).await.unwrap();
+        let datagram = cipher.seal(&reply, b"reply").unwrap();
+        relay.send_to(&datagram, client).await.unwrap();
+        relay.send_to(&datagram, client).await.unwrap();
+        let next = RelayHeader { sequence: 1, ..reply };
+        relay.send_to(&cipher.seal(&next, b"next").unwrap(), client).await.unwrap();
+
+        assert_eq!(inbound_rx.recv().await.unwrap(), (1, b"reply".to_vec()));
+        assert_eq!(inbound_rx.recv().await.unwrap(), (1, b"next".to_vec()));
+
+        // The session ends rather than reuse a nonce
+        tunnel.sequence.store(u64::MAX, Ordering::Relaxed);
+        assert!(tunnel.send(&loopback, b"last").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_packets_carry_their_order_and_replies_are_reordered() {
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut config = RelayConfig::new(relay.local_addr().unwrap().to_string(), "secret".to_string());
+        config.sequenced = vec![TrafficType::File];
+        let (inbound_tx, mut inbound_rx) = mpsc::channel(4);
+        let tunnel = RelayTunnel::connect(&config, inbound_tx).await.unwrap();
+        let cipher = RelayCipher::new("secret", tunnel.session_tag, Direction::ToClient);
+        assert!(tunnel.sequences(TrafficType::File) && !tunnel.sequences(TrafficType::Gaming));
+
+        let loopback = loopback();
+        tunnel.send(&loopback, b"plain").await.unwrap();
+        tunnel.send_sequenced(&loopback, b"ordered").await.unwrap();
+
+        let mut buffer = [0u8; 64];
+        let (len, client) = relay.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(cipher.open(&buffer[..len]).unwrap().0.flags, 0);
+        let (len, _) = relay.recv_from(&mut buffer).await.unwrap();
+        let (header, packet) = cipher.open(&buffer[..len]).unwrap();
+        assert_eq!(header.flags, FLAG_SEQUENCED);
+        assert_eq!(packet, [&0u64.to_be_bytes()[..], b"ordered"].concat());
+
+        // Replies sent out of order are delivered in order
+        for (sequence, order, payload) in [(0, 1u64, &b"second"[..]), (1, 0, &b"first"[..])] {
+            let header = RelayHeader { flags: FLAG_SEQUENCED, session_tag: tunnel.session_tag, sequence };
+            let datagram = cipher.seal(&header, &[&order.to_be_bytes()[..], payload].concat()).unwrap();
+            relay.send_to(&datagram, client).await.unwrap();
+        }
+        assert_eq!(inbound_rx.recv().await.unwrap(), (1, b"first".to_vec()));
+        assert_eq!(inbound_rx.recv().await.unwrap(), (1, b"second".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_redundant_copies_share_a_sequence_number() {
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let config = RelayConfig::new(relay.local_addr().unwrap().to_string(), "secret".to_string());
+        let (inbound_tx, _inbound_rx) = mpsc::channel(4);
+        let tunnel = RelayTunnel::connect(&config, inbound_tx).await.unwrap();
+        let cipher = RelayCipher::new("secret", tunnel.session_tag, Direction::ToClient);
+
+        // Two links over loopback stand in for two physical interfaces
+        let links: Vec<PhysicalInterface> = [1, 2]
+            .into_iter()
+            .map(|index| PhysicalInterface {
+                name: format!("lo{}", index),
+                index,
+                ..loopback()
+            })
+            .collect();
+        tunnel.send(&links[0], b"plain").await.unwrap();
+        assert_eq!(tunnel.send_redundant(&links, b"copy").await.unwrap(), vec![1, 2]);
+
+        let mut buffer = [0u8; 64];
+        let mut sources = Vec::new();
+        let mut copies = Vec::new();
+        for _ in 0..3 {
+            let (len, from) = relay.recv_from(&mut buffer).await.unwrap();
+            let (header, packet) = cipher.open(&buffer[..len]).unwrap();
+            sources.push(from);
+            if header.flags & FLAG_DUPLICATE != 0 {
+                copies.push((header.sequence, packet));
+            }
+        }
+        assert_eq!(copies, vec![(1, b"copy".to_vec()), (1, b"copy".to_vec())]);
+        // Each copy left through its own link
+        assert_ne!(sources[1], sources[2]);
+    }
+}
@@ -0,0 +1,214 @@
+// src-tauri/src/routes.rs
+use anyhow::{bail, Context, Result};
+use std::net::Ipv4Addr;
+use std::process::Command;
+use tracing::{error, info, warn};
+
+use crate::cidr::Ipv4Cidr;
+
+/// Runs one route command, given as program and arguments
+type Runner = fn(&[String]) -> Result<()>;
+
+/// The two halves of the IPv4 address space. Together they cover what the
+/// default route does but are more specific, so they win while installed
+/// without the default route being touched.
+fn split_default() -> [Ipv4Cidr; 2] {
+    [
+        Ipv4Cidr::new(Ipv4Addr::UNSPECIFIED, 1).expect("valid prefix"),
+        Ipv4Cidr::new(Ipv4Addr::new(128, 0, 0, 0), 1).expect("valid prefix"),
+    ]
+}
+
+/// The four quarters of the IPv4 address space, for when another VPN, such
+/// as OpenVPN with `redirect-gateway def1`, already routes both halves
+fn quarter_default() -> [Ipv4Cidr; 4] {
+    [0, 64, 128, 192].map(|first| Ipv4Cidr::new(Ipv4Addr::new(first, 0, 0, 0), 2).expect("valid prefix"))
+}
+
+/// Whether a route couldn't be added because the same one already exists
+fn already_routed(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error);
+    message.contains("File exists") || message.contains("already exists")
+}
+
+/// Routes sending the host's IPv4 traffic into the TUN. The prior routing
+/// table is never modified, only added to, so removing what was installed
+/// restores it exactly. The service calls `restore` when it stops; dropping
+/// the guard restores too, as a fallback for when the service panics. If
+/// the process is killed outright, the OS removes the routes along with the
+/// TUN device they point at.
+pub struct RouteGuard {
+    tun: String,
+    /// Newest last
+    installed: Vec<Ipv4Cidr>,
+    run: Runner,
+}
+
+impl RouteGuard {
+    /// Route all IPv4 traffic into the TUN named `tun`. If any route can't
+    /// be added, the ones that were are removed again.
+    pub fn install(tun: &str) -> Result<Self> {
+        Self::install_or_narrow(tun, run_command)
+    }
+
+    /// Install the two halves, or the four quarters if another VPN already
+    /// routes the halves, so ours are still the more specific routes
+    fn install_or_narrow(tun: &str, run: Runner) -> Result<Self> {
+        match Self::install_with(tun, &split_default(), run) {
+            Err(e) if already_routed(&e) => {
+                warn!("Another VPN already routes both halves of the address space; routing quarters into '{}' instead", tun);
+                Self::install_with(tun, &quarter_default(), run)
+            }
+            result => result,
+        }
+    }
+
+    fn install_with(tun: &str, routes: &[Ipv4Cidr], run: Runner) -> Result<Self> {
+        let mut guard = Self {
+            tun: tun.to_string(),
+            installed: Vec::new(),
+            run,
+        };
+        for &cidr in routes {
+            (guard.run)(&route_command(RouteAction::Add, cidr, tun))
+                .with_context(|| format!("Failed to route {} into '{}'", cidr, tun))?;
+            guard.installed.push(cidr);
+        }
+        info!("Routing all IPv4 traffic into '{}'", tun);
+        Ok(guard)
+    }
+
+    /// Remove every installed route, newest first. Routes that can't be
+    /// removed are reported and forgotten, since retrying won't help.
+    pub fn restore(&mut self) -> Result<()> {
+        let mut failed = Vec::new();
+        while let Some(cidr) = self.installed.pop() {
+            if let Err(e) = (self.run)(&route_command(RouteAction::Delete, cidr, &self.tun)) {
+                warn!("Failed to remove the route for {} through '{}': {:#}", cidr, self.tun, e);
+                failed.push(cidr.to_string());
+            }
+        }
+        if !failed.is_empty() {
+            bail!("Routes for {} through '{}' may still be installed", failed.join(", "), self.tun);
+        }
+        info!("Restored the routing table");
+        Ok(())
+    }
+}
+
+impl Drop for RouteGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.restore() {
+            error!("{:#}", e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteAction {
+    Add,
+    Delete,
+}
+
+/// The command adding or removing a route for `cidr` through `tun`. Routes
+/// are only added to the running table, so a reboot clears them too.
+fn route_command(action: RouteAction, cidr: Ipv4Cidr, tun: &str) -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    let command = {
+        let verb = if action == RouteAction::Add { "add" } else { "del" };
+        vec!["ip".to_string(), "route".to_string(), verb.to_string(), cidr.to_string(), "dev".to_string(), tun.to_string()]
+    };
+    #[cfg(target_os = "macos")]
+    let command = {
+        let verb = if action == RouteAction::Add { "add" } else { "delete" };
+        ["route", "-n", verb, "-net", &cidr.to_string(), "-interface", tun].map(String::from).to_vec()
+    };
+    #[cfg(windows)]
+    let command = {
+        let verb = if action == RouteAction::Add { "add" } else { "delete" };
+        vec![
+            "netsh".to_string(),
+            "interface".to_string(),
+            "ipv4".to_string(),
+            verb.to_string(),
+            "route".to_string(),
+            format!("prefix={}", cidr),
+            format!("interface={}", tun),
+            "store=active".to_string(),
+        ]
+    };
+    command
+}
+
+fn run_command(command: &[String]) -> Result<()> {
+    let (program, args) = command.split_first().context("Empty route command")?;
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!("`{}` failed: {}", command.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static COMMANDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(command: &[String]) -> Result<()> {
+        COMMANDS.with(|commands| commands.borrow_mut().push(command.join(" ")));
+        Ok(())
+    }
+
+    /// Fails to add the upper half
+    fn fail_upper_half(command: &[String]) -> Result<()> {
+        if command.iter().any(|arg| arg.contains("128.0.0.0/1")) && !command.iter().any(|arg| arg == "del" || arg == "delete") {
+            bail!("File exists");
+        }
+        record(command)
+    }
+
+    fn taken() -> Vec<String> {
+        COMMANDS.with(|commands| std::mem::take(&mut *commands.borrow_mut()))
+    }
+
+    #[test]
+    fn test_routes_are_removed_newest_first_when_the_guard_drops() {
+        let guard = RouteGuard::install_with("netboost0", &split_default(), record).unwrap();
+        assert_eq!(guard.installed, split_default());
+        let added = taken();
+        assert_eq!(added.len(), 2);
+        assert!(added[0].contains("0.0.0.0/1") && added[0].contains("netboost0"));
+
+        drop(guard);
+        let removed = taken();
+        assert_eq!(removed.len(), 2);
+        assert!(removed[0].contains("128.0.0.0/1"));
+        assert!(removed[1].contains("0.0.0.0/1"));
+    }
+
+    #[test]
+    fn test_failed_install_rolls_back() {
+        assert!(RouteGuard::install_with("netboost0", &split_default(), fail_upper_half).is_err());
+        let commands = taken();
+        // The lower half was added, then removed again
+        assert_eq!(commands.len(), 2);
+        assert!(commands.iter().all(|command| command.contains("0.0.0.0/1")));
+    }
+
+    #[test]
+    fn test_routes_narrow_when_the_halves_are_taken() {
+        let guard = RouteGuard::install_or_narrow("netboost0", fail_upper_half).unwrap();
+        assert_eq!(guard.installed, quarter_default());
+        // The lower half was added and removed before the quarters went in
+        assert_eq!(taken().len(), 6);
+        drop(guard);
+        assert_eq!(taken().len(), 4);
+    }
+}
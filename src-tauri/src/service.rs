@@ -0,0 +1,342 @@
+// src-tauri/src/service.rs
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Name NetBoost Pro is registered under with the OS service manager
+pub const SERVICE_NAME: &str = "netboost-pro";
+#[cfg(windows)]
+const SERVICE_DISPLAY_NAME: &str = "NetBoost Pro";
+const SERVICE_DESCRIPTION: &str = "Combines network interfaces into one faster, more reliable connection";
+
+/// Requests from the OS service manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceRequest {
+    Stop,
+    /// Route everything over the primary interface until resumed
+    Pause,
+    Resume,
+}
+
+/// Reports the service's state to the OS service manager and receives its
+/// requests. Under systemd, readiness goes to `NOTIFY_SOCKET` and stop
+/// requests arrive as SIGTERM; outside a service manager reports go nowhere.
+pub struct ServiceLink {
+    requests: mpsc::UnboundedReceiver<ServiceRequest>,
+    // Keeps `requests` open when nothing can send on it
+    _requests_tx: Option<mpsc::UnboundedSender<ServiceRequest>>,
+    #[cfg(windows)]
+    status: Option<windows_service::service_control_handler::ServiceStatusHandle>,
+}
+
+impl Default for ServiceLink {
+    fn default() -> Self {
+        let (tx, requests) = mpsc::unbounded_channel();
+        Self {
+            requests,
+            _requests_tx: Some(tx),
+            #[cfg(windows)]
+            status: None,
+        }
+    }
+}
+
+impl ServiceLink {
+    /// The next request from the service manager; never resolves if none can arrive
+    pub async fn next_request(&mut self) -> ServiceRequest {
+        match self.requests.recv().await {
+            Some(request) => request,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// The service is routing traffic
+    pub fn ready(&self, status: &str) {
+        #[cfg(target_os = "linux")]
+        notify_systemd(&[sd_notify::NotifyState::Ready, sd_notify::NotifyState::Status(status)]);
+        #[cfg(windows)]
+        self.set_windows_state(windows_service::service::ServiceState::Running);
+        #[cfg(not(target_os = "linux"))]
+        let _ = status;
+    }
+
+    pub fn paused(&self, paused: bool) {
+        #[cfg(target_os = "linux")]
+        notify_systemd(&[sd_notify::NotifyState::Status(if paused { "Paused" } else { "Running" })]);
+        #[cfg(windows)]
+        self.set_windows_state(if paused {
+            windows_service::service::ServiceState::Paused
+        } else {
+            windows_service::service::ServiceState::Running
+        });
+        #[cfg(not(any(target_os = "linux", windows)))]
+        let _ = paused;
+    }
+
+    /// The service is flushing queued packets and shutting down
+    pub fn stopping(&self) {
+        #[cfg(target_os = "linux")]
+        notify_systemd(&[sd_notify::NotifyState::Stopping]);
+        #[cfg(windows)]
+        self.set_windows_state(windows_service::service::ServiceState::StopPending);
+    }
+
+    #[cfg(windows)]
+    fn set_windows_state(&self, state: windows_service::service::ServiceState) {
+        if let Some(status) = &self.status {
+            if let Err(e) = status.set_service_status(windows::service_status(state, windows_service::service::ServiceExitCode::NO_ERROR)) {
+                debug!("Failed to report {:?} to the service manager: {}", state, e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn notify_systemd(states: &[sd_notify::NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        debug!("Failed to notify systemd: {}", e);
+    }
+}
+
+/// Register NetBoost Pro to start at boot, running this executable with
+/// `args` on top of what starts the service
+#[cfg(target_os = "linux")]
+pub fn install_service(args: &[OsString]) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the CLI executable")?;
+    let path = systemd::unit_path();
+    std::fs::write(&path, systemd::unit(&exe, args)).with_context(|| format!("Failed to write {}", path.display()))?;
+    systemd::systemctl(&["daemon-reload"])?;
+    systemd::systemctl(&["enable", SERVICE_NAME])
+}
+
+/// Stop the service and remove its registration
+#[cfg(target_os = "linux")]
+pub fn uninstall_service() -> Result<()> {
+    let path = systemd::unit_path();
+    if !path.exists() {
+        anyhow::bail!("The {} service is not installed", SERVICE_NAME);
+    }
+    systemd::systemctl(&["disable", "--now", SERVICE_NAME])?;
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    systemd::systemctl(&["daemon-reload"])
+}
+
+#[cfg(target_os = "linux")]
+mod systemd {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    pub(super) fn unit_path() -> PathBuf {
+        Path::new("/etc/systemd/system").join(format!("{}.service", SERVICE_NAME))
+    }
+
+    pub(super) fn unit(exe: &Path, args: &[OsString]) -> String {
+        let mut exec_start = quote(exe.as_os_str());
+        exec_start.push_str(" --start");
+        for arg in args {
+            exec_start.push(' ');
+            exec_start.push_str(&quote(arg));
+        }
+        format!(
+            "[Unit]\n\
+             Description={}\n\
+             Wants=network-online.target\n\
+             After=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=notify\n\
+             ExecStart={}\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            SERVICE_DESCRIPTION, exec_start
+        )
+    }
+
+    /// Quote a word for `ExecStart` when it has spaces or characters systemd would interpret
+    fn quote(word: &std::ffi::OsStr) -> String {
+        let word = word.to_string_lossy();
+        if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '%' | '$' | ';')) {
+            return word.into_owned();
+        }
+        let escaped = word.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+        format!("\"{}\"", escaped)
+    }
+
+    pub(super) fn systemctl(args: &[&str]) -> Result<()> {
+        let status = std::process::Command::new("systemctl")
+            .args(args)
+            .status()
+            .context("Failed to run systemctl")?;
+        if !status.success() {
+            anyhow::bail!("systemctl {} failed ({})", args.join(" "), status);
+        }
+        Ok(())
+    }
+}
+
+/// Register NetBoost Pro to start at boot, running this executable with
+/// `args` on top of what starts the service
+#[cfg(windows)]
+pub fn install_service(args: &[OsString]) -> Result<()> {
+    use windows_service::service::{ServiceAccess, ServiceErrorControl, ServiceInfo, ServiceStartType, ServiceType};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)
+        .context("Failed to connect to the service manager")?;
+    let mut launch_arguments = vec![OsString::from("--service")];
+    launch_arguments.extend(args.iter().cloned());
+    let info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: SERVICE_DISPLAY_NAME.into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe().context("Failed to locate the CLI executable")?,
+        launch_arguments,
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .context("Failed to register the service")?;
+    service.set_description(SERVICE_DESCRIPTION).context("Failed to describe the service")?;
+    Ok(())
+}
+
+/// Stop the service and remove its registration
+#[cfg(windows)]
+pub fn uninstall_service() -> Result<()> {
+    use windows_service::service::{ServiceAccess, ServiceState};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .context("Failed to connect to the service manager")?;
+    let service = manager
+        .open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)
+        .with_context(|| format!("The {} service is not installed", SERVICE_NAME))?;
+    if service.query_status().context("Failed to query the service")?.current_state != ServiceState::Stopped {
+        service.stop().context("Failed to stop the service")?;
+    }
+    // Removed once the last handle to it closes
+    service.delete().context("Failed to remove the service")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn install_service(_args: &[OsString]) -> Result<()> {
+    anyhow::bail!("Installing as a service is only supported with systemd and on Windows")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn uninstall_service() -> Result<()> {
+    anyhow::bail!("Installing as a service is only supported with systemd and on Windows")
+}
+
+#[cfg(windows)]
+pub use windows::run_as_windows_service;
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use windows_service::service::{ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType};
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+
+    /// Long enough to flush the packet queue
+    const STATE_CHANGE_WAIT_HINT: Duration = Duration::from_secs(10);
+
+    type ServiceRun = Box<dyn FnOnce(ServiceLink) -> Result<()> + Send>;
+
+    static SERVICE_RUN: Mutex<Option<ServiceRun>> = Mutex::new(None);
+
+    windows_service::define_windows_service!(ffi_service_main, service_main);
+
+    /// Hand this process to the Windows service manager and call `run` on its
+    /// service thread, blocking until the service stops
+    pub fn run_as_windows_service<F>(run: F) -> Result<()>
+    where
+        F: FnOnce(ServiceLink) -> Result<()> + Send + 'static,
+    {
+        *SERVICE_RUN.lock().expect("service entry lock poisoned") = Some(Box::new(run));
+        windows_service::service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+            .context("Failed to connect to the service manager; --service is only for the installed service")
+    }
+
+    pub(super) fn service_status(state: ServiceState, exit_code: ServiceExitCode) -> ServiceStatus {
+        let pending = matches!(state, ServiceState::StartPending | ServiceState::StopPending | ServiceState::PausePending);
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: match state {
+                ServiceState::Running | ServiceState::Paused => {
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN | ServiceControlAccept::PAUSE_CONTINUE
+                }
+                _ => ServiceControlAccept::empty(),
+            },
+            exit_code,
+            checkpoint: 0,
+            wait_hint: if pending { STATE_CHANGE_WAIT_HINT } else { Duration::ZERO },
+            process_id: None,
+        }
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let Some(run) = SERVICE_RUN.lock().expect("service entry lock poisoned").take() else {
+            return;
+        };
+
+        let (tx, requests) = mpsc::unbounded_channel();
+        let handler = move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                let _ = tx.send(ServiceRequest::Stop);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Pause => {
+                let _ = tx.send(ServiceRequest::Pause);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Continue => {
+                let _ = tx.send(ServiceRequest::Resume);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+        let status = match service_control_handler::register(SERVICE_NAME, handler) {
+            Ok(status) => status,
+            Err(e) => {
+                tracing::error!("Failed to register with the service manager: {}", e);
+                return;
+            }
+        };
+        let _ = status.set_service_status(service_status(ServiceState::StartPending, ServiceExitCode::NO_ERROR));
+
+        let link = ServiceLink { requests, _requests_tx: None, status: Some(status) };
+        let exit_code = match run(link) {
+            Ok(()) => ServiceExitCode::NO_ERROR,
+            Err(e) => {
+                tracing::error!("NetBoost Pro failed: {:#}", e);
+                ServiceExitCode::ServiceSpecific(1)
+            }
+        };
+        let _ = status.set_service_status(service_status(ServiceState::Stopped, exit_code));
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_systemd_unit_starts_the_cli_with_its_flags() {
+        let args = ["--config".into(), "/etc/net boost/netboost.toml".into(), "--warmup".into(), "5".into()];
+        let unit = systemd::unit(Path::new("/usr/bin/netboost-cli"), &args);
+        assert!(unit.contains("Type=notify\n"));
+        assert!(unit.contains("ExecStart=/usr/bin/netboost-cli --start --config \"/etc/net boost/netboost.toml\" --warmup 5\n"));
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::classifier::ClassificationRule;
+use crate::dns::DnsSettings;
+use crate::flow_log::FlowLogSettings;
+use crate::packet_sampler::SamplerSettings;
+use crate::health::HealthConfig;
+use crate::interface_filter::InterfaceFilter;
+use crate::logging::LogSettings;
+use crate::performance_monitor::{DEFAULT_HISTORY_RESOLUTION, DEFAULT_HISTORY_RETENTION};
+use crate::qos::{DscpConfig, SchedulerConfig};
+use crate::relay::RelayConfig;
+use crate::speed_test::SpeedTestConfig;
+use crate::packet_router::{BypassRoute, LoadBalancingMode, DEFAULT_JITTER_WEIGHT, DEFAULT_METRICS_MAX_AGE, DEFAULT_TIER};
+use crate::virtual_adapter::{TunConfig, MAX_MONITORING_INTERVAL_SECS};
+use crate::vpn::VpnPolicy;
+
+/// Longest warmup, so a typo can't hold the service up indefinitely
+const MAX_WARMUP_SECS: u64 = 60;
+
+/// User preferences for a single physical interface, keyed by interface name
+/// since OS interface indices aren't stable across reboots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceSettings {
+    pub weight: f32,
+    pub enabled: bool,
+    pub rate_limit_bytes_per_sec: Option<u64>,
+    /// Failover tier in `ActivePassive` mode; lower tiers are used first
+    pub tier: u8,
+}
+
+impl Default for InterfaceSettings {
+    fn default() -> Self {
+        Self {
+            weight: 1.0,
+            enabled: true,
+            rate_limit_bytes_per_sec: None,
+            tier: DEFAULT_TIER,
+        }
+    }
+}
+
+/// Settings persisted across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub load_balancing_mode: LoadBalancingMode,
+    /// Balance across every interface; when off, use only the primary one
+    pub connection_aggregation: bool,
+    /// Interface used when aggregation is off and for bypassed traffic;
+    /// `None` uses the first one discovered
+    pub primary_interface: Option<String>,
+    /// Which discovered interfaces are aggregated
+    pub interface_filter: InterfaceFilter,
+    /// Keep file transfers off metered interfaces when others are available
+    pub avoid_metered: bool,
+    pub tun_config: TunConfig,
+    pub interfaces: HashMap<String, InterfaceSettings>,
+    pub probe_target: String,
+    pub monitoring_interval_secs: u64,
+    /// Seconds spent probing interfaces before serving traffic; 0 skips it
+    pub warmup_secs: u64,
+    /// Seconds of performance history kept for export; 0 turns recording off
+    pub history_retention_secs: u64,
+    /// Seconds between performance history samples
+    pub history_resolution_secs: u64,
+    /// Interface metrics older than this are treated as unknown
+    pub metrics_max_age_secs: u64,
+    /// Milliseconds of latency each millisecond of jitter counts as when
+    /// comparing interfaces
+    pub jitter_weight: f32,
+    /// Balancer picks less confident than this, from 0 to 1, go to the
+    /// primary interface instead; 0 trusts every pick
+    pub min_confidence: f32,
+    pub health: HealthConfig,
+    /// Address for the Prometheus endpoint, only used with the `metrics` feature
+    pub metrics_address: Option<SocketAddr>,
+    pub logging: LogSettings,
+    pub flow_log: FlowLogSettings,
+    /// Copies of routed packets for debugging
+    pub packet_sampler: SamplerSettings,
+    pub dscp: DscpConfig,
+    /// How queued packets are shared between traffic types under load
+    pub scheduler: SchedulerConfig,
+    /// Tasks processing packets at once, each taking a share of the flows;
+    /// 0 runs one per CPU core
+    pub packet_workers: usize,
+    pub dns: DnsSettings,
+    /// Traffic classification overrides, first match wins
+    pub classification_rules: Vec<ClassificationRule>,
+    /// Destinations that are never load balanced
+    pub bypass_routes: Vec<BypassRoute>,
+    /// Destinations always sent through the named interface
+    pub pinned_destinations: HashMap<IpAddr, String>,
+    pub speed_test: SpeedTestConfig,
+    /// Bond interfaces through this relay server instead of routing each flow directly
+    pub relay: Option<RelayConfig>,
+    /// What to do if another VPN is carrying traffic at startup
+    pub vpn_policy: VpnPolicy,
+    /// Route the host's traffic into the TUN while running, restoring the
+    /// routing table on stop. Off leaves routing to the user.
+    pub manage_routes: bool,
+    /// Block traffic that doesn't go through the TUN while running, and if
+    /// the service dies, until it is stopped cleanly
+    pub kill_switch: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            load_balancing_mode: LoadBalancingMode::Balanced,
+            connection_aggregation: true,
+            primary_interface: None,
+            interface_filter: InterfaceFilter::default(),
+            avoid_metered: false,
+            tun_config: TunConfig::default(),
+            interfaces: HashMap::new(),
+            probe_target: "1.1.1.1".to_string(),
+            monitoring_interval_secs: 5,
+            warmup_secs: 2,
+            history_retention_secs: DEFAULT_HISTORY_RETENTION.as_secs(),
+            history_resolution_secs: DEFAULT_HISTORY_RESOLUTION.as_secs(),
+            metrics_max_age_secs: DEFAULT_METRICS_MAX_AGE.as_secs(),
+            jitter_weight: DEFAULT_JITTER_WEIGHT,
+            min_confidence: 0.0,
+            health: HealthConfig::default(),
+            metrics_address: None,
+            logging: LogSettings::default(),
+            flow_log: FlowLogSettings::default(),
+            packet_sampler: SamplerSettings::default(),
+            dscp: DscpConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            packet_workers: 0,
+            dns: DnsSettings::default(),
+            classification_rules: Vec::new(),
+            bypass_routes: Vec::new(),
+            pinned_destinations: HashMap::new(),
+            speed_test: SpeedTestConfig::default(),
+            relay: None,
+            vpn_policy: VpnPolicy::default(),
+            manage_routes: true,
+            kill_switch: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Location of the settings file in the platform config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("netboost-pro").join("settings.json"))
+    }
+
+    /// Load settings from disk, falling back to defaults if the file is
+    /// missing or can't be parsed
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    warn!("Ignoring corrupt settings file {}: {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Failed to read settings file {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Write settings to disk
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("No config directory available on this platform")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        // Write to a temporary file first so a crash can't leave a truncated file behind
+        let contents = serde_json::to_string_pretty(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, contents)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Interval between performance samples, between one second and an hour
+    pub fn monitoring_interval(&self) -> Duration {
+        Duration::from_secs(self.monitoring_interval_secs.clamp(1, MAX_MONITORING_INTERVAL_SECS))
+    }
+
+    pub fn history_retention(&self) -> Duration {
+        Duration::from_secs(self.history_retention_secs)
+    }
+
+    /// Time between performance history samples, never less than one second
+    pub fn history_resolution(&self) -> Duration {
+        Duration::from_secs(self.history_resolution_secs.max(1))
+    }
+
+    /// How many packet processing tasks to run, at least one
+    pub fn packet_workers(&self) -> usize {
+        match self.packet_workers {
+            0 => std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+            workers => workers,
+        }
+    }
+
+    /// How long interfaces are probed before serving traffic, at most a minute
+    pub fn warmup(&self) -> Duration {
+        Duration::from_secs(self.warmup_secs.min(MAX_WARMUP_SECS))
+    }
+
+    /// How long interface metrics are trusted, never less than one second
+    pub fn metrics_max_age(&self) -> Duration {
+        Duration::from_secs(self.metrics_max_age_secs.max(1))
+    }
+
+    /// Settings for the named interface, creating a default entry if needed
+    pub fn interface_mut(&mut self, name: &str) -> &mut InterfaceSettings {
+        self.interfaces.entry(name.to_string()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_survive_a_round_trip() {
+        let mut settings = Settings {
+            load_balancing_mode: LoadBalancingMode::RoundRobin,
+            connection_aggregation: false,
+            primary_interface: Some("eth0".to_string()),
+            avoid_metered: true,
+            warmup_secs: 10,
+            jitter_weight: 0.5,
+            min_confidence: 0.25,
+            packet_workers: 3,
+            manage_routes: false,
+            kill_switch: true,
+            ..Settings::default()
+        };
+        *settings.interface_mut("wlan0") = InterfaceSettings {
+            weight: 2.5,
+            enabled: false,
+            rate_limit_bytes_per_sec: Some(1_000_000),
+            tier: 2,
+        };
+        settings.pinned_destinations.insert("1.1.1.1".parse().unwrap(), "eth0".to_string());
+
+        let json = serde_json::to_string(&settings).unwrap();
+        let restored: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&settings).unwrap());
+        assert_eq!(restored.interfaces["wlan0"].tier, 2);
+    }
+
+    #[test]
+    fn test_missing_fields_take_their_defaults() {
+        // A settings file written before most options existed
+        let settings: Settings = serde_json::from_str(r#"{"load_balancing_mode": "round_robin", "interfaces": {"eth0": {"weight": 3.0}}}"#).unwrap();
+        let defaults = Settings::default();
+
+        assert_eq!(settings.load_balancing_mode, LoadBalancingMode::RoundRobin);
+        assert_eq!(settings.interfaces["eth0"], InterfaceSettings { weight: 3.0, ..InterfaceSettings::default() });
+        assert_eq!(settings.tun_config, defaults.tun_config);
+        assert_eq!(settings.probe_target, defaults.probe_target);
+        assert!(settings.connection_aggregation && settings.manage_routes && !settings.kill_switch);
+        assert!(settings.primary_interface.is_none() && settings.pinned_destinations.is_empty());
+
+        // Out of range values are clamped where they are used
+        let settings: Settings = serde_json::from_str(r#"{"monitoring_interval_secs": 0, "warmup_secs": 86400}"#).unwrap();
+        assert_eq!(settings.monitoring_interval(), Duration::from_secs(1));
+        assert_eq!(settings.warmup(), Duration::from_secs(MAX_WARMUP_SECS));
+    }
+}
@@ -0,0 +1,467 @@
+// src-tauri/src/simulation.rs
+use anyhow::Result;
+use pnet_packet::ip::IpNextHeaderProtocols;
+use pnet_packet::ipv4::{self, MutableIpv4Packet};
+use pnet_packet::tcp::MutableTcpPacket;
+use pnet_packet::udp::MutableUdpPacket;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::interface_manager::{InterfaceManager, InterfaceType, PhysicalInterface};
+use crate::packet_router::{validate_packet, LoadBalancingMode, PacketRouter, RateLimited, TrafficType};
+use crate::pcap;
+use crate::performance_monitor::{DropReason, PerformanceMonitor};
+use crate::settings::Settings;
+use crate::virtual_adapter::VirtualNetworkInterface;
+
+/// Packets generated by `PacketSource::Synthetic` when no count is given
+pub const DEFAULT_SYNTHETIC_PACKETS: usize = 1000;
+
+/// Where a simulated interface gets its packets from
+#[derive(Debug, Clone)]
+pub enum PacketSource {
+    /// IPv4 packets from a pcap capture
+    Pcap(PathBuf),
+    /// A deterministic mix of flows and packet sizes
+    Synthetic { count: usize },
+}
+
+impl std::str::FromStr for PacketSource {
+    type Err = anyhow::Error;
+
+    /// `synthetic` or `synthetic:<count>` for generated traffic, anything else is a capture path
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            _ if s == "synthetic" => Ok(PacketSource::Synthetic { count: DEFAULT_SYNTHETIC_PACKETS }),
+            Some(("synthetic", count)) => Ok(PacketSource::Synthetic {
+                count: count.parse().map_err(|e| anyhow::anyhow!("Invalid packet count '{}': {}", count, e))?,
+            }),
+            _ => Ok(PacketSource::Pcap(PathBuf::from(s))),
+        }
+    }
+}
+
+impl PacketSource {
+    /// Read or generate every packet of the source
+    pub fn load(&self) -> Result<Vec<Vec<u8>>> {
+        match self {
+            PacketSource::Pcap(path) => pcap::read_ipv4_packets(path),
+            PacketSource::Synthetic { count } => Ok(synthetic_packets(*count)),
+        }
+    }
+}
+
+/// Width of each bucket of `SimulationReport::confidence_histogram`
+pub const CONFIDENCE_BUCKETS: usize = 10;
+
+/// Traffic routed through one interface
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InterfaceTally {
+    pub packets: u64,
+    pub bytes: u64,
+    pub traffic_types: BTreeMap<TrafficType, u64>,
+}
+
+/// Where the router sent one simulated packet
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedDecision {
+    pub interface_index: u32,
+    pub interface_name: String,
+    pub traffic_type: TrafficType,
+    pub confidence: f32,
+    pub reason: String,
+    pub bytes: usize,
+}
+
+/// Routing decisions for a replayed workload
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SimulationReport {
+    pub packets: u64,
+    pub dropped: u64,
+    pub throttled: u64,
+    /// Malformed packets, also counted in `dropped`
+    pub invalid: u64,
+    /// Keyed by interface name
+    pub interfaces: BTreeMap<String, InterfaceTally>,
+    pub traffic_types: BTreeMap<TrafficType, u64>,
+    /// Routed packets by decision confidence, in tenths: bucket 0 is [0.0, 0.1)
+    /// and the last bucket includes 1.0
+    pub confidence_histogram: [u64; CONFIDENCE_BUCKETS],
+    pub decisions: Vec<SimulatedDecision>,
+}
+
+impl SimulationReport {
+    /// Mean confidence of the routed packets
+    pub fn average_confidence(&self) -> Option<f32> {
+        if self.decisions.is_empty() {
+            return None;
+        }
+        Some(self.decisions.iter().map(|decision| decision.confidence).sum::<f32>() / self.decisions.len() as f32)
+    }
+}
+
+/// Route every packet as the service would, recording the decisions instead
+/// of sending anything
+pub async fn replay(router: &PacketRouter, performance_monitor: &PerformanceMonitor, packets: &[Vec<u8>]) -> SimulationReport {
+    let mut report = SimulationReport::default();
+
+    for packet in packets {
+        report.packets += 1;
+        performance_monitor.record_packet_received(packet.len()).await;
+
+        if validate_packet(packet).is_err() {
+            report.invalid += 1;
+            report.dropped += 1;
+            performance_monitor.record_packet_dropped(DropReason::InvalidPacket).await;
+            continue;
+        }
+
+        let traffic_type = router.classify(packet).await;
+        *report.traffic_types.entry(traffic_type).or_default() += 1;
+
+        match router.route_packet(packet).await {
+            Ok(decision) => {
+                performance_monitor
+                    .record_packet_forwarded(decision.interface_index, &decision.interface_name, decision.traffic_type, packet.len())
+                    .await;
+                if decision.low_confidence_fallback {
+                    performance_monitor.record_low_confidence_fallback().await;
+                }
+                let tally = report.interfaces.entry(decision.interface_name.clone()).or_default();
+                tally.packets += 1;
+                tally.bytes += packet.len() as u64;
+                *tally.traffic_types.entry(traffic_type).or_default() += 1;
+
+                let bucket = (decision.confidence.clamp(0.0, 1.0) * CONFIDENCE_BUCKETS as f32) as usize;
+                report.confidence_histogram[bucket.min(CONFIDENCE_BUCKETS - 1)] += 1;
+                report.decisions.push(SimulatedDecision {
+                    interface_index: decision.interface_index,
+                    interface_name: decision.interface_name,
+                    traffic_type,
+                    confidence: decision.confidence,
+                    reason: decision.reason,
+                    bytes: packet.len(),
+                });
+            }
+            Err(e) => {
+                if e.is::<RateLimited>() {
+                    report.throttled += 1;
+                    performance_monitor.record_packet_throttled(packet.len()).await;
+                } else {
+                    performance_monitor.record_packet_dropped(DropReason::NoInterface).await;
+                }
+                report.dropped += 1;
+            }
+        }
+    }
+
+    report
+}
+
+/// Replay `source` through a simulated interface configured from `settings`
+/// and report where each packet would have been sent
+pub async fn simulate(source: PacketSource, settings: &Settings) -> Result<SimulationReport> {
+    let vni = VirtualNetworkInterface::new_simulated(source, Arc::new(PerformanceMonitor::new()));
+    vni.apply_settings(settings).await;
+    vni.run().await?;
+    vni.simulation_report()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Simulation produced no report"))
+}
+
+/// Time taken by `route_packet` in one load balancing mode
+#[derive(Debug, Clone, Serialize)]
+pub struct ModeBenchmark {
+    pub mode: LoadBalancingMode,
+    pub decisions: u64,
+    pub elapsed: Duration,
+}
+
+impl ModeBenchmark {
+    pub fn nanos_per_decision(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.decisions.max(1) as f64
+    }
+
+    pub fn decisions_per_second(&self) -> f64 {
+        self.decisions as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Decisions routed before timing starts, so caches and maps are warm
+const BENCHMARK_WARMUP: usize = 1000;
+/// Mock interfaces each get their own 192.168.x.0/24 subnet
+const MAX_BENCHMARK_INTERFACES: usize = 256;
+
+/// The number of mock interfaces `benchmark_router` uses for a requested count
+pub fn benchmark_interface_count(requested: usize) -> usize {
+    requested.clamp(1, MAX_BENCHMARK_INTERFACES)
+}
+
+/// Time `decisions` routing decisions in every load balancing mode across
+/// `interface_count` mock interfaces with seeded metrics. Flow affinity is
+/// off so every packet goes through mode selection instead of the flow table.
+pub async fn benchmark_router(interface_count: usize, decisions: usize) -> Vec<ModeBenchmark> {
+    let interfaces = benchmark_interfaces(benchmark_interface_count(interface_count));
+    let packets = synthetic_packets(DEFAULT_SYNTHETIC_PACKETS);
+    let mut results = Vec::new();
+
+    for mode in LoadBalancingMode::ALL {
+        let mut router = PacketRouter::new(InterfaceManager { interfaces: interfaces.clone() });
+        router.set_load_balancing_mode(mode);
+        router.set_flow_affinity(false);
+        for (i, interface) in interfaces.iter().enumerate() {
+            router.set_interface_weight(interface.index, 1.0 + i as f32).await;
+            // A few samples each, so jitter is non-zero where latencies vary
+            for round in 0..4u64 {
+                let latency = Duration::from_millis(10 + 15 * i as u64 + round * (i as u64 % 3));
+                router.update_interface_metrics(interface.index, latency, 1000 * i as u64, 0.01 * (i % 3) as f32).await;
+            }
+        }
+
+        for packet in packets.iter().cycle().take(BENCHMARK_WARMUP) {
+            let _ = router.route_packet(packet).await;
+        }
+        let start = Instant::now();
+        for packet in packets.iter().cycle().take(decisions) {
+            let _ = std::hint::black_box(router.route_packet(std::hint::black_box(packet)).await);
+        }
+        results.push(ModeBenchmark {
+            mode,
+            decisions: decisions as u64,
+            elapsed: start.elapsed(),
+        });
+    }
+
+    results
+}
+
+/// Up to `MAX_BENCHMARK_INTERFACES` mock interfaces, one per subnet
+fn benchmark_interfaces(count: usize) -> Vec<PhysicalInterface> {
+    (0..=u8::MAX)
+        .take(count)
+        .map(|i| PhysicalInterface {
+            name: format!("bench{}", i),
+            description: "Benchmark interface".to_string(),
+            ip_address: Ipv4Addr::new(192, 168, i, 10),
+            index: i as u32 + 1,
+            link_speed_mbps: Some(100 * (i as u64 + 1)),
+            interface_type: if i % 2 == 0 { InterfaceType::Ethernet } else { InterfaceType::WiFi },
+            prefix_len: 24,
+            gateway: Some(Ipv4Addr::new(192, 168, i, 1)),
+            mtu: 1500,
+            is_metered: false,
+        })
+        .collect()
+}
+
+/// Stand-ins for machines whose physical interfaces can't be discovered, such as CI containers
+pub fn simulated_interfaces() -> Vec<PhysicalInterface> {
+    vec![
+        PhysicalInterface {
+            name: "sim-eth0".to_string(),
+            description: "Simulated Ethernet".to_string(),
+            ip_address: Ipv4Addr::new(192, 168, 1, 10),
+            index: 1,
+            link_speed_mbps: Some(1000),
+            interface_type: InterfaceType::Ethernet,
+            prefix_len: 24,
+            gateway: Some(Ipv4Addr::new(192, 168, 1, 1)),
+            mtu: 1500,
+            is_metered: false,
+        },
+        PhysicalInterface {
+            name: "sim-wifi0".to_string(),
+            description: "Simulated Wi-Fi".to_string(),
+            ip_address: Ipv4Addr::new(192, 168, 2, 10),
+            index: 2,
+            link_speed_mbps: Some(300),
+            interface_type: InterfaceType::WiFi,
+            prefix_len: 24,
+            gateway: Some(Ipv4Addr::new(192, 168, 2, 1)),
+            mtu: 1500,
+            is_metered: false,
+        },
+    ]
+}
+
+/// Flows the synthetic generator cycles through: protocol, destination port and packet size
+const SYNTHETIC_FLOWS: [(u8, u16, usize); 4] = [
+    (17, 3074, 60),    // Game traffic
+    (6, 443, 400),     // Web browsing
+    (6, 443, 1400),    // Video streaming
+    (17, 51820, 1200), // Tunnelled bulk transfer
+];
+
+/// Distinct source ports used per synthetic flow type
+const SYNTHETIC_CONNECTIONS: u16 = 8;
+
+fn synthetic_packets(count: usize) -> Vec<Vec<u8>> {
+    (0..count)
+        .map(|i| {
+            let (protocol, dst_port, size) = SYNTHETIC_FLOWS[i % SYNTHETIC_FLOWS.len()];
+            let connection = (i / SYNTHETIC_FLOWS.len()) as u16 % SYNTHETIC_CONNECTIONS;
+            synthetic_packet(protocol, 40000 + connection, dst_port, size)
+        })
+        .collect()
+}
+
+fn synthetic_packet(protocol: u8, src_port: u16, dst_port: u16, size: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; size];
+    {
+        let mut ip = MutableIpv4Packet::new(&mut buf).expect("buffer holds an IPv4 header");
+        ip.set_version(4);
+        ip.set_header_length(5);
+        ip.set_total_length(size as u16);
+        ip.set_ttl(64);
+        ip.set_next_level_protocol(pnet_packet::ip::IpNextHeaderProtocol(protocol));
+        ip.set_source(Ipv4Addr::new(10, 0, 0, 2));
+        ip.set_destination(Ipv4Addr::new(203, 0, 113, (dst_port % 254) as u8 + 1));
+        let checksum = ipv4::checksum(&ip.to_immutable());
+        ip.set_checksum(checksum);
+    }
+
+    let transport = &mut buf[20..];
+    if protocol == IpNextHeaderProtocols::Tcp.0 {
+        let mut tcp = MutableTcpPacket::new(transport).expect("buffer holds a TCP header");
+        tcp.set_source(src_port);
+        tcp.set_destination(dst_port);
+        tcp.set_data_offset(5);
+    } else {
+        let mut udp = MutableUdpPacket::new(transport).expect("buffer holds a UDP header");
+        udp.set_source(src_port);
+        udp.set_destination(dst_port);
+        udp.set_length((size - 20) as u16);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_batch::PacketBatch;
+    use crate::packet_queue::PacketQueue;
+    use crate::virtual_adapter::{MAX_BATCH_PACKETS, PACKET_QUEUE_CAPACITY};
+
+    #[tokio::test]
+    async fn test_replay_spreads_synthetic_flows() {
+        let mut router = PacketRouter::new(InterfaceManager { interfaces: simulated_interfaces() });
+        router.set_load_balancing_mode(LoadBalancingMode::RoundRobin);
+        let monitor = PerformanceMonitor::new();
+
+        let packets = "synthetic:64".parse::<PacketSource>().unwrap().load().unwrap();
+        let report = replay(&router, &monitor, &packets).await;
+
+        assert_eq!(report.packets, 64);
+        assert_eq!(report.dropped, 0);
+        assert_eq!(report.decisions.len(), 64);
+        // 32 distinct flows alternate between the two interfaces
+        assert_eq!(report.interfaces["sim-eth0"].packets, 32);
+        assert_eq!(report.interfaces["sim-wifi0"].packets, 32);
+        assert_eq!(report.interfaces["sim-eth0"].traffic_types[&TrafficType::Gaming], 8);
+        assert_eq!(report.confidence_histogram.iter().sum::<u64>(), 64);
+        let stats = monitor.get_current_stats().await;
+        assert_eq!(stats.packets_forwarded, 64);
+        assert_eq!(stats.traffic_types.values().map(|by_type| by_type.packets).sum::<u64>(), 64);
+        assert_eq!(
+            stats.traffic_types[&TrafficType::Gaming].packets,
+            report.traffic_types[&TrafficType::Gaming]
+        );
+        // Both links carried traffic, so the effective speed is their sum
+        assert_eq!(stats.aggregate_bandwidth_bps, stats.interfaces.values().map(|iface| iface.current_bps).sum::<u64>());
+        assert!(stats.interfaces.values().all(|iface| iface.current_bps > 0));
+
+        let report = replay(&router, &monitor, &[vec![0u8; 40]]).await;
+        assert_eq!((report.invalid, report.dropped), (1, 1));
+        assert!(report.decisions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_covers_every_mode() {
+        let results = benchmark_router(3, 100).await;
+        let modes: Vec<LoadBalancingMode> = results.iter().map(|result| result.mode).collect();
+        assert_eq!(modes, LoadBalancingMode::ALL);
+        assert!(results.iter().all(|result| result.decisions == 100 && result.nanos_per_decision() > 0.0));
+
+        // Every mock interface gets its own subnet, however many are asked for
+        let interfaces = benchmark_interfaces(benchmark_interface_count(1000));
+        let subnets: std::collections::HashSet<_> = interfaces.iter().map(|iface| iface.ip_address.octets()[2]).collect();
+        assert_eq!((interfaces.len(), subnets.len()), (256, 256));
+        assert_eq!(benchmark_interface_count(0), 1);
+
+    }
+
+    // The TUN reader used to hand each packet to the workers as a freshly
+    // allocated Vec through a channel; it now reads in batches into recycled
+    // buffers through the packet queue. Copying out of synthetic packets
+    // stands in for the device read. Run with
+    // `cargo test --release -- --ignored --nocapture` to compare
+    #[tokio::test]
+    #[ignore]
+    async fn bench_packet_reads() {
+        const PACKETS: usize = 1_000_000;
+        let source = synthetic_packets(DEFAULT_SYNTHETIC_PACKETS);
+        let read_buffer_size = source.iter().map(Vec::len).max().unwrap_or(0);
+        let read = |i: usize, buf: &mut [u8]| {
+            let packet = &source[i % source.len()];
+            buf[..packet.len()].copy_from_slice(packet);
+            packet.len()
+        };
+
+        let start = Instant::now();
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(PACKET_QUEUE_CAPACITY);
+        let consumer = tokio::spawn(async move {
+            while let Some(packet) = rx.recv().await {
+                std::hint::black_box(packet);
+            }
+        });
+        let mut buf = vec![0u8; read_buffer_size];
+        for i in 0..PACKETS {
+            let len = read(i, &mut buf);
+            let _ = tx.send(buf[..len].to_vec()).await;
+        }
+        drop(tx);
+        let _ = consumer.await;
+        let before = start.elapsed();
+
+        let start = Instant::now();
+        let queue = Arc::new(PacketQueue::new(PACKET_QUEUE_CAPACITY, TrafficType::PRIORITY_LEVELS));
+        let consumer = {
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move {
+                while let Some(packet) = queue.pop().await {
+                    queue.recycle(std::hint::black_box(packet));
+                }
+            })
+        };
+        let mut batch = PacketBatch::new(MAX_BATCH_PACKETS, read_buffer_size);
+        let mut read_count = 0;
+        while read_count < PACKETS {
+            batch.clear();
+            while read_count < PACKETS {
+                let Some(slot) = batch.next_slot() else {
+                    break;
+                };
+                let len = read(read_count, slot);
+                batch.commit(len);
+                read_count += 1;
+            }
+            for packet in batch.iter() {
+                queue.push(packet, 0).await;
+            }
+        }
+        queue.close();
+        let _ = consumer.await;
+        let after = start.elapsed();
+
+
+        println!(
+            "Vec per packet {:.1} ns/packet, batched queue {:.1} ns/packet",
+            before.as_nanos() as f64 / PACKETS as f64,
+            after.as_nanos() as f64 / PACKETS as f64
+        );
+    }
+}
@@ -0,0 +1,473 @@
+// src-tauri/src/speed_test.rs
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, SocketAddrV4};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::interface_manager::PhysicalInterface;
+use crate::net_util;
+
+/// A large file on a public speed test server
+pub const DEFAULT_SPEED_TEST_URL: &str = "http://speedtest.tele2.net/100MB.zip";
+
+/// Where the same server accepts uploads
+pub const DEFAULT_UPLOAD_URL: &str = "http://speedtest.tele2.net/upload.php";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a phase of a test runs, whatever the configured duration
+const MAX_TEST_DURATION: Duration = Duration::from_secs(600);
+
+/// Connections opened to measure an interface's latency and loss
+const BENCH_PROBES: u32 = 10;
+
+/// How long each of those may take before it counts as lost
+const BENCH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest the server may take to answer once an upload ends
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedTestConfig {
+    /// Plain HTTP URL of a file large enough to keep every link busy
+    pub url: String,
+    /// Plain HTTP URL that accepts a POST of any size, for interface benchmarks
+    pub upload_url: String,
+    /// How long each phase of the test downloads for
+    pub duration_secs: u64,
+}
+
+impl Default for SpeedTestConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_SPEED_TEST_URL.to_string(),
+            upload_url: DEFAULT_UPLOAD_URL.to_string(),
+            duration_secs: 10,
+        }
+    }
+}
+
+impl SpeedTestConfig {
+    /// How long each phase runs, between one second and ten minutes
+    pub fn duration(&self) -> Duration {
+        clamp_test_duration(Duration::from_secs(self.duration_secs))
+    }
+}
+
+/// `duration` kept to a length a test can run for, and a deadline can be
+/// computed from without overflowing
+fn clamp_test_duration(duration: Duration) -> Duration {
+    duration.clamp(Duration::from_secs(1), MAX_TEST_DURATION)
+}
+
+/// Speed test results for one interface
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceSpeed {
+    pub index: u32,
+    pub name: String,
+    /// Throughput with this interface downloading alone
+    pub single_bps: u64,
+    /// Throughput of this interface's stream while every interface downloads at once
+    pub aggregate_bps: u64,
+    /// TCP connect time to the test server
+    pub latency_ms: Option<f64>,
+    /// Why the interface couldn't download, if it couldn't
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeedTestResult {
+    pub url: String,
+    pub interfaces: Vec<InterfaceSpeed>,
+    /// Combined throughput with every interface downloading in parallel
+    pub aggregate_bps: u64,
+    /// Best throughput of a single interface on its own
+    pub fastest_single_bps: u64,
+}
+
+impl SpeedTestResult {
+    /// Whether downloading over every link beat the fastest link alone
+    pub fn aggregation_helped(&self) -> bool {
+        self.aggregate_bps > self.fastest_single_bps
+    }
+
+    /// Aggregate throughput relative to the fastest single link, e.g. 1.8 for 80% faster
+    pub fn speedup(&self) -> Option<f64> {
+        (self.fastest_single_bps > 0).then(|| self.aggregate_bps as f64 / self.fastest_single_bps as f64)
+    }
+}
+
+/// One interface measured on its own, end to end
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceBenchResult {
+    pub index: u32,
+    pub name: String,
+    /// Sustained download throughput
+    pub download_bps: u64,
+    /// Sustained upload throughput, unless the upload failed
+    pub upload_bps: Option<u64>,
+    /// Why the upload failed, if it did
+    pub upload_error: Option<String>,
+    /// Mean TCP connect time to the test server, unless every attempt failed
+    pub latency_ms: Option<f64>,
+    /// Share of connection attempts that failed, from 0 to 1
+    pub packet_loss: f32,
+}
+
+#[derive(Debug, Clone)]
+struct HttpTarget {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl HttpTarget {
+    fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .with_context(|| format!("Speed test URL must start with http://, got '{}'", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().with_context(|| format!("Invalid port in '{}'", url))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            bail!("Speed test URL '{}' has no host", url);
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    async fn resolve(&self) -> Result<SocketAddrV4> {
+        tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Failed to resolve {}", self.host))?
+            .find_map(|addr| match addr {
+                SocketAddr::V4(addr) => Some(addr),
+                SocketAddr::V6(_) => None,
+            })
+            .with_context(|| format!("{} has no IPv4 address", self.host))
+    }
+}
+
+struct Measurement {
+    bps: u64,
+    latency: Duration,
+}
+
+/// Download through each interface on its own, then through all of them at
+/// once, to show whether combining the links beats the fastest one
+pub async fn run_speed_test(config: &SpeedTestConfig, interfaces: &[PhysicalInterface]) -> Result<SpeedTestResult> {
+    if interfaces.is_empty() {
+        bail!("No interfaces to test");
+    }
+    let target = HttpTarget::parse(&config.url)?;
+    let server = target.resolve().await?;
+    let duration = config.duration();
+
+    let mut results = Vec::with_capacity(interfaces.len());
+    for interface in interfaces {
+        info!("Speed testing {} alone", interface.name);
+        let (single_bps, latency_ms, error) = match download(&target, server, interface, duration).await {
+            Ok(measurement) => (measurement.bps, Some(measurement.latency.as_secs_f64() * 1000.0), None),
+            Err(e) => {
+                warn!("Speed test over {} failed: {:#}", interface.name, e);
+                (0, None, Some(format!("{:#}", e)))
+            }
+        };
+        results.push(InterfaceSpeed {
+            index: interface.index,
+            name: interface.name.clone(),
+            single_bps,
+            aggregate_bps: 0,
+            latency_ms,
+            error,
+        });
+    }
+
+    // Only links that worked alone take part in the combined run
+    info!("Speed testing all interfaces together");
+    let mut streams = Vec::new();
+    for (i, interface) in interfaces.iter().enumerate() {
+        if results[i].error.is_some() {
+            continue;
+        }
+        let (target, interface) = (target.clone(), interface.clone());
+        streams.push((i, tokio::spawn(async move { download(&target, server, &interface, duration).await })));
+    }
+    for (i, stream) in streams {
+        match stream.await {
+            Ok(Ok(measurement)) => results[i].aggregate_bps = measurement.bps,
+            Ok(Err(e)) => warn!("Combined speed test over {} failed: {:#}", results[i].name, e),
+            Err(e) => warn!("Combined speed test over {} panicked: {}", results[i].name, e),
+        }
+    }
+
+    Ok(SpeedTestResult {
+        url: config.url.clone(),
+        aggregate_bps: results.iter().map(|result| result.aggregate_bps).sum(),
+        fastest_single_bps: results.iter().map(|result| result.single_bps).max().unwrap_or(0),
+        interfaces: results,
+    })
+}
+
+/// Measure one interface by itself: latency and loss connecting to the test
+/// server, then a download and an upload of `duration` each, every one
+/// bound to the interface's address so nothing else carries it
+pub async fn run_interface_benchmark(config: &SpeedTestConfig, interface: &PhysicalInterface, duration: Duration) -> Result<InterfaceBenchResult> {
+    let target = HttpTarget::parse(&config.url)?;
+    let server = target.resolve().await?;
+    let duration = clamp_test_duration(duration);
+
+    info!("Benchmarking {}", interface.name);
+    let mut total = Duration::ZERO;
+    let mut successes = 0u32;
+    for _ in 0..BENCH_PROBES {
+        if let Ok(latency) = connect(interface, server, BENCH_PROBE_TIMEOUT).await.map(|(_, latency)| latency) {
+            total += latency;
+            successes += 1;
+        }
+    }
+    let latency_ms = (successes > 0).then(|| (total / successes).as_secs_f64() * 1000.0);
+    let packet_loss = 1.0 - successes as f32 / BENCH_PROBES as f32;
+
+    let download_bps = download(&target, server, interface, duration).await?.bps;
+
+    let (upload_bps, upload_error) = match upload(&config.upload_url, interface, duration).await {
+        Ok(measurement) => (Some(measurement.bps), None),
+        Err(e) => {
+            warn!("Upload benchmark over {} failed: {:#}", interface.name, e);
+            (None, Some(format!("{:#}", e)))
+        }
+    };
+
+    Ok(InterfaceBenchResult {
+        index: interface.index,
+        name: interface.name.clone(),
+        download_bps,
+        upload_bps,
+        upload_error,
+        latency_ms,
+        packet_loss,
+    })
+}
+
+/// Open a TCP connection to `server` that can only leave through
+/// `interface`, returning it with the time the handshake took
+async fn connect(interface: &PhysicalInterface, server: SocketAddrV4, timeout: Duration) -> Result<(TcpStream, Duration)> {
+    let socket = net_util::tcp_socket(interface)?;
+    let started = Instant::now();
+    let stream = tokio::time::timeout(timeout, socket.connect(server.into()))
+        .await
+        .with_context(|| format!("Timed out connecting to {}", server))?
+        .with_context(|| format!("Failed to connect to {}", server))?;
+    Ok((stream, started.elapsed()))
+}
+
+/// Download from `server` through `interface` until `duration` passes or the
+/// server closes the connection
+async fn download(target: &HttpTarget, server: SocketAddrV4, interface: &PhysicalInterface, duration: Duration) -> Result<Measurement> {
+    let (mut stream, latency) = connect(interface, server, CONNECT_TIMEOUT).await?;
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: netboost-pro\r\nConnection: close\r\n\r\n",
+        target.path, target.host
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut buf = vec![0u8; 64 * 1024];
+    let started = Instant::now();
+    let deadline = started + duration;
+    let mut bytes = 0u64;
+    loop {
+        let len = match tokio::time::timeout_at(deadline, stream.read(&mut buf)).await {
+            Err(_) => break,
+            Ok(result) => result.context("Download failed")?,
+        };
+        if len == 0 {
+            break;
+        }
+        if bytes == 0 {
+            check_status(&buf[..len])?;
+        }
+        bytes += len as u64;
+    }
+
+    let elapsed = started.elapsed().as_secs_f64().max(0.001);
+    Ok(Measurement {
+        bps: (bytes as f64 * 8.0 / elapsed) as u64,
+        latency,
+    })
+}
+
+/// POST to `url` through `interface` for `duration`. The body is chunked
+/// so it can end whenever time is up, and only counts once the server
+/// answers it with a 2xx status.
+async fn upload(url: &str, interface: &PhysicalInterface, duration: Duration) -> Result<Measurement> {
+    let target = HttpTarget::parse(url)?;
+    let server = target.resolve().await?;
+    let (mut stream, latency) = connect(interface, server, CONNECT_TIMEOUT).await?;
+
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: netboost-pro\r\nContent-Type: application/octet-stream\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        target.path, target.host
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let payload_len = 64 * 1024;
+    let mut chunk = format!("{:x}\r\n", payload_len).into_bytes();
+    chunk.resize(chunk.len() + payload_len, 0);
+    chunk.extend_from_slice(b"\r\n");
+
+    let started = Instant::now();
+    let deadline = started + duration;
+    let mut bytes = 0u64;
+    while Instant::now() < deadline {
+        // A chunk can't be cut short, so a stalled link fails the upload
+        // rather than waiting on the deadline
+        tokio::time::timeout(RESPONSE_TIMEOUT, stream.write_all(&chunk))
+            .await
+            .context("Upload stalled")?
+            .context("Upload failed")?;
+        bytes += payload_len as u64;
+    }
+    let elapsed = started.elapsed().as_secs_f64().max(0.001);
+
+    stream.write_all(b"0\r\n\r\n").await.context("Upload failed")?;
+    let mut response = [0u8; 1024];
+    let len = tokio::time::timeout(RESPONSE_TIMEOUT, stream.read(&mut response))
+        .await
+        .context("The server didn't answer the upload")?
+        .context("Upload failed")?;
+    check_status(&response[..len])?;
+
+    Ok(Measurement {
+        bps: (bytes as f64 * 8.0 / elapsed) as u64,
+        latency,
+    })
+}
+
+/// Fail unless the response starts with a 2xx status line
+fn check_status(response: &[u8]) -> Result<()> {
+    let line = response.split(|byte| *byte == b'\r').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(line);
+    match line.split_whitespace().nth(1) {
+        Some(status) if line.starts_with("HTTP/") && status.starts_with('2') => Ok(()),
+        _ => bail!("Server responded '{}'", line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface_manager::InterfaceType;
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    fn loopback() -> PhysicalInterface {
+        PhysicalInterface {
+            name: "lo".to_string(),
+            description: "Loopback".to_string(),
+            ip_address: Ipv4Addr::LOCALHOST,
+            index: 1,
+            link_speed_mbps: None,
+            interface_type: InterfaceType::Virtual,
+            prefix_len: 8,
+            gateway: None,
+            mtu: 1500,
+            is_metered: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speed_test_against_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let _ = stream.read(&mut request).await;
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await;
+                    let _ = stream.write_all(&[0u8; 256 * 1024]).await;
+                });
+            }
+        });
+
+        let config = SpeedTestConfig {
+            url: format!("http://127.0.0.1:{}/file", port),
+            duration_secs: 5,
+            ..Default::default()
+        };
+
+        let result = run_speed_test(&config, &[loopback()]).await.unwrap();
+        assert!(result.interfaces[0].error.is_none());
+        assert!(result.interfaces[0].single_bps > 0);
+        assert!(result.interfaces[0].latency_ms.is_some());
+        assert_eq!(result.aggregate_bps, result.interfaces[0].aggregate_bps);
+        assert!(result.speedup().is_some());
+
+        assert!(HttpTarget::parse("https://example.com/").is_err());
+        assert!(check_status(b"HTTP/1.1 404 Not Found\r\n").is_err());
+
+        let config = SpeedTestConfig { duration_secs: u64::MAX, ..Default::default() };
+        assert_eq!(config.duration(), MAX_TEST_DURATION);
+    }
+
+    #[tokio::test]
+    async fn test_interface_benchmark_against_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 64 * 1024];
+                    let Ok(n) = stream.read(&mut buf).await else {
+                        return;
+                    };
+                    if buf[..n].starts_with(b"POST") {
+                        // Take in the chunked upload, then answer it
+                        let mut tail = buf[..n].to_vec();
+                        while !tail.ends_with(b"\r\n0\r\n\r\n") {
+                            let Ok(n) = stream.read(&mut buf).await else {
+                                return;
+                            };
+                            if n == 0 {
+                                return;
+                            }
+                            tail.drain(..tail.len().saturating_sub(8));
+                            tail.extend_from_slice(&buf[..n]);
+                        }
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await;
+                    } else if n > 0 {
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await;
+                        let _ = stream.write_all(&[0u8; 256 * 1024]).await;
+                    }
+                });
+            }
+        });
+
+        let config = SpeedTestConfig {
+            url: format!("http://127.0.0.1:{}/file", port),
+            upload_url: format!("http://127.0.0.1:{}/upload", port),
+            duration_secs: 1,
+        };
+        let result = run_interface_benchmark(&config, &loopback(), Duration::from_secs(1)).await.unwrap();
+        assert_eq!(result.name, "lo");
+        assert!(result.download_bps > 0);
+        assert!(result.upload_error.is_none());
+        assert!(result.upload_bps.is_some_and(|bps| bps > 0));
+        assert!(result.latency_ms.is_some());
+        assert_eq!(result.packet_loss, 0.0);
+    }
+}
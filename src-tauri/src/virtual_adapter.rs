@@ -1,34 +1,238 @@
 // src-tauri/src/virtual_adapter.rs
 use anyhow::{Context, Result};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use tokio::time::{Duration, interval};
+use tokio::sync::{broadcast, mpsc, watch, Notify, RwLock};
+use tokio::time::{Duration, Instant, interval, interval_at};
 
-use crate::interface_manager::InterfaceManager;
-use crate::packet_router::{PacketRouter, LoadBalancingMode};
-use crate::performance_monitor::PerformanceMonitor;
+use crate::arp::ArpCache;
+use crate::cidr::Ipv4Cidr;
+use crate::classifier::ClassificationRule;
+use crate::datalink::{self, DatalinkSenders, LinkLayer};
+use crate::dns::{DnsCache, DnsSettings};
+use crate::reassembly::{Reassembled, Reassembler};
+use crate::flow_log::{FlowLog, FlowLogSettings};
+use crate::health::{self, HealthStatus};
+use crate::interface_filter::InterfaceFilter;
+use crate::interface_manager::{InterfaceEvent, InterfaceManager, PhysicalInterface};
+use crate::mtu;
+use crate::nat::{self, NatTable};
+use crate::packet_batch::PacketBatch;
+use crate::packet_queue::{Enqueued, PacketQueue};
+use crate::packet_sampler::{PacketSampler, SamplerSettings};
+use crate::packet_router::{validate_packet, FlowKey, InterfaceGroup, PacketRouter, LoadBalancingMode, RateLimited, RoutingDecision, TrafficType};
+use crate::performance_monitor::{DropReason, PerformanceMonitor, PerformanceStats, DEFAULT_HISTORY_RESOLUTION};
+use crate::qos::{self, DscpConfig, SchedulerConfig};
+use crate::relay::{self, RelayConfig, RelayTunnel};
+use crate::routes::RouteGuard;
+use crate::kill_switch;
+use crate::settings::Settings;
+use crate::simulation::{self, PacketSource, SimulationReport};
 use pnet_datalink::{self, Channel};
-use std::net::Ipv4Addr;
+use pnet_packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet_packet::ipv4::Ipv4Packet;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use tracing::{debug, error, info, info_span, trace, warn, Instrument};
 use tun::{DeviceBuilder, AsyncDevice};
 
+/// How often performance stats are sampled and published
+const DEFAULT_MONITORING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest monitoring interval accepted, in seconds
+pub(crate) const MAX_MONITORING_INTERVAL_SECS: u64 = 3600;
+
+/// How long a flow may stay idle before it is dropped from the flow table
+const FLOW_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where interfaces are probed when the configured target isn't an IPv4 address
+pub(crate) const DEFAULT_PROBE_TARGET: Ipv4Addr = Ipv4Addr::new(1, 1, 1, 1);
+
+/// Pause between rounds of warmup probes
+const WARMUP_PROBE_GAP: Duration = Duration::from_millis(200);
+
+/// How often interfaces are re-discovered to pick up hotplugged adapters
+const INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/// Room reserved past the MTU in each TUN read buffer. Some platforms put a
+/// 4-byte packet information header (flags and protocol) in front of every
+/// packet, and a read that fills the whole buffer can't be told apart from a
+/// truncated one, so the buffer must be larger than any packet we expect.
+const TUN_READ_OVERHEAD: usize = 4;
+
+/// Times TUN creation is attempted before giving up
+const TUN_CREATE_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry of TUN creation, doubled after each failure
+const TUN_CREATE_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Most packets read from the TUN and handed to the processing loop at once
+pub(crate) const MAX_BATCH_PACKETS: usize = 32;
+
+/// Packets that may wait between the TUN reader and the processing loop
+pub(crate) const PACKET_QUEUE_CAPACITY: usize = 1000;
+
+/// Packets handed to each worker ahead of the one it is processing. Kept
+/// short so that most packets wait in the priority queue instead.
+const WORKER_QUEUE_LEN: usize = 16;
+
+/// How long `stop` lets the processing loop flush queued packets, and then
+/// how long it waits for the loop to exit after abandoning the rest
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What happened to the packets still queued when the interface was stopped
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+pub struct ShutdownReport {
+    /// Routed and sent after the stop was requested
+    pub drained: u64,
+    /// Abandoned because draining took too long
+    pub dropped: u64,
+}
+
+/// Configuration for the virtual TUN interface
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TunConfig {
+    pub address: Ipv4Addr,
+    pub netmask_prefix: u8,
+    pub mtu: u16,
+    pub name: String,
+}
+
+impl Default for TunConfig {
+    fn default() -> Self {
+        Self {
+            address: Ipv4Addr::new(10, 0, 0, 1),
+            netmask_prefix: 24,
+            mtu: 1500,
+            name: "NetBoost-TUN".to_string(),
+        }
+    }
+}
+
+impl TunConfig {
+    /// Check that the configuration can be applied to a TUN device
+    pub fn validate(&self) -> Result<()> {
+        if self.netmask_prefix == 0 || self.netmask_prefix > 32 {
+            return Err(anyhow::anyhow!("Invalid netmask prefix /{}", self.netmask_prefix));
+        }
+        // 576 is the minimum datagram size every IPv4 host must accept
+        if self.mtu < 576 {
+            return Err(anyhow::anyhow!("MTU {} is below the IPv4 minimum of 576", self.mtu));
+        }
+        if self.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("TUN interface name must not be empty"));
+        }
+        Ok(())
+    }
+
+    /// Size of the buffer each packet is read from the TUN device into
+    pub fn read_buffer_size(&self) -> usize {
+        self.mtu as usize + TUN_READ_OVERHEAD
+    }
+}
+
+/// What the forwarding path needs to put a routed packet on the wire
+struct Egress<'a> {
+    /// Where ICMP errors and cached DNS answers for the sender are written
+    device: &'a AsyncDevice,
+    dns_cache: &'a DnsCache,
+    arp_cache: &'a Arc<ArpCache>,
+    nat_table: &'a NatTable,
+    datalink_senders: &'a DatalinkSenders,
+    relay: &'a RwLock<Option<Relay>>,
+}
+
+/// An active relay tunnel and the task writing its replies into the TUN
+struct Relay {
+    tunnel: Arc<RelayTunnel>,
+    writer: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.writer.abort();
+    }
+}
+
+/// The task recording performance history. It stops when dropped, so a quick
+/// restart can't leave the previous run's sampler going alongside the new one.
+struct HistorySampler(tokio::task::JoinHandle<()>);
+
+impl Drop for HistorySampler {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 struct TunInterface {
     device: Arc<AsyncDevice>,
+    config: TunConfig,
 }
 
 impl TunInterface {
-    async fn new(name: &str) -> Result<Self> {
-        let address: Ipv4Addr = "10.0.0.1".parse()?;
-        let dev = DeviceBuilder::new()
-            .name(name.to_string())
-            .ipv4(address, 24, None)
-            .build_async()?;
+    /// Create the TUN device, retrying with exponential backoff since drivers
+    /// are sometimes not ready yet at boot. If the name is taken, e.g. by an
+    /// adapter left behind by an unclean shutdown, `<name>-1`, `<name>-2`...
+    /// are tried instead.
+    async fn new(config: &TunConfig) -> Result<Self> {
+        #[cfg(windows)]
+        let wintun_dll = wintun::locate_dll()?.to_string_lossy().into_owned();
 
-        println!("Created TUN interface: {}", dev.name()?);
+        let base_name = config.name.clone();
+        let mut config = config.clone();
+        let mut backoff = TUN_CREATE_BACKOFF;
+        let mut suffix = 0;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let builder = DeviceBuilder::new()
+                .name(config.name.clone())
+                .ipv4(config.address, config.netmask_prefix, None)
+                .mtu(config.mtu);
 
-        Ok(Self {
-            device: Arc::new(dev),
-        })
+            // On Windows the device is a Wintun adapter. tun-rs drives Wintun's
+            // send and receive rings behind the same async recv/send API, so only
+            // loading the driver needs platform-specific handling.
+            #[cfg(windows)]
+            let builder = builder.wintun_file(wintun_dll.clone());
+
+            let error = match builder.build_async() {
+                Ok(dev) => {
+                    info!(name = %dev.name()?, address = %config.address, prefix = config.netmask_prefix, "Created TUN interface");
+                    return Ok(Self {
+                        device: Arc::new(dev),
+                        config,
+                    });
+                }
+                Err(e) => e,
+            };
+
+            if attempt == TUN_CREATE_ATTEMPTS || error.kind() == std::io::ErrorKind::PermissionDenied {
+                #[cfg(windows)]
+                let error = wintun::explain_error(error);
+                #[cfg(not(windows))]
+                let error = anyhow::Error::from(error);
+                return Err(error.context(format!("Could not create TUN interface '{}' (attempt {} of {})", config.name, attempt, TUN_CREATE_ATTEMPTS)));
+            }
+
+            if matches!(
+                error.kind(),
+                std::io::ErrorKind::AlreadyExists | std::io::ErrorKind::AddrInUse | std::io::ErrorKind::ResourceBusy
+            ) {
+                suffix += 1;
+                let name = format!("{}-{}", base_name, suffix);
+                warn!("TUN interface name '{}' is in use ({}); trying '{}'", config.name, error, name);
+                config.name = name;
+            } else {
+                warn!("Failed to create TUN interface '{}': {}; retrying in {:?}", config.name, error, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
     }
 
     fn name(&self) -> Result<String> {
@@ -36,168 +240,944 @@ impl TunInterface {
     }
 }
 
+/// Whether the OS can create TUN devices: Windows needs the Wintun DLL and
+/// Linux the tun module, while other Unixes have utun built in
+pub fn tun_driver_available() -> bool {
+    #[cfg(windows)]
+    return wintun::locate_dll().is_ok();
+    #[cfg(target_os = "linux")]
+    return std::path::Path::new("/dev/net/tun").exists();
+    #[cfg(all(unix, not(target_os = "linux")))]
+    return true;
+}
+
+/// The worker a packet goes to: always the same one for a flow, and the
+/// first for packets that aren't part of one
+fn worker_for(packet_data: &[u8], workers: usize) -> usize {
+    FlowKey::from_packet(packet_data).map_or(0, |flow| flow.shard(workers))
+}
+
+#[cfg(windows)]
+mod wintun {
+    use anyhow::{anyhow, Result};
+    use std::path::PathBuf;
+
+    const DLL_NAME: &str = "wintun.dll";
+    const DOWNLOAD_URL: &str = "https://www.wintun.net/";
+
+    /// Find `wintun.dll` next to the executable, in the working directory or
+    /// in System32, the places Windows loads it from
+    pub fn locate_dll() -> Result<PathBuf> {
+        let mut candidates = Vec::new();
+        if let Some(dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from)) {
+            candidates.push(dir.join(DLL_NAME));
+        }
+        if let Ok(dir) = std::env::current_dir() {
+            candidates.push(dir.join(DLL_NAME));
+        }
+        if let Some(windir) = std::env::var_os("SystemRoot") {
+            candidates.push(PathBuf::from(windir).join("System32").join(DLL_NAME));
+        }
+
+        candidates.into_iter().find(|path| path.is_file()).ok_or_else(|| {
+            anyhow!(
+                "The Wintun driver ({}) was not found. Download it from {} and copy the {} for your architecture next to the NetBoost Pro executable.",
+                DLL_NAME,
+                DOWNLOAD_URL,
+                DLL_NAME
+            )
+        })
+    }
+
+    /// Turn the common adapter creation failures into actionable messages
+    pub fn explain_error(error: std::io::Error) -> anyhow::Error {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                anyhow!("Creating a Wintun adapter requires administrator rights. Run NetBoost Pro as Administrator. ({})", error)
+            }
+            _ => anyhow!(
+                "Failed to create the Wintun adapter: {}. Make sure {} matches your architecture (see {}).",
+                error,
+                DLL_NAME,
+                DOWNLOAD_URL
+            ),
+        }
+    }
+}
+
+/// Where packets come from: a real TUN device, or a workload replayed
+/// through the router without touching the network
+enum Backend {
+    Tun(TunInterface),
+    Simulated(PacketSource),
+}
+
 pub struct VirtualNetworkInterface {
-    tun_interface: TunInterface,
+    backend: Backend,
     packet_router: Arc<RwLock<PacketRouter>>,
     performance_monitor: Arc<PerformanceMonitor>,
+    arp_cache: Arc<ArpCache>,
+    nat_table: Arc<NatTable>,
+    datalink_senders: Arc<DatalinkSenders>,
+    /// Set while traffic is bonded through a relay server
+    relay: Arc<RwLock<Option<Relay>>>,
+    /// Copies routed packets out for analysis while sampling is on
+    packet_sampler: Arc<RwLock<Option<PacketSampler>>>,
+    dns_cache: Arc<DnsCache>,
+    reassembler: Arc<Reassembler>,
+    dscp_config: Arc<RwLock<DscpConfig>>,
+    /// Between the TUN reader and the processing loop; latency-sensitive
+    /// packets are served first, or by weight under fair queueing
+    packet_queue: Arc<PacketQueue>,
     is_running: Arc<tokio::sync::RwLock<bool>>,
+    /// Set by `stop` to end the TUN reader while the queue drains
+    shutdown_tx: Arc<watch::Sender<bool>>,
+    /// Set once `run` has put the routing table back and returned
+    stopped_tx: watch::Sender<bool>,
+    /// Published by the processing loop once it has exited
+    drain_tx: Arc<watch::Sender<Option<ShutdownReport>>>,
+    /// The monitoring loop restarts its timer whenever this changes
+    monitoring_interval: Arc<watch::Sender<Duration>>,
+    probe_target: Arc<RwLock<Ipv4Addr>>,
+    /// How long interfaces are probed before traffic is served
+    warmup: RwLock<Duration>,
+    /// Packet processing tasks run by the next `start`
+    packet_workers: RwLock<usize>,
+    /// Whether `run` routes the host's traffic into the TUN
+    manage_routes: RwLock<bool>,
+    /// Whether `run` blocks traffic that doesn't go through the TUN
+    kill_switch: RwLock<bool>,
+    /// Time between performance history samples
+    history_resolution: Arc<RwLock<Duration>>,
+    stats_tx: broadcast::Sender<PerformanceStats>,
+    interface_events_tx: broadcast::Sender<InterfaceEvent>,
+    /// Wakes the interface watcher to rediscover without waiting for its timer
+    rediscover: Arc<Notify>,
+    simulation_report: RwLock<Option<SimulationReport>>,
 }
 
 impl VirtualNetworkInterface {
-    pub async fn new() -> Result<Self> {
-        println!("Creating virtual network interface...");
+    pub async fn new(config: TunConfig, performance_monitor: Arc<PerformanceMonitor>) -> Result<Self> {
+        debug!("Creating virtual network interface");
+
+        config.validate().context("Invalid TUN configuration")?;
         
         // Create TUN interface
-        let tun = TunInterface::new("NetBoost-TUN")
+        let tun = TunInterface::new(&config)
             .await
             .context("Failed to create TUN interface")?;
 
-        println!("Virtual network interface '{}' created.", tun.name()?);
+        let tun_name = tun.name()?;
+        info!("Virtual network interface '{}' created", tun_name);
 
         // Initialize interface manager
         let interface_manager = InterfaceManager::new()
             .context("Failed to initialize interface manager")?;
 
+        let vni = Self::with_backend(Backend::Tun(tun), interface_manager, performance_monitor);
+        // Discovery ran after the TUN came up, so it is among the interfaces
+        vni.packet_router.write().await.exclude_tun(tun_name, config.address);
+        Ok(vni)
+    }
+
+    /// An interface that routes packets from `source` and records where they
+    /// would go, without creating a TUN device or sending anything. Uses the
+    /// discovered interfaces, or simulated ones if none are found.
+    pub fn new_simulated(source: PacketSource, performance_monitor: Arc<PerformanceMonitor>) -> Self {
+        let mut interfaces = InterfaceManager::discover();
+        if interfaces.is_empty() {
+            interfaces = simulation::simulated_interfaces();
+        }
+        info!("Simulating {:?} across {} interfaces", source, interfaces.len());
+
+        Self::with_backend(Backend::Simulated(source), InterfaceManager { interfaces }, performance_monitor)
+    }
+
+    fn with_backend(backend: Backend, interface_manager: InterfaceManager, performance_monitor: Arc<PerformanceMonitor>) -> Self {
         // Create packet router
         let packet_router = Arc::new(RwLock::new(PacketRouter::new(interface_manager)));
 
-        // Create performance monitor
-        let performance_monitor = Arc::new(PerformanceMonitor::new());
+        // Subscribers only care about the latest snapshots
+        let (stats_tx, _) = broadcast::channel(16);
+        let (interface_events_tx, _) = broadcast::channel(16);
 
-        Ok(Self {
-            tun_interface: tun,
+        Self {
+            backend,
             packet_router,
             performance_monitor,
+            arp_cache: Arc::new(ArpCache::new()),
+            nat_table: Arc::new(NatTable::new()),
+            datalink_senders: Arc::new(DatalinkSenders::new()),
+            relay: Arc::new(RwLock::new(None)),
+            packet_sampler: Arc::new(RwLock::new(None)),
+            dns_cache: Arc::new(DnsCache::new()),
+            reassembler: Arc::new(Reassembler::default()),
+            dscp_config: Arc::new(RwLock::new(DscpConfig::default())),
+            packet_queue: Arc::new(PacketQueue::new(PACKET_QUEUE_CAPACITY, TrafficType::PRIORITY_LEVELS)),
             is_running: Arc::new(tokio::sync::RwLock::new(false)),
-        })
+            shutdown_tx: Arc::new(watch::channel(false).0),
+            stopped_tx: watch::channel(true).0,
+            drain_tx: Arc::new(watch::channel(None).0),
+            monitoring_interval: Arc::new(watch::channel(DEFAULT_MONITORING_INTERVAL).0),
+            probe_target: Arc::new(RwLock::new(DEFAULT_PROBE_TARGET)),
+            warmup: RwLock::new(Duration::ZERO),
+            packet_workers: RwLock::new(1),
+            manage_routes: RwLock::new(true),
+            kill_switch: RwLock::new(false),
+            history_resolution: Arc::new(RwLock::new(DEFAULT_HISTORY_RESOLUTION)),
+            stats_tx,
+            interface_events_tx,
+            rediscover: Arc::new(Notify::new()),
+            simulation_report: RwLock::new(None),
+        }
     }
 
-    pub async fn run(mut self) -> Result<()> {
-        println!("Starting NetBoost Pro virtual network interface...");
-        
+    pub async fn run(&self) -> Result<()> {
+        info!("Starting NetBoost Pro virtual network interface");
+
+        // Measure every link first so the first packets aren't routed on defaults
+        if matches!(self.backend, Backend::Tun(_)) {
+            self.warm_up().await;
+        }
+
         // Set running state
         *self.is_running.write().await = true;
+        self.shutdown_tx.send_replace(false);
+        self.drain_tx.send_replace(None);
+        self.stopped_tx.send_replace(false);
+
+        // Session stats start from zero; the monitor's lifetime counters keep accumulating
+        self.performance_monitor.reset_stats().await;
+
+        if let Backend::Simulated(source) = &self.backend {
+            let result = self.run_simulation(source).await;
+            *self.is_running.write().await = false;
+            return result;
+        }
 
         // Start performance monitoring
         let monitor_handle = self.start_performance_monitoring().await;
+        let _history_sampler = self.start_history_sampling();
 
-        // Start packet processing
-        let packet_handle = self.start_packet_processing().await?;
+        // Block everything but the TUN before routing into it, so there is
+        // no moment traffic could leak
+        let kill_switch_on = match &self.backend {
+            Backend::Tun(tun) if *self.kill_switch.read().await => {
+                let name = tun.name()?;
+                tokio::task::spawn_blocking(move || kill_switch::enable(&name)).await??;
+                true
+            }
+            _ => false,
+        };
+
+        // Replies to translated flows are for the interface readers; the
+        // host would answer them with resets
+        let host_replies_blocked = match &self.backend {
+            Backend::Tun(tun) => {
+                let name = tun.name()?;
+                match tokio::task::spawn_blocking(move || nat::block_host_replies(&name)).await? {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!("{:#}; the host may reset connections routed through NetBoost Pro", e);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+
+        let started = async {
+            // Send the host's traffic into the TUN. The routes come out
+            // again when `run` ends, or when the guard drops on a panic.
+            let routes = match &self.backend {
+                Backend::Tun(tun) if *self.manage_routes.read().await => {
+                    let name = tun.name()?;
+                    Some(tokio::task::spawn_blocking(move || RouteGuard::install(&name)).await??)
+                }
+                _ => None,
+            };
+            let packet_handle = self.start_packet_processing().await?;
+            anyhow::Ok((routes, packet_handle))
+        }
+        .await;
+        let (routes, packet_handle) = match started {
+            Ok(started) => started,
+            Err(e) => {
+                // Nothing was routed yet, so there is nothing to protect
+                if kill_switch_on {
+                    Self::disable_kill_switch().await;
+                }
+                if host_replies_blocked {
+                    Self::unblock_host_replies().await;
+                }
+                return Err(e);
+            }
+        };
 
         // Wait for shutdown signal or error
         tokio::select! {
             result = packet_handle => {
-                println!("Packet processing ended: {:?}", result);
+                info!(?result, "Packet processing ended");
             }
             _ = monitor_handle => {
-                println!("Performance monitoring ended");
+                info!("Performance monitoring ended");
             }
         }
 
         // Clean shutdown
+        if let Some(routes) = routes {
+            Self::restore_routes(routes).await;
+        }
+        if host_replies_blocked {
+            Self::unblock_host_replies().await;
+        }
+        if kill_switch_on {
+            if *self.shutdown_tx.borrow() {
+                Self::disable_kill_switch().await;
+            } else {
+                warn!("Packet processing stopped unexpectedly; the kill-switch stays on, blocking traffic, until it is disabled");
+            }
+        }
         *self.is_running.write().await = false;
-        println!("NetBoost Pro virtual interface stopped.");
+        self.stopped_tx.send_replace(true);
+        info!("NetBoost Pro virtual interface stopped");
         
         Ok(())
     }
 
-    async fn start_packet_processing(&mut self) -> Result<tokio::task::JoinHandle<Result<()>>> {
+    async fn restore_routes(mut routes: RouteGuard) {
+        match tokio::task::spawn_blocking(move || routes.restore()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("{:#}", e),
+            Err(e) => error!("Failed to restore the routing table: {}", e),
+        }
+    }
+
+    async fn unblock_host_replies() {
+        match tokio::task::spawn_blocking(nat::unblock_host_replies).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("{:#}", e),
+            Err(e) => error!("Failed to remove the NAT's rules: {}", e),
+        }
+    }
+
+    async fn disable_kill_switch() {
+        match tokio::task::spawn_blocking(kill_switch::disable).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("{:#}; traffic stays blocked until it is disabled", e),
+            Err(e) => error!("Failed to disable the kill-switch: {}", e),
+        }
+    }
+
+    /// Probe every interface for the warmup period and log what was measured
+    async fn warm_up(&self) {
+        let duration = *self.warmup.read().await;
+        if duration.is_zero() {
+            return;
+        }
+        let target = *self.probe_target.read().await;
+        info!("Probing interfaces for {:?} before serving traffic", duration);
+
+        let deadline = Instant::now() + duration;
+        loop {
+            Self::probe_interfaces(&self.packet_router, &self.performance_monitor, target).await;
+            if Instant::now() + WARMUP_PROBE_GAP >= deadline {
+                break;
+            }
+            tokio::time::sleep(WARMUP_PROBE_GAP).await;
+        }
+
+        let router = self.packet_router.read().await;
+        let health = router.get_interface_health().await;
+        for interface in router.get_all_interfaces() {
+            let Some(status) = health.get(&interface.index) else {
+                continue;
+            };
+            match status.last_latency_ms {
+                Some(latency) => info!(
+                    "Interface '{}': {:.0} ms ± {:.1} ms, {:.0}% loss{}",
+                    interface.name,
+                    latency,
+                    status.jitter_ms.unwrap_or(0.0),
+                    status.last_packet_loss * 100.0,
+                    if status.healthy { "" } else { ", unhealthy" }
+                ),
+                None => warn!("Interface '{}' did not respond to warmup probes", interface.name),
+            }
+        }
+    }
+
+    /// Replay the simulated workload through the router once
+    async fn run_simulation(&self, source: &PacketSource) -> Result<()> {
+        let packets = source.load().context("Failed to load simulated packets")?;
+        info!("Replaying {} packets", packets.len());
+
+        let report = simulation::replay(&*self.packet_router.read().await, &self.performance_monitor, &packets).await;
+        info!(
+            "Simulation finished: {} packets, {} dropped across {} interfaces",
+            report.packets,
+            report.dropped,
+            report.interfaces.len()
+        );
+
+        *self.simulation_report.write().await = Some(report);
+        let _ = self.stats_tx.send(self.performance_monitor.get_current_stats().await);
+        Ok(())
+    }
+
+    /// Routing decisions of the last simulation run; `None` for a real TUN
+    pub async fn simulation_report(&self) -> Option<SimulationReport> {
+        self.simulation_report.read().await.clone()
+    }
+
+    fn tun(&self) -> Result<&TunInterface> {
+        match &self.backend {
+            Backend::Tun(tun) => Ok(tun),
+            Backend::Simulated(_) => Err(anyhow::anyhow!("A simulated interface has no TUN device")),
+        }
+    }
+
+    fn tun_device(&self) -> Result<Arc<AsyncDevice>> {
+        self.tun().map(|tun| Arc::clone(&tun.device))
+    }
+
+    async fn start_packet_processing(&self) -> Result<tokio::task::JoinHandle<Result<()>>> {
         let packet_router = Arc::clone(&self.packet_router);
         let performance_monitor = Arc::clone(&self.performance_monitor);
+        let arp_cache = Arc::clone(&self.arp_cache);
+        let nat_table = Arc::clone(&self.nat_table);
+        let datalink_senders = Arc::clone(&self.datalink_senders);
+        let relay = Arc::clone(&self.relay);
+        let packet_sampler = Arc::clone(&self.packet_sampler);
+        let dns_cache = Arc::clone(&self.dns_cache);
+        let reassembler = Arc::clone(&self.reassembler);
+        let dscp_config = Arc::clone(&self.dscp_config);
         let is_running = Arc::clone(&self.is_running);
+        let shutdown_rx = self.shutdown_tx.subscribe();
+        let drain_tx = Arc::clone(&self.drain_tx);
+        let queue = Arc::clone(&self.packet_queue);
+
+        let device = self.tun_device()?;
+
+        // Open the send channels up front; any that fail are retried on first use
+        for interface in self.packet_router.read().await.get_all_interfaces() {
+            if let Err(e) = self.datalink_senders.open(interface.index) {
+                warn!("Failed to open a send channel on '{}': {:#}", interface.name, e);
+            }
+        }
 
-        // Create channels for packet processing
-        let (packet_tx, mut packet_rx) = mpsc::channel::<Vec<u8>>(1000);
-        
         // Spawn packet reader task
-        let _reader_handle = self.spawn_packet_reader(packet_tx).await?;
+        let _reader_handle = self.spawn_packet_reader(Arc::clone(&queue), Arc::clone(&device), &self.tun()?.config).await?;
+
+        // Spawn the return path from the physical interfaces back into the TUN,
+        // fed by readers the interface watcher starts and stops
+        let (return_tx, return_rx) = mpsc::channel::<(u32, Vec<u8>)>(1000);
+        let _return_handle = self.spawn_return_path(return_rx, Arc::clone(&device));
+        let _watch_handle = self.watch_interfaces(return_tx).await;
 
-        // Main packet processing task
+        // Workers process packets concurrently. Each flow always goes to the
+        // same worker, so its packets still leave in the order they came.
+        let worker_count = (*self.packet_workers.read().await).max(1);
+        let mut workers = Vec::with_capacity(worker_count);
+        let mut worker_handles = Vec::with_capacity(worker_count);
+        for worker in 0..worker_count {
+            let (worker_tx, mut worker_rx) = mpsc::channel::<Vec<u8>>(WORKER_QUEUE_LEN);
+            workers.push(worker_tx);
+            let device = Arc::clone(&device);
+            let packet_router = Arc::clone(&packet_router);
+            let performance_monitor = Arc::clone(&performance_monitor);
+            let arp_cache = Arc::clone(&arp_cache);
+            let nat_table = Arc::clone(&nat_table);
+            let datalink_senders = Arc::clone(&datalink_senders);
+            let relay = Arc::clone(&relay);
+            let packet_sampler = Arc::clone(&packet_sampler);
+            let dns_cache = Arc::clone(&dns_cache);
+            let dscp_config = Arc::clone(&dscp_config);
+            let queue = Arc::clone(&queue);
+            worker_handles.push(tokio::spawn(async move {
+                let egress = Egress {
+                    device: &device,
+                    dns_cache: &dns_cache,
+                    arp_cache: &arp_cache,
+                    nat_table: &nat_table,
+                    datalink_senders: &datalink_senders,
+                    relay: &relay,
+                };
+                while let Some(packet_data) = worker_rx.recv().await {
+                    if let Err(e) = Self::process_packet(
+                        &packet_data,
+                        &egress,
+                        &packet_router,
+                        &performance_monitor,
+                        &dscp_config,
+                        &packet_sampler,
+                    ).await {
+                        debug!("Error processing packet: {}", e);
+                    }
+                    queue.recycle(packet_data);
+                }
+            }.instrument(info_span!("packet_worker", worker))));
+        }
+
+        // Main packet processing task, handing queued packets to the workers.
+        // It runs until the reader closes the queue and the queue is empty,
+        // unless `stop` gives up on draining.
         let handle = tokio::spawn(async move {
-            println!("Packet processing loop started");
-            
-            while *is_running.read().await {
-                tokio::select! {
-                    Some(packet_data) = packet_rx.recv() => {
-                        if let Err(e) = Self::process_packet(
-                            packet_data,
-                            &packet_router,
-                            &performance_monitor
-                        ).await {
-                            eprintln!("Error processing packet: {}", e);
-                        }
+            debug!("Packet processing loop started with {} worker(s)", worker_count);
+            let mut report = ShutdownReport::default();
+
+            while let Some(packet_data) = queue.pop().await {
+                if !*is_running.read().await {
+                    report.dropped = 1 + queue.len() as u64;
+                    for _ in 0..report.dropped {
+                        performance_monitor.record_packet_dropped(DropReason::Shutdown).await;
                     }
-                    else => {
-                        println!("Packet channel closed");
-                        break;
+                    break;
+                }
+                let draining = *shutdown_rx.borrow();
+                performance_monitor.record_queue_depth(queue.depths());
+                if draining {
+                    report.drained += 1;
+                }
+
+                performance_monitor.record_packet_received(packet_data.len()).await;
+                // Only IPv4 is routed into the TUN, but the OS can still hand
+                // it IPv6 such as router solicitations, which can't be NATed
+                if packet_data.first().is_some_and(|byte| byte >> 4 == 6) {
+                    performance_monitor.record_packet_dropped(DropReason::Ipv6).await;
+                    queue.recycle(packet_data);
+                    continue;
+                }
+                // Garbage from the TUN would otherwise still be classified and routed
+                if let Err(e) = validate_packet(&packet_data) {
+                    trace!("Dropping malformed packet: {}", e);
+                    performance_monitor.record_packet_dropped(DropReason::InvalidPacket).await;
+                    queue.recycle(packet_data);
+                    continue;
+                }
+                // Fragments wait for the rest of their datagram, which is then
+                // routed as one packet and fragmented again on the way out if it
+                // has to be. That happens here rather than in the workers, since
+                // only the first fragment has the ports that pick a flow's worker.
+                let packet_data = match reassembler.reassemble(&packet_data) {
+                    Reassembled::Unfragmented => packet_data,
+                    Reassembled::Incomplete => {
+                        queue.recycle(packet_data);
+                        continue;
+                    }
+                    Reassembled::Complete(datagram) => {
+                        queue.recycle(packet_data);
+                        datagram
                     }
+                };
+
+                let worker = &workers[worker_for(&packet_data, workers.len())];
+                if worker.send(packet_data).await.is_err() {
+                    error!("A packet worker exited unexpectedly");
+                    break;
                 }
             }
 
-            println!("Packet processing loop ended");
+            // Let the workers finish what they were handed
+            drop(workers);
+            for worker in worker_handles {
+                let _ = worker.await;
+            }
+            debug!("Packet processing loop ended");
+            drain_tx.send_replace(Some(report));
             Ok(())
-        });
+        }.instrument(info_span!("packet_loop")));
 
         Ok(handle)
     }
 
-    async fn spawn_packet_reader(&mut self, packet_tx: mpsc::Sender<Vec<u8>>) -> Result<tokio::task::JoinHandle<()>> {
+    async fn spawn_packet_reader(&self, queue: Arc<PacketQueue>, device: Arc<AsyncDevice>, config: &TunConfig) -> Result<tokio::task::JoinHandle<()>> {
         let is_running = Arc::clone(&self.is_running);
-        let device: Arc<AsyncDevice> = Arc::clone(&self.tun_interface.device);
-        
+        let performance_monitor = Arc::clone(&self.performance_monitor);
+        let packet_router = Arc::clone(&self.packet_router);
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let mtu = config.mtu as usize;
+        let read_buffer_size = config.read_buffer_size();
+
         let handle = tokio::spawn(async move {
-            let mut buf = [0u8; 1504]; // MTU + some overhead
-            
-            while *is_running.read().await {
-                match device.recv(&mut buf).await {
-                    Ok(len) => {
-                        if packet_tx.send(buf[..len].to_vec()).await.is_err() {
-                            println!("Packet receiver dropped");
+            let mut batch = PacketBatch::new(MAX_BATCH_PACKETS, read_buffer_size);
+            let mut stopping = false;
+
+            while !stopping && *is_running.read().await {
+                batch.clear();
+                tokio::select! {
+                    result = Self::read_batch(&device, &mut batch, mtu) => {
+                        if let Err(e) = result {
+                            error!("Error reading from TUN device: {}", e);
                             break;
                         }
                     }
+                    // Queue whatever was read before the stop, then let the queue drain
+                    _ = shutdown_rx.wait_for(|stop| *stop) => stopping = true,
+                }
+
+                // A full queue makes `push` wait on the workers, which need the
+                // router too, so classify first and release it before queueing
+                let router = packet_router.read().await;
+                let mut classified = Vec::with_capacity(MAX_BATCH_PACKETS);
+                for packet_data in batch.iter() {
+                    classified.push((packet_data, router.classify(packet_data).await.priority()));
+                }
+                drop(router);
+                for (packet_data, priority) in classified {
+                    if queue.push(packet_data, priority).await == Enqueued::DisplacedLower {
+                        performance_monitor.record_packet_dropped(DropReason::QueueFull).await;
+                    }
+                }
+                performance_monitor.record_queue_depth(queue.depths());
+            }
+
+            queue.close();
+        }.instrument(info_span!("tun_reader")));
+
+        Ok(handle)
+    }
+
+    /// Wait for one packet, then take whatever else is already queued on the
+    /// device without waiting. TUN descriptors don't support `recvmmsg`, so
+    /// draining ready packets is how reads are batched. Packets larger than
+    /// the MTU may have been cut short, so they are dropped rather than
+    /// forwarded with their tail missing.
+    async fn read_batch(device: &AsyncDevice, batch: &mut PacketBatch, mtu: usize) -> std::io::Result<()> {
+        if let Some(slot) = batch.next_slot() {
+            let len = device.recv(slot).await?;
+            Self::commit_read(batch, len, mtu);
+        }
+
+        while batch.len() < MAX_BATCH_PACKETS {
+            let Some(slot) = batch.next_slot() else {
+                break;
+            };
+            // A zero timeout polls the read once and gives up if nothing is
+            // ready. Errors are left for the next blocking read to report.
+            match tokio::time::timeout(Duration::ZERO, device.recv(slot)).await {
+                Ok(Ok(len)) => Self::commit_read(batch, len, mtu),
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn commit_read(batch: &mut PacketBatch, len: usize, mtu: usize) {
+        if len > mtu {
+            warn!("Dropping {} byte packet read from the TUN device, larger than its {} byte MTU", len, mtu);
+            return;
+        }
+        batch.commit(len);
+    }
+
+    /// Write replies arriving on the physical interfaces that belong to our
+    /// outbound flows back into the TUN device
+    fn spawn_return_path(
+        &self,
+        mut return_rx: mpsc::Receiver<(u32, Vec<u8>)>,
+        device: Arc<AsyncDevice>,
+    ) -> tokio::task::JoinHandle<()> {
+        let packet_router = Arc::clone(&self.packet_router);
+        let nat_table = Arc::clone(&self.nat_table);
+        let performance_monitor = Arc::clone(&self.performance_monitor);
+        let dns_cache = Arc::clone(&self.dns_cache);
+
+        tokio::spawn(async move {
+            while let Some((interface_index, mut packet_data)) = return_rx.recv().await {
+                // Undo the source NAT applied on the way out
+                if !nat_table.translate_inbound(&mut packet_data, interface_index).await {
+                    continue;
+                }
+
+                let Some(reply_key) = FlowKey::from_packet(&packet_data) else {
+                    continue;
+                };
+
+                if !packet_router.read().await.match_return_flow(&reply_key, interface_index).await {
+                    continue;
+                }
+                dns_cache.store(&packet_data);
+
+                match device.send(&packet_data).await {
+                    Ok(_) => performance_monitor.record_packet_returned(interface_index, packet_data.len()).await,
+                    Err(e) => warn!("Error writing to TUN device: {}", e),
+                }
+            }
+            debug!("Return path ended");
+        }.instrument(info_span!("return_path")))
+    }
+
+    /// Start return-path readers for the current interfaces, then periodically
+    /// re-discover interfaces so adapters plugged in or removed mid-session are
+    /// added to or dropped from the routing pool
+    async fn watch_interfaces(&self, return_tx: mpsc::Sender<(u32, Vec<u8>)>) -> tokio::task::JoinHandle<()> {
+        let is_running = Arc::clone(&self.is_running);
+        let packet_router = Arc::clone(&self.packet_router);
+        let datalink_senders = Arc::clone(&self.datalink_senders);
+        let relay = Arc::clone(&self.relay);
+        let interface_events_tx = self.interface_events_tx.clone();
+        let rediscover = Arc::clone(&self.rediscover);
+        let performance_monitor = Arc::clone(&self.performance_monitor);
+        let nat_table = Arc::clone(&self.nat_table);
+
+        let start_reader = {
+            let is_running = Arc::clone(&is_running);
+            move |interface: &PhysicalInterface| {
+                Self::spawn_interface_reader(interface, return_tx.clone(), Arc::clone(&is_running))
+                    .map_err(|e| warn!("Failed to start return path on '{}': {}", interface.name, e))
+                    .ok()
+            }
+        };
+
+        // Stop flags for the running readers, keyed by interface index
+        let mut readers: HashMap<u32, Arc<AtomicBool>> = HashMap::new();
+        for interface in packet_router.read().await.get_all_interfaces() {
+            if let Some(stop) = start_reader(&interface) {
+                readers.insert(interface.index, stop);
+            }
+        }
+
+        let mut had_interfaces = !packet_router.read().await.get_all_interfaces().is_empty();
+        // Everything the last discovery found, including interfaces the router leaves out
+        let mut discovered = packet_router.read().await.get_all_interfaces();
+
+        tokio::spawn(async move {
+            let mut interval = interval(INTERFACE_POLL_INTERVAL);
+            interval.tick().await;
+
+            while *is_running.read().await {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = rediscover.notified() => {}
+                }
+
+                let previous = std::mem::take(&mut discovered);
+                let interfaces = match tokio::task::spawn_blocking(move || InterfaceManager::rediscover(&previous)).await {
+                    Ok(interfaces) => interfaces,
+                    Err(e) => {
+                        warn!("Interface discovery failed: {}", e);
+                        continue;
+                    }
+                };
+
+                discovered = interfaces.clone();
+                let events = packet_router.write().await.update_interfaces(interfaces).await;
+
+                // Routing resumes by itself once an interface is back
+                let has_interfaces = !packet_router.read().await.get_all_interfaces().is_empty();
+                if had_interfaces && !has_interfaces {
+                    warn!("No network interfaces left; packets are dropped until one is connected");
+                } else if !had_interfaces && has_interfaces {
+                    info!("Network interface available again; resuming routing");
+                }
+                had_interfaces = has_interfaces;
+                for event in events {
+                    match &event {
+                        InterfaceEvent::Added(interface) => {
+                            info!("Interface '{}' added ({})", interface.name, interface.ip_address);
+                            if let Some(stop) = start_reader(interface) {
+                                readers.insert(interface.index, stop);
+                            }
+                        }
+                        InterfaceEvent::Removed(interface) => {
+                            info!("Interface '{}' removed", interface.name);
+                            datalink_senders.invalidate(interface.index);
+                            if let Some(relay) = relay.read().await.as_ref() {
+                                relay.tunnel.close(interface.index);
+                            }
+                            if let Some(stop) = readers.remove(&interface.index) {
+                                stop.store(true, Ordering::Relaxed);
+                            }
+                            nat_table.forget_interface(interface.index).await;
+                            Self::reroute_flows(&packet_router, &performance_monitor, interface.index, &interface.name).await;
+                            performance_monitor.forget_interface(interface.index).await;
+                        }
+                    }
+                    // Nobody listening is fine
+                    let _ = interface_events_tx.send(event);
+                }
+            }
+        }.instrument(info_span!("interface_watcher")))
+    }
+
+    /// Spawn a blocking reader on the receive half of a physical interface's
+    /// datalink channel, forwarding the IP packet in every frame. Returns a
+    /// flag that stops the reader when set.
+    fn spawn_interface_reader(
+        interface: &PhysicalInterface,
+        return_tx: mpsc::Sender<(u32, Vec<u8>)>,
+        is_running: Arc<RwLock<bool>>,
+    ) -> Result<Arc<AtomicBool>> {
+        let datalink_interface = pnet_datalink::interfaces()
+            .into_iter()
+            .find(|iface| iface.index == interface.index)
+            .context("Failed to find the interface")?;
+
+        // A read timeout lets the reader notice when the service stops
+        let config = pnet_datalink::Config {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+
+        let mut rx = match pnet_datalink::channel(&datalink_interface, config) {
+            Ok(Channel::Ethernet(_, rx)) => rx,
+            Ok(_) => return Err(anyhow::anyhow!("Unsupported channel type")),
+            Err(e) => return Err(e.into()),
+        };
+        let layer = LinkLayer::of(&datalink_interface);
+
+        let interface_index = interface.index;
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader_stop = Arc::clone(&stop);
+        tokio::task::spawn_blocking(move || {
+            while *is_running.blocking_read() && !reader_stop.load(Ordering::Relaxed) {
+                let frame = match rx.next() {
+                    Ok(frame) => frame,
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
                     Err(e) => {
-                        eprintln!("Error reading from TUN device: {}", e);
+                        error!("Error reading from interface {}: {}", interface_index, e);
                         break;
                     }
+                };
+
+                // Point-to-point links deliver bare IP packets; only IPv4 is sent,
+                // so only IPv4 replies are ours
+                let payload = match layer {
+                    LinkLayer::Ethernet => match EthernetPacket::new(frame) {
+                        Some(ethernet) if ethernet.get_ethertype() == EtherTypes::Ipv4 => &frame[ETHERNET_HEADER_LEN..],
+                        _ => continue,
+                    },
+                    LinkLayer::RawIp if frame.first().is_some_and(|byte| byte >> 4 == 4) => frame,
+                    LinkLayer::RawIp => continue,
+                };
+                // Strip any Ethernet padding past the IP total length
+                let len = match Ipv4Packet::new(payload) {
+                    Some(ip) => ip.get_total_length() as usize,
+                    None => continue,
+                };
+                let len = len.min(payload.len());
+
+                if return_tx.blocking_send((interface_index, payload[..len].to_vec())).is_err() {
+                    break;
                 }
             }
         });
 
-        Ok(handle)
+        Ok(stop)
     }
 
+    #[tracing::instrument(level = "trace", skip_all, fields(len = packet_data.len()))]
     async fn process_packet(
-        packet_data: Vec<u8>,
+        packet_data: &[u8],
+        egress: &Egress<'_>,
         packet_router: &Arc<RwLock<PacketRouter>>,
         performance_monitor: &PerformanceMonitor,
+        dscp_config: &RwLock<DscpConfig>,
+        packet_sampler: &RwLock<Option<PacketSampler>>,
     ) -> Result<()> {
         let start_time = std::time::Instant::now();
 
-        // Record packet received
-        performance_monitor.record_packet_received(packet_data.len()).await;
+        if let Some(reply) = egress.dns_cache.answer(packet_data) {
+            performance_monitor.record_dns_cache_hit().await;
+            if let Err(e) = egress.device.send(&reply).await {
+                warn!("Error writing to TUN device: {}", e);
+            }
+            return Ok(());
+        }
 
-        // Route the packet
-        match packet_router.read().await.route_packet(&packet_data).await {
+        // Route the packet. The router is released before sending, which
+        // takes it again and mustn't queue behind a waiting writer.
+        let decision = packet_router.read().await.route_packet(packet_data).await;
+        match decision {
             Ok(routing_decision) => {
-                println!(
+                trace!(
                     "Routing packet to interface '{}' (confidence: {:.2}%): {}",
                     routing_decision.interface_name,
                     routing_decision.confidence * 100.0,
                     routing_decision.reason
                 );
+                if routing_decision.low_confidence_fallback {
+                    performance_monitor.record_low_confidence_fallback().await;
+                }
+                if let Some(sampler) = packet_sampler.read().await.as_ref() {
+                    sampler.offer(packet_data, &routing_decision);
+                }
 
                 // Send packet to selected interface
-                if let Err(e) = Self::send_packet_to_interface(&packet_data, &routing_decision).await {
-                    eprintln!("Failed to send packet to interface: {}", e);
-                    performance_monitor.record_packet_dropped().await;
+                let interface = packet_router.read().await.find_interface(routing_decision.interface_index);
+                let dscp = dscp_config.read().await.dscp_for(routing_decision.traffic_type);
+                let relay = egress.relay.read().await.as_ref().map(|relay| Arc::clone(&relay.tunnel));
+                // Tunneled packets must leave room for the relay encapsulation
+                let sequenced = relay.as_ref().is_some_and(|tunnel| tunnel.sequences(routing_decision.traffic_type));
+                let overhead = match &relay {
+                    Some(_) if sequenced => (relay::OVERHEAD + relay::SEQUENCING_LEN) as u16,
+                    Some(_) => relay::OVERHEAD as u16,
+                    None => 0,
+                };
+                let result = match interface {
+                    // Path MTU discovery: tell the sender to shrink its packets
+                    Some(interface) if packet_data.len() + overhead as usize > interface.mtu as usize && mtu::dont_fragment(packet_data) => {
+                        let mtu = interface.mtu.saturating_sub(overhead);
+                        if let Some(reply) = mtu::fragmentation_needed(packet_data, mtu, interface.ip_address) {
+                            if let Err(e) = egress.device.send(&reply).await {
+                                warn!("Error writing to TUN device: {}", e);
+                            }
+                        }
+                        Err(anyhow::anyhow!(
+                            "{} byte packet exceeds the {} byte MTU of '{}' and can't be fragmented",
+                            packet_data.len(),
+                            mtu,
+                            interface.name
+                        ))
+                    }
+                    Some(interface) => {
+                        let index = interface.index;
+                        let result = match &relay {
+                            // Copies only help when the relay can drop the extras; the
+                            // destination itself would see them as separate packets
+                            Some(tunnel) if !routing_decision.duplicates.is_empty() => {
+                                Self::send_redundant(tunnel, interface, &routing_decision.duplicates, packet_data, packet_router, performance_monitor).await
+                            }
+                            // Split flows arrive out of order unless the relay restores it
+                            Some(tunnel) if sequenced => tunnel.send_sequenced(&interface, packet_data).await,
+                            Some(tunnel) => tunnel.send(&interface, packet_data).await,
+                            None => Self::send_packet_to_interface(packet_data, &interface, egress, dscp).await,
+                        };
+                        let transition = packet_router.read().await.record_send_result(index, result.is_ok()).await;
+                        match transition {
+                            Some(false) => {
+                                warn!("Sends keep failing on '{}'; taking it out of rotation for now", routing_decision.interface_name);
+                                performance_monitor.record_circuit_breaker_trip().await;
+                                Self::reroute_flows(packet_router, performance_monitor, index, &routing_decision.interface_name).await;
+                            }
+                            Some(true) => info!("Sends on '{}' are working again; returning it to rotation", routing_decision.interface_name),
+                            None => {}
+                        }
+                        result
+                    }
+                    None => Err(anyhow::anyhow!("Interface {} is no longer available", routing_decision.interface_index)),
+                };
+                if let Err(e) = result {
+                    debug!("Failed to send packet to interface: {:#}", e);
+                    performance_monitor.record_packet_dropped(DropReason::SendError).await;
                 } else {
-                    performance_monitor.record_packet_forwarded(packet_data.len()).await;
+                    packet_router.read().await.record_forwarded(routing_decision.interface_index, packet_data.len()).await;
+                    performance_monitor.record_packet_forwarded(
+                        routing_decision.interface_index,
+                        &routing_decision.interface_name,
+                        routing_decision.traffic_type,
+                        packet_data.len(),
+                    ).await;
                 }
             }
+            Err(e) if e.is::<RateLimited>() => {
+                performance_monitor.record_packet_throttled(packet_data.len()).await;
+            }
             Err(e) => {
-                eprintln!("Failed to route packet: {}", e);
-                performance_monitor.record_packet_dropped().await;
+                debug!("Failed to route packet: {}", e);
+                performance_monitor.record_packet_dropped(DropReason::NoInterface).await;
             }
         }
 
@@ -208,26 +1188,107 @@ impl VirtualNetworkInterface {
         Ok(())
     }
 
+    /// Send a packet through the relay out of `interface` and every interface
+    /// in `duplicates` whose MTU and rate limit fit it, counting the extra copies
+    async fn send_redundant(
+        tunnel: &RelayTunnel,
+        interface: PhysicalInterface,
+        duplicates: &[u32],
+        packet_data: &[u8],
+        packet_router: &RwLock<PacketRouter>,
+        performance_monitor: &PerformanceMonitor,
+    ) -> Result<()> {
+        let primary = interface.index;
+        let mut interfaces = vec![interface];
+        {
+            let router = packet_router.read().await;
+            for copy in duplicates.iter().filter_map(|index| router.find_interface(*index)) {
+                if packet_data.len() + relay::OVERHEAD <= copy.mtu as usize
+                    && router.charge_rate_limit(copy.index, packet_data.len()).await.is_ok()
+                {
+                    interfaces.push(copy);
+                }
+            }
+        }
+
+        let sent = tunnel.send_redundant(&interfaces, packet_data).await?;
+        for copy in interfaces.iter().filter(|copy| copy.index != primary && sent.contains(&copy.index)) {
+            packet_router.read().await.record_forwarded(copy.index, packet_data.len()).await;
+            performance_monitor.record_packet_duplicated(copy.index, &copy.name, packet_data.len()).await;
+        }
+        Ok(())
+    }
+
+    /// Wrap an IPv4 packet in an Ethernet frame addressed to its next hop,
+    /// source-NAT it to the interface's address and send it out of `interface`,
+    /// fragmenting it if it exceeds the interface's MTU
     async fn send_packet_to_interface(
         packet_data: &[u8],
-        routing_decision: &crate::packet_router::RoutingDecision,
+        interface: &PhysicalInterface,
+        egress: &Egress<'_>,
+        dscp: Option<u8>,
     ) -> Result<()> {
-        let interfaces = pnet_datalink::interfaces();
-        let interface = interfaces
-            .into_iter()
-            .find(|iface| iface.index == routing_decision.interface_index)
-            .context("Failed to find the selected interface")?;
+        let Egress { arp_cache, nat_table, datalink_senders, .. } = egress;
+        let sender = datalink_senders.get(interface.index)?;
 
-        let (mut tx, _) = match pnet_datalink::channel(&interface, Default::default()) {
-            Ok(Channel::Ethernet(tx, rx)) => (tx, rx),
-            Ok(_) => return Err(anyhow::anyhow!("Unsupported channel type")),
-            Err(e) => return Err(e.into()),
+        // Point-to-point links take the packet as it is; Ethernet needs the
+        // next hop's MAC address for a header
+        let ethernet_header = match sender.layer() {
+            LinkLayer::RawIp => None,
+            LinkLayer::Ethernet => {
+                let destination = Ipv4Packet::new(packet_data)
+                    .context("Packet is too short for an IPv4 header")?
+                    .get_destination();
+                let next_hop = interface.next_hop(destination);
+                let source_mac = sender.interface.mac.context("Interface has no MAC address")?;
+                // Resolving can take a second, too long to hold up the worker;
+                // the packet is dropped and the next one finds the address
+                let destination_mac = arp_cache
+                    .lookup(&sender.interface, interface.ip_address, next_hop)
+                    .await
+                    .with_context(|| format!("Next hop {} on '{}' isn't resolved yet", next_hop, interface.name))?;
+
+                let mut header = vec![0u8; ETHERNET_HEADER_LEN];
+                let mut ethernet = MutableEthernetPacket::new(&mut header).context("Failed to build Ethernet frame")?;
+                ethernet.set_destination(destination_mac);
+                ethernet.set_source(source_mac);
+                ethernet.set_ethertype(EtherTypes::Ipv4);
+                Some(header)
+            }
         };
 
-        tx.send_to(packet_data, None)
-            .context("Failed to send packet")?
-            .context("Failed to send packet")?;
+        let mut packet = packet_data.to_vec();
+
+        // Let downstream routers prioritise by traffic type
+        if let Some(dscp) = dscp {
+            qos::mark_dscp(&mut packet, dscp)?;
+        }
+
+        // The far end only answers to the interface's own address
+        nat_table
+            .translate_outbound(&mut packet, interface.index, interface.ip_address)
+            .await?;
+
+        // Fragment after NAT, since later fragments carry no ports to translate
+        let packets = if packet.len() > interface.mtu as usize {
+            mtu::fragment(&packet, interface.mtu)?
+        } else {
+            vec![packet]
+        };
+        let frames: Vec<Vec<u8>> = match &ethernet_header {
+            Some(header) => packets.iter().map(|packet| [&header[..], &packet[..]].concat()).collect(),
+            None => packets,
+        };
 
+        // Either failure counts the packet as dropped. If the interface went
+        // away or down, the channel is reopened for the next packet.
+        for frame in frames {
+            sender.send(&frame).inspect_err(|e| {
+                if datalink::link_is_gone(e) {
+                    datalink_senders.invalidate(interface.index);
+                }
+            })?;
+        }
         Ok(())
     }
 
@@ -235,62 +1296,544 @@ impl VirtualNetworkInterface {
         let performance_monitor = Arc::clone(&self.performance_monitor);
         let packet_router: Arc<RwLock<PacketRouter>> = Arc::clone(&self.packet_router);
         let is_running = Arc::clone(&self.is_running);
+        let mut monitoring_interval = self.monitoring_interval.subscribe();
+        let nat_table = Arc::clone(&self.nat_table);
+        let reassembler = Arc::clone(&self.reassembler);
+        let probe_target = Arc::clone(&self.probe_target);
+        let stats_tx = self.stats_tx.clone();
 
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(5));
-            
+            let mut interval = interval(*monitoring_interval.borrow_and_update());
+            // Packets forwarded per traffic type as of the previous tick
+            let mut last_traffic_types = HashMap::new();
+
             while *is_running.read().await {
-                interval.tick().await;
-                
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    Ok(()) = monitoring_interval.changed() => {
+                        let period = *monitoring_interval.borrow_and_update();
+                        debug!("Monitoring interval changed to {:?}", period);
+                        interval = interval_at(Instant::now() + period, period);
+                        continue;
+                    }
+                }
+
                 // Update interface metrics
-                let stats = performance_monitor.get_current_stats().await;
+                let mut stats = performance_monitor.get_current_stats().await;
+
+                // Let `Adaptive` mode follow what was forwarded since the last tick
+                let traffic_mix: HashMap<TrafficType, u64> = stats
+                    .traffic_types
+                    .iter()
+                    .map(|(traffic_type, forwarded)| {
+                        let before = last_traffic_types.get(traffic_type).copied().unwrap_or(0);
+                        (*traffic_type, forwarded.packets.saturating_sub(before))
+                    })
+                    .collect();
+                last_traffic_types = stats.traffic_types.iter().map(|(traffic_type, forwarded)| (*traffic_type, forwarded.packets)).collect();
+                {
+                    let router = packet_router.read().await;
+                    if router.get_load_balancing_mode() == LoadBalancingMode::Adaptive {
+                        if let Some(strategy) = router.observe_traffic_mix(&traffic_mix).await {
+                            info!("Adaptive mode switched to {} for the current traffic mix", strategy);
+                        }
+                    }
+                    stats.effective_load_balancing_mode = Some(router.effective_mode().await);
+                }
+
+                // Drop idle flows so the flow table doesn't grow without bound
+                packet_router.read().await.expire_flows(FLOW_IDLE_TIMEOUT).await;
+                nat_table.expire(FLOW_IDLE_TIMEOUT).await;
+                let incomplete = reassembler.expire();
+                if incomplete > 0 {
+                    debug!("Dropped {} fragmented datagram(s) that never completed", incomplete);
+                    performance_monitor.record_reassembly_timeouts(incomplete).await;
+                }
                 
-                // For now, simulate metrics updates
-                // In real implementation, this would ping interfaces and measure actual performance
-                packet_router.write().await.update_interface_metrics(
-                    1, // interface index
-                    Duration::from_millis(20), // simulated latency
-                    stats.bandwidth_usage,
-                    stats.packet_loss_rate,
-                ).await;
+                let target = *probe_target.read().await;
+                Self::probe_interfaces(&packet_router, &performance_monitor, target).await;
 
-                // Log performance stats
-                println!(
-                    "Performance Stats - Packets: {}/{}/{}, Latency: {:.2}ms, Loss: {:.2}%",
+                // Logged at info so the log file keeps a history of throughput
+                info!(
+                    "Performance Stats - Packets: {}/{}/{}, Latency: {:.2}ms, Loss: {:.2}%, Up: {} bps, Down: {} bps",
                     stats.packets_received,
                     stats.packets_forwarded,
-                    stats.packets_dropped,
+                    stats.packets_dropped.total,
                     stats.average_latency.as_secs_f64() * 1000.0,
-                    stats.packet_loss_rate * 100.0
+                    stats.packet_loss_rate * 100.0,
+                    stats.upload_bps,
+                    stats.download_bps
                 );
+
+                // Publish to subscribers; an error only means nobody is listening
+                let _ = stats_tx.send(stats);
             }
-        })
+        }.instrument(info_span!("performance_monitor")))
+    }
+
+    /// Sample the performance history at the configured resolution until the
+    /// service stops
+    fn start_history_sampling(&self) -> HistorySampler {
+        let performance_monitor = Arc::clone(&self.performance_monitor);
+        let is_running = Arc::clone(&self.is_running);
+        let resolution = Arc::clone(&self.history_resolution);
+
+        HistorySampler(tokio::spawn(async move {
+            while *is_running.read().await {
+                let period = *resolution.read().await;
+                tokio::time::sleep(period).await;
+                let stats = performance_monitor.get_current_stats().await;
+                performance_monitor.record_history_sample(&stats).await;
+            }
+        }.instrument(info_span!("history_sampler"))))
+    }
+
+    /// Probe every interface through its own address and record the results
+    async fn probe_interfaces(
+        packet_router: &RwLock<PacketRouter>,
+        performance_monitor: &PerformanceMonitor,
+        target: Ipv4Addr,
+    ) {
+        let timeout = packet_router.read().await.health_config().probe_timeout();
+        // Each interface's own forwarded traffic since the last probe
+        let rates = packet_router.read().await.take_forwarded_rates().await;
+        let mut probes = tokio::task::JoinSet::new();
+        for interface in packet_router.read().await.get_all_interfaces() {
+            probes.spawn(async move {
+                let (latency, packet_loss) = health::probe_interface(&interface, target, timeout).await;
+                (interface, latency, packet_loss)
+            });
+        }
+        while let Some(result) = probes.join_next().await {
+            let Ok((interface, latency, packet_loss)) = result else {
+                continue;
+            };
+            let transition = {
+                let router = packet_router.read().await;
+                router.update_interface_metrics(
+                    interface.index,
+                    latency.unwrap_or(timeout),
+                    rates.get(&interface.index).copied().unwrap_or(0),
+                    packet_loss,
+                ).await;
+                router.record_probe(interface.index, latency, packet_loss).await
+            };
+            match transition {
+                Some(false) => {
+                    warn!("Interface '{}' is unhealthy; draining it", interface.name);
+                    Self::reroute_flows(packet_router, performance_monitor, interface.index, &interface.name).await;
+                }
+                Some(true) => info!("Interface '{}' recovered", interface.name),
+                None => {}
+            }
+        }
+    }
+
+    /// Move the flows of an interface that went down onto the others
+    async fn reroute_flows(packet_router: &RwLock<PacketRouter>, performance_monitor: &PerformanceMonitor, index: u32, name: &str) {
+        let moved = packet_router.read().await.reroute_flows(index).await;
+        if moved > 0 {
+            info!("Moved {} flows off '{}'", moved, name);
+            performance_monitor.record_flows_migrated(moved).await;
+        }
+    }
+
+    /// Receive a `PerformanceStats` snapshot on every monitoring tick
+    pub fn subscribe_stats(&self) -> broadcast::Receiver<PerformanceStats> {
+        self.stats_tx.subscribe()
+    }
+
+    /// Receive an event whenever an interface is added or removed while running
+    pub fn subscribe_interface_events(&self) -> broadcast::Receiver<InterfaceEvent> {
+        self.interface_events_tx.subscribe()
     }
 
     /// Configure load balancing mode
-    pub async fn set_load_balancing_mode(&mut self, mode: LoadBalancingMode) {
+    pub async fn set_load_balancing_mode(&self, mode: LoadBalancingMode) {
         self.packet_router.write().await.set_load_balancing_mode(mode);
-        println!("Load balancing mode changed to: {:?}", mode);
+        info!("Load balancing mode changed to: {}", mode);
+    }
+
+    pub async fn get_load_balancing_mode(&self) -> LoadBalancingMode {
+        self.packet_router.read().await.get_load_balancing_mode()
+    }
+
+    /// Turn multi-interface balancing on, or pass everything through the primary interface
+    pub async fn set_connection_aggregation(&self, enabled: bool) {
+        self.packet_router.write().await.set_aggregation_enabled(enabled);
+        info!("Connection aggregation {}", if enabled { "enabled" } else { "disabled" });
+    }
+
+    pub async fn is_connection_aggregation_enabled(&self) -> bool {
+        self.packet_router.read().await.is_aggregation_enabled()
+    }
+
+    /// Record new flows to the log described by `settings`, or stop recording.
+    /// Returns the path being written to.
+    pub async fn set_flow_log(&self, settings: &FlowLogSettings) -> Result<Option<PathBuf>> {
+        let flow_log = FlowLog::from_settings(settings)?.map(Arc::new);
+        let path = flow_log.as_ref().map(|flow_log| flow_log.path().to_path_buf());
+        self.packet_router.write().await.set_flow_log(flow_log);
+        match &path {
+            Some(path) => info!("Logging new flows to {}", path.display()),
+            None => info!("Flow logging disabled"),
+        }
+        Ok(path)
+    }
+
+    /// Copy routed packets to the file or collector described by `settings`,
+    /// or stop sampling. Returns where samples go.
+    pub async fn set_packet_sampler(&self, settings: &SamplerSettings) -> Result<Option<String>> {
+        // Reopening the file on every config reload would truncate the capture
+        if let Some(sampler) = self.packet_sampler.read().await.as_ref().filter(|sampler| sampler.settings() == settings) {
+            return Ok(Some(sampler.destination().to_string()));
+        }
+        // The old writer finishes before a new one can replace its file
+        let old = self.packet_sampler.write().await.take();
+        if let Some(old) = old {
+            tokio::task::spawn_blocking(move || old.close()).await?;
+        }
+
+        let sampler = PacketSampler::from_settings(settings)?;
+        let destination = sampler.as_ref().map(|sampler| sampler.destination().to_string());
+        *self.packet_sampler.write().await = sampler;
+        match &destination {
+            Some(destination) => info!("Sampling 1 in {} routed packets to {}", settings.rate.max(1), destination),
+            None => info!("Packet sampling disabled"),
+        }
+        Ok(destination)
+    }
+
+    /// Use the interface with the given index as primary
+    pub async fn set_primary_interface(&self, index: u32) -> Result<PhysicalInterface> {
+        let primary = self.packet_router.write().await.set_primary_interface(index)?;
+        info!("Primary interface set to {}", primary.name);
+        Ok(primary)
+    }
+
+    /// Keep file transfers off metered interfaces when others are available
+    pub async fn set_avoid_metered(&self, avoid: bool) {
+        self.packet_router.write().await.set_avoid_metered(avoid);
+        info!("{} metered interfaces for file transfers", if avoid { "Avoiding" } else { "Allowing" });
+    }
+
+    pub async fn primary_interface(&self) -> Option<PhysicalInterface> {
+        self.packet_router.read().await.primary_interface()
+    }
+
+    /// Use the named interface as primary, or the first discovered one with `None`
+    pub async fn set_primary_interface_name(&self, name: Option<String>) {
+        self.packet_router.write().await.set_primary_interface_name(name);
+    }
+
+    /// Choose which discovered interfaces are aggregated
+    pub async fn set_interface_filter(&self, filter: InterfaceFilter) {
+        // Workers route through the router, so it is only locked to swap the
+        // filter in, never across the wait for the running state
+        let interfaces = {
+            let mut router = self.packet_router.write().await;
+            router.set_interface_filter(filter);
+            router.get_all_interfaces()
+        };
+        if *self.is_running.read().await {
+            // The watcher rediscovers, so the return paths follow the new set
+            self.rediscover.notify_one();
+        } else {
+            // Nothing is routing yet, so the router can be held while the
+            // interfaces the filter now excludes are dropped. Ones it now
+            // allows are picked up by the first rediscovery.
+            self.packet_router.write().await.update_interfaces(interfaces).await;
+        }
+    }
+
+    /// Tunnel all traffic through a relay server that reassembles it, so even
+    /// a single flow is split across interfaces, or go back to routing each
+    /// flow directly with `None`
+    pub async fn set_relay(&self, config: Option<&RelayConfig>) -> Result<()> {
+        let relay = match config {
+            Some(config) => {
+                let device = self.tun_device()?;
+                let (inbound_tx, inbound_rx) = mpsc::channel(1000);
+                let tunnel = Arc::new(RelayTunnel::connect(config, inbound_tx).await?);
+                info!("Bonding interfaces through relay {}", tunnel.endpoint());
+                let writer = tokio::spawn(Self::write_relay_replies(inbound_rx, device, Arc::clone(&self.performance_monitor)));
+                Some(Relay { tunnel, writer })
+            }
+            None => None,
+        };
+
+        let enabled = relay.is_some();
+        let previous = std::mem::replace(&mut *self.relay.write().await, relay);
+        if previous.is_some() && !enabled {
+            info!("Relay bonding disabled");
+        }
+        // The relay puts split flows back together, so they can be spread per packet
+        self.packet_router.write().await.set_flow_affinity(!enabled);
+        Ok(())
+    }
+
+    pub async fn is_relay_enabled(&self) -> bool {
+        self.relay.read().await.is_some()
+    }
+
+    /// Write the packets the relay sends back into the TUN
+    async fn write_relay_replies(mut inbound: mpsc::Receiver<(u32, Vec<u8>)>, device: Arc<AsyncDevice>, performance_monitor: Arc<PerformanceMonitor>) {
+        while let Some((interface_index, packet)) = inbound.recv().await {
+            if let Err(e) = validate_packet(&packet) {
+                trace!("Dropping malformed packet from the relay: {}", e);
+                continue;
+            }
+            match device.send(&packet).await {
+                Ok(_) => performance_monitor.record_packet_returned(interface_index, packet.len()).await,
+                Err(e) => warn!("Error writing to TUN device: {}", e),
+            }
+        }
+    }
+
+    pub async fn active_interface_count(&self) -> usize {
+        self.packet_router.read().await.active_interface_count().await
+    }
+
+    /// Apply persisted settings to the running router
+    pub async fn apply_settings(&self, settings: &Settings) {
+        self.monitoring_interval.send_replace(settings.monitoring_interval());
+        *self.warmup.write().await = settings.warmup();
+        *self.packet_workers.write().await = settings.packet_workers();
+        *self.manage_routes.write().await = settings.manage_routes;
+        *self.kill_switch.write().await = settings.kill_switch;
+        self.performance_monitor.set_history_retention(settings.history_retention()).await;
+        *self.history_resolution.write().await = settings.history_resolution();
+        match settings.probe_target.parse() {
+            Ok(target) => *self.probe_target.write().await = target,
+            Err(_) => warn!(
+                "Probe target '{}' is not an IPv4 address; using {}",
+                settings.probe_target, DEFAULT_PROBE_TARGET
+            ),
+        }
+        self.packet_router.write().await.set_health_config(settings.health.clone());
+        self.packet_router.write().await.set_metrics_max_age(settings.metrics_max_age());
+        self.packet_router.write().await.set_jitter_weight(settings.jitter_weight);
+        self.packet_router.write().await.set_min_confidence(settings.min_confidence);
+        self.set_dscp_config(settings.dscp.clone()).await;
+        self.set_scheduler_config(&settings.scheduler);
+        self.set_dns_settings(&settings.dns).await;
+        self.packet_router.read().await.set_classification_rules(settings.classification_rules.clone()).await;
+        self.packet_router.read().await.set_bypass_routes(settings.bypass_routes.clone()).await;
+        self.packet_router.read().await.apply_destination_pins(&settings.pinned_destinations).await;
+        self.set_load_balancing_mode(settings.load_balancing_mode).await;
+        self.set_connection_aggregation(settings.connection_aggregation).await;
+        self.set_interface_filter(settings.interface_filter.clone()).await;
+        self.set_primary_interface_name(settings.primary_interface.clone()).await;
+        self.set_avoid_metered(settings.avoid_metered).await;
+        if let Err(e) = self.set_flow_log(&settings.flow_log).await {
+            warn!("Flow logging disabled: {:#}", e);
+        }
+        if let Err(e) = self.set_packet_sampler(&settings.packet_sampler).await {
+            warn!("Packet sampling disabled: {:#}", e);
+        }
+        if let Err(e) = self.set_relay(settings.relay.as_ref()).await {
+            warn!("Routing directly instead of through the relay: {:#}", e);
+        }
+        self.packet_router.read().await.apply_interface_settings(&settings.interfaces).await;
+    }
+
+    /// Apply the changes between two versions of the settings that a config
+    /// file can make, without restarting. The TUN device can't be changed live.
+    pub async fn apply_config_changes(&self, previous: &Settings, settings: &Settings) {
+        if settings.tun_config != previous.tun_config {
+            warn!("TUN settings changed; restart NetBoost Pro to apply them");
+        }
+        if settings.load_balancing_mode != previous.load_balancing_mode {
+            self.set_load_balancing_mode(settings.load_balancing_mode).await;
+        }
+        let router = self.packet_router.read().await;
+        if settings.classification_rules != previous.classification_rules {
+            router.set_classification_rules(settings.classification_rules.clone()).await;
+        }
+        if settings.bypass_routes != previous.bypass_routes {
+            router.set_bypass_routes(settings.bypass_routes.clone()).await;
+        }
+        if settings.interfaces != previous.interfaces {
+            router.apply_interface_settings(&settings.interfaces).await;
+        }
+    }
+
+    /// Append a traffic classification rule, checked after the existing ones
+    pub async fn add_classification_rule(&self, rule: ClassificationRule) {
+        self.packet_router.read().await.add_classification_rule(rule).await;
+    }
+
+    /// Send every packet for `destination` through one interface
+    pub async fn pin_destination(&self, destination: IpAddr, interface_index: u32) -> Result<()> {
+        self.packet_router.read().await.pin_destination(destination, interface_index).await
+    }
+
+    pub async fn unpin_destination(&self, destination: IpAddr) -> bool {
+        self.packet_router.read().await.unpin_destination(destination).await
+    }
+
+    /// Where a packet of `size` bytes on `flow` would be routed right now,
+    /// without sending anything
+    pub async fn preview_route(&self, flow: &FlowKey, size: usize) -> Result<RoutingDecision> {
+        self.packet_router.read().await.preview_route(flow, size).await
+    }
+
+    /// Keep traffic for `cidr` off the load balancer
+    pub async fn add_bypass_route(&self, cidr: Ipv4Cidr, interface: Option<String>) {
+        self.packet_router.read().await.add_bypass_route(cidr, interface).await;
+    }
+
+    pub async fn remove_bypass_route(&self, cidr: Ipv4Cidr) -> bool {
+        self.packet_router.read().await.remove_bypass_route(cidr).await
+    }
+
+    /// Sample and publish performance stats every `secs` seconds, taking
+    /// effect immediately if the monitoring loop is running
+    pub fn set_monitoring_interval(&self, secs: u64) -> Result<()> {
+        // Zero would make the loop spin, and the next tick is scheduled an
+        // interval ahead, which a huge one would overflow
+        if !(1..=MAX_MONITORING_INTERVAL_SECS).contains(&secs) {
+            return Err(anyhow::anyhow!("Monitoring interval must be between 1 and {} seconds", MAX_MONITORING_INTERVAL_SECS));
+        }
+        self.monitoring_interval.send_replace(Duration::from_secs(secs));
+        Ok(())
+    }
+
+    /// Change how outbound packets are marked for QoS
+    pub async fn set_dscp_config(&self, config: DscpConfig) {
+        *self.dscp_config.write().await = config;
+    }
+
+    /// Change how queued packets are shared between traffic types
+    pub fn set_scheduler_config(&self, config: &SchedulerConfig) {
+        self.packet_queue.set_weights(config.queue_weights());
+    }
+
+    /// Health of each probed interface, keyed by index
+    pub async fn get_interface_health(&self) -> HashMap<u32, HealthStatus> {
+        self.packet_router.read().await.get_interface_health().await
+    }
+
+    /// Set the relative weight of a physical interface
+    pub async fn set_interface_weight(&self, index: u32, weight: f32) {
+        self.packet_router.read().await.set_interface_weight(index, weight).await;
+    }
+
+    /// Set the failover tier of a physical interface for `ActivePassive` mode
+    pub async fn set_interface_tier(&self, index: u32, tier: u8) {
+        self.packet_router.read().await.set_interface_tier(index, tier).await;
+    }
+
+    /// Bond physical interfaces into a group the balancer treats as one link
+    pub async fn create_interface_group(&self, indices: &[u32]) -> Result<InterfaceGroup> {
+        self.packet_router.read().await.create_interface_group(indices).await
+    }
+
+    pub async fn remove_interface_group(&self, index: u32) -> Result<InterfaceGroup> {
+        self.packet_router.read().await.remove_interface_group(index).await
+    }
+
+    pub async fn interface_groups(&self) -> Vec<InterfaceGroup> {
+        self.packet_router.read().await.interface_groups().await
+    }
+
+    /// Cap the throughput of a physical interface, or remove the cap with `None`
+    pub async fn set_interface_rate_limit(&self, index: u32, bytes_per_sec: Option<u64>) {
+        self.packet_router.read().await.set_interface_rate_limit(index, bytes_per_sec).await;
+    }
+
+    /// Choose how DNS lookups are routed and whether repeated ones are
+    /// answered from the cache
+    pub async fn set_dns_settings(&self, settings: &DnsSettings) {
+        self.packet_router.write().await.set_dns_settings(settings);
+        self.dns_cache.set_capacity(if settings.cache { settings.cache_size } else { 0 });
+    }
+
+    /// Look up a physical interface known to the router
+    pub async fn find_interface(&self, index: u32) -> Option<PhysicalInterface> {
+        self.packet_router.read().await.find_interface(index)
+    }
+
+    /// Include or exclude a physical interface from aggregation
+    pub async fn set_interface_enabled(&self, index: u32, enabled: bool) {
+        let router = self.packet_router.read().await;
+        if enabled {
+            router.enable_interface(index).await;
+        } else {
+            router.disable_interface(index).await;
+        }
+        info!("Interface {} {}", index, if enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Every interface in the routing pool, including disabled ones
+    pub async fn interfaces(&self) -> Vec<PhysicalInterface> {
+        self.packet_router.read().await.get_all_interfaces()
+    }
+
+    /// Combined measured throughput of every interface, in bits per second
+    pub async fn get_aggregate_bandwidth(&self) -> u64 {
+        self.performance_monitor.aggregate_bandwidth_bps().await
     }
 
     /// Get current performance statistics
-    pub async fn get_performance_stats(&self) -> crate::performance_monitor::PerformanceStats {
-        self.performance_monitor.get_current_stats().await
+    pub async fn get_performance_stats(&self) -> PerformanceStats {
+        let mut stats = self.performance_monitor.get_current_stats().await;
+        stats.effective_load_balancing_mode = Some(self.packet_router.read().await.effective_mode().await);
+        stats
+    }
+
+    /// Zero the session statistics, leaving the lifetime counters
+    pub async fn reset_performance_stats(&self) {
+        self.performance_monitor.reset_stats().await;
     }
 
-    /// Stop the virtual interface
-    pub async fn stop(&self) {
-        println!("Stopping virtual network interface...");
+    /// Stop the virtual interface in two phases: stop reading from the TUN
+    /// and flush the queued packets through the router, then tear down the
+    /// other tasks. Packets still queued after `SHUTDOWN_DRAIN_TIMEOUT` are dropped.
+    pub async fn stop(&self) -> Result<ShutdownReport> {
+        info!("Stopping virtual network interface");
+        if !*self.is_running.read().await || matches!(self.backend, Backend::Simulated(_)) {
+            *self.is_running.write().await = false;
+            return Ok(ShutdownReport::default());
+        }
+
+        self.shutdown_tx.send_replace(true);
+        let mut drain_rx = self.drain_tx.subscribe();
+        let drained_in_time = tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, drain_rx.wait_for(Option::is_some))
+            .await
+            .is_ok();
+        if !drained_in_time {
+            warn!("Queued packets not flushed within {:?}; dropping the rest", SHUTDOWN_DRAIN_TIMEOUT);
+        }
+
         *self.is_running.write().await = false;
+
+        tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, drain_rx.wait_for(Option::is_some))
+            .await
+            .context("Packet processing did not stop")?
+            .context("Packet processing ended without reporting")?;
+        let report = drain_rx.borrow().unwrap_or_default();
+        info!("Shutdown flushed {} queued packets and dropped {}", report.drained, report.dropped);
+
+        // Only report stopped once the host's traffic goes its usual way again
+        let mut stopped_rx = self.stopped_tx.subscribe();
+        tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, stopped_rx.wait_for(|stopped| *stopped))
+            .await
+            .context("Routes were not restored in time")?
+            .context("The service ended without restoring routes")?;
+        Ok(report)
     }
 
     pub fn name(&self) -> Result<String> {
-        self.tun_interface.name()
+        match &self.backend {
+            Backend::Tun(tun) => tun.name(),
+            Backend::Simulated(_) => Ok("simulated".to_string()),
+        }
     }
 }
 
 impl Drop for VirtualNetworkInterface {
     fn drop(&mut self) {
-        println!("Virtual network interface dropped");
+        debug!("Virtual network interface dropped");
     }
 }
\ No newline at end of file
@@ -0,0 +1,230 @@
+// src-tauri/src/vpn.rs
+use anyhow::{bail, Result};
+use pnet_datalink::NetworkInterface;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use tracing::{info, warn};
+
+use crate::interface_filter::InterfaceFilter;
+use crate::settings::Settings;
+use crate::virtual_adapter::{TunConfig, DEFAULT_PROBE_TARGET};
+
+/// Interface names VPN clients give their tunnels, matched case-insensitively
+/// at the start of the name
+const TUNNEL_NAME_PREFIXES: &[&str] = &[
+    "tun", "tap", "wg", "utun", "ipsec", "tailscale", "zt", "nordlynx", "proton", "mullvad", "cscotun", "gpd",
+];
+
+/// Words in adapter descriptions, which name the tunnel on Windows
+const TUNNEL_DESCRIPTIONS: &[&str] = &[
+    "tap-windows", "wintun", "wireguard", "openvpn", "anyconnect", "tailscale", "zerotier", "vpn",
+];
+
+/// What to do about a VPN that is carrying traffic when NetBoost Pro starts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VpnPolicy {
+    /// Start alongside it, warning; its tunnel is balanced over like any
+    /// other interface, so traffic sent that way still goes through the VPN
+    #[default]
+    Coexist,
+    /// Don't start while a VPN is active
+    Refuse,
+    /// Start and leave VPN tunnels out, so routed traffic bypasses the VPN
+    TakePrecedence,
+}
+
+impl VpnPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VpnPolicy::Coexist => "coexist",
+            VpnPolicy::Refuse => "refuse",
+            VpnPolicy::TakePrecedence => "take_precedence",
+        }
+    }
+}
+
+impl std::fmt::Display for VpnPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for VpnPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [VpnPolicy::Coexist, VpnPolicy::Refuse, VpnPolicy::TakePrecedence]
+            .into_iter()
+            .find(|policy| policy.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Invalid VPN policy '{}'", s))
+    }
+}
+
+/// A TUN, TAP or other tunnel interface that isn't ours
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelInterface {
+    pub name: String,
+    pub description: String,
+    pub index: u32,
+    pub address: Ipv4Addr,
+}
+
+/// Tunnels found at startup and which interface internet traffic leaves by
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VpnReport {
+    pub tunnels: Vec<TunnelInterface>,
+    /// The interface the OS would send internet traffic out of, if any
+    pub default_route_interface: Option<String>,
+}
+
+impl VpnReport {
+    /// The tunnel carrying internet traffic, which means a VPN is active
+    pub fn active_vpn(&self) -> Option<&TunnelInterface> {
+        let owner = self.default_route_interface.as_deref()?;
+        self.tunnels.iter().find(|tunnel| tunnel.name == owner)
+    }
+
+    /// One line for logs and the diagnostics report
+    pub fn summary(&self) -> String {
+        if let Some(vpn) = self.active_vpn() {
+            return format!("A VPN appears to be active: internet traffic goes through its tunnel '{}'", vpn.name);
+        }
+        if self.tunnels.is_empty() {
+            return "No other VPN or tunnel interface is up".to_string();
+        }
+        let names: Vec<&str> = self.tunnels.iter().map(|tunnel| tunnel.name.as_str()).collect();
+        format!("Tunnel interface(s) {} are up but don't carry internet traffic", names.join(", "))
+    }
+}
+
+/// Look for tunnel interfaces other than `own_tun`, and ask the OS which
+/// interface it would send to `probe_target` through. Blocks briefly.
+pub fn detect(own_tun: &TunConfig, probe_target: Ipv4Addr) -> VpnReport {
+    let interfaces = pnet_datalink::interfaces();
+    let tunnels = interfaces
+        .iter()
+        .filter(|iface| iface.is_up() && !iface.is_loopback() && iface.name != own_tun.name)
+        .filter(|iface| is_tunnel(iface))
+        .filter_map(|iface| {
+            let address = ipv4_address(iface)?;
+            (address != own_tun.address).then(|| TunnelInterface {
+                name: iface.name.clone(),
+                description: iface.description.clone(),
+                index: iface.index,
+                address,
+            })
+        })
+        .collect();
+
+    let default_route_interface = route_source(probe_target)
+        .and_then(|source| interfaces.iter().find(|iface| ipv4_address(iface) == Some(source)))
+        .map(|iface| iface.name.clone());
+    VpnReport {
+        tunnels,
+        default_route_interface,
+    }
+}
+
+/// Detect other VPNs and apply the settings' VPN policy to them before
+/// starting, see [`apply_policy`]. Blocks briefly.
+pub fn check_vpn(settings: &mut Settings) -> Result<VpnReport> {
+    let probe_target = settings.probe_target.parse().unwrap_or(DEFAULT_PROBE_TARGET);
+    let report = detect(&settings.tun_config, probe_target);
+    apply_policy(settings.vpn_policy, &report, &mut settings.interface_filter)?;
+    Ok(report)
+}
+
+/// Check `report` against `policy` before starting. Fails under `Refuse`
+/// while a VPN is active; under `TakePrecedence` excludes every tunnel from
+/// `filter` so nothing is routed through one.
+pub fn apply_policy(policy: VpnPolicy, report: &VpnReport, filter: &mut InterfaceFilter) -> Result<()> {
+    if let Some(vpn) = report.active_vpn() {
+        match policy {
+            VpnPolicy::Refuse => bail!(
+                "{}. Disconnect it, or set the VPN policy to coexist or take_precedence, and try again",
+                report.summary()
+            ),
+            VpnPolicy::Coexist => warn!("{}; starting alongside it", report.summary()),
+            VpnPolicy::TakePrecedence => warn!("{}; routing around it", report.summary()),
+        }
+        info!("If there's no internet after starting, '{}' may be conflicting with NetBoost Pro", vpn.name);
+    }
+    if policy == VpnPolicy::TakePrecedence {
+        filter.exclude.names.extend(report.tunnels.iter().map(|tunnel| tunnel.name.clone()));
+    }
+    Ok(())
+}
+
+fn is_tunnel(iface: &NetworkInterface) -> bool {
+    // TUN and TAP devices have these flags whatever they're called
+    #[cfg(target_os = "linux")]
+    if std::path::Path::new("/sys/class/net").join(&iface.name).join("tun_flags").exists() {
+        return true;
+    }
+    looks_like_tunnel(&iface.name, &iface.description)
+}
+
+fn looks_like_tunnel(name: &str, description: &str) -> bool {
+    let name = name.to_lowercase();
+    let description = description.to_lowercase();
+    TUNNEL_NAME_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        || TUNNEL_DESCRIPTIONS.iter().any(|word| description.contains(word))
+}
+
+fn ipv4_address(iface: &NetworkInterface) -> Option<Ipv4Addr> {
+    iface.ips.iter().find_map(|ip| match ip.ip() {
+        IpAddr::V4(address) => Some(address),
+        _ => None,
+    })
+}
+
+/// The local address the OS picks to reach `target`. Connecting a UDP
+/// socket only consults the routing table; nothing is sent.
+fn route_source(target: Ipv4Addr) -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))).ok()?;
+    socket.connect(SocketAddr::from((target, 53))).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(address) if !address.is_unspecified() => Some(address),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tunnel(name: &str) -> TunnelInterface {
+        TunnelInterface {
+            name: name.to_string(),
+            description: String::new(),
+            index: 9,
+            address: Ipv4Addr::new(10, 8, 0, 2),
+        }
+    }
+
+    #[test]
+    fn test_policy_when_a_vpn_owns_the_default_route() {
+        assert!(looks_like_tunnel("wg0", ""));
+        assert!(looks_like_tunnel("{4D36E972}", "TAP-Windows Adapter V9"));
+        assert!(!looks_like_tunnel("eth0", ""));
+
+        let mut report = VpnReport {
+            tunnels: vec![tunnel("wg0")],
+            default_route_interface: Some("eth0".to_string()),
+        };
+        assert!(report.active_vpn().is_none());
+        let mut filter = InterfaceFilter::default();
+        assert!(apply_policy(VpnPolicy::Refuse, &report, &mut filter).is_ok());
+
+        report.default_route_interface = Some("wg0".to_string());
+        assert_eq!(report.active_vpn().map(|vpn| vpn.name.as_str()), Some("wg0"));
+        assert!(apply_policy(VpnPolicy::Refuse, &report, &mut filter).is_err());
+        assert!(apply_policy(VpnPolicy::Coexist, &report, &mut filter).is_ok());
+        assert!(filter.exclude.names.is_empty());
+        assert!(apply_policy(VpnPolicy::TakePrecedence, &report, &mut filter).is_ok());
+        assert_eq!(filter.exclude.names, ["wg0"]);
+
+        assert_eq!("take_precedence".parse::<VpnPolicy>().unwrap(), VpnPolicy::TakePrecedence);
+    }
+}
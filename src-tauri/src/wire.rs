@@ -0,0 +1,76 @@
+// src-tauri/src/wire.rs
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Encoding for messages passed between NetBoost components. The Tauri
+/// boundary always uses JSON; CBOR is a compact option for internal and
+/// relay messages, enabled by the `cbor` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+pub fn encode<T: Serialize>(format: WireFormat, value: &T) -> Result<Vec<u8>> {
+    match format {
+        WireFormat::Json => serde_json::to_vec(value).context("Failed to encode JSON"),
+        #[cfg(feature = "cbor")]
+        WireFormat::Cbor => {
+            let mut buf = Vec::new();
+            ciborium::into_writer(value, &mut buf).context("Failed to encode CBOR")?;
+            Ok(buf)
+        }
+    }
+}
+
+pub fn decode<T: DeserializeOwned>(format: WireFormat, bytes: &[u8]) -> Result<T> {
+    match format {
+        WireFormat::Json => serde_json::from_slice(bytes).context("Failed to decode JSON"),
+        #[cfg(feature = "cbor")]
+        WireFormat::Cbor => ciborium::from_reader(bytes).context("Failed to decode CBOR"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_router::TrafficType;
+    use crate::performance_monitor::{PerformanceMonitor, PerformanceStats};
+
+    async fn sample_stats() -> PerformanceStats {
+        let monitor = PerformanceMonitor::new();
+        monitor.record_packet_received(1200).await;
+        monitor.record_packet_forwarded(2, "eth0", TrafficType::Streaming, 1200).await;
+        monitor.get_current_stats().await
+    }
+
+    fn assert_round_trips(format: WireFormat, stats: &PerformanceStats) -> usize {
+        let bytes = encode(format, stats).unwrap();
+        let decoded: PerformanceStats = decode(format, &bytes).unwrap();
+        assert_eq!(decoded.packets_forwarded, 1);
+        assert_eq!(decoded.uptime, stats.uptime);
+        assert_eq!(decoded.interfaces[&2].name, "eth0");
+        assert_eq!(decoded.traffic_types[&TrafficType::Streaming].bytes, 1200);
+        bytes.len()
+    }
+
+    #[tokio::test]
+    async fn test_performance_stats_round_trip() {
+        let stats = sample_stats().await;
+        assert_round_trips(WireFormat::Json, &stats);
+        assert!(decode::<PerformanceStats>(WireFormat::Json, b"{").is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[tokio::test]
+    async fn test_cbor_is_smaller_than_json() {
+        let stats = sample_stats().await;
+        let json_len = assert_round_trips(WireFormat::Json, &stats);
+        assert!(assert_round_trips(WireFormat::Cbor, &stats) < json_len);
+        assert!(decode::<PerformanceStats>(WireFormat::Cbor, &[0xff]).is_err());
+    }
+}
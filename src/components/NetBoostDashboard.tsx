@@ -1,24 +1,47 @@
 // src/components/NetBoostDashboard.tsx
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 // Types matching the Rust backend
+interface DropReasons {
+  no_interface: number;
+  send_error: number;
+  queue_full: number;
+  invalid_packet: number;
+  rate_limited: number;
+  shutdown: number;
+  ipv6: number;
+  total: number;
+}
+
 interface PerformanceStats {
   packets_received: number;
   packets_forwarded: number;
-  packets_dropped: number;
-  bytes_received: number;
-  bytes_forwarded: number;
+  packets_dropped: DropReasons;
   average_latency: { secs: number; nanos: number };
   packet_loss_rate: number;
   bandwidth_usage: number;
+  current_bandwidth_bps: number;
+  upload_bytes: number;
+  download_bytes: number;
+  upload_bps: number;
+  download_bps: number;
+  aggregate_bandwidth_bps: number;
   uptime: { secs: number; nanos: number };
+  traffic_types: Record<string, { packets: number; bytes: number }>;
+  effective_load_balancing_mode: string | null;
 }
 
 interface ServiceStatus {
   is_running: boolean;
   uptime_seconds?: number;
   virtual_interface_name?: string;
+  active_interface_count: number;
+  aggregation_enabled: boolean;
+  load_balancing_mode: string;
+  total_bytes_uploaded: number;
+  total_bytes_downloaded: number;
 }
 
 interface PhysicalInterface {
@@ -26,6 +49,57 @@ interface PhysicalInterface {
   description: string;
   ip_address: string;
   index: number;
+  is_metered: boolean;
+}
+
+interface ModeInfo {
+  name: string;
+  display_name: string;
+  description: string;
+}
+
+interface HealthStatus {
+  healthy: boolean;
+  consecutive_failures: number;
+  consecutive_successes: number;
+  last_latency_ms: number | null;
+  last_packet_loss: number;
+  jitter_ms: number | null;
+  circuit_open: boolean;
+}
+
+interface LogSettings {
+  file: string | null;
+  max_files: number;
+}
+
+interface FlowLogSettings {
+  enabled: boolean;
+  file: string | null;
+  max_file_bytes: number;
+}
+
+interface RelayConfig {
+  endpoint: string;
+  auth_token: string;
+}
+
+interface InterfaceRules {
+  names: string[];
+  types: string[];
+  subnets: string[];
+}
+
+interface InterfaceFilter {
+  include: InterfaceRules;
+  exclude: InterfaceRules;
+}
+
+interface OsInterfaceStats {
+  rx_bytes: number;
+  tx_bytes: number;
+  rx_packets: number;
+  tx_packets: number;
 }
 
 interface SystemInfo {
@@ -35,13 +109,79 @@ interface SystemInfo {
   build_date: string;
 }
 
+interface BackendInfo {
+  version: string;
+  tun_driver: boolean;
+  has_privileges: boolean;
+  features: string[];
+}
+
+// [timestamp_ms, upload_bps, download_bps]
+type ThroughputPoint = [number, number, number];
+
+// Span of the speed graph
+const SPEED_GRAPH_WINDOW_SECS = 300;
+
+// SVG polyline points for one series of the speed graph, scaled to a 100x30 box
+const sparkline = (points: ThroughputPoint[], series: 1 | 2): string => {
+  if (points.length < 2) return '';
+  const start = points[0][0];
+  const span = Math.max(points[points.length - 1][0] - start, 1);
+  const peak = Math.max(1, ...points.map((point) => Math.max(point[1], point[2])));
+  return points
+    .map((point) => `${(((point[0] - start) / span) * 100).toFixed(2)},${(30 - (point[series] / peak) * 30).toFixed(2)}`)
+    .join(' ');
+};
+
+// Serialized `NetBoostError` returned by failed commands
+interface BackendError {
+  code: string;
+  message: string;
+}
+
+const isBackendError = (err: unknown): err is BackendError =>
+  typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+
+const errorMessage = (err: unknown): string => (isBackendError(err) ? err.message : String(err));
+
+const DROP_REASONS: { key: Exclude<keyof DropReasons, 'total'>; label: string }[] = [
+  { key: 'no_interface', label: 'No interface' },
+  { key: 'send_error', label: 'Send errors' },
+  { key: 'queue_full', label: 'Queue full' },
+  { key: 'invalid_packet', label: 'Malformed' },
+  { key: 'rate_limited', label: 'Rate limited' },
+  { key: 'shutdown', label: 'Dropped at stop' },
+  { key: 'ipv6', label: 'IPv6 (not aggregated)' },
+];
+
 const NetBoostDashboard: React.FC = () => {
-  const [serviceStatus, setServiceStatus] = useState<ServiceStatus>({ is_running: false });
+  const [serviceStatus, setServiceStatus] = useState<ServiceStatus>({
+    is_running: false,
+    active_interface_count: 0,
+    aggregation_enabled: true,
+    load_balancing_mode: 'balanced',
+    total_bytes_uploaded: 0,
+    total_bytes_downloaded: 0,
+  });
   const [performanceStats, setPerformanceStats] = useState<PerformanceStats | null>(null);
+  const [throughputHistory, setThroughputHistory] = useState<ThroughputPoint[]>([]);
   const [interfaces, setInterfaces] = useState<PhysicalInterface[]>([]);
+  const [primaryInterface, setPrimaryInterface] = useState<string | null>(null);
+  const [interfaceHealth, setInterfaceHealth] = useState<Record<number, HealthStatus>>({});
+  const [osInterfaceStats, setOsInterfaceStats] = useState<Record<number, OsInterfaceStats>>({});
   const [systemInfo, setSystemInfo] = useState<SystemInfo | null>(null);
+  // null until the backend answers, 'unreachable' if it never does
+  const [backendInfo, setBackendInfo] = useState<BackendInfo | 'unreachable' | null>(null);
   const [loadBalancingMode, setLoadBalancingMode] = useState<string>('balanced');
+  const [loadBalancingModes, setLoadBalancingModes] = useState<ModeInfo[]>([]);
+  const [logSettings, setLogSettings] = useState<LogSettings>({ file: null, max_files: 7 });
   const [isAggregationEnabled, setIsAggregationEnabled] = useState<boolean>(true);
+  const [isFlowLogEnabled, setIsFlowLogEnabled] = useState<boolean>(false);
+  const [avoidMetered, setAvoidMetered] = useState<boolean>(false);
+  const [interfaceFilter, setInterfaceFilter] = useState<InterfaceFilter | null>(null);
+  const [excludedNames, setExcludedNames] = useState<string>('');
+  const [relayConfig, setRelayConfig] = useState<RelayConfig>({ endpoint: '', auth_token: '' });
+  const [isRelayEnabled, setIsRelayEnabled] = useState<boolean>(false);
   const [isStarting, setIsStarting] = useState(false);
   const [isStopping, setIsStopping] = useState(false);
   const [error, setError] = useState<string>('');
@@ -61,6 +201,15 @@ const NetBoostDashboard: React.FC = () => {
     return parseFloat((bytes / Math.pow(k, i)).toFixed(2)) + ' ' + sizes[i];
   };
 
+  // Utility function to format a bit rate
+  const formatBitrate = (bps: number): string => {
+    if (bps < 1000) return `${bps} bps`;
+    const k = 1000;
+    const sizes = ['bps', 'Kbps', 'Mbps', 'Gbps'];
+    const i = Math.min(Math.floor(Math.log(bps) / Math.log(k)), sizes.length - 1);
+    return parseFloat((bps / Math.pow(k, i)).toFixed(2)) + ' ' + sizes[i];
+  };
+
   // Utility function to format uptime
   const formatUptime = (seconds: number): string => {
     const hours = Math.floor(seconds / 3600);
@@ -86,22 +235,78 @@ const NetBoostDashboard: React.FC = () => {
 
   // Load initial data
   useEffect(() => {
+    pingBackend();
     loadSystemInfo();
     loadNetworkInterfaces();
+    loadLoadBalancingMode();
+    loadLogSettings();
+    loadRelayConfig();
     updateServiceStatus();
   }, []);
 
-  // Auto-refresh performance stats when service is running
+  // Receive performance stats pushed by the backend while the service is running
   useEffect(() => {
     if (serviceStatus.is_running) {
-      const interval = setInterval(() => {
-        updatePerformanceStats();
+      updatePerformanceStats();
+      // Backfill the speed graph, then extend it with each update
+      invoke<ThroughputPoint[]>('get_throughput_history', { windowSecs: SPEED_GRAPH_WINDOW_SECS })
+        .then(setThroughputHistory)
+        .catch((err) => console.error('Failed to load throughput history:', err));
+      const unlisten = listen<PerformanceStats>('performance-update', (event) => {
+        setPerformanceStats(event.payload);
+        const now = Date.now();
+        setThroughputHistory((history) => [
+          ...history.filter((point) => point[0] >= now - SPEED_GRAPH_WINDOW_SECS * 1000),
+          [now, event.payload.upload_bps, event.payload.download_bps],
+        ]);
         updateServiceStatus();
-      }, 2000);
-      return () => clearInterval(interval);
+        updateInterfaceHealth();
+      });
+      return () => {
+        unlisten.then((stop) => stop());
+      };
     }
   }, [serviceStatus.is_running]);
 
+  // Refresh the interface list when adapters are plugged in or removed
+  useEffect(() => {
+    const unlisten = listen('interfaces-changed', () => {
+      loadNetworkInterfaces();
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  // Pick up edits to a loaded config file
+  useEffect(() => {
+    const unlisten = listen('settings-reloaded', () => {
+      loadLoadBalancingMode();
+      loadNetworkInterfaces();
+      setSuccess('Configuration reloaded');
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  const pingBackend = async () => {
+    try {
+      setBackendInfo(await invoke<BackendInfo>('ping'));
+    } catch (err) {
+      console.error('Backend did not answer ping:', err);
+      setBackendInfo('unreachable');
+    }
+  };
+
+  // Why the service can't be started, if the backend already knows
+  const backendProblem = (): string | null => {
+    if (backendInfo === 'unreachable') return 'The NetBoost backend is not responding. Restart the application.';
+    if (backendInfo && !backendInfo.tun_driver) return 'The TUN driver is missing. Install it before starting NetBoost.';
+    if (backendInfo && !backendInfo.has_privileges) return 'NetBoost needs administrator rights to start. Restart it as administrator.';
+    return null;
+  };
+
   const loadSystemInfo = async () => {
     try {
       const info = await invoke<SystemInfo>('get_system_info');
@@ -111,12 +316,107 @@ const NetBoostDashboard: React.FC = () => {
     }
   };
 
+  const loadLogSettings = async () => {
+    try {
+      setLogSettings(await invoke<LogSettings>('get_log_settings'));
+    } catch (err) {
+      console.error('Failed to load log settings:', err);
+    }
+    try {
+      setIsFlowLogEnabled((await invoke<FlowLogSettings>('get_flow_log_settings')).enabled);
+    } catch (err) {
+      console.error('Failed to load flow log settings:', err);
+    }
+  };
+
+  const toggleFlowLog = async () => {
+    try {
+      const result = await invoke<string>('set_flow_log', { enabled: !isFlowLogEnabled });
+      setIsFlowLogEnabled(!isFlowLogEnabled);
+      setSuccess(result);
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const loadRelayConfig = async () => {
+    try {
+      const config = await invoke<RelayConfig | null>('get_relay_config');
+      setIsRelayEnabled(config !== null);
+      if (config) {
+        setRelayConfig(config);
+      }
+    } catch (err) {
+      console.error('Failed to load relay config:', err);
+    }
+  };
+
+  const toggleRelay = async () => {
+    try {
+      const config = isRelayEnabled ? null : relayConfig;
+      const result = await invoke<string>('set_relay_config', { config });
+      setIsRelayEnabled(!isRelayEnabled);
+      setSuccess(result);
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const saveLogSettings = async () => {
+    try {
+      const result = await invoke<string>('set_log_settings', { logging: logSettings });
+      setSuccess(result);
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
   const loadNetworkInterfaces = async () => {
     try {
       const interfaceList = await invoke<PhysicalInterface[]>('get_network_interfaces');
       setInterfaces(interfaceList);
+      setOsInterfaceStats(await invoke<Record<number, OsInterfaceStats>>('get_os_interface_stats'));
+      const primary = await invoke<PhysicalInterface | null>('get_primary_interface');
+      setPrimaryInterface(primary?.name ?? null);
+      setAvoidMetered(await invoke<boolean>('get_avoid_metered'));
+      const filter = await invoke<InterfaceFilter>('get_interface_filter');
+      setInterfaceFilter(filter);
+      setExcludedNames(filter.exclude.names.join(', '));
+    } catch (err) {
+      setError('Failed to load network interfaces: ' + errorMessage(err));
+    }
+  };
+
+  const toggleAvoidMetered = async () => {
+    try {
+      const result = await invoke<string>('set_avoid_metered', { avoid: !avoidMetered });
+      setAvoidMetered(!avoidMetered);
+      setSuccess(result);
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const saveInterfaceFilter = async () => {
+    if (!interfaceFilter) return;
+    const names = excludedNames.split(',').map((name) => name.trim()).filter((name) => name !== '');
+    const filter = { ...interfaceFilter, exclude: { ...interfaceFilter.exclude, names } };
+    try {
+      const result = await invoke<string>('set_interface_filter', { filter });
+      setInterfaceFilter(filter);
+      setSuccess(result);
     } catch (err) {
-      setError('Failed to load network interfaces: ' + String(err));
+      setError(errorMessage(err));
+    }
+  };
+
+  const makePrimary = async (index: number) => {
+    try {
+      const result = await invoke<string>('set_primary_interface', { index });
+      await loadNetworkInterfaces();
+      setSuccess(result);
+    } catch (err) {
+      setError(errorMessage(err));
     }
   };
 
@@ -124,6 +424,7 @@ const NetBoostDashboard: React.FC = () => {
     try {
       const status = await invoke<ServiceStatus>('get_service_status');
       setServiceStatus(status);
+      setIsAggregationEnabled(status.aggregation_enabled);
     } catch (err) {
       console.error('Failed to get service status:', err);
     }
@@ -140,6 +441,33 @@ const NetBoostDashboard: React.FC = () => {
     }
   };
 
+  const updateInterfaceHealth = async () => {
+    try {
+      const health = await invoke<Record<number, HealthStatus>>('get_interface_health');
+      setInterfaceHealth(health);
+    } catch (err) {
+      console.error('Failed to get interface health:', err);
+    }
+  };
+
+  const exportHistory = async () => {
+    try {
+      setSuccess(await invoke<string>('export_performance_history', { path: null }));
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const resetStats = async () => {
+    try {
+      const result = await invoke<string>('reset_performance_stats');
+      setSuccess(result);
+      await updatePerformanceStats();
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
   const startService = async () => {
     setIsStarting(true);
     setError('');
@@ -148,7 +476,12 @@ const NetBoostDashboard: React.FC = () => {
       setSuccess(result);
       await updateServiceStatus();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
+      if (isBackendError(err)) {
+        if (err.code === 'already_running') await updateServiceStatus();
+        // Refresh the readiness banner, e.g. after the driver was uninstalled
+        if (err.code === 'no_privileges' || err.code === 'tun_driver_missing') await pingBackend();
+      }
     } finally {
       setIsStarting(false);
     }
@@ -163,19 +496,28 @@ const NetBoostDashboard: React.FC = () => {
       setPerformanceStats(null);
       await updateServiceStatus();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setIsStopping(false);
     }
   };
 
+  const loadLoadBalancingMode = async () => {
+    try {
+      setLoadBalancingModes(await invoke<ModeInfo[]>('list_load_balancing_modes'));
+      setLoadBalancingMode(await invoke<string>('get_load_balancing_mode'));
+    } catch (err) {
+      console.error('Failed to get load balancing mode:', err);
+    }
+  };
+
   const changeLoadBalancingMode = async (mode: string) => {
     try {
       const result = await invoke<string>('set_load_balancing_mode', { mode });
       setLoadBalancingMode(mode);
       setSuccess(result);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     }
   };
 
@@ -203,6 +545,11 @@ const NetBoostDashboard: React.FC = () => {
         </header>
 
         {/* Status Messages */}
+        {backendProblem() && (
+          <div className="mb-4 p-4 bg-yellow-500/20 border border-yellow-500/50 rounded-lg text-yellow-200">
+            {backendProblem()}
+          </div>
+        )}
         {error && (
           <div className="mb-4 p-4 bg-red-500/20 border border-red-500/50 rounded-lg text-red-200">
             {error}
@@ -231,7 +578,7 @@ const NetBoostDashboard: React.FC = () => {
               <div className="flex space-x-4">
                 <button
                   onClick={startService}
-                  disabled={serviceStatus.is_running || isStarting}
+                  disabled={serviceStatus.is_running || isStarting || backendProblem() !== null}
                   className="px-6 py-2 bg-green-600 hover:bg-green-700 disabled:bg-gray-600 disabled:cursor-not-allowed rounded-lg font-medium transition-colors"
                 >
                   {isStarting ? 'Starting...' : 'Start NetBoost'}
@@ -249,6 +596,15 @@ const NetBoostDashboard: React.FC = () => {
                 <div className="mt-4 p-3 bg-slate-700/50 rounded-lg">
                   <div className="text-sm text-slate-400">Uptime</div>
                   <div className="font-mono text-green-400">{formatUptime(serviceStatus.uptime_seconds || 0)}</div>
+                  <div className="text-sm text-slate-400 mt-2">Transferred</div>
+                  <div className="font-mono text-green-400">
+                    {formatBytes(serviceStatus.total_bytes_uploaded + serviceStatus.total_bytes_downloaded)}
+                  </div>
+                  <div className="text-sm text-slate-400 mt-2">Links</div>
+                  <div className="font-mono text-green-400">
+                    {serviceStatus.active_interface_count} of {interfaces.length} active
+                    {!serviceStatus.aggregation_enabled && ' (aggregation off)'}
+                  </div>
                   {serviceStatus.virtual_interface_name && (
                     <>
                       <div className="text-sm text-slate-400 mt-2">Virtual Interface</div>
@@ -268,29 +624,114 @@ const NetBoostDashboard: React.FC = () => {
               disabled={!serviceStatus.is_running}
               className="w-full p-2 bg-slate-700 border border-slate-600 rounded-lg text-white disabled:opacity-50"
             >
-              <option value="balanced">Balanced</option>
-              <option value="round_robin">Round Robin</option>
-              <option value="latency_based">Latency Based</option>
-              <option value="bandwidth_based">Bandwidth Based</option>
+              {loadBalancingModes.map((mode) => (
+                <option key={mode.name} value={mode.name} title={mode.description}>
+                  {mode.display_name}
+                </option>
+              ))}
             </select>
+            <p className="mt-2 text-xs text-slate-400">
+              {loadBalancingModes.find((mode) => mode.name === loadBalancingMode)?.description}
+            </p>
+            {loadBalancingMode === 'adaptive' && performanceStats?.effective_load_balancing_mode && (
+              <p className="mt-1 text-xs text-blue-300">
+                Currently{' '}
+                {loadBalancingModes.find((mode) => mode.name === performanceStats.effective_load_balancing_mode)?.display_name ??
+                  performanceStats.effective_load_balancing_mode}
+              </p>
+            )}
+          </div>
+        </div>
+
+        {/* Log File */}
+        <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6 mb-8">
+          <h2 className="text-2xl font-semibold mb-4">Log File</h2>
+          <div className="flex items-center space-x-4">
+            <input
+              type="text"
+              placeholder="Console only"
+              value={logSettings.file ?? ''}
+              onChange={(e) => setLogSettings({ ...logSettings, file: e.target.value || null })}
+              className="flex-1 p-2 bg-slate-700 border border-slate-600 rounded-lg text-white"
+            />
+            <label className="text-slate-400 text-sm">Keep</label>
+            <input
+              type="number"
+              min={1}
+              value={logSettings.max_files}
+              onChange={(e) => setLogSettings({ ...logSettings, max_files: Math.max(1, Number(e.target.value)) })}
+              className="w-20 p-2 bg-slate-700 border border-slate-600 rounded-lg text-white"
+            />
+            <button
+              onClick={saveLogSettings}
+              className="px-6 py-2 rounded-lg font-medium bg-blue-600 hover:bg-blue-700 transition-colors"
+            >
+              Save
+            </button>
+          </div>
+          <p className="text-slate-500 text-sm mt-2">Rotated daily. Changes apply after restarting NetBoost Pro.</p>
+          <div className="flex items-center justify-between mt-4">
+            <p className="text-slate-400">Record which interface each new flow was sent through.</p>
+            <button
+              onClick={toggleFlowLog}
+              className={`px-6 py-2 rounded-lg font-medium transition-colors ${
+                isFlowLogEnabled ? 'bg-blue-600 hover:bg-blue-700' : 'bg-gray-600 hover:bg-gray-700'
+              }`}
+            >
+              Flow Log {isFlowLogEnabled ? 'On' : 'Off'}
+            </button>
           </div>
         </div>
 
+        {/* Relay Bonding */}
+        <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6 mb-8">
+          <h2 className="text-2xl font-semibold mb-4">Relay Bonding</h2>
+          <div className="flex items-center space-x-4">
+            <input
+              type="text"
+              placeholder="relay.example.com:4500"
+              value={relayConfig.endpoint}
+              onChange={(e) => setRelayConfig({ ...relayConfig, endpoint: e.target.value })}
+              disabled={isRelayEnabled}
+              className="flex-1 p-2 bg-slate-700 border border-slate-600 rounded-lg text-white disabled:opacity-50"
+            />
+            <input
+              type="password"
+              placeholder="Auth token"
+              value={relayConfig.auth_token}
+              onChange={(e) => setRelayConfig({ ...relayConfig, auth_token: e.target.value })}
+              disabled={isRelayEnabled}
+              className="w-48 p-2 bg-slate-700 border border-slate-600 rounded-lg text-white disabled:opacity-50"
+            />
+            <button
+              onClick={toggleRelay}
+              disabled={!isRelayEnabled && (!relayConfig.endpoint || !relayConfig.auth_token)}
+              className={`px-6 py-2 rounded-lg font-medium transition-colors disabled:opacity-50 ${
+                isRelayEnabled ? 'bg-blue-600 hover:bg-blue-700' : 'bg-gray-600 hover:bg-gray-700'
+              }`}
+            >
+              Relay {isRelayEnabled ? 'On' : 'Off'}
+            </button>
+          </div>
+          <p className="text-slate-500 text-sm mt-2">
+            Split even single downloads across every connection by tunneling through a relay server that reassembles them.
+          </p>
+        </div>
+
         {/* Connection Aggregation Control */}
         <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6 mb-8">
           <h2 className="text-2xl font-semibold mb-4">Connection Aggregation</h2>
           <div className="flex items-center space-x-4">
-            <p className="text-slate-400">Enable or disable connection aggregation.</p>
+            <p className="text-slate-400">Balance traffic across every connection, or send it all through the primary one.</p>
             <button
               onClick={() => {
-                // This is a placeholder for now
                 const new_state = !isAggregationEnabled;
                 invoke('set_connection_aggregation', { enabled: new_state })
                   .then(() => {
                     setIsAggregationEnabled(new_state);
                     setSuccess(`Connection aggregation ${new_state ? 'enabled' : 'disabled'}`);
                   })
-                  .catch(err => setError(String(err)));
+                  .catch(err => setError(errorMessage(err)));
               }}
               className={`px-6 py-2 rounded-lg font-medium transition-colors ${
                 isAggregationEnabled
@@ -304,6 +745,22 @@ const NetBoostDashboard: React.FC = () => {
         </div>
 
         {/* Performance Stats */}
+        {performanceStats && (
+          <div className="flex justify-end space-x-2 mb-2">
+            <button
+              onClick={exportHistory}
+              className="px-4 py-1 text-sm rounded-lg bg-slate-700 hover:bg-slate-600 transition-colors"
+            >
+              Download Report
+            </button>
+            <button
+              onClick={resetStats}
+              className="px-4 py-1 text-sm rounded-lg bg-slate-700 hover:bg-slate-600 transition-colors"
+            >
+              Reset Stats
+            </button>
+          </div>
+        )}
         {performanceStats && (
           <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-4 gap-6 mb-8">
             <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6">
@@ -325,9 +782,24 @@ const NetBoostDashboard: React.FC = () => {
 
             <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6">
               <div className="text-slate-400 text-sm">Bandwidth Usage</div>
-              <div className="text-2xl font-bold text-purple-400">
-                {formatBytes(performanceStats.bandwidth_usage)}/s
+              <div className="text-lg font-bold text-purple-400">
+                ↑ {formatBitrate(performanceStats.upload_bps)}
+              </div>
+              <div className="text-lg font-bold text-cyan-400">
+                ↓ {formatBitrate(performanceStats.download_bps)}
               </div>
+              <div className="text-xs text-slate-500">
+                {formatBytes(performanceStats.upload_bytes)} up · {formatBytes(performanceStats.download_bytes)} down
+              </div>
+              <div className="text-xs text-slate-400 mt-1">
+                Effective speed: {formatBitrate(performanceStats.aggregate_bandwidth_bps)}
+              </div>
+              {throughputHistory.length > 1 && (
+                <svg viewBox="0 0 100 30" preserveAspectRatio="none" className="w-full h-10 mt-2">
+                  <polyline points={sparkline(throughputHistory, 1)} fill="none" stroke="#c084fc" strokeWidth="1" vectorEffect="non-scaling-stroke" />
+                  <polyline points={sparkline(throughputHistory, 2)} fill="none" stroke="#22d3ee" strokeWidth="1" vectorEffect="non-scaling-stroke" />
+                </svg>
+              )}
             </div>
 
             <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6">
@@ -339,25 +811,114 @@ const NetBoostDashboard: React.FC = () => {
           </div>
         )}
 
+        {/* Traffic Mix */}
+        {performanceStats && performanceStats.bytes_forwarded > 0 && (
+          <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6 mb-8">
+            <h3 className="text-xl font-semibold mb-4">Traffic Mix</h3>
+            <div className="space-y-2">
+              {Object.entries(performanceStats.traffic_types)
+                .sort(([, a], [, b]) => b.bytes - a.bytes)
+                .map(([type, stats]) => {
+                  const share = (stats.bytes / performanceStats.bytes_forwarded) * 100;
+                  return (
+                    <div key={type}>
+                      <div className="flex justify-between text-sm">
+                        <span className="capitalize">{type}</span>
+                        <span className="text-slate-400">
+                          {share.toFixed(1)}% · {formatBytes(stats.bytes)} · {stats.packets.toLocaleString()} packets
+                        </span>
+                      </div>
+                      <div className="h-2 bg-slate-700 rounded-full">
+                        <div className="h-2 bg-blue-500 rounded-full" style={{ width: `${share}%` }} />
+                      </div>
+                    </div>
+                  );
+                })}
+            </div>
+          </div>
+        )}
+
         {/* Network Interfaces */}
         <div className="bg-slate-800/50 backdrop-blur-sm border border-slate-700 rounded-xl p-6">
-          <h3 className="text-xl font-semibold mb-4">Network Interfaces</h3>
+          <div className="flex items-center justify-between mb-4">
+            <h3 className="text-xl font-semibold">Network Interfaces</h3>
+            <label className="flex items-center space-x-2 text-sm text-slate-400">
+              <input type="checkbox" checked={avoidMetered} onChange={toggleAvoidMetered} />
+              <span>Keep file transfers off metered connections</span>
+            </label>
+          </div>
+          <div className="flex items-center space-x-4 mb-4">
+            <label className="text-slate-400 text-sm">Exclude</label>
+            <input
+              type="text"
+              placeholder="e.g. docker*, tun*"
+              value={excludedNames}
+              onChange={(e) => setExcludedNames(e.target.value)}
+              className="flex-1 p-2 bg-slate-700 border border-slate-600 rounded-lg text-white"
+            />
+            <button
+              onClick={saveInterfaceFilter}
+              className="px-6 py-2 rounded-lg font-medium bg-blue-600 hover:bg-blue-700 transition-colors"
+            >
+              Save
+            </button>
+          </div>
           <div className="space-y-3">
             {interfaces.map((iface) => (
               <div key={iface.index} className="p-4 bg-slate-700/50 rounded-lg">
                 <div className="flex items-center justify-between">
                   <div>
-                    <div className="font-medium text-blue-400">{iface.name}</div>
+                    <div className="font-medium text-blue-400">
+                      {iface.name}
+                      {primaryInterface === iface.name && (
+                        <span className="ml-2 px-2 py-0.5 text-xs rounded bg-blue-600/40 text-blue-200">Primary</span>
+                      )}
+                      {iface.is_metered && (
+                        <span className="ml-2 px-2 py-0.5 text-xs rounded bg-amber-600/40 text-amber-200">Metered</span>
+                      )}
+                    </div>
                     <div className="text-sm text-slate-400">{iface.description}</div>
                   </div>
                   <div className="text-right">
                     <div className="font-mono text-sm">{iface.ip_address}</div>
                     <div className="text-xs text-slate-500">Index: {iface.index}</div>
+                    {primaryInterface !== iface.name && (
+                      <button
+                        onClick={() => makePrimary(iface.index)}
+                        className="mt-1 px-2 py-0.5 text-xs rounded bg-slate-600 hover:bg-slate-500 transition-colors"
+                      >
+                        Make Primary
+                      </button>
+                    )}
                   </div>
                 </div>
-                {/* Placeholder for per-interface stats */}
                 <div className="mt-2 pt-2 border-t border-slate-600/50 text-xs text-slate-400">
-                  Stats: (coming soon)
+                  {interfaceHealth[iface.index] ? (
+                    <span>
+                      <span className={interfaceHealth[iface.index].healthy && !interfaceHealth[iface.index].circuit_open ? 'text-green-400' : 'text-red-400'}>
+                        {interfaceHealth[iface.index].circuit_open
+                          ? 'Paused after send failures'
+                          : interfaceHealth[iface.index].healthy ? 'Healthy' : 'Degraded'}
+                      </span>
+                      {' · '}
+                      {interfaceHealth[iface.index].last_latency_ms !== null
+                        ? `${interfaceHealth[iface.index].last_latency_ms!.toFixed(0)} ms`
+                        : 'no response'}
+                      {interfaceHealth[iface.index].jitter_ms !== null &&
+                        ` ± ${interfaceHealth[iface.index].jitter_ms!.toFixed(1)} ms`}
+                      {' · '}
+                      {(interfaceHealth[iface.index].last_packet_loss * 100).toFixed(0)}% loss
+                    </span>
+                  ) : (
+                    'Health: not probed yet'
+                  )}
+                  {osInterfaceStats[iface.index] && (
+                    <div className="mt-1">
+                      Since boot: {formatBytes(osInterfaceStats[iface.index].rx_bytes)} received
+                      {' · '}
+                      {formatBytes(osInterfaceStats[iface.index].tx_bytes)} sent
+                    </div>
+                  )}
                 </div>
               </div>
             ))}
@@ -379,19 +940,25 @@ const NetBoostDashboard: React.FC = () => {
                   <span>{formatUptime(performanceStats.uptime.secs)}</span>
                 </div>
                 <div className="flex justify-between">
-                  <span className="text-slate-400">Data Received:</span>
-                  <span>{formatBytes(performanceStats.bytes_received)}</span>
+                  <span className="text-slate-400">Data Uploaded:</span>
+                  <span>{formatBytes(serviceStatus.total_bytes_uploaded)}</span>
                 </div>
                 <div className="flex justify-between">
-                  <span className="text-slate-400">Data Forwarded:</span>
-                  <span>{formatBytes(performanceStats.bytes_forwarded)}</span>
+                  <span className="text-slate-400">Data Downloaded:</span>
+                  <span>{formatBytes(serviceStatus.total_bytes_downloaded)}</span>
                 </div>
                 <div className="flex justify-between">
                   <span className="text-slate-400">Packets Dropped:</span>
-                  <span className={performanceStats.packets_dropped > 0 ? 'text-red-400' : 'text-green-400'}>
-                    {performanceStats.packets_dropped.toLocaleString()}
+                  <span className={performanceStats.packets_dropped.total > 0 ? 'text-red-400' : 'text-green-400'}>
+                    {performanceStats.packets_dropped.total.toLocaleString()}
                   </span>
                 </div>
+                {DROP_REASONS.filter(({ key }) => performanceStats.packets_dropped[key] > 0).map(({ key, label }) => (
+                  <div key={key} className="flex justify-between pl-4 text-sm">
+                    <span className="text-slate-500">{label}:</span>
+                    <span className="text-yellow-400">{performanceStats.packets_dropped[key].toLocaleString()}</span>
+                  </div>
+                ))}
               </div>
             )}
             {!performanceStats && serviceStatus.is_running && (
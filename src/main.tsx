@@ -5,7 +5,7 @@ import { invoke } from '@tauri-apps/api/core';
 interface PerformanceStats {
   packets_received: number;
   packets_forwarded: number;
-  packets_dropped: number;
+  packets_dropped: { total: number };
   bytes_received: number;
   bytes_forwarded: number;
   average_latency: { secs: number; nanos: number };
@@ -349,8 +349,8 @@ const NetBoostDashboard: React.FC = () => {
                 </div>
                 <div className="flex justify-between">
                   <span className="text-slate-400">Packets Dropped:</span>
-                  <span className={performanceStats.packets_dropped > 0 ? 'text-red-400' : 'text-green-400'}>
-                    {performanceStats.packets_dropped.toLocaleString()}
+                  <span className={performanceStats.packets_dropped.total > 0 ? 'text-red-400' : 'text-green-400'}>
+                    {performanceStats.packets_dropped.total.toLocaleString()}
                   </span>
                 </div>
               </div>